@@ -0,0 +1,46 @@
+//! Advertise a media server with a generated description, and leave cleanly on Ctrl-C.
+//!
+//! `cargo run --example advertise --features description`
+
+use tokio_ssdp::{
+    DescriptionServer, Device, DeviceDescription, Server, ShutdownProgress, Teardown, UpnpVersion,
+    persistent_uuid,
+};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let uuid = persistent_uuid(std::env::temp_dir().join("tokio-ssdp-advertise.uuid"))?;
+    let device = Device::new(&uuid, "urn:schemas-upnp-org:device:MediaServer:1", "")
+        .with_service("urn:schemas-upnp-org:service:ContentDirectory:1")
+        .with_service("urn:schemas-upnp-org:service:ConnectionManager:1");
+
+    let mut descriptions = DescriptionServer::bind(0)?.describe(
+        &device,
+        DeviceDescription::new("tokio-ssdp example", "tokio-ssdp", "Advertise").model_number("1"),
+    );
+    let mut server = Server::new([device])
+        .upnp_version(UpnpVersion::V1_1)
+        .location_resolver(descriptions.locations());
+
+    let teardown = Teardown::new()
+        .ssdp(server.handle())
+        .description_server(descriptions.handle());
+    let descriptions = tokio::spawn(descriptions.serve()?);
+    let server = tokio::spawn(server.serve()?);
+    println!("Advertising uuid:{uuid}, press Ctrl-C to stop");
+
+    tokio::signal::ctrl_c().await?;
+    teardown
+        .shutdown_all(|progress| {
+            if let ShutdownProgress::Finished(phase) = progress {
+                println!("{phase} stopped");
+            }
+        })
+        .await;
+
+    server.await??;
+    descriptions.await??;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Bridge SSDP discovery to other tools: print the devices coming and going, and the searches
+//! seen on the network, as JSON lines.
+//!
+//! `cargo run --example bridge --features serde`
+
+use serde_json::json;
+use tokio_ssdp::{Client, DeviceRegistry, RegistryEvent, RemoteDevice, Server, SsdpEvent};
+
+fn print(change: &str, device: &RemoteDevice) {
+    let line = json!({
+        "change": change,
+        "usn": device.usn,
+        "search_target": device.search_target,
+        "location": device.location,
+        "remote_addr": device.remote_addr,
+        "server": device.header("SERVER"),
+    });
+    println!("{line}");
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let (mut registry, mut changes) = DeviceRegistry::new(256);
+
+    // Listen for announcements, without advertising anything
+    let mut server = Server::new([]);
+    let mut events = server.event_stream(256);
+    tokio::spawn(server.serve()?);
+
+    // Find the devices that are already there
+    let mut search = Client::new().mx(2).search("ssdp:all").await?;
+    let mut searching = true;
+
+    loop {
+        tokio::select! {
+            response = search.next(), if searching => match response {
+                Some(Ok(response)) => registry.observe_response(&response),
+                Some(Err(e)) => eprintln!("search failed: {e}"),
+                None => searching = false,
+            },
+            Some(event) = events.next() => {
+                if let SsdpEvent::Search(search) = &event {
+                    println!("{}", serde_json::to_string(search)?);
+                }
+                registry.observe(&event);
+            }
+            Some(change) = changes.next() => match change {
+                RegistryEvent::Added(device) => print("added", &device),
+                RegistryEvent::Updated(device) => print("updated", &device),
+                RegistryEvent::Expired(device) => print("expired", &device),
+                RegistryEvent::Removed(device) => print("removed", &device),
+                _ => {}
+            },
+            _ = registry.expired() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Find the Internet gateway and ask it to forward a TCP port to this host (UPnP IGD).
+//!
+//! `cargo run --example igd_portmap -- <external port> [internal port]`
+
+use std::net::SocketAddr;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_ssdp::Client;
+
+const IGD: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const SERVICES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+type Error = Box<dyn std::error::Error>;
+
+/// Split `http://host:port/path` into the address and path.
+fn split_url(url: &str) -> Result<(String, String), Error> {
+    let rest = url.strip_prefix("http://").ok_or("not an http:// URL")?;
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let host = if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{host}:80")
+    };
+    Ok((host, if path.is_empty() { "/" } else { path }.to_owned()))
+}
+
+/// Send an HTTP/1.0 request and return the body of the response and the local address used.
+async fn http(
+    url: &str,
+    method: &str,
+    headers: &str,
+    body: &str,
+) -> Result<(String, SocketAddr), Error> {
+    let (host, path) = split_url(url)?;
+    let mut stream = TcpStream::connect(&host).await?;
+    let request = format!(
+        "{method} {path} HTTP/1.0\r\nHost: {host}\r\n{headers}Content-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    let status = response.lines().next().unwrap_or_default().to_owned();
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_owned())
+        .unwrap_or_default();
+    if !status.contains(" 200 ") {
+        return Err(format!("{method} {url}: {status}\n{body}").into());
+    }
+    Ok((body, stream.local_addr()?))
+}
+
+/// The text of the first `<name>` element after `from` in `xml`.
+fn element<'a>(xml: &'a str, name: &str, from: usize) -> Option<&'a str> {
+    let open = format!("<{name}>");
+    let start = xml[from..].find(&open)? + from + open.len();
+    let end = xml[start..].find(&format!("</{name}>"))? + start;
+    Some(xml[start..end].trim())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let external: u16 = args
+        .next()
+        .ok_or("usage: <external port> [internal port]")?
+        .parse()?;
+    let internal: u16 = args.next().map_or(Ok(external), |p| p.parse())?;
+
+    let mut search = Client::new().mx(2).search(IGD).await?;
+    let gateway = search
+        .next()
+        .await
+        .ok_or("no Internet gateway answered")??;
+    println!("Found {} at {}", gateway.usn, gateway.location);
+
+    // Forward to the address this host reaches the gateway from
+    let (description, local) = http(&gateway.location, "GET", "", "").await?;
+    let local = local.ip();
+    let (service, offset) = SERVICES
+        .iter()
+        .find_map(|service| {
+            let at = description.find(&format!("<serviceType>{service}</serviceType>"))?;
+            Some((*service, at))
+        })
+        .ok_or("the gateway has no WAN connection service")?;
+    let control = element(&description, "controlURL", offset).ok_or("no control URL")?;
+    let control = if control.starts_with("http://") {
+        control.to_owned()
+    } else {
+        let (host, _) = split_url(&gateway.location)?;
+        format!("http://{host}/{}", control.trim_start_matches('/'))
+    };
+
+    let body = format!(
+        concat!(
+            "<?xml version=\"1.0\"?>",
+            "<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" ",
+            "s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body>",
+            "<u:AddPortMapping xmlns:u=\"{service}\">",
+            "<NewRemoteHost></NewRemoteHost>",
+            "<NewExternalPort>{external}</NewExternalPort>",
+            "<NewProtocol>TCP</NewProtocol>",
+            "<NewInternalPort>{internal}</NewInternalPort>",
+            "<NewInternalClient>{local}</NewInternalClient>",
+            "<NewEnabled>1</NewEnabled>",
+            "<NewPortMappingDescription>tokio-ssdp</NewPortMappingDescription>",
+            "<NewLeaseDuration>3600</NewLeaseDuration>",
+            "</u:AddPortMapping></s:Body></s:Envelope>",
+        ),
+        service = service,
+        external = external,
+        internal = internal,
+        local = local,
+    );
+    let headers = format!(
+        "Content-Type: text/xml; charset=\"utf-8\"\r\nSOAPAction: \"{service}#AddPortMapping\"\r\n"
+    );
+    http(&control, "POST", &headers, &body).await?;
+    println!("Forwarding TCP port {external} to {local}:{internal} for an hour");
+
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use tokio_ssdp::Client;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let st = std::env::args().nth(1).unwrap_or_else(|| "ssdp:all".into());
+    let mut search = Client::new()
+        .mx(2)
+        .recv_buffer_size(1 << 20)
+        .search(&st)
+        .await?;
+
+    while let Some(response) = search.next().await {
+        let response = response?;
+        println!(
+            "{} ({}) at {}",
+            response.usn,
+            response.server().unwrap_or("unknown server"),
+            response.location
+        );
+    }
+
+    let stats = search.stats();
+    println!(
+        "{} datagrams received, {} invalid, {} dropped",
+        stats.received,
+        stats.invalid,
+        stats
+            .dropped
+            .map_or_else(|| "unknown".to_owned(), |n| n.to_string())
+    );
+
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use std::time::{SystemTime, UNIX_EPOCH};
-use tokio_ssdp::{Device, Server};
+use tokio_ssdp::{Device, Server, UpnpVersion};
 
 const DEVICE_UUID: &str = "a1ab85e9-e299-4005-a427-f7e49cb1e119";
 
@@ -30,6 +30,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             "http://192.168.2.129:4399/desc.xml",
         ),
     ])
+    .upnp_version(UpnpVersion::V1_1)
     .extra_header("BOOTID.UPNP.ORG", unix_ts_secs().to_string())
     .extra_header("CONFIGID.UPNP.ORG", "1")
     .serve()?;
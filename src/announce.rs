@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+/// How `ssdp:alive` rounds are scheduled, see `Server::announce_strategy`.
+///
+/// The default sends every message once per round, all devices back to back, at the interval of
+/// `Server::announce_interval`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tokio_ssdp::{AnnounceStrategy, Server};
+///
+/// // Send everything twice, spread over up to 2 seconds, and refresh caches in time
+/// Server::new([])
+///   .max_age(1800)
+///   .announce_strategy(
+///       AnnounceStrategy::new()
+///           .copies(2)
+///           .jitter(Duration::from_secs(2))
+///           .half_max_age(true),
+///   );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnnounceStrategy {
+    pub(crate) copies: u32,
+    pub(crate) copy_spacing: Duration,
+    pub(crate) jitter: Duration,
+    pub(crate) half_max_age: bool,
+}
+
+impl Default for AnnounceStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnnounceStrategy {
+    /// Send each message once, without jitter.
+    pub fn new() -> Self {
+        Self {
+            copies: 1,
+            copy_spacing: Duration::from_millis(200),
+            jitter: Duration::ZERO,
+            half_max_age: false,
+        }
+    }
+
+    /// Send each round `copies` times, at least once.
+    ///
+    /// The UPnP Device Architecture recommends sending announcements more than once, as UDP
+    /// datagrams get lost on busy or wireless networks.
+    pub fn copies(mut self, copies: u32) -> Self {
+        self.copies = copies.max(1);
+        self
+    }
+
+    /// Wait `spacing` between the copies of a round, defaults to 200ms.
+    pub fn copy_spacing(mut self, spacing: Duration) -> Self {
+        self.copy_spacing = spacing;
+        self
+    }
+
+    /// Delay the messages of each device by a random time below `jitter` from the start of the
+    /// round, so hosts and devices announcing together do not send in bursts.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Start rounds early enough that the last copy goes out before half the `max-age` elapsed,
+    /// even with the jitter, so a lost round does not let caches lapse. Defaults to `false`.
+    pub fn half_max_age(mut self, enabled: bool) -> Self {
+        self.half_max_age = enabled;
+        self
+    }
+
+    /// The time from the start of a round to its last message, besides the pacing of packets.
+    pub(crate) fn round_length(&self) -> Duration {
+        self.jitter.saturating_add(
+            self.copy_spacing
+                .saturating_mul(self.copies.saturating_sub(1)),
+        )
+    }
+}
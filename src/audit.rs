@@ -0,0 +1,164 @@
+use std::{
+    fmt::{Debug, Write as _},
+    io::Write,
+    net::SocketAddr,
+    sync::mpsc,
+    thread::JoinHandle,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+
+/// The type of datagram an [`AuditRecord`] describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AuditKind {
+    /// A multicast `ssdp:alive` announcement.
+    Alive,
+    /// A multicast `ssdp:byebye` announcement.
+    Byebye,
+    /// A multicast `ssdp:update` announcement.
+    Update,
+    /// A multicast announcement with an `NTS` of its own, see `AnnouncementKind::Custom`.
+    Custom,
+    /// A unicast response to an `M-SEARCH`.
+    SearchResponse,
+}
+
+impl AuditKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditKind::Alive => "alive",
+            AuditKind::Byebye => "byebye",
+            AuditKind::Update => "update",
+            AuditKind::Custom => "custom",
+            AuditKind::SearchResponse => "search-response",
+        }
+    }
+}
+
+/// A datagram sent by the server.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AuditRecord {
+    /// What was sent.
+    pub kind: AuditKind,
+    /// The `NT` or `ST` of the message.
+    pub search_target: String,
+    /// The `USN` of the message, `None` for template responses.
+    pub usn: Option<String>,
+    /// Where the datagram was sent.
+    pub destination: SocketAddr,
+    /// When the datagram was sent.
+    pub timestamp: SystemTime,
+    /// When the `M-SEARCH` this responds to was received, `None` for announcements.
+    pub search_received: Option<SystemTime>,
+    /// The [`id`](crate::SearchRequest::id) of the `M-SEARCH` this responds to, `None` for
+    /// announcements.
+    pub search_id: Option<u64>,
+}
+
+/// Receives a record of every datagram the server sends, see `Server::audit_sink`.
+///
+/// Records are delivered from the sending task, so implementations should not block for long.
+pub trait AuditSink: Debug + Send + Sync {
+    /// Called after a datagram was sent successfully.
+    fn record(&self, record: &AuditRecord);
+}
+
+/// An [`AuditSink`] writing one JSON object per line.
+///
+/// Timestamps are milliseconds since the Unix epoch. The lines are written and flushed from a
+/// thread of their own, so a slow writer does not hold up the server's tasks.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{JsonLines, Server};
+///
+/// Server::new([])
+///   .audit_sink(JsonLines::new(std::io::stderr()));
+/// ```
+#[derive(Debug)]
+pub struct JsonLines<W> {
+    lines: mpsc::Sender<String>,
+    writer: JoinHandle<W>,
+}
+
+impl<W: Write + Send + 'static> JsonLines<W> {
+    /// Write records to `writer`, e.g. a file opened in append mode.
+    pub fn new(mut writer: W) -> Self {
+        let (lines, received) = mpsc::channel::<String>();
+        let writer = std::thread::spawn(move || {
+            for line in received {
+                if let Err(e) = writer
+                    .write_all(line.as_bytes())
+                    .and_then(|()| writer.flush())
+                {
+                    warn!("Failed to write audit record: {e}");
+                }
+            }
+            writer
+        });
+        Self { lines, writer }
+    }
+
+    /// Returns the underlying writer, once the records sent so far are written.
+    pub fn into_inner(self) -> W {
+        drop(self.lines);
+        self.writer
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+}
+
+impl<W: Write + Debug + Send> AuditSink for JsonLines<W> {
+    fn record(&self, record: &AuditRecord) {
+        let mut line = String::with_capacity(256);
+        let _ = write!(line, "{{\"kind\":\"{}\",\"st\":", record.kind.as_str());
+        write_json_str(&mut line, &record.search_target);
+        line.push_str(",\"usn\":");
+        match &record.usn {
+            Some(usn) => write_json_str(&mut line, usn),
+            None => line.push_str("null"),
+        }
+        let _ = write!(
+            line,
+            ",\"destination\":\"{}\",\"timestamp_ms\":{}",
+            record.destination,
+            unix_millis(record.timestamp)
+        );
+        if let Some(received) = record.search_received {
+            let _ = write!(line, ",\"search_received_ms\":{}", unix_millis(received));
+        }
+        if let Some(id) = record.search_id {
+            let _ = write!(line, ",\"search_id\":{id}");
+        }
+        line.push_str("}\n");
+
+        // Only fails if the writing thread panicked
+        if self.lines.send(line).is_err() {
+            warn!("Failed to write audit record: the writer is gone");
+        }
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis())
+}
+
+/// Append `value` as a JSON string literal.
+fn write_json_str(buf: &mut String, value: &str) {
+    buf.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(buf, "\\u{:04x}", c as u32);
+            }
+            c => buf.push(c),
+        }
+    }
+    buf.push('"');
+}
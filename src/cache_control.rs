@@ -0,0 +1,97 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::header;
+
+/// A parsed `CACHE-CONTROL` header.
+///
+/// Parsing is lenient: directive names are case-insensitive, whitespace around `=` is ignored and
+/// directives that are unknown or carry an unparsable value are kept in `extensions`.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::CacheControl;
+/// use std::time::Duration;
+///
+/// let cc: CacheControl = "MAX-AGE = 1800, no-cache, x-vendor=\"a\"".parse().unwrap();
+/// assert_eq!(cc.max_age, Some(Duration::from_secs(1800)));
+/// assert_eq!(cc.extensions, [("no-cache".to_owned(), None), ("x-vendor".to_owned(), Some("a".to_owned()))]);
+///
+/// let cc: CacheControl = "s-maxage=60".parse().unwrap();
+/// assert_eq!(cc.effective_max_age(), Some(Duration::from_secs(60)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CacheControl {
+    /// The `max-age` directive.
+    pub max_age: Option<Duration>,
+    /// The `s-maxage` directive.
+    pub s_maxage: Option<Duration>,
+    /// Every other directive with its unquoted value, in order of appearance.
+    pub extensions: Vec<(String, Option<String>)>,
+}
+
+impl CacheControl {
+    /// Parse a `CACHE-CONTROL` header value, this never fails.
+    pub fn parse(value: &str) -> Self {
+        let mut cc = Self::default();
+
+        for directive in header::split_list(value) {
+            let (name, value) = header::split_pair(directive);
+            let seconds = value.as_deref().and_then(|v| v.parse().ok());
+
+            match seconds {
+                Some(secs) if name.eq_ignore_ascii_case("max-age") => {
+                    cc.max_age = Some(Duration::from_secs(secs));
+                }
+                Some(secs) if name.eq_ignore_ascii_case("s-maxage") => {
+                    cc.s_maxage = Some(Duration::from_secs(secs));
+                }
+                _ => cc
+                    .extensions
+                    .push((name.to_ascii_lowercase(), value.map(|v| v.into_owned()))),
+            }
+        }
+
+        cc
+    }
+
+    /// How long the advertisement is valid: `max-age`, falling back to `s-maxage`.
+    pub fn effective_max_age(&self) -> Option<Duration> {
+        self.max_age.or(self.s_maxage)
+    }
+}
+
+impl FromStr for CacheControl {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(s))
+    }
+}
+
+impl fmt::Display for CacheControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "max-age={}", max_age.as_secs())?;
+            sep = ", ";
+        }
+        if let Some(s_maxage) = self.s_maxage {
+            write!(f, "{sep}s-maxage={}", s_maxage.as_secs())?;
+            sep = ", ";
+        }
+        for (name, value) in &self.extensions {
+            match value {
+                Some(value) if value.contains([' ', ',', ';', '"']) => {
+                    write!(f, "{sep}{name}=\"{}\"", value.replace('"', "\\\""))?
+                }
+                Some(value) => write!(f, "{sep}{name}={value}")?,
+                None => write!(f, "{sep}{name}")?,
+            }
+            sep = ", ";
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,446 @@
+use std::{
+    borrow::Cow,
+    fmt::Debug,
+    io::{self, BufRead, Write},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    task::{Context, Poll},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use tokio::{io::ReadBuf, time::Instant};
+
+use crate::{
+    Clock, Direction, Error, HandlerDecision, NotifyRequest, RequestHandler, Result, SsdpSocket,
+};
+
+/// The largest UDP payload, and so the largest datagram a capture can hold.
+const MAX_DATAGRAM: usize = 65535;
+
+/// How many records wait for the writer before new ones are dropped, see [`Capture`].
+const CAPTURE_BACKLOG: usize = 1024;
+
+/// A datagram sent or received by a server, as captured by `Server::capture`.
+///
+/// A capture is a sequence of records, each a line with the time in microseconds since the Unix
+/// epoch, `in` or `out`, the local and the peer address and the length of the datagram, followed
+/// by the datagram and a line feed:
+///
+/// ```text
+/// 1700000000123456 in 0.0.0.0:1900 192.0.2.50:53124 94
+/// M-SEARCH * HTTP/1.1
+/// ...
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct CaptureRecord {
+    /// When the datagram was sent or received, by the server's clock.
+    pub timestamp: SystemTime,
+    /// Whether the server received or sent the datagram.
+    pub direction: Direction,
+    /// The address of the server's socket, unspecified if it is bound to every interface.
+    pub local: SocketAddr,
+    /// The sender of a received datagram, the destination of a sent one.
+    pub peer: SocketAddr,
+    /// The datagram.
+    pub data: Vec<u8>,
+}
+
+impl CaptureRecord {
+    /// The datagram as text, with invalid UTF-8 replaced.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+
+    /// Write the record in the capture format.
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let micros = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_micros());
+        let direction = match self.direction {
+            Direction::Inbound => "in",
+            Direction::Outbound => "out",
+        };
+        let header = format!(
+            "{micros} {direction} {} {} {}\n",
+            self.local,
+            self.peer,
+            self.data.len()
+        );
+        let mut record = Vec::with_capacity(header.len() + self.data.len() + 1);
+        record.extend_from_slice(header.as_bytes());
+        record.extend_from_slice(&self.data);
+        record.push(b'\n');
+        writer.write_all(&record)
+    }
+}
+
+/// Reads the [`CaptureRecord`]s of a capture, in the order they were written.
+///
+/// # Examples
+/// ```
+/// use std::{io::Cursor, net::SocketAddr};
+/// use tokio_ssdp::{CaptureReader, Direction};
+///
+/// let capture = "1700000000000000 in 0.0.0.0:1900 192.0.2.50:53124 5\nhello\n";
+/// let records = CaptureReader::new(Cursor::new(capture))
+///     .collect::<tokio_ssdp::Result<Vec<_>>>()
+///     .unwrap();
+/// assert_eq!(records[0].direction, Direction::Inbound);
+/// assert_eq!(records[0].peer, "192.0.2.50:53124".parse::<SocketAddr>().unwrap());
+/// assert_eq!(records[0].text(), "hello");
+/// ```
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    reader: R,
+    line: usize,
+}
+
+impl<R: BufRead> CaptureReader<R> {
+    /// Read the capture from `reader`, e.g. a `BufReader` over the captured file.
+    pub fn new(reader: R) -> Self {
+        Self { reader, line: 0 }
+    }
+
+    fn read_record(&mut self) -> Result<Option<CaptureRecord>> {
+        let mut header = String::new();
+        if self.reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        let malformed = |line: usize, why: &str| {
+            Error::Protocol(format!("malformed capture record on line {line}: {why}"))
+        };
+
+        let fields: Vec<&str> = header.split_ascii_whitespace().collect();
+        let [micros, direction, local, peer, len] = fields[..] else {
+            return Err(malformed(self.line, "expected 5 fields"));
+        };
+        let micros = micros
+            .parse()
+            .map_err(|_| malformed(self.line, "invalid timestamp"))?;
+        let direction = match direction {
+            "in" => Direction::Inbound,
+            "out" => Direction::Outbound,
+            _ => return Err(malformed(self.line, "direction is neither in nor out")),
+        };
+        let local = local
+            .parse()
+            .map_err(|_| malformed(self.line, "invalid local address"))?;
+        let peer = peer
+            .parse()
+            .map_err(|_| malformed(self.line, "invalid peer address"))?;
+        let len: usize = len
+            .parse()
+            .map_err(|_| malformed(self.line, "invalid length"))?;
+        if len > MAX_DATAGRAM {
+            return Err(malformed(self.line, "length above the UDP maximum"));
+        }
+
+        let mut data = vec![0; len + 1];
+        self.reader.read_exact(&mut data)?;
+        if data.pop() != Some(b'\n') {
+            return Err(malformed(self.line, "datagram longer than its length"));
+        }
+        self.line += data.iter().filter(|&&b| b == b'\n').count() + 1;
+
+        Ok(Some(CaptureRecord {
+            timestamp: UNIX_EPOCH + Duration::from_micros(micros),
+            direction,
+            local,
+            peer,
+            data,
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Run the datagrams a server received, read from the capture `reader`, through `handler`,
+/// returning each with the handler's decision.
+///
+/// Datagrams are parsed as [`NotifyRequest`]s from their sender, like the server does before
+/// running its request handlers, and those it cannot parse are skipped, as are sent ones. This
+/// reproduces how a handler deals with the traffic of a misbehaving device, without the device.
+///
+/// # Examples
+/// ```
+/// use std::io::Cursor;
+/// use tokio_ssdp::{HandlerDecision, NotifyRequest, RequestHandler, replay};
+///
+/// /// Drops the searches of a TV flooding the network.
+/// #[derive(Debug)]
+/// struct MuteTv;
+///
+/// impl RequestHandler for MuteTv {
+///     fn handle(&self, request: &mut NotifyRequest) -> HandlerDecision {
+///         match request.header("USER-AGENT") {
+///             Some(agent) if agent.contains("SmartTV") => HandlerDecision::Drop,
+///             _ => HandlerDecision::Continue,
+///         }
+///     }
+/// }
+///
+/// let search = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: ssdp:all\r\nUSER-AGENT: SmartTV/2.0\r\n\r\n";
+/// let capture = format!("1700000000000000 in 0.0.0.0:1900 192.0.2.77:50000 {}\n{search}\n", search.len());
+///
+/// let decisions = replay(Cursor::new(capture), &MuteTv).unwrap();
+/// assert!(matches!(decisions[0].1, HandlerDecision::Drop));
+/// ```
+pub fn replay<R: BufRead>(
+    reader: R,
+    handler: &dyn RequestHandler,
+) -> Result<Vec<(CaptureRecord, HandlerDecision)>> {
+    let mut decisions = vec![];
+    for record in CaptureReader::new(reader) {
+        let record = record?;
+        if let Some(decision) = handle(&record, handler) {
+            decisions.push((record, decision));
+        }
+    }
+    Ok(decisions)
+}
+
+/// How fast [`replay_paced`] replays a capture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum ReplaySpeed {
+    /// As fast as possible, ignoring the timestamps.
+    Unpaced,
+    /// At the pace the datagrams were captured, sped up by the factor: `Scaled(1.0)` replays in
+    /// real time, `Scaled(10.0)` ten times faster.
+    Scaled(f64),
+}
+
+/// Like [`replay`], but handing each datagram to `handler` at the time it was captured, relative
+/// to the first one, at `speed`.
+///
+/// Replaying a capture of a busy network in real time, or sped up, shows how a handler copes with
+/// realistic traffic, e.g. to catch a performance regression. Waits with the tokio timer, so a
+/// paused clock replays instantly.
+///
+/// # Examples
+/// ```
+/// use std::{io::Cursor, time::Duration};
+/// use tokio_ssdp::{HandlerDecision, NotifyRequest, ReplaySpeed, RequestHandler, replay_paced};
+///
+/// #[derive(Debug)]
+/// struct Pass;
+///
+/// impl RequestHandler for Pass {
+///     fn handle(&self, _: &mut NotifyRequest) -> HandlerDecision {
+///         HandlerDecision::Continue
+///     }
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread", start_paused = true)]
+/// # async fn main() -> tokio_ssdp::Result<()> {
+/// let search = "M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\nST: ssdp:all\r\n\r\n";
+/// let capture = format!(
+///     "1700000000000000 in 0.0.0.0:1900 192.0.2.77:50000 {len}\n{search}\n\
+///      1700000010000000 in 0.0.0.0:1900 192.0.2.77:50000 {len}\n{search}\n",
+///     len = search.len(),
+/// );
+///
+/// // Ten seconds apart when captured, one second apart ten times faster
+/// let start = tokio::time::Instant::now();
+/// let decisions = replay_paced(Cursor::new(capture), &Pass, ReplaySpeed::Scaled(10.0)).await?;
+/// assert_eq!(decisions.len(), 2);
+/// assert_eq!(start.elapsed(), Duration::from_secs(1));
+/// # Ok(())
+/// # }
+/// ```
+pub async fn replay_paced<R: BufRead>(
+    reader: R,
+    handler: &dyn RequestHandler,
+    speed: ReplaySpeed,
+) -> Result<Vec<(CaptureRecord, HandlerDecision)>> {
+    let start = Instant::now();
+    let mut first = None;
+    let mut decisions = vec![];
+    for record in CaptureReader::new(reader) {
+        let record = record?;
+        if let ReplaySpeed::Scaled(factor) = speed {
+            let first = *first.get_or_insert(record.timestamp);
+            let offset = record.timestamp.duration_since(first).unwrap_or_default();
+            if factor > 0.0 {
+                tokio::time::sleep_until(start + offset.div_f64(factor)).await;
+            }
+        }
+        if let Some(decision) = handle(&record, handler) {
+            decisions.push((record, decision));
+        }
+    }
+    Ok(decisions)
+}
+
+/// Run the datagram of `record` through `handler`, `None` if it was sent or cannot be parsed.
+fn handle(record: &CaptureRecord, handler: &dyn RequestHandler) -> Option<HandlerDecision> {
+    if record.direction != Direction::Inbound {
+        return None;
+    }
+    let mut request = NotifyRequest::parse(record.peer, &record.data).ok()?;
+    Some(handler.handle(&mut request))
+}
+
+/// Where a server writes its capture.
+///
+/// Records are written by a thread of their own, so a slow writer never blocks sending or
+/// receiving. The writer is flushed whenever it caught up, and records arriving while
+/// `CAPTURE_BACKLOG` of them wait are dropped.
+#[derive(Debug)]
+pub(crate) struct Capture {
+    tx: mpsc::SyncSender<CaptureRecord>,
+    dropped: AtomicU64,
+}
+
+impl Capture {
+    pub(crate) fn new<W: Write + Debug + Send + 'static>(writer: W) -> Self {
+        let (tx, rx) = mpsc::sync_channel(CAPTURE_BACKLOG);
+        let spawned = thread::Builder::new()
+            .name("ssdp-capture".into())
+            .spawn(move || write_records(writer, rx));
+        if let Err(e) = spawned {
+            warn!("Failed to start capturing: {e}");
+        }
+        Self {
+            tx,
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn write(&self, record: CaptureRecord) {
+        match self.tx.try_send(record) {
+            Ok(()) => {}
+            Err(mpsc::TrySendError::Full(_)) => {
+                if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    warn!("Capture writer falls behind, dropping records");
+                }
+            }
+            Err(mpsc::TrySendError::Disconnected(_)) => {}
+        }
+    }
+}
+
+/// Write the records received on `rx` to `writer` until the [`Capture`] is dropped.
+fn write_records(mut writer: impl Write, rx: mpsc::Receiver<CaptureRecord>) {
+    while let Ok(record) = rx.recv() {
+        let mut written = record.write_to(&mut writer);
+        // Flush once caught up, not for every record of a burst
+        while written.is_ok() {
+            let Ok(record) = rx.try_recv() else {
+                break;
+            };
+            written = record.write_to(&mut writer);
+        }
+        if let Err(e) = written.and_then(|()| writer.flush()) {
+            warn!("Failed to write capture record: {e}");
+        }
+    }
+}
+
+/// A socket writing every datagram it sends and receives to a [`Capture`].
+#[derive(Debug)]
+pub(crate) struct CapturingSocket {
+    inner: Arc<dyn SsdpSocket>,
+    local: SocketAddr,
+    capture: Arc<Capture>,
+    clock: Arc<dyn Clock>,
+}
+
+impl CapturingSocket {
+    pub(crate) fn wrap(
+        inner: Arc<dyn SsdpSocket>,
+        capture: &Arc<Capture>,
+        clock: &Arc<dyn Clock>,
+    ) -> Arc<dyn SsdpSocket> {
+        let local = inner
+            .local_addr()
+            .unwrap_or_else(|_| (Ipv4Addr::UNSPECIFIED, 0).into());
+        Arc::new(Self {
+            inner,
+            local,
+            capture: Arc::clone(capture),
+            clock: Arc::clone(clock),
+        })
+    }
+
+    fn record(&self, direction: Direction, peer: SocketAddr, data: &[u8]) {
+        self.capture.write(CaptureRecord {
+            timestamp: self.clock.now(),
+            direction,
+            local: self.local,
+            peer,
+            data: data.to_vec(),
+        });
+    }
+}
+
+impl SsdpSocket for CapturingSocket {
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<SocketAddr>> {
+        let filled = buf.filled().len();
+        let res = self.inner.poll_recv_from(cx, buf);
+        if let Poll::Ready(Ok(peer)) = res {
+            self.record(Direction::Inbound, peer, &buf.filled()[filled..]);
+        }
+        res
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        let res = self.inner.poll_send_to(cx, buf, target);
+        if let Poll::Ready(Ok(n)) = res {
+            self.record(Direction::Outbound, target, &buf[..n]);
+        }
+        res
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.inner.join_multicast_v4(group, interface)
+    }
+
+    fn leave_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.inner.leave_multicast_v4(group, interface)
+    }
+
+    fn join_multicast_v6(&self, group: Ipv6Addr, index: u32) -> io::Result<()> {
+        self.inner.join_multicast_v6(group, index)
+    }
+
+    fn leave_multicast_v6(&self, group: Ipv6Addr, index: u32) -> io::Result<()> {
+        self.inner.leave_multicast_v6(group, index)
+    }
+
+    fn set_multicast_if_v4(&self, interface: Ipv4Addr) -> io::Result<()> {
+        self.inner.set_multicast_if_v4(interface)
+    }
+
+    fn set_multicast_if_v6(&self, index: u32) -> io::Result<()> {
+        self.inner.set_multicast_if_v6(index)
+    }
+}
@@ -0,0 +1,478 @@
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    task::Poll,
+    time::Duration,
+};
+
+use log::{debug, error, info, warn};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tokio::{io::ReadBuf, net::UdpSocket, sync::Mutex};
+
+use crate::{Error, ResponseSource, Result, SSDP_ADDR, SsdpSocket, interface};
+
+/// The link-local IPv6 SSDP multicast group.
+pub(crate) const SSDP_ADDR_V6_LINK: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xc);
+/// The site-local IPv6 SSDP multicast group.
+pub(crate) const SSDP_ADDR_V6_SITE: Ipv6Addr = Ipv6Addr::new(0xff05, 0, 0, 0, 0, 0, 0, 0xc);
+
+/// Socket settings shared by the channels of a server.
+#[derive(Debug, Clone)]
+pub(crate) struct ChannelOptions {
+    /// The port to listen on and announce to.
+    pub(crate) port: u16,
+    /// The multicast TTL (IPv4) or hop limit (IPv6), the OS default if `None`.
+    pub(crate) ttl: Option<u32>,
+    /// The unicast TTL (IPv4) or hop limit (IPv6) of search responses, the OS default if `None`.
+    pub(crate) response_ttl: Option<u32>,
+    /// The socket search responses are sent from.
+    pub(crate) source: ResponseSource,
+    /// Set `SO_REUSEPORT` right away, not only when the port is taken.
+    pub(crate) reuse_port: bool,
+    /// Whether multicast datagrams sent loop back to this host, the OS default if `None`.
+    pub(crate) multicast_loop: Option<bool>,
+    /// Expect another program on the port: send responses from a socket of their own and, on
+    /// Windows, fall back to binding the interface address.
+    pub(crate) port_sharing: bool,
+    /// Also bind the IPv4 interface addresses, receiving the searches sent straight to them.
+    pub(crate) unicast_search: bool,
+    /// The network device the sockets are bound to (`SO_BINDTODEVICE`).
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub(crate) bind_device: Option<String>,
+}
+
+/// An interface a channel joins its groups on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Interface {
+    /// An IPv4 interface by address, unspecified letting the OS pick.
+    V4(Ipv4Addr),
+    /// An IPv6 interface by index, `0` letting the OS pick.
+    V6(u32),
+}
+
+impl Interface {
+    /// The address of the interface, if known.
+    pub(crate) fn addr(&self) -> Option<IpAddr> {
+        match self {
+            Interface::V4(ip) if !ip.is_unspecified() => Some((*ip).into()),
+            _ => None,
+        }
+    }
+}
+
+/// The multicast socket of one address family and the groups announcements are sent to.
+#[derive(Debug)]
+pub(crate) struct Channel {
+    /// The socket bound to the SSDP port, receiving searches and sending announcements.
+    pub(crate) socket: Arc<dyn SsdpSocket>,
+    /// The socket search responses are sent from.
+    pub(crate) responder: Arc<dyn SsdpSocket>,
+    /// Sockets bound to the interface addresses on the SSDP port, receiving the unicast searches
+    /// that would reach another program sharing the port instead.
+    pub(crate) unicast: Vec<Arc<dyn SsdpSocket>>,
+    /// The multicast groups joined, with the SSDP port.
+    pub(crate) groups: Vec<SocketAddr>,
+    /// The interfaces the groups are joined on.
+    pub(crate) interfaces: Vec<Interface>,
+    /// Serializes switching the outgoing multicast interface and sending.
+    send_lock: Mutex<()>,
+}
+
+impl Channel {
+    /// Join the IPv4 group on the interfaces with addresses `ips`, listening on `options.port`.
+    ///
+    /// The interfaces the group cannot be joined on are left out, failing only if none is left.
+    pub(crate) fn v4(ips: &[Ipv4Addr], options: &ChannelOptions) -> Result<Self> {
+        let addr = (Ipv4Addr::UNSPECIFIED, options.port).into();
+        let fallback = match ips {
+            [ip] if !ip.is_unspecified() => Some((*ip, options.port).into()),
+            _ => None,
+        };
+        let s = bind_shared(Domain::IPV4, addr, fallback, options)?;
+        // One interface without multicast must not keep the server off the others
+        let mut joined = Vec::with_capacity(ips.len());
+        let mut failure = None;
+        for ip in ips {
+            match s.join_multicast_v4(&SSDP_ADDR, ip) {
+                Ok(()) => joined.push(*ip),
+                Err(e) => {
+                    warn!("Not joining {SSDP_ADDR} on {ip}: {e}");
+                    failure = Some(e);
+                }
+            }
+        }
+        if let (true, Some(e)) = (joined.is_empty(), failure) {
+            return Err(e.into());
+        }
+        let ips = &joined[..];
+        if let Some(ttl) = options.ttl {
+            s.set_multicast_ttl_v4(ttl)?;
+        }
+        if let Some(enabled) = options.multicast_loop {
+            s.set_multicast_loop_v4(enabled)?;
+        }
+
+        let bound = s.local_addr()?.as_socket();
+        let interfaces = ips.iter().copied().map(Interface::V4).collect();
+        let mut channel = Self::new(
+            s,
+            vec![(SSDP_ADDR, options.port).into()],
+            interfaces,
+            options,
+        )?;
+        if options.unicast_search {
+            channel.unicast = ips
+                .iter()
+                .map(|ip| SocketAddr::from((*ip, options.port)))
+                .filter(|addr| !addr.ip().is_unspecified() && Some(*addr) != bound)
+                .filter_map(|addr| bind_unicast(addr, options))
+                .collect();
+        }
+        Ok(channel)
+    }
+
+    /// Join the link-local and site-local IPv6 groups on the interfaces with the given indexes.
+    pub(crate) fn v6(indexes: &[u32], options: &ChannelOptions) -> Result<Self> {
+        let addr = (Ipv6Addr::UNSPECIFIED, options.port).into();
+        let s = bind_shared(Domain::IPV6, addr, None, options)?;
+        let groups = [SSDP_ADDR_V6_LINK, SSDP_ADDR_V6_SITE];
+        for group in groups.iter() {
+            for index in indexes {
+                s.join_multicast_v6(group, *index)?;
+            }
+        }
+
+        if let Some(hops) = options.ttl {
+            s.set_multicast_hops_v6(hops)?;
+        }
+        if let Some(enabled) = options.multicast_loop {
+            s.set_multicast_loop_v6(enabled)?;
+        }
+
+        let groups = groups.map(|group| (group, options.port).into()).to_vec();
+        let interfaces = indexes.iter().copied().map(Interface::V6).collect();
+        Self::new(s, groups, interfaces, options)
+    }
+
+    fn new(
+        socket: Socket,
+        groups: Vec<SocketAddr>,
+        interfaces: Vec<Interface>,
+        options: &ChannelOptions,
+    ) -> Result<Self> {
+        if let [interface] = interfaces[..] {
+            set_multicast_if(&socket, interface)?;
+        }
+
+        let socket = Arc::new(UdpSocket::from_std(socket.into())?);
+        info!("Listening on {}", socket.local_addr()?);
+        // Only applies to unicast, so the multicast TTL of a shared socket is left alone
+        let set_response_ttl = |s: &UdpSocket| -> io::Result<()> {
+            match (options.response_ttl, s.local_addr()?) {
+                (Some(ttl), SocketAddr::V4(_)) => s.set_ttl(ttl),
+                (Some(ttl), SocketAddr::V6(_)) => SockRef::from(s).set_unicast_hops_v6(ttl),
+                (None, _) => Ok(()),
+            }
+        };
+
+        let responder: Arc<dyn SsdpSocket> = match options.source {
+            ResponseSource::Multicast => {
+                set_response_ttl(&socket)?;
+                Arc::clone(&socket) as _
+            }
+            ResponseSource::Ephemeral => {
+                let local = match interfaces[..] {
+                    [Interface::V4(ip)] => IpAddr::V4(ip),
+                    [Interface::V4(_), ..] => Ipv4Addr::UNSPECIFIED.into(),
+                    _ => Ipv6Addr::UNSPECIFIED.into(),
+                };
+                let s = Socket::new(Domain::for_address((local, 0).into()), Type::DGRAM, None)?;
+                #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+                if let Some(device) = &options.bind_device {
+                    s.bind_device(Some(device.as_bytes()))?;
+                }
+                s.set_nonblocking(true)?;
+                s.bind(&SocketAddr::new(local, 0).into())?;
+                let s = UdpSocket::from_std(s.into())?;
+                info!("Sending search responses from {}", s.local_addr()?);
+                set_response_ttl(&s)?;
+                Arc::new(s)
+            }
+        };
+
+        Ok(Self {
+            socket: socket as _,
+            responder,
+            unicast: vec![],
+            groups,
+            interfaces,
+            send_lock: Mutex::new(()),
+        })
+    }
+
+    /// A channel receiving and sending through `socket`, multicasting to the IPv4 group on
+    /// `port` through the interface the transport picks.
+    pub(crate) fn from_socket(socket: Arc<dyn SsdpSocket>, port: u16) -> Self {
+        Self {
+            responder: Arc::clone(&socket),
+            socket,
+            unicast: vec![],
+            groups: vec![(SSDP_ADDR, port).into()],
+            interfaces: vec![Interface::V4(Ipv4Addr::UNSPECIFIED)],
+            send_lock: Mutex::new(()),
+        }
+    }
+
+    /// Replace every socket of the channel with what `wrap` makes of it.
+    pub(crate) fn wrap_sockets(
+        &mut self,
+        wrap: impl Fn(Arc<dyn SsdpSocket>) -> Arc<dyn SsdpSocket>,
+    ) {
+        self.socket = wrap(Arc::clone(&self.socket));
+        self.responder = wrap(Arc::clone(&self.responder));
+        for socket in self.unicast.iter_mut() {
+            *socket = wrap(Arc::clone(socket));
+        }
+    }
+
+    /// Send `message` to the multicast `group` through `interface`.
+    pub(crate) async fn send_multicast(
+        &self,
+        message: &[u8],
+        group: SocketAddr,
+        interface: Interface,
+    ) -> io::Result<()> {
+        let _guard = self.send_lock.lock().await;
+        if self.interfaces.len() > 1 {
+            match interface {
+                Interface::V4(ip) if !ip.is_unspecified() => self.socket.set_multicast_if_v4(ip)?,
+                Interface::V6(index) if index != 0 => self.socket.set_multicast_if_v6(index)?,
+                _ => {}
+            }
+        }
+        self.socket.send_to(message, group).await?;
+        Ok(())
+    }
+
+    /// Leave and join the multicast groups again, e.g. after the interface was reset.
+    pub(crate) fn rejoin(&self) {
+        for group in self.groups.iter() {
+            for interface in self.interfaces.iter() {
+                let res = match (group.ip(), interface) {
+                    (IpAddr::V4(group), Interface::V4(ip)) => {
+                        let _ = self.socket.leave_multicast_v4(group, *ip);
+                        self.socket.join_multicast_v4(group, *ip)
+                    }
+                    (IpAddr::V6(group), Interface::V6(index)) => {
+                        let _ = self.socket.leave_multicast_v6(group, *index);
+                        self.socket.join_multicast_v6(group, *index)
+                    }
+                    _ => continue,
+                };
+                if let Err(e) = res {
+                    error!("Re-joining multicast group {group} failed: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Make `interface` the outgoing interface for multicast datagrams of `socket`.
+fn set_multicast_if(socket: &Socket, interface: Interface) -> io::Result<()> {
+    match interface {
+        Interface::V4(ip) if !ip.is_unspecified() => socket.set_multicast_if_v4(&ip),
+        Interface::V6(index) if index != 0 => socket.set_multicast_if_v6(index),
+        _ => Ok(()),
+    }
+}
+
+/// Join the SSDP groups on the interfaces with addresses `ips`, on IPv6 too if `ipv6` is set.
+pub(crate) fn bind(ips: &[Ipv4Addr], options: &ChannelOptions, ipv6: bool) -> Result<Vec<Channel>> {
+    let mut channels = vec![Channel::v4(ips, options)?];
+    if ipv6 {
+        let mut indexes: Vec<u32> = ips.iter().map(|ip| interface::index_of(*ip)).collect();
+        indexes.sort_unstable();
+        indexes.dedup();
+        channels.push(Channel::v6(&indexes, options)?);
+    }
+    Ok(channels)
+}
+
+/// Like `recv_any`, but re-join the multicast groups whenever nothing arrived for `watchdog`.
+pub(crate) async fn recv_watched(
+    channels: &[Channel],
+    buf: &mut [u8],
+    watchdog: Option<Duration>,
+) -> io::Result<(usize, SocketAddr, usize)> {
+    let Some(period) = watchdog else {
+        return recv_any(channels, buf).await;
+    };
+
+    loop {
+        match tokio::time::timeout(period, recv_any(channels, buf)).await {
+            Ok(res) => return res,
+            Err(_) => {
+                warn!("No SSDP traffic for {period:?}, re-joining multicast groups");
+                for channel in channels.iter() {
+                    channel.rejoin();
+                }
+            }
+        }
+    }
+}
+
+/// Where `recv_any` starts polling, shared by every server as only the rotation matters.
+static NEXT_SOCKET: AtomicUsize = AtomicUsize::new(0);
+
+/// Receive a datagram from any of `channels`, returning its length, sender and channel index.
+pub(crate) async fn recv_any(
+    channels: &[Channel],
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, usize)> {
+    let count: usize = channels.iter().map(|c| 1 + c.unicast.len()).sum();
+    std::future::poll_fn(|cx| {
+        // Start at another socket each time, so a busy one cannot starve the rest
+        let start = NEXT_SOCKET.fetch_add(1, Ordering::Relaxed);
+        for n in 0..count {
+            let (i, socket) = nth_socket(channels, start.wrapping_add(n) % count);
+            let mut read = ReadBuf::new(buf);
+            if let Poll::Ready(res) = socket.poll_recv_from(cx, &mut read) {
+                return Poll::Ready(res.map(|addr| (read.filled().len(), addr, i)));
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// The `n`th receiving socket of `channels`, with the index of its channel.
+fn nth_socket(channels: &[Channel], mut n: usize) -> (usize, &Arc<dyn SsdpSocket>) {
+    for (i, channel) in channels.iter().enumerate() {
+        if n == 0 {
+            return (i, &channel.socket);
+        }
+        if let Some(socket) = channel.unicast.get(n - 1) {
+            return (i, socket);
+        }
+        n -= 1 + channel.unicast.len();
+    }
+    unreachable!("fewer sockets than counted")
+}
+
+/// Bind the SSDP port at `addr`.
+///
+/// If another program holds the port, binding is retried with `SO_REUSEPORT` where available,
+/// unless `options` asks for it from the start. With port sharing on Windows, where services bind
+/// the port without letting others share the wildcard address, the interface address `fallback`
+/// is tried last: sockets bound to it still receive the multicast traffic of the interface.
+/// Should that fail too, the owner cannot be sharing the port and [`Error::PortInUse`] names it.
+fn bind_shared(
+    domain: Domain,
+    addr: SocketAddr,
+    fallback: Option<SocketAddr>,
+    options: &ChannelOptions,
+) -> Result<Socket> {
+    let bind = |addr: SocketAddr, reuse_port: bool| -> io::Result<Socket> {
+        let s = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+        if domain == Domain::IPV6 {
+            s.set_only_v6(true)?;
+        }
+        s.set_reuse_address(true)?;
+        if reuse_port {
+            set_reuse_port(&s)?;
+        }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(device) = &options.bind_device {
+            s.bind_device(Some(device.as_bytes()))?;
+        }
+        s.set_nonblocking(true)?;
+        // Binding to a unicast address would filter out multicast traffic on some platforms
+        s.bind(&addr.into())?;
+        Ok(s)
+    };
+
+    // Windows refuses access to ports bound exclusively
+    let in_use = |e: &io::Error| {
+        e.kind() == io::ErrorKind::AddrInUse
+            || (cfg!(windows) && e.kind() == io::ErrorKind::PermissionDenied)
+    };
+    let res = match bind(addr, options.reuse_port) {
+        Err(e) if in_use(&e) => {
+            debug!("Port {} is in use, retrying with SO_REUSEPORT", addr.port());
+            bind(addr, true)
+        }
+        res => res,
+    };
+    let res = match (res, fallback) {
+        (Err(e), Some(fallback)) if in_use(&e) && options.port_sharing && cfg!(windows) => {
+            info!("Port {} is in use, binding {fallback} instead", addr.port());
+            bind(fallback, true)
+        }
+        (res, _) => res,
+    };
+
+    match res {
+        Err(e) if in_use(&e) => Err(Error::PortInUse {
+            port: addr.port(),
+            owner: interface::udp_port_owner(addr.port()),
+        }),
+        res => Ok(res?),
+    }
+}
+
+/// Bind the interface address `addr` on the SSDP port, for the unicast searches to it.
+///
+/// Unicast datagrams to a port bound by several sockets reach only one of them, but the most
+/// specific binding takes precedence over the wildcard address of the multicast socket. Failing
+/// to bind only loses those searches, so it is logged and `None` returned.
+fn bind_unicast(addr: SocketAddr, options: &ChannelOptions) -> Option<Arc<dyn SsdpSocket>> {
+    let bind = || -> io::Result<UdpSocket> {
+        let s = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+        s.set_reuse_address(true)?;
+        if options.reuse_port {
+            set_reuse_port(&s)?;
+        }
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(device) = &options.bind_device {
+            s.bind_device(Some(device.as_bytes()))?;
+        }
+        s.set_nonblocking(true)?;
+        s.bind(&addr.into())?;
+        let s = UdpSocket::from_std(s.into())?;
+        if let Some(ttl) = options.response_ttl {
+            s.set_ttl(ttl)?;
+        }
+        Ok(s)
+    };
+
+    match bind() {
+        Ok(s) => {
+            info!("Listening for unicast searches on {addr}");
+            Some(Arc::new(s))
+        }
+        Err(e) => {
+            warn!("Not listening for unicast searches on {addr}: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+))]
+fn set_reuse_port(s: &Socket) -> io::Result<()> {
+    s.set_reuse_port(true)
+}
+
+#[cfg(not(all(
+    unix,
+    not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+)))]
+fn set_reuse_port(_: &Socket) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,378 @@
+use std::{
+    future::Future,
+    net::Ipv4Addr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use log::debug;
+use tokio::{
+    io::ReadBuf,
+    net::UdpSocket,
+    time::{Instant, Sleep},
+};
+
+use crate::{MSearchBuilder, Result, SearchResponse, SsdpSocket};
+
+/// A control point discovering devices with `M-SEARCH` requests.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::Client;
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// let mut search = Client::new().mx(2).search("upnp:rootdevice").await?;
+/// while let Some(response) = search.next().await {
+///     let response = response?;
+///     println!("{} at {}", response.usn, response.location);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Client {
+    mx: u32,
+    ttl: u32,
+    interface: Ipv4Addr,
+    timeouts: Timeouts,
+    recv_buffer_size: Option<usize>,
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Client {
+    /// Create a client with the default settings.
+    pub fn new() -> Self {
+        Self {
+            mx: 1,
+            ttl: 2,
+            interface: Ipv4Addr::UNSPECIFIED,
+            timeouts: Timeouts::new(),
+            recv_buffer_size: None,
+        }
+    }
+
+    /// Set the `MX` header, the number of seconds devices may wait before responding, defaults
+    /// to 1. Must be at least 1, searching fails with `Error::Config` otherwise; the specification
+    /// recommends at most 5.
+    pub fn mx(mut self, mx: u32) -> Self {
+        self.mx = mx;
+        self
+    }
+
+    /// Set the IP time-to-live of the multicast request, defaults to 2 as recommended by the
+    /// specification.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Send the request through the interface with this address, defaults to letting the OS pick.
+    pub fn interface(mut self, ip: Ipv4Addr) -> Self {
+        self.interface = ip;
+        self
+    }
+
+    /// Set how long to collect responses, defaults to `MX` plus one second, like
+    /// `Timeouts::search`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.search = Some(timeout);
+        self
+    }
+
+    /// Set every timeout of the client at once, e.g. from an application-wide latency budget.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use tokio_ssdp::{Client, Timeouts};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let timeouts = Timeouts::new()
+    ///     .search(Duration::from_secs(3))
+    ///     .read(Duration::from_millis(500));
+    /// let mut search = Client::new().timeouts(timeouts).search("ssdp:all").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Set the size of the socket receive buffer (`SO_RCVBUF`) in bytes, defaults to the OS
+    /// default.
+    ///
+    /// Responses arriving while the buffer is full are dropped by the kernel, raise it for large
+    /// `ssdp:all` sweeps. The OS may cap or round the size.
+    pub fn recv_buffer_size(mut self, bytes: usize) -> Self {
+        self.recv_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Multicast an `M-SEARCH` for `search_target` and collect the responses.
+    ///
+    /// Use `ssdp:all` to find every device and service, `upnp:rootdevice` for root devices only.
+    pub async fn search(&self, search_target: &str) -> Result<Search> {
+        let socket = std::net::UdpSocket::bind((self.interface, 0))?;
+        self.search_on(socket, search_target).await
+    }
+
+    /// Like `search`, but send the request from an already bound `socket`.
+    ///
+    /// This lets applications bind the socket themselves, e.g. to a specific port or with
+    /// platform-specific options. The client's TTL, interface and receive buffer settings are
+    /// applied to it.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::net::UdpSocket;
+    /// use tokio_ssdp::Client;
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let socket = UdpSocket::bind("0.0.0.0:50000")?;
+    /// let mut search = Client::new().search_on(socket, "ssdp:all").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search_on(
+        &self,
+        socket: std::net::UdpSocket,
+        search_target: &str,
+    ) -> Result<Search> {
+        socket.set_nonblocking(true)?;
+        let socket = UdpSocket::from_std(socket)?;
+        socket.set_multicast_ttl_v4(self.ttl)?;
+        if let Some(bytes) = self.recv_buffer_size {
+            let socket = socket2::SockRef::from(&socket);
+            socket.set_recv_buffer_size(bytes)?;
+            debug!(
+                "Receive buffer size is {} bytes",
+                socket.recv_buffer_size()?
+            );
+        }
+        if !self.interface.is_unspecified() {
+            socket2::SockRef::from(&socket).set_multicast_if_v4(&self.interface)?;
+        }
+
+        MSearchBuilder::new(search_target)
+            .mx(self.mx)
+            .send_via(&socket)
+            .await?;
+
+        let inode = socket_inode(&socket);
+        Ok(self.collect(Arc::new(socket), inode))
+    }
+
+    /// Like `search`, but send the request through `socket`, any [`SsdpSocket`] transport, e.g.
+    /// a `testing::MockSocket` with the `testing` feature.
+    ///
+    /// The client's interface is applied with `SsdpSocket::set_multicast_if_v4`, its TTL and
+    /// receive buffer settings are not.
+    pub async fn search_via<S: SsdpSocket + 'static>(
+        &self,
+        socket: S,
+        search_target: &str,
+    ) -> Result<Search> {
+        if !self.interface.is_unspecified() {
+            socket.set_multicast_if_v4(self.interface)?;
+        }
+
+        let request = MSearchBuilder::new(search_target).mx(self.mx);
+        let bytes = request.to_bytes()?;
+        debug!("Sending M-SEARCH: {}", String::from_utf8_lossy(&bytes));
+        let socket: Arc<dyn SsdpSocket> = Arc::new(socket);
+        socket.send_to(&bytes, request.destination()).await?;
+
+        Ok(self.collect(socket, None))
+    }
+
+    /// Collect the responses arriving on `socket` until the timeout, `inode` identifies the
+    /// socket to read the kernel drop counter of.
+    fn collect(&self, socket: Arc<dyn SsdpSocket>, inode: Option<u64>) -> Search {
+        let timeout = (self.timeouts.search).unwrap_or(Duration::from_secs(self.mx as u64 + 1));
+        let read = (self.timeouts.read).map(|read| (read, Box::pin(tokio::time::sleep(read))));
+
+        Search {
+            socket,
+            inode,
+            deadline: Box::pin(tokio::time::sleep(timeout)),
+            read,
+            buf: vec![0; 4096],
+            stats: SearchStats::default(),
+        }
+    }
+}
+
+/// How long a [`Client`] waits at each step, see `Client::timeouts`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tokio_ssdp::{Client, Timeouts};
+///
+/// // Collect for up to 3 seconds, but stop once responses dry up for half a second
+/// Client::new().timeouts(
+///     Timeouts::new()
+///         .search(Duration::from_secs(3))
+///         .read(Duration::from_millis(500)),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Timeouts {
+    search: Option<Duration>,
+    read: Option<Duration>,
+}
+
+impl Timeouts {
+    /// The default timeouts, see each setter.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the responses to a search for `timeout`, defaults to `MX` plus one second.
+    pub fn search(mut self, timeout: Duration) -> Self {
+        self.search = Some(timeout);
+        self
+    }
+
+    /// End a search early once no datagram arrived for `timeout`, defaults to waiting for the
+    /// whole search timeout.
+    pub fn read(mut self, timeout: Duration) -> Self {
+        self.read = Some(timeout);
+        self
+    }
+}
+
+/// Counters describing how a [`Search`] went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct SearchStats {
+    /// Datagrams received.
+    pub received: u64,
+    /// Datagrams skipped because they were not valid search responses.
+    pub invalid: u64,
+    /// Datagrams the kernel dropped because the receive buffer was full, `None` where this is not
+    /// available (only Linux reports it, and only for UDP sockets).
+    pub dropped: Option<u64>,
+}
+
+/// The responses to an `M-SEARCH`, ending once the client's timeout elapsed.
+///
+/// Malformed responses are logged and skipped, socket errors are yielded.
+#[derive(Debug)]
+pub struct Search {
+    socket: Arc<dyn SsdpSocket>,
+    inode: Option<u64>,
+    deadline: Pin<Box<Sleep>>,
+    /// How long to wait for the next datagram, and until when.
+    read: Option<(Duration, Pin<Box<Sleep>>)>,
+    buf: Vec<u8>,
+    stats: SearchStats,
+}
+
+impl Search {
+    /// The next response, `None` once the search is over.
+    pub async fn next(&mut self) -> Option<Result<SearchResponse>> {
+        std::future::poll_fn(|cx| self.poll_response(cx)).await
+    }
+
+    /// The counters of this search so far.
+    pub fn stats(&self) -> SearchStats {
+        let dropped = self.inode.and_then(udp_drops);
+
+        SearchStats {
+            dropped,
+            ..self.stats
+        }
+    }
+
+    fn poll_response(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<SearchResponse>>> {
+        loop {
+            if self.deadline.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(None);
+            }
+            if let Some((read, idle)) = &mut self.read {
+                if idle.as_mut().poll(cx).is_ready() {
+                    debug!("Nothing received for {read:?}, ending the search");
+                    return Poll::Ready(None);
+                }
+            }
+
+            let mut buf = ReadBuf::new(&mut self.buf);
+            let addr = match ready!(self.socket.poll_recv_from(cx, &mut buf)) {
+                Ok(addr) => addr,
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            };
+            self.stats.received += 1;
+            if let Some((read, idle)) = &mut self.read {
+                idle.as_mut().reset(Instant::now() + *read);
+            }
+
+            match SearchResponse::parse(addr, buf.filled()) {
+                Ok(response) => return Poll::Ready(Some(Ok(response))),
+                Err(e) => {
+                    self.stats.invalid += 1;
+                    debug!("Ignoring invalid search response from {addr}: {e}");
+                }
+            }
+        }
+    }
+}
+
+impl Stream for Search {
+    type Item = Result<SearchResponse>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_response(cx)
+    }
+}
+
+/// The inode of `socket`, which identifies it in `/proc/net/udp` even if other sockets share its
+/// port.
+#[cfg(target_os = "linux")]
+fn socket_inode(socket: &UdpSocket) -> Option<u64> {
+    use std::os::fd::AsRawFd;
+
+    // The descriptor links to `socket:[<inode>]`
+    let link = std::fs::read_link(format!("/proc/self/fd/{}", socket.as_raw_fd())).ok()?;
+    let inode = link.to_str()?.strip_prefix("socket:[")?.strip_suffix(']')?;
+    inode.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn socket_inode(_socket: &UdpSocket) -> Option<u64> {
+    None
+}
+
+/// The number of datagrams the kernel dropped for the UDP socket with the inode `inode`.
+#[cfg(target_os = "linux")]
+fn udp_drops(inode: u64) -> Option<u64> {
+    // Lines look like `sl local_address rem_address st ... inode ref pointer drops`
+    let inode = inode.to_string();
+    ["/proc/net/udp", "/proc/net/udp6"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .find_map(|table| {
+            table.lines().skip(1).find_map(|line| {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                if *fields.get(9)? == inode {
+                    fields.last()?.parse().ok()
+                } else {
+                    None
+                }
+            })
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn udp_drops(_inode: u64) -> Option<u64> {
+    None
+}
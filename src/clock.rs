@@ -0,0 +1,38 @@
+use std::{fmt::Debug, time::SystemTime};
+
+/// A source of wall-clock time, used for the `DATE` header of search responses.
+///
+/// Install a custom implementation with `Server::clock` to make responses reproducible.
+pub trait Clock: Debug + Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// The default [`Clock`], reading the system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A [`Clock`] always returning the same instant.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Clock, FixedClock};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let clock = FixedClock(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+/// assert_eq!(clock.now(), clock.now());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub SystemTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
@@ -0,0 +1,815 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{Debug, Write as _},
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use log::{debug, warn};
+use socket2::{Domain, Protocol, Socket, Type};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{Semaphore, watch},
+    task::JoinSet,
+    time::Instant,
+};
+
+use crate::{Device, Error, LocationResolver, Result};
+
+/// The largest request head the description server reads.
+const MAX_REQUEST: usize = 8192;
+
+/// How long the description server waits for a request head before closing the connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many connections the description server serves at once.
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long the description server waits after failing to accept a connection.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// What the UPnP device description says about a device besides its type, UUID and services.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Device, DeviceDescription};
+///
+/// let device = Device::new(
+///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+///     "urn:schemas-upnp-org:device:MediaServer:1",
+///     "http://192.168.1.100:8080/desc.xml",
+/// )
+/// .with_service("urn:schemas-upnp-org:service:ContentDirectory:1");
+///
+/// let xml = DeviceDescription::new("Living room", "ACME", "Media Box")
+///     .model_number("2")
+///     .to_xml(&device);
+/// assert!(xml.contains("<friendlyName>Living room</friendlyName>"));
+/// assert!(xml.contains("<UDN>uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043</UDN>"));
+/// assert!(xml.contains("<serviceId>urn:upnp-org:serviceId:ContentDirectory</serviceId>"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceDescription {
+    friendly_name: String,
+    manufacturer: String,
+    model_name: String,
+    manufacturer_url: Option<String>,
+    model_description: Option<String>,
+    model_number: Option<String>,
+    model_url: Option<String>,
+    serial_number: Option<String>,
+    presentation_url: Option<String>,
+    /// The language of the names given to `new`.
+    language: Option<String>,
+    /// The `friendlyName` and `manufacturer` by language tag.
+    localized: Vec<(String, String, String)>,
+}
+
+impl DeviceDescription {
+    /// Describe a device with the names the specification requires.
+    pub fn new(
+        friendly_name: impl Into<String>,
+        manufacturer: impl Into<String>,
+        model_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            friendly_name: friendly_name.into(),
+            manufacturer: manufacturer.into(),
+            model_name: model_name.into(),
+            manufacturer_url: None,
+            model_description: None,
+            model_number: None,
+            model_url: None,
+            serial_number: None,
+            presentation_url: None,
+            language: None,
+            localized: vec![],
+        }
+    }
+
+    /// Set the `manufacturerURL`.
+    pub fn manufacturer_url(mut self, url: impl Into<String>) -> Self {
+        self.manufacturer_url = Some(url.into());
+        self
+    }
+
+    /// Set the `modelDescription`.
+    pub fn model_description(mut self, description: impl Into<String>) -> Self {
+        self.model_description = Some(description.into());
+        self
+    }
+
+    /// Set the `modelNumber`.
+    pub fn model_number(mut self, number: impl Into<String>) -> Self {
+        self.model_number = Some(number.into());
+        self
+    }
+
+    /// Set the `modelURL`.
+    pub fn model_url(mut self, url: impl Into<String>) -> Self {
+        self.model_url = Some(url.into());
+        self
+    }
+
+    /// Set the `serialNumber`.
+    pub fn serial_number(mut self, serial: impl Into<String>) -> Self {
+        self.serial_number = Some(serial.into());
+        self
+    }
+
+    /// Set the `presentationURL`, the device's web interface.
+    pub fn presentation_url(mut self, url: impl Into<String>) -> Self {
+        self.presentation_url = Some(url.into());
+        self
+    }
+
+    /// Add the `friendlyName` and `manufacturer` in `language`, a tag such as `de` or `pt-BR`.
+    ///
+    /// `DescriptionServer` answers clients preferring the language in their `ACCEPT-LANGUAGE`
+    /// header with these names and a `CONTENT-LANGUAGE` header, others get the names of `new`.
+    /// `DescriptionServer::serve` fails with `Error::Config` if `language` is not a well-formed
+    /// tag.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, DeviceDescription};
+    ///
+    /// let device = Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:1",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// );
+    /// let description = DeviceDescription::new("Living room", "ACME", "Media Box")
+    ///     .language("en")
+    ///     .localized("de", "Wohnzimmer", "ACME GmbH");
+    ///
+    /// assert!(description.to_xml_in(&device, "de").contains("<friendlyName>Wohnzimmer</friendlyName>"));
+    /// assert!(description.to_xml_in(&device, "fr").contains("<friendlyName>Living room</friendlyName>"));
+    /// ```
+    pub fn localized(
+        mut self,
+        language: impl Into<String>,
+        friendly_name: impl Into<String>,
+        manufacturer: impl Into<String>,
+    ) -> Self {
+        self.localized
+            .push((language.into(), friendly_name.into(), manufacturer.into()));
+        self
+    }
+
+    /// Set the language of the names given to `new`, so clients preferring it over the localized
+    /// ones get them with a `CONTENT-LANGUAGE` header. Checked like the tags of `localized`.
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// The languages added with `localized`.
+    pub fn languages(&self) -> impl Iterator<Item = &str> {
+        self.localized
+            .iter()
+            .map(|(language, _, _)| language.as_str())
+    }
+
+    /// Check that the language tags are well-formed, they end up in `CONTENT-LANGUAGE` headers.
+    fn validate(&self) -> Result<()> {
+        match self
+            .language
+            .iter()
+            .map(String::as_str)
+            .chain(self.languages())
+            .find(|tag| !is_language_tag(tag))
+        {
+            Some(tag) => Err(Error::Config(format!("{tag:?} is not a language tag"))),
+            None => Ok(()),
+        }
+    }
+
+    /// The description document of the device tree rooted at `device`.
+    ///
+    /// `device`'s search target is the device type. Services get the `serviceId`
+    /// `urn:upnp-org:serviceId:<name>` and URLs below `/<uuid>/<name>/`, which the application
+    /// serves. Embedded devices are listed with the names of this description.
+    pub fn to_xml(&self, device: &Device) -> String {
+        self.document(device, (&self.friendly_name, &self.manufacturer))
+    }
+
+    /// The description document with the names in `language`, see `localized`.
+    ///
+    /// Falls back to `to_xml` for languages without names, tags are compared case-insensitively.
+    pub fn to_xml_in(&self, device: &Device, language: &str) -> String {
+        let names = self
+            .localized
+            .iter()
+            .find(|(tag, _, _)| tag.eq_ignore_ascii_case(language))
+            .map_or(
+                (&self.friendly_name, &self.manufacturer),
+                |(_, name, manufacturer)| (name, manufacturer),
+            );
+        self.document(device, (names.0, names.1))
+    }
+
+    fn document(&self, device: &Device, names: (&str, &str)) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<root xmlns=\"urn:schemas-upnp-org:device-1-0\">\n",
+            "<specVersion><major>1</major><minor>0</minor></specVersion>\n",
+        ));
+        self.write_device(&mut xml, device, names);
+        xml.push_str("</root>\n");
+        xml
+    }
+
+    fn write_device(&self, xml: &mut String, device: &Device, names: (&str, &str)) {
+        let (friendly_name, manufacturer) = names;
+        xml.push_str("<device>\n");
+        write_element(xml, "deviceType", &device.search_target);
+        write_element(xml, "friendlyName", friendly_name);
+        write_element(xml, "manufacturer", manufacturer);
+        let optional = [
+            ("manufacturerURL", &self.manufacturer_url),
+            ("modelDescription", &self.model_description),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                write_element(xml, name, value);
+            }
+        }
+        write_element(xml, "modelName", &self.model_name);
+        let optional = [
+            ("modelNumber", &self.model_number),
+            ("modelURL", &self.model_url),
+            ("serialNumber", &self.serial_number),
+        ];
+        for (name, value) in optional {
+            if let Some(value) = value {
+                write_element(xml, name, value);
+            }
+        }
+        write_element(xml, "UDN", &format!("uuid:{}", device.uuid));
+
+        if !device.services.is_empty() {
+            xml.push_str("<serviceList>\n");
+            for service in device.services.iter() {
+                let name = service_name(service);
+                let base = format!("/{}/{name}", device.uuid);
+                xml.push_str("<service>\n");
+                write_element(xml, "serviceType", service);
+                write_element(xml, "serviceId", &format!("urn:upnp-org:serviceId:{name}"));
+                write_element(xml, "SCPDURL", &format!("{base}/scpd.xml"));
+                write_element(xml, "controlURL", &format!("{base}/control"));
+                write_element(xml, "eventSubURL", &format!("{base}/event"));
+                xml.push_str("</service>\n");
+            }
+            xml.push_str("</serviceList>\n");
+        }
+        if !device.embedded.is_empty() {
+            xml.push_str("<deviceList>\n");
+            for embedded in device.embedded.iter() {
+                self.write_device(xml, embedded, names);
+            }
+            xml.push_str("</deviceList>\n");
+        }
+        if let Some(url) = &self.presentation_url {
+            write_element(xml, "presentationURL", url);
+        }
+        xml.push_str("</device>\n");
+    }
+}
+
+/// Serves the description documents of devices over HTTP.
+///
+/// Use [`DescriptionServer::locations`] as the server's `LocationResolver`, so the devices
+/// advertise the URL of their document on the address each interface is reached through.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::{DescriptionServer, Device, DeviceDescription, Server};
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// let device = Device::new(
+///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+///     "urn:schemas-upnp-org:device:MediaServer:1",
+///     "",
+/// );
+/// let descriptions = DescriptionServer::bind(0)?
+///     .describe(&device, DeviceDescription::new("Living room", "ACME", "Media Box"));
+///
+/// let server = Server::new([device]).location_resolver(descriptions.locations());
+/// tokio::spawn(descriptions.serve()?);
+/// server.serve()?.await
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DescriptionServer {
+    listener: std::net::TcpListener,
+    port: u16,
+    ipv6: bool,
+    /// The documents of each root device by its UUID.
+    documents: HashMap<String, Document>,
+    /// The root device UUID of each device UUID in the trees.
+    roots: HashMap<String, String>,
+    pub(crate) handler: Option<Arc<dyn HttpHandler>>,
+    application_url: Option<String>,
+    control: Option<(watch::Receiver<bool>, watch::Sender<bool>)>,
+    /// The first configuration error of `describe`, reported by `serve`.
+    invalid: Option<Error>,
+}
+
+impl DescriptionServer {
+    /// Listen for HTTP requests on `port` of every address, `0` picks a free port.
+    ///
+    /// Listens on IPv4 only where IPv6 is unavailable; devices then keep their configured
+    /// `LOCATION` on IPv6.
+    pub fn bind(port: u16) -> Result<Self> {
+        let (listener, ipv6) = match bind_dual_stack(port) {
+            Ok(listener) => (listener, true),
+            Err(e) => {
+                debug!("Serving device descriptions on IPv4 only: {e}");
+                let listener = std::net::TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))?;
+                (listener, false)
+            }
+        };
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+        debug!("Serving device descriptions on port {port}");
+
+        Ok(Self {
+            listener,
+            port,
+            ipv6,
+            documents: HashMap::new(),
+            roots: HashMap::new(),
+            handler: None,
+            application_url: None,
+            control: None,
+            invalid: None,
+        })
+    }
+
+    /// The port the server listens on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Serve the description of the device tree rooted at `device` at `/<uuid>/description.xml`.
+    ///
+    /// The document is in the language the client prefers among those of `description`, see
+    /// `DeviceDescription::localized`. `serve` fails with `Error::Config` if a language tag of
+    /// `description` is malformed.
+    pub fn describe(mut self, device: &Device, description: DeviceDescription) -> Self {
+        fn add_roots(roots: &mut HashMap<String, String>, device: &Device, root: &str) {
+            roots.insert(device.uuid.clone(), root.to_owned());
+            for embedded in device.embedded.iter() {
+                add_roots(roots, embedded, root);
+            }
+        }
+
+        add_roots(&mut self.roots, device, &device.uuid);
+        if let Err(e) = description.validate() {
+            self.invalid.get_or_insert(e);
+        }
+        let xml = description.to_xml(device);
+        let default = description
+            .language
+            .clone()
+            .map(|language| (language, xml.clone()));
+        let localized = description
+            .languages()
+            .map(|language| (language.to_owned(), description.to_xml_in(device, language)));
+        let document = Document {
+            localized: default.into_iter().chain(localized).collect(),
+            xml,
+        };
+        self.documents.insert(device.uuid.clone(), document);
+        self
+    }
+
+    /// Answer the `GET` and `HEAD` requests for other paths than the descriptions with
+    /// `handler`, e.g. for the service descriptions or a presentation page.
+    pub fn http_handler(mut self, handler: impl HttpHandler + 'static) -> Self {
+        self.handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Answer requests for the descriptions with an `Application-URL` header pointing to `path`
+    /// on the address the request came in on, as DIAL clients expect.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::DescriptionServer;
+    ///
+    /// # fn run() -> tokio_ssdp::Result<()> {
+    /// // `Application-URL: http://192.168.1.100:8080/apps/`
+    /// DescriptionServer::bind(8080)?
+    ///   .application_url("/apps/");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn application_url(mut self, path: impl Into<String>) -> Self {
+        self.application_url = Some(path.into());
+        self
+    }
+
+    /// The `LocationResolver` pointing the described devices to their document.
+    pub fn locations(&self) -> DescriptionLocations {
+        DescriptionLocations {
+            port: self.port,
+            ipv6: self.ipv6,
+            roots: Arc::new(self.roots.clone()),
+        }
+    }
+
+    /// Create a [`DescriptionHandle`] to stop the server, replacing a handle created before.
+    pub fn handle(&mut self) -> DescriptionHandle {
+        let (requested_tx, requested_rx) = watch::channel(false);
+        let (done_tx, done_rx) = watch::channel(false);
+        self.control = Some((requested_rx, done_tx));
+        DescriptionHandle {
+            requested: requested_tx,
+            done: done_rx,
+        }
+    }
+
+    /// Start serving, returns a future that needs to be `await`ed to keep the server running.
+    ///
+    /// The future resolves once stopped through the handle, after answering the requests being
+    /// served. At most 64 connections are served at once, and failing to accept one is logged
+    /// and retried shortly.
+    pub fn serve(self) -> Result<impl Future<Output = Result<()>> + use<>> {
+        if let Some(e) = self.invalid {
+            return Err(e);
+        }
+        let listener = TcpListener::from_std(self.listener)?;
+        let documents = Arc::new(self.documents);
+        let handler = self.handler;
+        let application_url = self.application_url.map(Arc::<str>::from);
+        let control = self.control;
+
+        Ok(async move {
+            let mut connections = JoinSet::new();
+            let receiver = control.as_ref().map(|(requested, _)| requested.clone());
+            let mut requested = std::pin::pin!(stop_requested(receiver.clone()));
+
+            let connections_left = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+
+            loop {
+                // Further connections wait in the listen backlog
+                let permit = tokio::select! {
+                    permit = Arc::clone(&connections_left).acquire_owned() => permit,
+                    _ = &mut requested => break,
+                };
+                let Ok(permit) = permit else { break };
+                let (stream, addr) = tokio::select! {
+                    res = listener.accept() => match res {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            // E.g. out of file descriptors, which the connections served free up
+                            warn!("Accepting a description connection failed: {e}");
+                            tokio::select! {
+                                _ = tokio::time::sleep(ACCEPT_BACKOFF) => continue,
+                                _ = &mut requested => break,
+                            }
+                        }
+                    },
+                    _ = &mut requested => break,
+                };
+                let documents = Arc::clone(&documents);
+                let handler = handler.clone();
+                let application_url = application_url.clone();
+                let stopping = stop_requested(receiver.clone());
+                connections.spawn(async move {
+                    let _permit = permit;
+                    let res = respond(
+                        stream,
+                        &documents,
+                        handler.as_deref(),
+                        application_url.as_deref(),
+                        stopping,
+                    );
+                    if let Err(e) = res.await {
+                        warn!("Serve description to {addr} failed: {e}");
+                    }
+                });
+                // Reap the connections already answered
+                while connections.try_join_next().is_some() {}
+            }
+
+            drop(listener);
+            connections.join_all().await;
+            if let Some((_, done)) = control {
+                done.send_replace(true);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Serves the requests a [`DescriptionServer`] has no description for, see
+/// `DescriptionServer::http_handler`.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::HttpHandler;
+///
+/// #[derive(Debug)]
+/// struct Presentation;
+///
+/// impl HttpHandler for Presentation {
+///     fn get(&self, path: &str) -> Option<(String, Vec<u8>)> {
+///         (path == "/").then(|| ("text/html".into(), b"<h1>Media Box</h1>".to_vec()))
+///     }
+/// }
+/// ```
+pub trait HttpHandler: Debug + Send + Sync {
+    /// The content type and body to answer a `GET` of `path` with, `None` for `404 Not Found`.
+    fn get(&self, path: &str) -> Option<(String, Vec<u8>)>;
+}
+
+/// Stops a running [`DescriptionServer`], see `DescriptionServer::handle`.
+#[derive(Debug)]
+pub struct DescriptionHandle {
+    requested: watch::Sender<bool>,
+    done: watch::Receiver<bool>,
+}
+
+impl DescriptionHandle {
+    /// Stop accepting connections and wait until the requests being served were answered.
+    ///
+    /// Returns immediately if the server was dropped.
+    pub async fn shutdown(mut self) {
+        self.requested.send_replace(true);
+        let _ = self.done.wait_for(|done| *done).await;
+    }
+}
+
+/// Points the devices of a [`DescriptionServer`] to their description, see
+/// `DescriptionServer::locations`.
+///
+/// Devices without a description keep their configured `LOCATION`, as do all devices on IPv6 if
+/// the server listens on IPv4 only.
+#[derive(Debug, Clone)]
+pub struct DescriptionLocations {
+    port: u16,
+    ipv6: bool,
+    roots: Arc<HashMap<String, String>>,
+}
+
+impl LocationResolver for DescriptionLocations {
+    fn location(&self, device: &Device, local_addr: IpAddr) -> String {
+        let Some(root) = self
+            .roots
+            .get(&device.uuid)
+            .filter(|_| self.ipv6 || local_addr.is_ipv4())
+        else {
+            return device.location.clone();
+        };
+        let addr = SocketAddr::new(local_addr, self.port);
+        format!("http://{addr}/{root}/description.xml")
+    }
+}
+
+/// Listen on `port` of every IPv6 address and, through mapped addresses, every IPv4 one.
+fn bind_dual_stack(port: u16) -> io::Result<std::net::TcpListener> {
+    let socket = Socket::new(Domain::IPV6, Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_only_v6(false)?;
+    // Like `std::net::TcpListener::bind`, so the port can be bound again right after a restart
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)).into())?;
+    socket.listen(128)?;
+    Ok(socket.into())
+}
+
+/// Resolves once the server is asked to stop through `requested`, never without a handle.
+async fn stop_requested(requested: Option<watch::Receiver<bool>>) {
+    match requested {
+        Some(mut requested) => {
+            if requested.wait_for(|requested| *requested).await.is_err() {
+                std::future::pending().await
+            }
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Answer the HTTP request on `stream` and close it, or just close it if no request arrived
+/// within `READ_TIMEOUT` or before `stopping` resolved.
+async fn respond(
+    mut stream: TcpStream,
+    documents: &HashMap<String, Document>,
+    handler: Option<&dyn HttpHandler>,
+    application_url: Option<&str>,
+    stopping: impl Future<Output = ()>,
+) -> Result<()> {
+    let mut stopping = std::pin::pin!(stopping);
+    let mut buf = vec![0u8; MAX_REQUEST];
+    let mut len = 0;
+    let mut described = false;
+    // Whether the document depends on `ACCEPT-LANGUAGE`, for caches
+    let mut negotiated = false;
+    let mut language = None;
+    let deadline = Instant::now() + READ_TIMEOUT;
+    // The status, and the content type and body with whether to send it (not for `HEAD`)
+    let (status, document) = loop {
+        let read = tokio::time::timeout_at(deadline, stream.read(&mut buf[len..]));
+        let n = tokio::select! {
+            n = read => match n {
+                Ok(n) => n?,
+                Err(_) => {
+                    debug!("Closing a description connection without a request after {READ_TIMEOUT:?}");
+                    return Ok(());
+                }
+            },
+            // Requests already coming in are still answered
+            _ = &mut stopping, if len == 0 => return Ok(()),
+        };
+        if n == 0 {
+            return Ok(());
+        }
+        len += n;
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut req = httparse::Request::new(&mut headers);
+        match req.parse(&buf[..len]) {
+            Ok(httparse::Status::Complete(_)) => {
+                let path = req.path.unwrap_or_default();
+                let document = path
+                    .strip_prefix('/')
+                    .and_then(|path| path.strip_suffix("/description.xml"))
+                    .and_then(|uuid| documents.get(uuid))
+                    .map(|document| {
+                        described = true;
+                        negotiated = !document.localized.is_empty();
+                        let accept_language = req
+                            .headers
+                            .iter()
+                            .find(|h| h.name.eq_ignore_ascii_case("Accept-Language"))
+                            .and_then(|h| std::str::from_utf8(h.value).ok());
+                        let xml =
+                            match accept_language.and_then(|accept| document.negotiate(accept)) {
+                                Some((tag, xml)) => {
+                                    language = Some(tag);
+                                    xml
+                                }
+                                None => &document.xml,
+                            };
+                        let content_type = Cow::Borrowed("text/xml; charset=\"utf-8\"");
+                        (content_type, Cow::Borrowed(xml.as_bytes()))
+                    });
+                let document = match (document, handler) {
+                    (None, Some(handler)) if matches!(req.method, Some("GET" | "HEAD")) => handler
+                        .get(path)
+                        .map(|(content_type, body)| (Cow::Owned(content_type), Cow::Owned(body))),
+                    (document, _) => document,
+                };
+                break match (req.method, document) {
+                    (Some(method @ ("GET" | "HEAD")), Some((content_type, body))) => {
+                        ("200 OK", Some((content_type, body, method == "GET")))
+                    }
+                    (Some("GET" | "HEAD"), None) => ("404 Not Found", None),
+                    _ => ("405 Method Not Allowed", None),
+                };
+            }
+            Ok(httparse::Status::Partial) if len < buf.len() => continue,
+            _ => break ("400 Bad Request", None),
+        }
+    };
+
+    let mut response = format!("HTTP/1.1 {status}\r\nConnection: close\r\n");
+    if let Some(path) = application_url.filter(|_| described) {
+        let local_addr = stream.local_addr()?;
+        // Not the IPv4-mapped address IPv4 requests arrive on
+        let local_addr = SocketAddr::new(local_addr.ip().to_canonical(), local_addr.port());
+        let _ = write!(response, "Application-URL: http://{local_addr}{path}\r\n");
+    }
+    if negotiated {
+        response.push_str("Vary: Accept-Language\r\n");
+    }
+    if let Some(language) = language {
+        let _ = write!(response, "Content-Language: {language}\r\n");
+    }
+    let response = match document {
+        Some((content_type, body, send)) => {
+            let _ = write!(
+                response,
+                "Content-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            let mut response = response.into_bytes();
+            if send {
+                response.extend_from_slice(&body);
+            }
+            response
+        }
+        None => {
+            response.push_str("Content-Length: 0\r\n\r\n");
+            response.into_bytes()
+        }
+    };
+
+    stream.write_all(&response).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Whether `tag` is a well-formed language tag: subtags of 1 to 8 ASCII letters or digits
+/// separated by `-`, the first one letters only, e.g. `de`, `pt-BR` or `zh-Hant-TW`.
+fn is_language_tag(tag: &str) -> bool {
+    let mut subtags = tag.split('-');
+    let primary = subtags.next().unwrap_or_default();
+    (1..=8).contains(&primary.len())
+        && primary.bytes().all(|b| b.is_ascii_alphabetic())
+        && subtags.all(|subtag| {
+            (1..=8).contains(&subtag.len()) && subtag.bytes().all(|b| b.is_ascii_alphanumeric())
+        })
+}
+
+/// The description of a device tree, by language.
+#[derive(Debug)]
+struct Document {
+    /// The document with the default names.
+    xml: String,
+    /// The documents by language tag, the default one first if its language is known.
+    localized: Vec<(String, String)>,
+}
+
+impl Document {
+    /// The language and document best matching the `ACCEPT-LANGUAGE` header `accept`, `None` for
+    /// the default one.
+    ///
+    /// Ranges are tried by decreasing quality. A range matches a tag equal to it or starting with
+    /// it, `en` matching `en-US`, and falls back to its prefix, `en-GB` matching `en`.
+    fn negotiate(&self, accept: &str) -> Option<(&str, &str)> {
+        let mut ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut params = range.split(';');
+                let tag = params.next()?.trim();
+                let quality = params
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+                (!tag.is_empty() && quality > 0.0).then_some((tag, quality))
+            })
+            .collect();
+        // Stable, so ranges of equal quality keep their order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let find = |tag: &str| {
+            self.localized
+                .iter()
+                .find(|(language, _)| {
+                    language.eq_ignore_ascii_case(tag)
+                        || language
+                            .get(..tag.len() + 1)
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{tag}-")))
+                })
+                .map(|(language, xml)| (language.as_str(), xml.as_str()))
+        };
+        for (range, _) in ranges {
+            if range == "*" {
+                return None;
+            }
+            let mut range = range;
+            loop {
+                if let Some(found) = find(range) {
+                    return Some(found);
+                }
+                match range.rsplit_once('-') {
+                    Some((prefix, _)) => range = prefix,
+                    None => break,
+                }
+            }
+        }
+        None
+    }
+}
+
+/// The name of a service type, e.g. `ContentDirectory` for
+/// `urn:schemas-upnp-org:service:ContentDirectory:1`.
+fn service_name(service_type: &str) -> &str {
+    match service_type.split(':').collect::<Vec<_>>()[..] {
+        ["urn", _, "service", name, _] => name,
+        _ => service_type.rsplit(':').next().unwrap_or(service_type),
+    }
+}
+
+pub(crate) fn write_element(xml: &mut String, name: &str, value: &str) {
+    let _ = write!(xml, "<{name}>");
+    for c in value.chars() {
+        match c {
+            '&' => xml.push_str("&amp;"),
+            '<' => xml.push_str("&lt;"),
+            '>' => xml.push_str("&gt;"),
+            '"' => xml.push_str("&quot;"),
+            '\'' => xml.push_str("&apos;"),
+            c => xml.push(c),
+        }
+    }
+    let _ = writeln!(xml, "</{name}>");
+}
@@ -1,13 +1,100 @@
+use std::net::IpAddr;
+
 use log::debug;
 
+use crate::{Error, Result, SearchRequest, Subnet, Urn, Usn};
+
+/// Compose the `USN` advertised for the notification type `nt` of the device `uuid`.
+///
+/// Per the UPnP Device Architecture the USN is `uuid:<uuid>` for the `uuid:<uuid>` target and
+/// `uuid:<uuid>::<nt>` for every other one. `uuid` may carry the `uuid:` prefix, an empty `nt`
+/// stands for the `uuid:<uuid>` target.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::compose_usn;
+///
+/// let uuid = "ad8782a0-9e28-422b-a6ae-670fe7c4c043";
+/// assert_eq!(compose_usn(uuid, ""), format!("uuid:{uuid}"));
+/// assert_eq!(compose_usn(uuid, &format!("uuid:{uuid}")), format!("uuid:{uuid}"));
+/// assert_eq!(compose_usn(uuid, "upnp:rootdevice"), format!("uuid:{uuid}::upnp:rootdevice"));
+/// ```
+pub fn compose_usn(uuid: &str, nt: &str) -> String {
+    Usn::new(uuid, nt).to_string()
+}
+
+/// Check that `nt` is a well-formed notification type.
+///
+/// `upnp:rootdevice`, `uuid:` targets and vendor-specific types are accepted as is, `urn:` types
+/// must follow `urn:<domain>:device|service:<type>:<version>`.
+fn validate_nt(nt: &str) -> Result<()> {
+    if nt.starts_with("urn:") {
+        nt.parse::<Urn>()?;
+    }
+    Ok(())
+}
+
+/// Identifies requesters in the visibility rules of a [`Device`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Requester {
+    /// Requesters whose address is in the subnet.
+    Subnet(Subnet),
+    /// The control point sending this UUID in its `CPUUID.UPNP.ORG` header, with or without the
+    /// `uuid:` prefix.
+    CpUuid(String),
+}
+
+impl Requester {
+    /// Whether the sender of `search` is identified by this rule.
+    pub fn matches(&self, search: &SearchRequest) -> bool {
+        match self {
+            Requester::Subnet(subnet) => subnet.contains(search.remote_addr.ip()),
+            Requester::CpUuid(uuid) => {
+                fn strip(uuid: &str) -> &str {
+                    uuid.trim().trim_start_matches("uuid:")
+                }
+
+                search
+                    .header("CPUUID.UPNP.ORG")
+                    .is_some_and(|cp| strip(cp).eq_ignore_ascii_case(strip(uuid)))
+            }
+        }
+    }
+}
+
 /// Information about a SSDP device or service.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Device {
+    pub(crate) uuid: String,
     pub(crate) usn: String,
     pub(crate) search_target: String,
     pub(crate) location: String,
+    pub(crate) secure_location: Option<String>,
+    pub(crate) headers: Vec<(String, String)>,
     pub(crate) content_type: Option<String>,
     pub(crate) body: Option<String>,
+    pub(crate) allow: Vec<Requester>,
+    pub(crate) deny: Vec<Requester>,
+    pub(crate) services: Vec<String>,
+    pub(crate) embedded: Vec<Device>,
+    pub(crate) lazy: bool,
+    pub(crate) family: Option<AddressFamily>,
+    pub(crate) lowest_version: Option<u32>,
+    pub(crate) max_age: Option<u64>,
+    pub(crate) server: Option<String>,
+}
+
+/// An IP version, see `Device::address_family`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddressFamily {
+    /// IPv4
+    V4,
+    /// IPv6
+    V6,
 }
 
 impl Device {
@@ -19,7 +106,7 @@ impl Device {
     /// let uuid = "ad8782a0-9e28-422b-a6ae-670fe7c4c043";
     /// // uuid:{}::upnp:rootdevice
     /// Device::new(uuid, "upnp:rootdevice", "http://192.168.1.100:8080/desc.xml");
-    /// // uuid:{}
+    /// // uuid:{}, `""` is a shorthand for the `uuid:{}` target
     /// Device::new(uuid, "", "http://192.168.1.100:8080/desc.xml");
     /// // uuid:{}::urn:schemas-upnp-org:device:MediaRenderer:1
     /// Device::new(uuid, "urn:schemas-upnp-org:device:MediaRenderer:1", "http://192.168.1.100:8080/desc.xml");
@@ -29,29 +116,466 @@ impl Device {
         search_target: impl Into<String>,
         location: impl Into<String>,
     ) -> Self {
-        let st: String = search_target.into();
+        let uuid = uuid.as_ref();
+        let uuid = uuid.strip_prefix("uuid:").unwrap_or(uuid).to_owned();
+        let mut st: String = search_target.into();
+        if st.is_empty() {
+            st = format!("uuid:{uuid}");
+        }
 
-        let usn = if st.is_empty() {
-            format!("uuid:{}", uuid.as_ref())
-        } else {
-            format!("uuid:{}::{}", uuid.as_ref(), st)
-        };
+        let usn = compose_usn(&uuid, &st);
 
         debug!("USN: {usn}");
 
         Self {
+            uuid,
             usn,
             search_target: st,
             location: location.into(),
+            secure_location: None,
+            headers: vec![],
             content_type: None,
             body: None,
+            allow: vec![],
+            deny: vec![],
+            services: vec![],
+            embedded: vec![],
+            lazy: false,
+            family: None,
+            lowest_version: None,
+            max_age: None,
+            server: None,
         }
     }
 
+    /// Replace the composed `USN` with `usn`, see `validate` for the rules it must follow.
+    pub fn with_usn(mut self, usn: impl Into<String>) -> Self {
+        self.usn = usn.into();
+        self
+    }
+
+    /// Check the device against the composition rules of the UPnP Device Architecture.
+    ///
+    /// The UUID must not be empty, `urn:` search targets must be well-formed, the `max-age` must
+    /// not be `0` and the `USN` must be the one `compose_usn` derives from the UUID and search
+    /// target.
+    /// `Server::serve` refuses to start with invalid devices.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Device;
+    ///
+    /// let uuid = "ad8782a0-9e28-422b-a6ae-670fe7c4c043";
+    /// let loc = "http://192.168.1.100:8080/desc.xml";
+    ///
+    /// assert!(Device::new(uuid, "upnp:rootdevice", loc).validate().is_ok());
+    /// assert!(Device::new(uuid, "urn:schemas-upnp-org:device:MediaRenderer", loc).validate().is_err());
+    /// assert!(Device::new(uuid, "upnp:rootdevice", loc).with_usn(format!("uuid:{uuid}")).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        if self.uuid.trim().is_empty() {
+            return Err(Error::Config(format!(
+                "empty UUID for {}",
+                self.search_target
+            )));
+        }
+
+        validate_nt(&self.search_target)?;
+
+        if self.max_age == Some(0) {
+            return Err(Error::Config(format!("max-age of {} is 0", self.usn)));
+        }
+
+        if let Some((name, _)) = self
+            .headers
+            .iter()
+            .find(|(n, v)| n.contains(['\r', '\n']) || v.contains(['\r', '\n']))
+        {
+            return Err(Error::Config(format!(
+                "header {name:?} of {} contains a line break",
+                self.usn
+            )));
+        }
+        if self
+            .secure_location
+            .as_ref()
+            .is_some_and(|loc| loc.contains(['\r', '\n']))
+        {
+            return Err(Error::Config(format!(
+                "secure location of {} contains a line break",
+                self.usn
+            )));
+        }
+        if self
+            .server
+            .as_ref()
+            .is_some_and(|server| server.contains(['\r', '\n']))
+        {
+            return Err(Error::Config(format!(
+                "SERVER header of {} contains a line break",
+                self.usn
+            )));
+        }
+
+        let expected = compose_usn(&self.uuid, &self.search_target);
+        if self.usn != expected {
+            return Err(Error::Config(format!(
+                "USN {} does not match {} for NT {}",
+                self.usn, expected, self.search_target
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// The UUID of the device, without the `uuid:` prefix.
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    /// The unique service name (`USN` header).
+    pub fn usn(&self) -> &str {
+        &self.usn
+    }
+
+    /// The search target (`ST`/`NT` header).
+    pub fn search_target(&self) -> &str {
+        &self.search_target
+    }
+
+    /// The device or service type of the search target, `None` for `upnp:rootdevice`, `uuid:`
+    /// and malformed targets.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, Urn};
+    ///
+    /// let uuid = "ad8782a0-9e28-422b-a6ae-670fe7c4c043";
+    /// let loc = "http://192.168.1.100:8080/desc.xml";
+    ///
+    /// let urn = Urn::device("schemas-upnp-org", "MediaServer", 1).unwrap();
+    /// assert_eq!(Device::new(uuid, urn.clone(), loc).urn(), Some(urn));
+    /// assert_eq!(Device::new(uuid, "upnp:rootdevice", loc).urn(), None);
+    /// ```
+    pub fn urn(&self) -> Option<Urn> {
+        self.search_target.parse().ok()
+    }
+
+    /// The unique service name, parsed.
+    pub fn parsed_usn(&self) -> Result<Usn> {
+        self.usn.parse()
+    }
+
+    /// The URL of the description (`LOCATION` header).
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// The URL of the description over HTTPS (`SECURELOCATION.UPNP.ORG` header), if any.
+    pub fn secure_location(&self) -> Option<&str> {
+        self.secure_location.as_deref()
+    }
+
+    /// Advertise the description over HTTPS at `url` (`SECURELOCATION.UPNP.ORG` header).
+    ///
+    /// Only sent by servers conforming to `UpnpVersion::V2_0`.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, Server, UpnpVersion};
+    ///
+    /// let device = Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "upnp:rootdevice",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// )
+    /// .with_secure_location("https://192.168.1.100:8443/desc.xml")
+    /// .extra_header("X-VENDOR-HOUSEHOLD", "Sonos_abc123");
+    ///
+    /// Server::new([device])
+    ///   .upnp_version(UpnpVersion::V2_0);
+    /// ```
+    pub fn with_secure_location(mut self, url: impl Into<String>) -> Self {
+        self.secure_location = Some(url.into());
+        self
+    }
+
+    /// Add a header to the announcements of and search responses for this device, e.g. a
+    /// vendor extension.
+    ///
+    /// It is sent as given, whatever the `Server::upnp_version`.
+    pub fn extra_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
     /// Set the body of the SSDP response.
     pub fn with_body(mut self, content_type: impl Into<String>, body: impl Into<String>) -> Self {
         self.content_type = Some(content_type.into());
         self.body = Some(body.into());
         self
     }
+
+    /// Add a service of the device by its type, e.g.
+    /// `urn:schemas-upnp-org:service:ContentDirectory:1`, see `expand`.
+    pub fn with_service(mut self, service_type: impl Into<String>) -> Self {
+        self.services.push(service_type.into());
+        self
+    }
+
+    /// Add an embedded device, created with its own UUID and device type, see `expand`.
+    ///
+    /// The location of the embedded device is replaced by the root's, which describes the whole
+    /// tree.
+    pub fn with_embedded(mut self, device: Device) -> Self {
+        self.embedded.push(device);
+        self
+    }
+
+    /// Also advertise the versions from `lowest` up to the current one of the device type and
+    /// service types, defaults to only the current version.
+    ///
+    /// Newer versions of standard types are backward compatible, and control points searching for
+    /// an older one expect to find the device too. `expand` adds an advertisement for each
+    /// version, e.g. `ContentDirectory:1` and `ContentDirectory:2` for a `ContentDirectory:2`
+    /// service. Types without a version are advertised as is.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Device;
+    ///
+    /// let device = Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:2",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// )
+    /// .with_service("urn:schemas-upnp-org:service:ContentDirectory:2")
+    /// .versions_from(1);
+    ///
+    /// let targets: Vec<_> = device.expand().iter().map(|d| d.search_target().to_owned()).collect();
+    /// assert_eq!(targets, [
+    ///     "upnp:rootdevice",
+    ///     "uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:2",
+    ///     "urn:schemas-upnp-org:device:MediaServer:1",
+    ///     "urn:schemas-upnp-org:service:ContentDirectory:2",
+    ///     "urn:schemas-upnp-org:service:ContentDirectory:1",
+    /// ]);
+    /// ```
+    pub fn versions_from(mut self, lowest: u32) -> Self {
+        self.lowest_version = Some(lowest);
+        self
+    }
+
+    /// `nt` and, if it is a versioned type, its versions down to the lowest advertised.
+    fn versions(&self, nt: &str) -> Vec<String> {
+        match (nt.parse::<Urn>(), self.lowest_version) {
+            // A lowest version above the type's own still advertises the type
+            (Ok(urn), Some(lowest)) => (lowest.max(1).min(urn.version())..=urn.version())
+                .rev()
+                .map(|version| urn.clone().with_version(version).to_string())
+                .collect(),
+            _ => vec![nt.to_owned()],
+        }
+    }
+
+    /// The advertisements the UPnP Device Architecture requires for this device tree.
+    ///
+    /// A device without services or embedded devices is advertised as is. Otherwise it is
+    /// treated as a root device created with its device type, and expands to `upnp:rootdevice`,
+    /// `uuid:`, the device type and each service type; then `uuid:`, device type and services of
+    /// every embedded device. All advertisements share the root's location, body and visibility
+    /// rules. `Server::new` expands the devices it is given.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Device;
+    ///
+    /// let root = Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:1",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// )
+    /// .with_service("urn:schemas-upnp-org:service:ContentDirectory:1")
+    /// .with_embedded(
+    ///     Device::new(
+    ///         "0b9b9c36-3a0c-4c40-8b34-9cf1f4fdc1a1",
+    ///         "urn:schemas-upnp-org:device:MediaRenderer:1",
+    ///         "",
+    ///     )
+    ///     .with_service("urn:schemas-upnp-org:service:AVTransport:1"),
+    /// );
+    ///
+    /// let targets: Vec<_> = root.expand().iter().map(|d| d.search_target().to_owned()).collect();
+    /// assert_eq!(targets, [
+    ///     "upnp:rootdevice",
+    ///     "uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:1",
+    ///     "urn:schemas-upnp-org:service:ContentDirectory:1",
+    ///     "uuid:0b9b9c36-3a0c-4c40-8b34-9cf1f4fdc1a1",
+    ///     "urn:schemas-upnp-org:device:MediaRenderer:1",
+    ///     "urn:schemas-upnp-org:service:AVTransport:1",
+    /// ]);
+    /// ```
+    pub fn expand(&self) -> Vec<Device> {
+        if self.services.is_empty() && self.embedded.is_empty() {
+            return self
+                .versions(&self.search_target)
+                .iter()
+                .map(|nt| self.advertisement(&self.uuid, nt))
+                .collect();
+        }
+
+        let mut advertisements = vec![self.advertisement(&self.uuid, "upnp:rootdevice")];
+        self.expand_into(self, &mut advertisements);
+
+        let mut seen = std::collections::HashSet::new();
+        advertisements.retain(|d| seen.insert(d.usn.clone()));
+        advertisements
+    }
+
+    /// Append the advertisements of `self` as part of the tree of `root`.
+    fn expand_into(&self, root: &Device, advertisements: &mut Vec<Device>) {
+        advertisements.push(root.advertisement(&self.uuid, ""));
+        let types = std::iter::once(&self.search_target).chain(self.services.iter());
+        for nt in types.flat_map(|nt| self.versions(nt)) {
+            advertisements.push(root.advertisement(&self.uuid, &nt));
+        }
+        for device in self.embedded.iter() {
+            device.expand_into(root, advertisements);
+        }
+    }
+
+    /// A leaf copy of `self` advertising `nt` for the device `uuid`.
+    pub(crate) fn advertisement(&self, uuid: &str, nt: &str) -> Device {
+        let search_target = match nt {
+            "" => format!("uuid:{uuid}"),
+            nt => nt.to_owned(),
+        };
+        let usn = if uuid == self.uuid && search_target == self.search_target {
+            self.usn.clone()
+        } else {
+            compose_usn(uuid, &search_target)
+        };
+
+        Device {
+            uuid: uuid.to_owned(),
+            usn,
+            search_target,
+            services: vec![],
+            embedded: vec![],
+            ..self.clone()
+        }
+    }
+
+    /// Set the `max-age` of the messages advertising the device, in seconds, defaults to the
+    /// server's `max_age`. Must not be `0`, see `validate`.
+    ///
+    /// Announcements are sent for the whole server, at the interval derived from the shortest
+    /// `max-age` of its devices unless `Server::announce_interval` is set; a device with a shorter
+    /// `max-age` than that interval expires between rounds.
+    pub fn max_age(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set the `SERVER` header of the messages advertising the device, defaults to the server's
+    /// `server_name`.
+    ///
+    /// For devices bridged or proxied by the server, which should present their own OS and
+    /// product. Pass a [`ServerHeader`](crate::ServerHeader) to have the form checked.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, ServerHeader, UpnpVersion};
+    ///
+    /// let camera = ServerHeader::new("Linux/4.9", UpnpVersion::V1_0, "AcmeCam/3.1").unwrap();
+    /// Device::new("ad8782a0-9e28-422b-a6ae-670fe7c4c043", "urn:schemas-upnp-org:device:DigitalSecurityCamera:1", "http://192.168.1.100:8080/cam.xml")
+    ///     .server_name(camera);
+    /// ```
+    pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
+        self.server = Some(server_name.into());
+        self
+    }
+
+    /// Only reveal the device to requesters matching `requester` (or another allowed one).
+    ///
+    /// Multicast announcements cannot be limited to some requesters, so devices with an allow
+    /// list are not announced and only show up in answers to searches.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, Requester};
+    ///
+    /// Device::new("ad8782a0-9e28-422b-a6ae-670fe7c4c043", "urn:example:device:Admin:1", "http://192.168.1.100:8080/admin.xml")
+    ///     .allow(Requester::Subnet("192.168.10.0/24".parse().unwrap()));
+    /// ```
+    pub fn allow(mut self, requester: Requester) -> Self {
+        self.allow.push(requester);
+        self
+    }
+
+    /// Never reveal the device to requesters matching `requester`, this takes precedence over
+    /// `allow`. The device is still announced, since denying some requesters does not hide it
+    /// from the others.
+    pub fn deny(mut self, requester: Requester) -> Self {
+        self.deny.push(requester);
+        self
+    }
+
+    /// Only announce the device once it was searched for, defaults to `false`.
+    ///
+    /// A lazy device answers searches as usual but sends no `ssdp:alive` until a search matched
+    /// it, apart from `ssdp:all`. The server then announces every advertisement of its UUID right
+    /// away and with each round after that. This spares the network the announcements of rarely
+    /// used virtual devices.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Device;
+    ///
+    /// Device::new("ad8782a0-9e28-422b-a6ae-670fe7c4c043", "urn:example:device:Archive:1", "http://192.168.1.100:8080/archive.xml")
+    ///     .lazy(true);
+    /// ```
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// Only advertise the device over `family`, e.g. because its `LOCATION` server does not
+    /// listen on the other one, defaults to both.
+    ///
+    /// The dual-stack server then neither announces the device to the multicast groups of the
+    /// other family nor answers searches coming from it.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{AddressFamily, Device};
+    ///
+    /// Device::new("ad8782a0-9e28-422b-a6ae-670fe7c4c043", "urn:schemas-upnp-org:device:Printer:1", "http://192.168.1.100:631/desc.xml")
+    ///     .address_family(AddressFamily::V4);
+    /// ```
+    pub fn address_family(mut self, family: AddressFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    /// Whether the device is advertised over the address family of `ip`.
+    pub(crate) fn on_family_of(&self, ip: IpAddr) -> bool {
+        match self.family {
+            None => true,
+            Some(AddressFamily::V4) => ip.is_ipv4(),
+            Some(AddressFamily::V6) => ip.is_ipv6(),
+        }
+    }
+
+    /// Whether the device has an allow list, and thus must not be announced.
+    pub(crate) fn is_restricted(&self) -> bool {
+        !self.allow.is_empty()
+    }
+
+    /// Whether the device may be revealed to the sender of `search`.
+    pub fn visible_to(&self, search: &SearchRequest) -> bool {
+        !self.deny.iter().any(|r| r.matches(search))
+            && (self.allow.is_empty() || self.allow.iter().any(|r| r.matches(search)))
+    }
 }
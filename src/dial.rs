@@ -0,0 +1,212 @@
+//! Discovery for [DIAL](https://www.dial-multiscreen.org) servers, the "first screens" (TVs,
+//! streaming sticks) that second-screen apps find and launch applications on.
+//!
+//! A DIAL server is a UPnP device of type [`DEVICE_TYPE`] with the service [`SERVICE_TYPE`],
+//! which clients search for. Its description is served with an `Application-URL` header, the base
+//! URL of the REST service for the applications: `GET <Application-URL>/<name>` returns the
+//! [`AppStatus`] of the application `name`.
+//!
+//! [`DialServer`] sets up the SSDP server and the description server accordingly. Launching and
+//! stopping applications (`POST` and `DELETE`) is up to the application's own HTTP server.
+
+use std::{fmt::Write as _, future::Future, sync::Arc};
+
+use log::info;
+
+use crate::{
+    DescriptionServer, Device, DeviceDescription, HttpHandler, Result, Server,
+    description::write_element,
+};
+
+/// The device type of DIAL servers.
+pub const DEVICE_TYPE: &str = "urn:dial-multiscreen-org:device:dial:1";
+/// The service type DIAL clients search for.
+pub const SERVICE_TYPE: &str = "urn:dial-multiscreen-org:service:dial:1";
+/// The path the applications are found below, the `Application-URL` of [`DialServer`]s.
+pub const APPS_PATH: &str = "/apps/";
+
+/// A DIAL server: answers the searches of DIAL clients and serves its description with the
+/// `Application-URL`.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::{
+///     DeviceDescription, HttpHandler,
+///     dial::{AppState, AppStatus, DialServer},
+/// };
+///
+/// #[derive(Debug)]
+/// struct Apps;
+///
+/// impl HttpHandler for Apps {
+///     fn get(&self, path: &str) -> Option<(String, Vec<u8>)> {
+///         match path {
+///             "/apps/YouTube" => Some(AppStatus::new("YouTube", AppState::Stopped).response()),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// DialServer::new(
+///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+///     DeviceDescription::new("Living room TV", "ACME", "Smart TV"),
+/// )
+/// .apps(Apps)
+/// .serve()?
+/// .await
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DialServer {
+    uuid: String,
+    description: DeviceDescription,
+    port: u16,
+    apps: Option<Arc<dyn HttpHandler>>,
+}
+
+impl DialServer {
+    /// Create a DIAL server with the UUID `uuid`, described by `description`.
+    pub fn new(uuid: impl Into<String>, description: DeviceDescription) -> Self {
+        Self {
+            uuid: uuid.into(),
+            description,
+            port: 0,
+            apps: None,
+        }
+    }
+
+    /// Serve the description and the applications on `port`, defaults to a free one.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Answer the requests for the applications, below [`APPS_PATH`], with `handler`.
+    pub fn apps(mut self, handler: impl HttpHandler + 'static) -> Self {
+        self.apps = Some(Arc::new(handler));
+        self
+    }
+
+    /// The SSDP and description servers, to configure them further before serving both.
+    ///
+    /// The SSDP server advertises the device and its DIAL service, pointing to the description
+    /// server for their `LOCATION`.
+    pub fn into_parts(self) -> Result<(Server, DescriptionServer)> {
+        let device = Device::new(&self.uuid, DEVICE_TYPE, "").with_service(SERVICE_TYPE);
+        device.validate()?;
+
+        let mut descriptions = DescriptionServer::bind(self.port)?
+            .describe(&device, self.description)
+            .application_url(APPS_PATH);
+        descriptions.handler = self.apps;
+        info!(
+            "DIAL server {}, applications on port {}",
+            self.uuid,
+            descriptions.port()
+        );
+
+        let server = Server::new([device]).location_resolver(descriptions.locations());
+        Ok((server, descriptions))
+    }
+
+    /// Start serving, returns a future that needs to be `await`ed to keep the server running.
+    pub fn serve(self) -> Result<impl Future<Output = Result<()>> + use<>> {
+        let (server, descriptions) = self.into_parts()?;
+        let descriptions = descriptions.serve()?;
+        let server = server.serve()?;
+
+        Ok(async move {
+            tokio::select! {
+                res = descriptions => res,
+                res = server => res,
+            }
+        })
+    }
+}
+
+/// Whether a DIAL application runs, see [`AppStatus`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AppState {
+    /// Running, possibly in the background.
+    Running,
+    /// Installed but not running.
+    Stopped,
+    /// Not installed, but can be from this URL.
+    Installable(String),
+}
+
+/// The answer to `GET <Application-URL>/<name>`.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::dial::{AppState, AppStatus};
+///
+/// let xml = AppStatus::new("YouTube", AppState::Running)
+///     .allow_stop(true)
+///     .to_xml();
+/// assert!(xml.contains("<name>YouTube</name>"));
+/// assert!(xml.contains("<options allowStop=\"true\"/>"));
+/// assert!(xml.contains("<state>running</state>"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppStatus {
+    name: String,
+    state: AppState,
+    allow_stop: bool,
+    additional_data: Option<String>,
+}
+
+impl AppStatus {
+    /// The status of the application `name` in `state`.
+    pub fn new(name: impl Into<String>, state: AppState) -> Self {
+        Self {
+            name: name.into(),
+            state,
+            allow_stop: false,
+            additional_data: None,
+        }
+    }
+
+    /// Whether clients may stop the application with `DELETE`, defaults to `false`.
+    pub fn allow_stop(mut self, allow: bool) -> Self {
+        self.allow_stop = allow;
+        self
+    }
+
+    /// Add the `additionalData` element, with XML the application defines.
+    pub fn additional_data(mut self, xml: impl Into<String>) -> Self {
+        self.additional_data = Some(xml.into());
+        self
+    }
+
+    /// The XML document DIAL clients expect.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<service xmlns=\"urn:dial-multiscreen-org:schemas:dial\" dialVer=\"2.2\">\n",
+        ));
+        write_element(&mut xml, "name", &self.name);
+        let _ = writeln!(xml, "<options allowStop=\"{}\"/>", self.allow_stop);
+        let state = match &self.state {
+            AppState::Running => "running".to_owned(),
+            AppState::Stopped => "stopped".to_owned(),
+            AppState::Installable(url) => format!("installable={url}"),
+        };
+        write_element(&mut xml, "state", &state);
+        if let Some(data) = &self.additional_data {
+            let _ = writeln!(xml, "<additionalData>{data}</additionalData>");
+        }
+        xml.push_str("</service>\n");
+        xml
+    }
+
+    /// The content type and body answering the request, as [`HttpHandler::get`] returns them.
+    pub fn response(&self) -> (String, Vec<u8>) {
+        (
+            "text/xml; charset=\"utf-8\"".to_owned(),
+            self.to_xml().into_bytes(),
+        )
+    }
+}
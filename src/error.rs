@@ -0,0 +1,133 @@
+use crate::{Violation, notify::NotifyError, strict};
+
+/// A `Result` alias using the crate's [`Error`] type by default.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The broad category of an [`Error`], useful for deciding how to react to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A message or header value could not be parsed.
+    Parse,
+    /// An I/O operation on a socket failed.
+    Io,
+    /// The configuration is invalid.
+    Config,
+    /// A peer violated the SSDP protocol.
+    Protocol,
+    /// An operation did not complete in time.
+    Timeout,
+}
+
+/// The error type for all fallible operations of this crate.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A message could not be parsed.
+    #[error("Failed to parse message: {0}")]
+    Parse(#[from] NotifyError),
+    /// A header was present but its value could not be parsed.
+    #[error("Invalid {name} header: {source}")]
+    InvalidHeader {
+        /// The name of the offending header.
+        name: &'static str,
+        /// The underlying parse error.
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// An I/O operation failed.
+    #[error("IoError: {0}")]
+    Io(#[from] std::io::Error),
+    /// Another program holds the SSDP port without allowing it to be shared.
+    #[error(
+        "UDP port {port} is used exclusively by {}, stop it or configure it to share the port",
+        .owner.as_deref().unwrap_or("another program")
+    )]
+    PortInUse {
+        /// The contested port.
+        port: u16,
+        /// The name and PID of the owning process, if it could be determined.
+        owner: Option<String>,
+    },
+    /// The configuration is invalid.
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+    /// A peer sent a message violating the protocol.
+    #[error("Protocol violation: {0}")]
+    Protocol(String),
+    /// A request failed strict validation, see `validate_request`.
+    #[error("Invalid request: {}", strict::List(.0))]
+    Invalid(Vec<Violation>),
+    /// An operation did not complete in time.
+    #[error("Operation timed out")]
+    Timeout,
+}
+
+impl Error {
+    /// Returns the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Parse(_) | Error::InvalidHeader { .. } => ErrorKind::Parse,
+            Error::Io(_) | Error::PortInUse { .. } => ErrorKind::Io,
+            Error::Config(_) => ErrorKind::Config,
+            Error::Protocol(_) | Error::Invalid(_) => ErrorKind::Protocol,
+            Error::Timeout => ErrorKind::Timeout,
+        }
+    }
+}
+
+/// An error of a running [`Server`](crate::Server), see `Server::error_stream`.
+///
+/// Only `Socket` stops the server. Setting it up, e.g. binding the port or joining the multicast
+/// group, fails with an [`Error`] from `Server::serve` before it runs.
+#[cfg(feature = "server")]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ServerError {
+    /// Receiving failed in a way that clears up, e.g. an ICMP error caused by an earlier datagram
+    /// or the network going down for a moment. The server keeps receiving.
+    #[error("Receiving failed: {0}")]
+    Receive(#[source] std::io::Error),
+    /// Sending announcements failed, the server sends them again in the next round.
+    #[error("Sending announcements failed: {0}")]
+    Announce(#[source] Error),
+    /// Sending a response to `destination` failed, the server keeps answering other requests.
+    #[error("Responding to {destination} failed: {source}")]
+    Respond {
+        /// The requester the response was for.
+        destination: std::net::SocketAddr,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// The socket failed for good, or receiving failed too many times in a row, the server stops
+    /// and its future resolves to the error.
+    #[error("Socket failed: {0}")]
+    Socket(#[source] std::io::Error),
+}
+
+#[cfg(feature = "server")]
+impl ServerError {
+    /// Whether the server stopped because of this error.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ServerError::Socket(_))
+    }
+
+    /// Classify the receive error `error`: failures of the socket itself, such as it being
+    /// closed or unusable for receiving, are fatal, anything else is assumed to clear up.
+    pub(crate) fn receive(error: std::io::Error) -> Self {
+        if Self::is_socket_failure(&error) {
+            ServerError::Socket(error)
+        } else {
+            ServerError::Receive(error)
+        }
+    }
+
+    /// Whether the receive error `error` is a failure of the socket itself, see `receive`.
+    pub(crate) fn is_socket_failure(error: &std::io::Error) -> bool {
+        use std::io::ErrorKind::*;
+
+        matches!(
+            error.kind(),
+            NotConnected | InvalidInput | Unsupported | PermissionDenied | OutOfMemory
+        )
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{NotifyRequest, Nts, SearchRequest, SearchResponse};
+
+/// An SSDP message received by the server, see `Server::event_stream`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum SsdpEvent {
+    /// A device announced itself with `ssdp:alive`.
+    Alive(NotifyRequest),
+    /// A device left with `ssdp:byebye`.
+    Byebye(NotifyRequest),
+    /// A device changed its boot ID with `ssdp:update`.
+    Update(NotifyRequest),
+    /// A control point searched.
+    Search(SearchRequest),
+    /// A device answered a search sent from the server's port.
+    SearchResponse(SearchResponse),
+}
+
+impl SsdpEvent {
+    /// The event for a `NOTIFY` message, `None` if its `NTS` is missing or unknown.
+    pub(crate) fn from_notify(request: NotifyRequest) -> Option<Self> {
+        match request.nts()? {
+            Nts::Alive => Some(SsdpEvent::Alive(request)),
+            Nts::Byebye => Some(SsdpEvent::Byebye(request)),
+            Nts::Update => Some(SsdpEvent::Update(request)),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt;
+
+/// A coarse category of device, guessed from its `SERVER` or `USER-AGENT` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DeviceKind {
+    /// A Sonos speaker.
+    Sonos,
+    /// A DLNA media server such as MiniDLNA, Plex or Jellyfin.
+    MediaServer,
+    /// An IP camera.
+    Camera,
+    /// A network printer.
+    Printer,
+    /// A smart TV or streaming box.
+    Television,
+    /// A router or internet gateway.
+    Router,
+    /// A Windows host.
+    Windows,
+    /// Nothing in the header was recognized.
+    Unknown,
+}
+
+/// Case-insensitive product tokens and the kind they reveal, checked in order.
+const FINGERPRINTS: &[(&str, DeviceKind)] = &[
+    ("sonos", DeviceKind::Sonos),
+    ("minidlna", DeviceKind::MediaServer),
+    ("readydlna", DeviceKind::MediaServer),
+    ("plex", DeviceKind::MediaServer),
+    ("jellyfin", DeviceKind::MediaServer),
+    ("emby", DeviceKind::MediaServer),
+    ("serviio", DeviceKind::MediaServer),
+    ("twonky", DeviceKind::MediaServer),
+    ("gerbera", DeviceKind::MediaServer),
+    ("rygel", DeviceKind::MediaServer),
+    ("hikvision", DeviceKind::Camera),
+    ("dahua", DeviceKind::Camera),
+    ("foscam", DeviceKind::Camera),
+    ("amcrest", DeviceKind::Camera),
+    ("ipcam", DeviceKind::Camera),
+    ("camera", DeviceKind::Camera),
+    ("printer", DeviceKind::Printer),
+    ("hp http server", DeviceKind::Printer),
+    ("epson", DeviceKind::Printer),
+    ("webos", DeviceKind::Television),
+    ("tizen", DeviceKind::Television),
+    ("bravia", DeviceKind::Television),
+    ("roku", DeviceKind::Television),
+    ("smarttv", DeviceKind::Television),
+    ("miniupnpd", DeviceKind::Router),
+    ("fritz!box", DeviceKind::Router),
+    ("openwrt", DeviceKind::Router),
+    ("dd-wrt", DeviceKind::Router),
+    ("router", DeviceKind::Router),
+    ("windows", DeviceKind::Windows),
+];
+
+impl DeviceKind {
+    /// Classify a device from its `SERVER` (or a control point from its `USER-AGENT`) header.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::DeviceKind;
+    ///
+    /// let kind = DeviceKind::from_server("Linux UPnP/1.0 Sonos/70.3-35220 (ZPS1)");
+    /// assert_eq!(kind, DeviceKind::Sonos);
+    /// let kind = DeviceKind::from_server("4.4.0 DLNADOC/1.50 UPnP/1.0 MiniDLNA/1.3.0");
+    /// assert_eq!(kind, DeviceKind::MediaServer);
+    /// let kind = DeviceKind::from_server("Microsoft-Windows/10.0 UPnP/1.0 UPnP-Device-Host/1.0");
+    /// assert_eq!(kind, DeviceKind::Windows);
+    /// assert_eq!(DeviceKind::from_server("Linux/5.4 UPnP/1.0"), DeviceKind::Unknown);
+    /// ```
+    pub fn from_server(server: &str) -> Self {
+        let server = server.to_ascii_lowercase();
+        FINGERPRINTS
+            .iter()
+            .find(|(token, _)| server.contains(token))
+            .map_or(Self::Unknown, |(_, kind)| *kind)
+    }
+}
+
+impl fmt::Display for DeviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeviceKind::Sonos => "Sonos",
+            DeviceKind::MediaServer => "media server",
+            DeviceKind::Camera => "camera",
+            DeviceKind::Printer => "printer",
+            DeviceKind::Television => "television",
+            DeviceKind::Router => "router",
+            DeviceKind::Windows => "Windows",
+            DeviceKind::Unknown => "unknown",
+        })
+    }
+}
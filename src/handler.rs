@@ -0,0 +1,58 @@
+use std::fmt::Debug;
+
+use crate::{MSearchResponse, NotifyRequest, NotifyResponse};
+
+/// What the server does with an incoming message after a [`RequestHandler`] saw it.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum HandlerDecision {
+    /// Pass the message, with any changes the handler made, on to the next handler and then to
+    /// the built-in processing.
+    Continue,
+    /// Ignore the message.
+    Drop,
+    /// Send this response and skip the built-in processing.
+    Respond(NotifyResponse),
+    /// Answer the `M-SEARCH` with these responses, sent within its `MX` delay like the built-in
+    /// ones, and skip the built-in processing. Other messages are dropped.
+    ///
+    /// `local_addr_towards` tells the address of the interface facing the requester, e.g. to
+    /// derive the `LOCATION`.
+    Reply(Vec<MSearchResponse>),
+}
+
+/// Inspects every incoming SSDP message before the server processes it.
+///
+/// Handlers run in the order they were added with `Server::request_handler`, on messages parsed
+/// as [`NotifyRequest`] whatever their method (`NOTIFY`, `M-SEARCH` or custom ones). They may
+/// rewrite the request in place, e.g. to fix a misbehaving device's headers. Messages that cannot
+/// be parsed skip the handlers.
+///
+/// Handlers run on the server task and must not block, spawn a task and reply through
+/// `ServerHandle::send` for slow work.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{HandlerDecision, NotifyRequest, RequestHandler, Server, Subnet};
+///
+/// /// Ignore everything from outside the LAN.
+/// #[derive(Debug)]
+/// struct LanOnly(Subnet);
+///
+/// impl RequestHandler for LanOnly {
+///     fn handle(&self, request: &mut NotifyRequest) -> HandlerDecision {
+///         if self.0.contains(request.remote_addr.ip()) {
+///             HandlerDecision::Continue
+///         } else {
+///             HandlerDecision::Drop
+///         }
+///     }
+/// }
+///
+/// Server::new([])
+///   .request_handler(LanOnly("192.168.1.0/24".parse().unwrap()));
+/// ```
+pub trait RequestHandler: Debug + Send + Sync {
+    /// Decide what happens to `request`, which may be modified.
+    fn handle(&self, request: &mut NotifyRequest) -> HandlerDecision;
+}
@@ -0,0 +1,136 @@
+//! Helpers for the grammar of header values: quoted strings, lists and parameters.
+
+use std::borrow::Cow;
+
+/// Strip the quotes of a quoted string and resolve its `\` escapes, other values are only trimmed.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::header::unquote;
+///
+/// assert_eq!(unquote(" \"ssdp:discover\" "), "ssdp:discover");
+/// assert_eq!(unquote(r#""a \"b\"""#), "a \"b\"");
+/// assert_eq!(unquote("max-age"), "max-age");
+/// ```
+pub fn unquote(value: &str) -> Cow<'_, str> {
+    let value = value.trim();
+    let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+        return Cow::Borrowed(value);
+    };
+
+    if !inner.contains('\\') {
+        return Cow::Borrowed(inner);
+    }
+
+    let mut unescaped = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// Split `value` at every `sep` outside of quoted strings, trimming the parts.
+fn split_unquoted(value: &str, sep: char) -> impl Iterator<Item = &str> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    value
+        .split(move |c: char| {
+            if escaped {
+                escaped = false;
+            } else if in_quotes && c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_quotes = !in_quotes;
+            } else if c == sep && !in_quotes {
+                return true;
+            }
+            false
+        })
+        .map(str::trim)
+}
+
+/// Iterate over the non-empty elements of a comma-separated list, e.g. `CACHE-CONTROL` directives.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::header::split_list;
+///
+/// let items: Vec<_> = split_list(r#"max-age=1800, x="a,b",, no-cache"#).collect();
+/// assert_eq!(items, ["max-age=1800", r#"x="a,b""#, "no-cache"]);
+/// ```
+pub fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    split_unquoted(value, ',').filter(|item| !item.is_empty())
+}
+
+/// Split a `name=value` pair, the value being unquoted. Whitespace around `=` is tolerated.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::header::split_pair;
+///
+/// assert_eq!(split_pair("max-age = 1800"), ("max-age", Some("1800".into())));
+/// assert_eq!(split_pair("no-cache"), ("no-cache", None));
+/// ```
+pub fn split_pair(item: &str) -> (&str, Option<Cow<'_, str>>) {
+    match item.split_once('=') {
+        Some((name, value)) => (name.trim(), Some(unquote(value))),
+        None => (item.trim(), None),
+    }
+}
+
+/// A header value made of a main value followed by `;`-separated parameters.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::header::HeaderValue;
+///
+/// let value = HeaderValue::parse(r#""ssdp:discover"; ns=01; note="a;b""#);
+/// assert_eq!(value.value(), "ssdp:discover");
+/// assert_eq!(value.param("NS"), Some("01"));
+/// assert_eq!(value.param("note"), Some("a;b"));
+/// assert_eq!(value.param("missing"), None);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderValue<'a> {
+    value: Cow<'a, str>,
+    params: Vec<(&'a str, Option<Cow<'a, str>>)>,
+}
+
+impl<'a> HeaderValue<'a> {
+    /// Parse a raw header value.
+    pub fn parse(raw: &'a str) -> Self {
+        let mut parts = split_unquoted(raw, ';');
+        let value = unquote(parts.next().unwrap_or_default());
+        let params = parts
+            .filter(|part| !part.is_empty())
+            .map(split_pair)
+            .collect();
+
+        Self { value, params }
+    }
+
+    /// The main value, unquoted.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The value of the parameter `name` (compared case-insensitively), unquoted.
+    ///
+    /// Returns `None` if the parameter is missing or has no value.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// All parameters in order of appearance.
+    pub fn params(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.params.iter().map(|(n, v)| (*n, v.as_deref()))
+    }
+}
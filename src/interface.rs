@@ -0,0 +1,234 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+
+use if_addrs::{IfAddr, IfOperStatus};
+use log::{debug, info, warn};
+
+use crate::{SSDP_ADDR, SSDP_PORT, Subnet, channel::Interface};
+
+/// Name prefixes of the virtual interfaces container runtimes and hypervisors create.
+const BRIDGE_PREFIXES: &[&str] = &[
+    "docker", "veth", "br-", "cni", "flannel", "cali", "virbr", "podman", "lxcbr",
+];
+
+/// Whether `name` looks like a container or VM bridge interface.
+pub(crate) fn is_bridge_interface(name: &str) -> bool {
+    BRIDGE_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Whether this process appears to run inside a container.
+pub(crate) fn in_container() -> bool {
+    std::path::Path::new("/.dockerenv").exists()
+        || std::path::Path::new("/run/.containerenv").exists()
+}
+
+/// All addresses assigned to interfaces of this host, empty if they cannot be listed.
+pub(crate) fn local_addrs() -> Vec<IpAddr> {
+    match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces.iter().map(|iface| iface.ip()).collect(),
+        Err(e) => {
+            warn!("Failed to list network interfaces: {e}");
+            vec![]
+        }
+    }
+}
+
+/// The subnets directly reachable through `interfaces`, those of every interface for an
+/// unspecified one, or `None` if the interfaces cannot be listed.
+pub(crate) fn link_subnets(interfaces: &[Interface]) -> Option<Vec<Subnet>> {
+    let all = match if_addrs::get_if_addrs() {
+        Ok(all) => all,
+        Err(e) => {
+            warn!("Failed to list network interfaces: {e}");
+            return None;
+        }
+    };
+
+    let subnets = all
+        .iter()
+        .filter(|iface| {
+            interfaces
+                .iter()
+                .any(|interface| match (interface, &iface.addr) {
+                    (Interface::V4(ip), IfAddr::V4(addr)) => ip.is_unspecified() || *ip == addr.ip,
+                    (Interface::V6(index), IfAddr::V6(_)) => {
+                        *index == 0 || iface.index == Some(*index)
+                    }
+                    _ => false,
+                })
+        })
+        .filter_map(|iface| match &iface.addr {
+            IfAddr::V4(addr) => Subnet::new(addr.ip.into(), addr.prefixlen).ok(),
+            IfAddr::V6(addr) => Subnet::new(addr.ip.into(), addr.prefixlen).ok(),
+        })
+        .collect();
+    Some(subnets)
+}
+
+/// The index of the interface with address `ip`, `0` (any interface) if it is unknown.
+pub(crate) fn index_of(ip: Ipv4Addr) -> u32 {
+    if ip.is_unspecified() {
+        return 0;
+    }
+
+    if_addrs::get_if_addrs()
+        .ok()
+        .and_then(|interfaces| {
+            interfaces
+                .into_iter()
+                .find(|iface| iface.ip() == IpAddr::V4(ip))
+                .and_then(|iface| iface.index)
+        })
+        .unwrap_or(0)
+}
+
+/// Picks the interface to advertise on when none was given explicitly.
+///
+/// Loopback, link-local-only and down interfaces are skipped, as are container bridges unless
+/// `include_bridges` is set. Among the remaining ones the interface the OS would route SSDP
+/// multicast traffic through is preferred, falling back to the first candidate. Returns `None` if
+/// no suitable interface exists.
+pub(crate) fn select_interface(include_bridges: bool) -> Option<Ipv4Addr> {
+    if in_container() {
+        info!(
+            "Running inside a container, SSDP only reaches the LAN with host networking (e.g. `docker run --network host`)"
+        );
+    }
+
+    let interfaces = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces,
+        Err(e) => {
+            warn!("Failed to list network interfaces: {e}");
+            return None;
+        }
+    };
+
+    let mut candidates = Vec::new();
+
+    for iface in interfaces {
+        let IfAddr::V4(addr) = &iface.addr else {
+            continue;
+        };
+
+        let skip_reason = if addr.ip.is_loopback() {
+            Some("loopback")
+        } else if addr.ip.is_link_local() {
+            Some("link-local only")
+        } else if matches!(
+            iface.oper_status,
+            IfOperStatus::Down | IfOperStatus::NotPresent | IfOperStatus::LowerLayerDown
+        ) {
+            Some("down")
+        } else if !include_bridges && is_bridge_interface(&iface.name) {
+            Some("container bridge")
+        } else {
+            None
+        };
+
+        match skip_reason {
+            Some(reason) => debug!("Skipping interface {} ({}): {reason}", iface.name, addr.ip),
+            None => candidates.push((iface.name, addr.ip)),
+        }
+    }
+
+    let routed = route_source_addr();
+    let selected = candidates
+        .iter()
+        .find(|(_, ip)| Some(*ip) == routed)
+        .or_else(|| candidates.first());
+
+    match selected {
+        Some((name, ip)) => {
+            info!("Selected interface {name} ({ip}) for SSDP");
+            Some(*ip)
+        }
+        None => {
+            warn!("No suitable interface found, letting the OS pick one");
+            None
+        }
+    }
+}
+
+/// Returns the source address the OS would use for SSDP multicast traffic.
+fn route_source_addr() -> Option<Ipv4Addr> {
+    match local_addr_towards((SSDP_ADDR, SSDP_PORT).into())? {
+        IpAddr::V4(ip) => Some(ip),
+        IpAddr::V6(_) => None,
+    }
+}
+
+/// Returns the local address the OS would send traffic to `remote` from.
+///
+/// Connecting a UDP socket only performs a route lookup, no packet is sent.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::local_addr_towards;
+///
+/// let local = local_addr_towards("127.0.0.1:1900".parse().unwrap());
+/// assert_eq!(local, Some("127.0.0.1".parse().unwrap()));
+/// ```
+pub fn local_addr_towards(remote: SocketAddr) -> Option<IpAddr> {
+    let local: IpAddr = match remote {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let socket = UdpSocket::bind((local, 0)).ok()?;
+    socket.connect(remote).ok()?;
+
+    Some(socket.local_addr().ok()?.ip()).filter(|ip| !ip.is_unspecified())
+}
+
+/// The process owning the UDP socket bound to `port`, as `name (pid N)`.
+///
+/// Only implemented on Linux, where sockets are matched to processes through `/proc`, and guessed
+/// on Windows. Sockets of other users' processes cannot be attributed without privileges.
+#[cfg(target_os = "linux")]
+pub(crate) fn udp_port_owner(port: u16) -> Option<String> {
+    use std::fs;
+
+    // Lines look like `sl local_address rem_address st ... uid timeout inode ...`
+    let port = format!(":{port:04X}");
+    let inode = ["/proc/net/udp", "/proc/net/udp6"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .find_map(|table| {
+            table.lines().skip(1).find_map(|line| {
+                let fields: Vec<_> = line.split_whitespace().collect();
+                if fields.get(1)?.ends_with(&port) {
+                    fields.get(9).map(|inode| inode.to_string())
+                } else {
+                    None
+                }
+            })
+        })?;
+    let link = format!("socket:[{inode}]");
+
+    fs::read_dir("/proc").ok()?.flatten().find_map(|proc| {
+        let pid = proc.file_name().into_string().ok()?;
+        pid.parse::<u32>().ok()?;
+        let owns = fs::read_dir(proc.path().join("fd"))
+            .ok()?
+            .flatten()
+            .any(|fd| {
+                fs::read_link(fd.path()).is_ok_and(|target| target.as_os_str() == link.as_str())
+            });
+        if !owns {
+            return None;
+        }
+        let name = fs::read_to_string(proc.path().join("comm")).unwrap_or_default();
+        Some(format!("{} (pid {pid})", name.trim()))
+    })
+}
+
+/// The likely owner of `port`, only guessed for the SSDP port on Windows.
+#[cfg(windows)]
+pub(crate) fn udp_port_owner(port: u16) -> Option<String> {
+    (port == SSDP_PORT).then(|| "the SSDP Discovery service (SSDPSRV) or another program".into())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+pub(crate) fn udp_port_owner(_port: u16) -> Option<String> {
+    None
+}
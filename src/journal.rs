@@ -0,0 +1,120 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::Device;
+
+/// A change to what a server advertises, see `ServerHandle::changes_since`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DeviceChange {
+    /// The device is advertised, from the start or since it was added.
+    Added(Device),
+    /// The device was removed, by a provider or `ServerHandle::remove_device`.
+    Removed(Device),
+    /// The device changed, keeping its USN.
+    Updated(Device),
+    /// The server switched to this boot ID, see `ServerHandle::update`.
+    BootId(u32),
+}
+
+/// A [`DeviceChange`] and its sequence number, which increases by one with each change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct JournalEntry {
+    /// The journal the entry belongs to, random for each server, see
+    /// `ServerHandle::journal_instance`.
+    pub instance: u64,
+    /// The sequence number, starting at 1 when the server starts.
+    pub seq: u64,
+    /// What changed.
+    pub change: DeviceChange,
+}
+
+/// The latest changes of a server, shared with its handles.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    instance: u64,
+    inner: Mutex<Entries>,
+}
+
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    last_seq: u64,
+    entries: VecDeque<JournalEntry>,
+}
+
+impl Journal {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            instance: rand::random(),
+            inner: Mutex::new(Entries {
+                capacity,
+                last_seq: 0,
+                entries: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Keep the latest `capacity` changes, dropping older ones.
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.capacity = capacity;
+        inner.truncate();
+    }
+
+    /// Append `changes`, numbering them in order.
+    pub(crate) fn record(&self, changes: impl IntoIterator<Item = DeviceChange>) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        for change in changes {
+            inner.last_seq += 1;
+            let seq = inner.last_seq;
+            inner.entries.push_back(JournalEntry {
+                instance: self.instance,
+                seq,
+                change,
+            });
+        }
+        inner.truncate();
+    }
+
+    pub(crate) fn instance(&self) -> u64 {
+        self.instance
+    }
+
+    pub(crate) fn last_seq(&self) -> u64 {
+        self.inner
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .last_seq
+    }
+
+    /// The changes after `seq`, or `None` if `instance` is another journal, some of the changes
+    /// were dropped already or `seq` was never reached.
+    pub(crate) fn since(&self, instance: u64, seq: u64) -> Option<Vec<JournalEntry>> {
+        if instance != self.instance {
+            return None;
+        }
+        let inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let first = inner.last_seq + 1 - inner.entries.len() as u64;
+        if seq.saturating_add(1) < first || seq > inner.last_seq {
+            return None;
+        }
+
+        Some(
+            inner
+                .entries
+                .iter()
+                .filter(|e| e.seq > seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+impl Entries {
+    fn truncate(&mut self) {
+        let excess = self.entries.len().saturating_sub(self.capacity);
+        self.entries.drain(..excess);
+    }
+}
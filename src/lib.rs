@@ -1,10 +1,248 @@
 //! A mininal SSDP device implementation using `tokio`.
+//!
+//! # Features
+//! - `server` (default): the [`Server`] advertising [`Device`]s and answering searches.
+//! - `client` (default): the [`Client`] discovering devices with searches, and
+//!   [`MSearchBuilder`] for hand-made ones.
+//! - `signal`: send `ssdp:byebye` when the process is asked to terminate.
+//! - `description`: generate device description documents and serve them over HTTP, see
+//!   [`DescriptionServer`], or publish a device in one call with [`publish_device`].
+//! - `dial`: advertise a [DIAL](https://www.dial-multiscreen.org) server, see [`dial`].
+//! - `serde`: `Serialize` and `Deserialize` for the parsed messages, [`Device`],
+//!   [`SsdpEvent`] and the header types, e.g. to log discovery results as JSON.
+//! - `tracing`: emit [`tracing`](https://docs.rs/tracing) spans for each datagram received, search
+//!   answered and response sent, and events for dropped or invalid datagrams.
+//!
+//! Message parsing ([`NotifyRequest`], [`SearchResponse`]) is always available.
 
+mod error;
+#[cfg(feature = "server")]
+pub use error::ServerError;
+pub use error::{Error, ErrorKind, Result};
+
+/// The SSDP multicast group.
+pub(crate) const SSDP_ADDR: std::net::Ipv4Addr = std::net::Ipv4Addr::new(239, 255, 255, 250);
+/// The SSDP port.
+#[cfg(any(feature = "server", feature = "client"))]
+pub(crate) const SSDP_PORT: u16 = 1900;
+
+#[cfg(feature = "server")]
+mod announce;
+#[cfg(feature = "server")]
+pub use announce::AnnounceStrategy;
+#[cfg(feature = "server")]
+mod audit;
+#[cfg(feature = "server")]
+pub use audit::{AuditKind, AuditRecord, AuditSink, JsonLines};
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "client")]
+pub use client::{Client, Search, SearchStats, Timeouts};
+#[cfg(feature = "client")]
+mod msearch;
+#[cfg(feature = "client")]
+pub use msearch::MSearchBuilder;
+
+#[cfg(feature = "server")]
+mod capture;
+#[cfg(feature = "server")]
+pub use capture::{CaptureReader, CaptureRecord, ReplaySpeed, replay, replay_paced};
+
+#[cfg(feature = "server")]
+mod clock;
+#[cfg(feature = "server")]
+pub use clock::{Clock, FixedClock, SystemClock};
+
+#[cfg(feature = "server")]
+mod rng;
+#[cfg(feature = "server")]
+pub use rng::{Rng, SeededRng, ThreadRng};
+
+#[cfg(feature = "server")]
+mod event;
+#[cfg(feature = "server")]
+pub use event::SsdpEvent;
+#[cfg(feature = "server")]
+mod handler;
+#[cfg(feature = "server")]
+pub use handler::{HandlerDecision, RequestHandler};
+#[cfg(feature = "server")]
+mod lifecycle;
+#[cfg(feature = "server")]
+pub use lifecycle::DeviceLifecycle;
+#[cfg(feature = "server")]
+mod posture;
+#[cfg(feature = "server")]
+pub use posture::ExposureReport;
+#[cfg(feature = "server")]
+mod journal;
+#[cfg(feature = "server")]
+pub use journal::{DeviceChange, JournalEntry};
+#[cfg(feature = "server")]
+mod metrics;
+#[cfg(feature = "server")]
+pub use metrics::{Metrics, ResponseLatency};
+#[cfg(feature = "server")]
+mod power;
+#[cfg(feature = "server")]
+pub use power::PowerProfile;
+#[cfg(feature = "server")]
+mod prune;
+#[cfg(feature = "server")]
+mod queue;
+#[cfg(feature = "server")]
+pub use queue::DropPolicy;
+#[cfg(feature = "server")]
+mod schedule;
+#[cfg(feature = "server")]
+mod trace;
+
+#[cfg(feature = "server")]
 mod device;
-pub use device::Device;
+#[cfg(feature = "server")]
+pub use device::{AddressFamily, Device, Requester, compose_usn};
+#[cfg(feature = "server")]
+mod static_device;
+#[cfg(feature = "server")]
+pub use static_device::StaticDevice;
+#[cfg(feature = "description")]
+mod description;
+#[cfg(feature = "description")]
+pub use description::{
+    DescriptionHandle, DescriptionLocations, DescriptionServer, DeviceDescription, HttpHandler,
+};
+#[cfg(feature = "dial")]
+pub mod dial;
+#[cfg(feature = "description")]
+mod publish;
+#[cfg(feature = "description")]
+pub use publish::{persistent_uuid, publish_device};
 
 mod notify;
-pub use notify::{NotifyMessage, NotifyRequest, NotifyResponse};
+pub use notify::{
+    DuplicateHeaders, NotifyError, NotifyMessage, NotifyRequest, NotifyRequestRef, NotifyResponse,
+    Nts, ParseOptions,
+};
+
+pub mod header;
+
+mod response;
+#[cfg(feature = "server")]
+pub use response::MSearchResponse;
+pub use response::SearchResponse;
+
+pub mod test_vectors;
+
+mod cache_control;
+pub use cache_control::CacheControl;
+
+#[cfg(any(feature = "server", feature = "client"))]
+mod registry;
+#[cfg(any(feature = "server", feature = "client"))]
+pub use registry::{DeviceRegistry, RegistryEvent, RegistryEvents, RemoteDevice};
+
+mod strict;
+pub use strict::{Violation, validate_request};
+
+mod subnet;
+pub use subnet::Subnet;
+
+mod urn;
+pub use urn::{Urn, UrnKind, Usn};
+
+mod version;
+pub use version::{ServerHeader, UpnpVersion};
 
+mod fingerprint;
+pub use fingerprint::DeviceKind;
+
+mod peer;
+pub use peer::{PEER_HEADER, PeerInfo};
+
+mod neighbor;
+pub use neighbor::{MacAddr, mac_address};
+
+#[cfg(feature = "server")]
+mod channel;
+
+#[cfg(feature = "server")]
+mod interface;
+#[cfg(feature = "server")]
+pub use interface::local_addr_towards;
+
+#[cfg(any(feature = "server", feature = "client"))]
+mod socket;
+#[cfg(any(feature = "server", feature = "client"))]
+pub use socket::SsdpSocket;
+
+#[cfg(all(feature = "testing", any(feature = "server", feature = "client")))]
+pub mod testing;
+
+#[cfg(feature = "server")]
+mod watcher;
+#[cfg(feature = "server")]
+pub use watcher::{InterfacePoller, NetworkWatcher};
+
+#[cfg(feature = "server")]
+mod listener;
+#[cfg(feature = "server")]
+pub use listener::SsdpListener;
+
+#[cfg(feature = "server")]
+mod location;
+
+#[cfg(feature = "server")]
+mod resolver;
+#[cfg(feature = "server")]
+pub use resolver::LocationResolver;
+#[cfg(feature = "server")]
+mod provider;
+#[cfg(feature = "server")]
+pub use provider::DeviceProvider;
+#[cfg(feature = "server")]
+mod redact;
+#[cfg(feature = "server")]
+pub use redact::Redaction;
+#[cfg(feature = "server")]
+mod requirements;
+#[cfg(feature = "server")]
+pub use requirements::{Direction, NetworkRequirements, NetworkRule};
+
+#[cfg(feature = "server")]
+mod storm;
+
+#[cfg(feature = "server")]
+mod template;
+#[cfg(feature = "server")]
+pub use template::ResponseTemplate;
+
+#[cfg(feature = "server")]
+mod search;
+#[cfg(feature = "server")]
+pub use search::{ResponsePolicy, SearchRequest};
+
+#[cfg(feature = "signal")]
+mod signal;
+
+#[cfg(feature = "server")]
+mod mux;
+#[cfg(feature = "server")]
 mod server;
-pub use server::Server;
+#[cfg(feature = "server")]
+pub use mux::SsdpMux;
+#[cfg(feature = "server")]
+mod relay;
+#[cfg(feature = "server")]
+pub use relay::SsdpRelay;
+
+#[cfg(feature = "server")]
+pub use server::{
+    AnnouncementKind, ErrorStream, EventStream, NotifyStream, PendingResponses, ResponseSource,
+    Server, ServerHandle,
+};
+
+#[cfg(feature = "server")]
+mod teardown;
+#[cfg(feature = "server")]
+pub use teardown::{ShutdownPhase, ShutdownProgress, Teardown};
@@ -0,0 +1,70 @@
+use std::{collections::HashSet, fmt::Debug, sync::Mutex};
+
+use crate::{Device, SearchRequest};
+
+/// Callbacks on the discovery activity of each device, see `Server::lifecycle_hook`.
+///
+/// Firmware can drive presence LEDs or logs from them without parsing diagnostics. Every method
+/// defaults to doing nothing; they run on the server's tasks and must not block.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Device, DeviceLifecycle, SearchRequest, Server};
+///
+/// /// Light the LED once a control point found the device.
+/// #[derive(Debug)]
+/// struct PresenceLed;
+///
+/// impl DeviceLifecycle for PresenceLed {
+///     fn on_first_search_received(&self, device: &Device, search: &SearchRequest) {
+///         println!("{} found by {}", device.usn(), search.remote_addr);
+///     }
+/// }
+///
+/// Server::new([])
+///   .lifecycle_hook(PresenceLed);
+/// ```
+pub trait DeviceLifecycle: Debug + Send + Sync {
+    /// A search matched `device` for the first time since the server started.
+    fn on_first_search_received(&self, device: &Device, search: &SearchRequest) {
+        let _ = (device, search);
+    }
+
+    /// `ssdp:alive` for `device` was sent on every channel, once per announcement round.
+    fn on_announced(&self, device: &Device) {
+        let _ = device;
+    }
+
+    /// `ssdp:byebye` for `device` was sent on every channel.
+    fn on_byebye_sent(&self, device: &Device) {
+        let _ = device;
+    }
+}
+
+/// A [`DeviceLifecycle`] hook with the devices that were already searched for.
+#[derive(Debug)]
+pub(crate) struct Lifecycle {
+    pub(crate) hook: Box<dyn DeviceLifecycle>,
+    searched: Mutex<HashSet<String>>,
+}
+
+impl Lifecycle {
+    pub(crate) fn new(hook: impl DeviceLifecycle + 'static) -> Self {
+        Self {
+            hook: Box::new(hook),
+            searched: Mutex::default(),
+        }
+    }
+
+    /// Report `device` as found by `search` if it was not before.
+    pub(crate) fn searched(&self, device: &Device, search: &SearchRequest) {
+        let first = self
+            .searched
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(device.usn.clone());
+        if first {
+            self.hook.on_first_search_received(device, search);
+        }
+    }
+}
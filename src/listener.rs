@@ -0,0 +1,88 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use crate::{
+    NotifyMessage, Result,
+    channel::{self, Channel, ChannelOptions},
+    interface,
+};
+
+/// The largest datagram received whole, larger ones are truncated like by the server.
+const MAX_DATAGRAM: usize = 4096;
+
+/// A socket joined to the SSDP multicast group, for applications running their own receive loop
+/// instead of a [`Server`](crate::Server).
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use tokio_ssdp::SsdpListener;
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// let listener = SsdpListener::bind(1900)?;
+/// let mut tick = tokio::time::interval(Duration::from_secs(30));
+/// loop {
+///     tokio::select! {
+///         msg = listener.recv_message() => {
+///             let msg = msg?;
+///             println!("{:?} from {}", msg.header("NT"), msg.remote_addr);
+///         }
+///         _ = tick.tick() => println!("still listening"),
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SsdpListener {
+    channel: Channel,
+}
+
+impl SsdpListener {
+    /// Listen on `port` of the interface the server would pick, see `Server::interfaces`.
+    pub fn bind(port: u16) -> Result<Self> {
+        let ip = interface::select_interface(false).unwrap_or(Ipv4Addr::UNSPECIFIED);
+        Self::bind_interfaces(&[ip], port)
+    }
+
+    /// Listen on `port`, joining the multicast group on the interfaces with addresses `ips`.
+    pub fn bind_interfaces(ips: &[Ipv4Addr], port: u16) -> Result<Self> {
+        let options = ChannelOptions {
+            port,
+            ttl: None,
+            response_ttl: None,
+            source: Default::default(),
+            reuse_port: false,
+            port_sharing: cfg!(windows),
+            multicast_loop: None,
+            unicast_search: false,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            bind_device: None,
+        };
+        Ok(Self {
+            channel: Channel::v4(ips, &options)?,
+        })
+    }
+
+    /// The next datagram received, multicast or unicast.
+    ///
+    /// Cancellation safe: the future only holds a buffer, and a datagram is either returned whole
+    /// or left in the socket. Dropping it, e.g. when another branch of `tokio::select!` completes
+    /// first, loses nothing, so it can be polled in a loop alongside other work.
+    pub async fn recv_message(&self) -> Result<NotifyMessage> {
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        let (n, remote_addr, _) =
+            channel::recv_any(std::slice::from_ref(&self.channel), &mut buf).await?;
+        buf.truncate(n);
+        Ok(NotifyMessage::new(remote_addr, buf))
+    }
+
+    /// Send `data` to `addr`, e.g. a response to a search or a message to the multicast group.
+    pub async fn send_to(&self, data: &[u8], addr: SocketAddr) -> Result<()> {
+        self.channel.socket.send_to(data, addr).await?;
+        Ok(())
+    }
+
+    /// The local address of the socket.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.channel.socket.local_addr()?)
+    }
+}
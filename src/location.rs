@@ -0,0 +1,60 @@
+use std::net::IpAddr;
+
+/// Splits `url` into its `scheme://host:port` origin and the rest (path, query, ...).
+fn split_origin(url: &str) -> Option<(&str, &str)> {
+    let authority_start = url.find("://")? + 3;
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| authority_start + i);
+
+    Some(url.split_at(authority_end))
+}
+
+/// The IP address in the host part of `url`, `None` if the host is a name or the URL is invalid.
+pub(crate) fn host_ip(url: &str) -> Option<IpAddr> {
+    let (origin, _) = split_origin(url)?;
+    let authority = &origin[origin.find("://")? + 3..];
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+
+    let host = if let Some(rest) = host_port.strip_prefix('[') {
+        &rest[..rest.find(']')?]
+    } else {
+        host_port.split(':').next()?
+    };
+
+    host.parse().ok()
+}
+
+/// Replaces the `scheme://host:port` origin of `url` with `origin`, keeping the path.
+pub(crate) fn rebase(url: &str, origin: &str) -> String {
+    match split_origin(url) {
+        Some((_, rest)) => format!("{}{rest}", origin.trim_end_matches('/')),
+        None => url.to_owned(),
+    }
+}
+
+/// Replaces the host of `url` with `ip`, keeping the scheme, port and path.
+pub(crate) fn rehost(url: &str, ip: IpAddr) -> String {
+    let Some((origin, rest)) = split_origin(url) else {
+        return url.to_owned();
+    };
+    let (scheme, authority) = origin.split_at(origin.find("://").unwrap_or(0) + 3);
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let port = match host_port.strip_prefix('[') {
+        Some(rest) => rest.split_once("]:").map(|(_, port)| port),
+        None => host_port.split_once(':').map(|(_, port)| port),
+    };
+
+    let host = match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    };
+    match port {
+        Some(port) => format!("{scheme}{host}:{port}{rest}"),
+        None => format!("{scheme}{host}{rest}"),
+    }
+}
@@ -0,0 +1,148 @@
+use std::{fmt::Debug, net::SocketAddr, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::Nts;
+
+/// Counts the discovery traffic of a server, see `Server::metrics`.
+///
+/// Wire the methods to counters of a metrics library to watch SSDP responders across a fleet.
+/// Every method defaults to doing nothing; they run on the server's tasks and must not block.
+///
+/// # Examples
+/// ```
+/// use std::{net::SocketAddr, sync::atomic::{AtomicU64, Ordering}};
+/// use tokio_ssdp::{Metrics, Server};
+///
+/// #[derive(Debug, Default)]
+/// struct Counters {
+///     searches: AtomicU64,
+///     responses: AtomicU64,
+/// }
+///
+/// impl Metrics for Counters {
+///     fn msearch_received(&self, _remote_addr: SocketAddr) {
+///         self.searches.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn response_sent(&self, _remote_addr: SocketAddr) {
+///         self.responses.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// Server::new([])
+///   .metrics(Counters::default());
+/// ```
+pub trait Metrics: Debug + Send + Sync {
+    /// A datagram of `len` bytes arrived from `remote_addr`.
+    fn packet_received(&self, remote_addr: SocketAddr, len: usize) {
+        let _ = (remote_addr, len);
+    }
+
+    /// An `M-SEARCH` request arrived from `remote_addr`, whether it is answered or not.
+    fn msearch_received(&self, remote_addr: SocketAddr) {
+        let _ = remote_addr;
+    }
+
+    /// A `NOTIFY` message arrived from `remote_addr`.
+    fn notify_received(&self, remote_addr: SocketAddr) {
+        let _ = remote_addr;
+    }
+
+    /// A response to an `M-SEARCH` was sent to `remote_addr`.
+    fn response_sent(&self, remote_addr: SocketAddr) {
+        let _ = remote_addr;
+    }
+
+    /// A `NOTIFY` message of type `nts` was sent to `destination`, once per device and group.
+    fn notify_sent(&self, nts: Nts, destination: SocketAddr) {
+        let _ = (nts, destination);
+    }
+
+    /// A `NOTIFY` message with the custom `NTS` value `nts` was sent to `destination`, see
+    /// `AnnouncementKind::Custom`.
+    fn custom_notify_sent(&self, nts: &str, destination: SocketAddr) {
+        let _ = (nts, destination);
+    }
+
+    /// The datagram from `remote_addr` could not be parsed.
+    fn parse_error(&self, remote_addr: SocketAddr) {
+        let _ = remote_addr;
+    }
+
+    /// The datagram from `remote_addr` was ignored, e.g. by a filter or rate limit.
+    fn dropped(&self, remote_addr: SocketAddr, reason: &'static str) {
+        let _ = (remote_addr, reason);
+    }
+
+    /// A response to an `M-SEARCH` was sent to `remote_addr`, `latency` after the search arrived.
+    fn response_latency(&self, remote_addr: SocketAddr, latency: ResponseLatency) {
+        let _ = (remote_addr, latency);
+    }
+}
+
+/// Where the time between receiving an `M-SEARCH` and sending a response to it went, see
+/// `Metrics::response_latency`.
+///
+/// The `delay` is mostly intended, the other phases are the responder's own cost.
+///
+/// # Examples
+/// ```
+/// use std::{net::SocketAddr, sync::Mutex, time::Duration};
+/// use tokio_ssdp::{Metrics, ResponseLatency, Server};
+///
+/// /// Keep the slowest processing seen.
+/// #[derive(Debug, Default)]
+/// struct Slowest(Mutex<Duration>);
+///
+/// impl Metrics for Slowest {
+///     fn response_latency(&self, _remote_addr: SocketAddr, latency: ResponseLatency) {
+///         let mut slowest = self.0.lock().unwrap();
+///         *slowest = (*slowest).max(latency.queued + latency.processing + latency.serialization);
+///     }
+/// }
+///
+/// Server::new([])
+///   .metrics(Slowest::default());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct ResponseLatency {
+    /// Waiting in the receive queue, see `Server::recv_queue`.
+    pub queued: Duration,
+    /// Parsing the search, running the request handlers and matching the devices.
+    pub processing: Duration,
+    /// Serializing the responses.
+    pub serialization: Duration,
+    /// Waiting to send: the random delay `MX` allows, the spacing of several responses and the
+    /// fair scheduling of `Server::fair_responses`.
+    pub delay: Duration,
+    /// Sending the datagram, or connecting and writing for responses over TCP.
+    pub send: Duration,
+}
+
+impl ResponseLatency {
+    /// The time from receiving the search to having sent the response.
+    pub fn total(&self) -> Duration {
+        self.queued + self.processing + self.serialization + self.delay + self.send
+    }
+}
+
+/// When a datagram was received, when its processing started and when serializing the
+/// responses to it started.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timing {
+    pub(crate) received: Instant,
+    pub(crate) dispatched: Instant,
+    pub(crate) serializing: Instant,
+}
+
+impl Timing {
+    /// The timing of a datagram whose responses are being serialized from now on.
+    pub(crate) fn serializing(self) -> Self {
+        Self {
+            serializing: Instant::now(),
+            ..self
+        }
+    }
+}
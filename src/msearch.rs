@@ -0,0 +1,155 @@
+use std::{fmt::Write as _, net::SocketAddr};
+
+use log::debug;
+use tokio::net::UdpSocket;
+
+use crate::{Error, Result, SSDP_ADDR, SSDP_PORT};
+
+/// An `M-SEARCH` request with full control over its headers and destination.
+///
+/// [`Client`](crate::Client) covers the common case, use this to add headers, change `MAN` or
+/// search a single device by unicast.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::MSearchBuilder;
+///
+/// let request = MSearchBuilder::new("upnp:rootdevice")
+///     .mx(3)
+///     .header("CPFN.UPNP.ORG", "Living room")
+///     .to_bytes()
+///     .unwrap();
+/// assert!(request.starts_with(b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n"));
+///
+/// // Unicast searches carry no MX
+/// let request = MSearchBuilder::new("ssdp:all")
+///     .target("192.0.2.10:1900".parse().unwrap())
+///     .to_bytes()
+///     .unwrap();
+/// assert!(!String::from_utf8(request).unwrap().contains("MX:"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MSearchBuilder {
+    search_target: String,
+    mx: u32,
+    man: String,
+    tcp_port: Option<u16>,
+    headers: Vec<(String, String)>,
+    target: SocketAddr,
+}
+
+impl MSearchBuilder {
+    /// Create a multicast search for `search_target` with `MX: 1`.
+    pub fn new(search_target: impl Into<String>) -> Self {
+        Self {
+            search_target: search_target.into(),
+            mx: 1,
+            man: "\"ssdp:discover\"".into(),
+            tcp_port: None,
+            headers: vec![],
+            target: (SSDP_ADDR, SSDP_PORT).into(),
+        }
+    }
+
+    /// Set the `MX` header, the number of seconds devices may wait before responding, defaults
+    /// to 1. Must be at least 1 for multicast searches, it is not sent for unicast ones.
+    pub fn mx(mut self, mx: u32) -> Self {
+        self.mx = mx;
+        self
+    }
+
+    /// Set the `MAN` header, defaults to `"ssdp:discover"`. Quotes are added if missing.
+    pub fn man(mut self, man: impl Into<String>) -> Self {
+        let man = man.into();
+        self.man = if man.starts_with('"') {
+            man
+        } else {
+            format!("\"{man}\"")
+        };
+        self
+    }
+
+    /// Ask devices to answer over TCP on `port` (`TCPPORT.UPNP.ORG` header, UPnP 2.0).
+    pub fn tcp_port(mut self, port: u16) -> Self {
+        self.tcp_port = Some(port);
+        self
+    }
+
+    /// Add a header, e.g. `USER-AGENT` or `CPFN.UPNP.ORG`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Send the search to `target` instead of the SSDP multicast group.
+    ///
+    /// A unicast `target` searches a single device, the request then carries no `MX`.
+    pub fn target(mut self, target: SocketAddr) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// The destination of the search.
+    pub fn destination(&self) -> SocketAddr {
+        self.target
+    }
+
+    /// Serialize the request.
+    ///
+    /// Fails with `Error::Config` if the search target is empty, the search target, `MAN` or a
+    /// header contains a line break, or `MX` is 0 for a multicast search.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let multicast = self.target.ip().is_multicast();
+        if self.search_target.trim().is_empty() {
+            return Err(Error::Config("the search target is empty".into()));
+        }
+        if self.search_target.contains(['\r', '\n']) {
+            return Err(Error::Config(
+                "the search target contains a line break".into(),
+            ));
+        }
+        if self.man.contains(['\r', '\n']) {
+            return Err(Error::Config("MAN contains a line break".into()));
+        }
+        if multicast && self.mx == 0 {
+            return Err(Error::Config(
+                "MX must be at least 1 for multicast searches".into(),
+            ));
+        }
+        if let Some((name, _)) = self
+            .headers
+            .iter()
+            .find(|(n, v)| n.contains(['\r', '\n']) || v.contains(['\r', '\n']))
+        {
+            return Err(Error::Config(format!(
+                "header {name:?} contains a line break"
+            )));
+        }
+
+        let mut request = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: {}\r\n",
+            self.target, self.man
+        );
+        if multicast {
+            let _ = write!(request, "MX: {}\r\n", self.mx);
+        }
+        let _ = write!(request, "ST: {}\r\n", self.search_target);
+        if let Some(port) = self.tcp_port {
+            let _ = write!(request, "TCPPORT.UPNP.ORG: {port}\r\n");
+        }
+        for (name, value) in self.headers.iter() {
+            let _ = write!(request, "{name}: {value}\r\n");
+        }
+        request.push_str("\r\n");
+
+        Ok(request.into_bytes())
+    }
+
+    /// Serialize the request and send it from `socket`.
+    pub async fn send_via(&self, socket: &UdpSocket) -> Result<()> {
+        let request = self.to_bytes()?;
+        debug!("Sending M-SEARCH: {}", String::from_utf8_lossy(&request));
+        socket.send_to(&request, self.target).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use std::{future::Future, net::Ipv4Addr, pin::Pin, sync::Arc, task::Poll, time::Duration};
+
+use log::info;
+use tokio::{task::JoinSet, time::Instant};
+
+use crate::{
+    Error, ResponseSource, Result, SSDP_PORT, Server,
+    channel::{self, ChannelOptions},
+    interface,
+    server::{RECEIVE_BACKOFF, Tenant, copy_io_error},
+};
+
+/// Runs several [`Server`]s on one set of sockets.
+///
+/// Each server keeps its own devices, policies, handlers and streams, and sees every datagram
+/// received. This lets independent parts of an application, e.g. plugins, advertise devices
+/// without competing for the SSDP port. The socket settings of the mux apply, those of the
+/// registered servers (`port`, `ttl`, `ipv6`, `interfaces`, ...) are ignored.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::{Device, Server, SsdpMux};
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// let media = Server::new([Device::new(
+///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+///     "urn:schemas-upnp-org:device:MediaServer:1",
+///     "http://192.168.1.100:8080/media.xml",
+/// )]);
+/// let mut lights = Server::new([Device::new(
+///     "0b9b9c36-3a0c-4c40-8b34-9cf1f4fdc1a1",
+///     "urn:schemas-upnp-org:device:DimmableLight:1",
+///     "http://192.168.1.100:8081/light.xml",
+/// )]);
+/// let lights_handle = lights.handle();
+///
+/// tokio::spawn(SsdpMux::new().register(media).register(lights).serve()?);
+///
+/// // Retire the lights alone, the media server keeps running
+/// lights_handle.shutdown().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SsdpMux {
+    servers: Vec<Server>,
+    interfaces: Vec<Ipv4Addr>,
+    port: u16,
+    ttl: Option<u32>,
+    response_ttl: Option<u32>,
+    reuse_port: bool,
+    port_sharing: bool,
+    multicast_loop: Option<bool>,
+    unicast_search: bool,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    bind_device: Option<String>,
+    ipv6: bool,
+    response_source: ResponseSource,
+    watchdog: Option<Duration>,
+    #[cfg(feature = "signal")]
+    shutdown_on_signal: bool,
+}
+
+impl Default for SsdpMux {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SsdpMux {
+    /// Create a mux without servers, listening on the SSDP port.
+    pub fn new() -> Self {
+        Self {
+            servers: vec![],
+            interfaces: vec![],
+            port: SSDP_PORT,
+            ttl: None,
+            response_ttl: None,
+            reuse_port: false,
+            port_sharing: cfg!(windows),
+            multicast_loop: None,
+            unicast_search: false,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            bind_device: None,
+            ipv6: false,
+            response_source: ResponseSource::default(),
+            watchdog: None,
+            #[cfg(feature = "signal")]
+            shutdown_on_signal: false,
+        }
+    }
+
+    /// Add a server sharing the sockets.
+    ///
+    /// Use `Server::handle` before registering to shut it down on its own later.
+    pub fn register(mut self, server: Server) -> Self {
+        self.servers.push(server);
+        self
+    }
+
+    /// Serve on the interfaces with these addresses, see `Server::interfaces`.
+    pub fn interfaces(mut self, ips: impl IntoIterator<Item = Ipv4Addr>) -> Self {
+        self.interfaces = ips.into_iter().collect();
+        self
+    }
+
+    /// Listen on `port`, see `Server::port`.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Set the multicast TTL, see `Server::ttl`.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Set the unicast TTL of search responses, see `Server::response_ttl`.
+    pub fn response_ttl(mut self, ttl: u32) -> Self {
+        self.response_ttl = Some(ttl);
+        self
+    }
+
+    /// Set `SO_REUSEPORT` from the start, see `Server::reuse_port`.
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
+
+    /// Expect another program to hold the SSDP port too, see `Server::port_sharing`.
+    pub fn port_sharing(mut self, enabled: bool) -> Self {
+        self.port_sharing = enabled;
+        self
+    }
+
+    /// Bind the sockets to the network device `name`, see `Server::bind_device`.
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn bind_device(mut self, name: impl Into<String>) -> Self {
+        self.bind_device = Some(name.into());
+        self
+    }
+
+    /// Whether multicast messages loop back to this host, see `Server::multicast_loop`.
+    pub fn multicast_loop(mut self, enabled: bool) -> Self {
+        self.multicast_loop = Some(enabled);
+        self
+    }
+
+    /// Listen for searches sent straight to the interface addresses, disabled by default, see
+    /// `Server::unicast_search`.
+    pub fn unicast_search(mut self, enabled: bool) -> Self {
+        self.unicast_search = enabled;
+        self
+    }
+
+    /// Also serve over IPv6, see `Server::ipv6`.
+    pub fn ipv6(mut self, enabled: bool) -> Self {
+        self.ipv6 = enabled;
+        self
+    }
+
+    /// Set the socket search responses are sent from, see `Server::response_source`.
+    pub fn response_source(mut self, source: ResponseSource) -> Self {
+        self.response_source = source;
+        self
+    }
+
+    /// Re-join the multicast groups when nothing arrived for `period`, see `Server::watchdog`.
+    pub fn watchdog(mut self, period: Duration) -> Self {
+        self.watchdog = Some(period);
+        self
+    }
+
+    /// Stop every server when the process is asked to terminate, see
+    /// `Server::shutdown_on_signal`.
+    #[cfg(feature = "signal")]
+    pub fn shutdown_on_signal(mut self, enabled: bool) -> Self {
+        self.shutdown_on_signal = enabled;
+        self
+    }
+
+    /// Start serving, returns a future that needs to be `await`ed to keep the servers running.
+    ///
+    /// The future resolves once every server was shut down through its handle, or all of them
+    /// on a signal. Fails with `Error::Config` if no server is registered.
+    pub fn serve(self) -> Result<impl Future<Output = Result<()>> + use<>> {
+        if self.servers.is_empty() {
+            return Err(Error::Config("no server registered with the mux".into()));
+        }
+        for server in self.servers.iter() {
+            server.prepare()?;
+        }
+
+        let ips = match self.interfaces[..] {
+            [] => vec![interface::select_interface(false).unwrap_or(Ipv4Addr::UNSPECIFIED)],
+            _ => self.interfaces.clone(),
+        };
+        let options = ChannelOptions {
+            port: self.port,
+            ttl: self.ttl,
+            response_ttl: self.response_ttl,
+            source: self.response_source,
+            reuse_port: self.reuse_port,
+            port_sharing: self.port_sharing,
+            multicast_loop: self.multicast_loop,
+            unicast_search: self.unicast_search,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            bind_device: self.bind_device.clone(),
+        };
+        let channels: Arc<[_]> = channel::bind(&ips, &options, self.ipv6)?.into();
+        let servers: Vec<_> = self
+            .servers
+            .into_iter()
+            .map(|server| {
+                let extra_headers = server.extra_headers(self.ipv6);
+                (Arc::new(server), extra_headers)
+            })
+            .collect();
+
+        #[cfg(feature = "signal")]
+        let signaled = crate::signal::terminated(self.shutdown_on_signal);
+        #[cfg(not(feature = "signal"))]
+        let signaled = std::future::pending::<()>();
+        let watchdog = self.watchdog;
+
+        Ok(async move {
+            let mut buf = [0u8; 4096];
+            let mut tenants: Vec<Tenant> = servers
+                .into_iter()
+                .map(|(server, headers)| Tenant::start(server, Arc::clone(&channels), headers))
+                .collect();
+            let mut requested: Vec<Pin<Box<dyn Future<Output = ()> + Send>>> = tenants
+                .iter()
+                .map(|tenant| Box::pin(tenant.requested()) as _)
+                .collect();
+            let mut signaled = std::pin::pin!(signaled);
+            let mut failures = 0;
+            // Tenants shut down on their own, stopped without holding up the others
+            let mut stopping = JoinSet::new();
+
+            loop {
+                let stopped = std::future::poll_fn(|cx| {
+                    match requested
+                        .iter_mut()
+                        .position(|r| r.as_mut().poll(cx).is_ready())
+                    {
+                        Some(i) => Poll::Ready(i),
+                        None => Poll::Pending,
+                    }
+                });
+
+                // `Err(Some(i))` if server `i` was shut down, `Err(None)` on a signal
+                let next = tokio::select! {
+                    res = channel::recv_watched(&channels, &mut buf, watchdog) => Ok(res),
+                    i = stopped => Err(Some(i)),
+                    _ = &mut signaled => Err(None),
+                };
+
+                match next {
+                    Ok(Ok((n, addr, channel))) => {
+                        failures = 0;
+                        let received = Instant::now();
+                        for tenant in tenants.iter_mut() {
+                            tenant.dispatch(addr, &buf[..n], channel, received).await;
+                        }
+                    }
+                    Ok(Err(e)) => {
+                        // Every server is told, and stops if the error is fatal
+                        failures += 1;
+                        let mut fatal = false;
+                        for tenant in tenants.iter() {
+                            fatal |= tenant
+                                .server
+                                .receive_error(copy_io_error(&e), failures)
+                                .is_err();
+                        }
+                        if fatal {
+                            return Err(e.into());
+                        }
+                        tokio::time::sleep(RECEIVE_BACKOFF).await;
+                    }
+                    Err(Some(i)) => {
+                        drop(requested.swap_remove(i));
+                        stopping.spawn(tenants.swap_remove(i).stop());
+                        if tenants.is_empty() {
+                            while stopping.join_next().await.is_some() {}
+                            return Ok(());
+                        }
+                    }
+                    Err(None) => {
+                        info!("Termination requested, shutting down");
+                        for tenant in tenants {
+                            stopping.spawn(tenant.stop());
+                        }
+                        while stopping.join_next().await.is_some() {}
+                        return Ok(());
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fmt, net::IpAddr};
+
+/// A 48-bit hardware address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MacAddr(pub [u8; 6]);
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+/// Look up the MAC address of `ip` in the OS neighbor table, e.g. for the `remote_addr` of a
+/// [`NotifyRequest`](crate::NotifyRequest).
+///
+/// Unlike UUIDs, which some devices regenerate on a factory reset, the MAC address identifies the
+/// hardware. No packet is sent, so the address is only known if the host talked to `ip` recently.
+/// Only IPv4 on Linux is supported for now, `None` is returned otherwise.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::mac_address;
+///
+/// assert_eq!(mac_address("127.0.0.1".parse().unwrap()), None);
+/// ```
+pub fn mac_address(ip: IpAddr) -> Option<MacAddr> {
+    #[cfg(target_os = "linux")]
+    if let IpAddr::V4(ip) = ip {
+        let table = std::fs::read_to_string("/proc/net/arp").ok()?;
+        return parse_arp_table(&table, ip);
+    }
+
+    let _ = ip;
+    None
+}
+
+/// Find `ip` in the contents of `/proc/net/arp`, skipping incomplete entries.
+#[cfg(target_os = "linux")]
+fn parse_arp_table(table: &str, ip: std::net::Ipv4Addr) -> Option<MacAddr> {
+    table.lines().skip(1).find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next()?.parse::<std::net::Ipv4Addr>().ok()? != ip {
+            return None;
+        }
+
+        let hw_addr = fields.nth(2)?;
+        let mut mac = [0; 6];
+        let mut octets = hw_addr.split(':');
+        for byte in mac.iter_mut() {
+            *byte = u8::from_str_radix(octets.next()?, 16).ok()?;
+        }
+
+        (mac != [0; 6]).then_some(MacAddr(mac))
+    })
+}
@@ -1,18 +1,52 @@
-use std::net::SocketAddr;
+use std::{
+    borrow::Cow,
+    fmt::{self, Write as _},
+    net::SocketAddr,
+    time::Duration,
+};
 
-/// Represents a Universal Resource Name (URN) used in SSDP.
+use crate::{CacheControl, DeviceKind, PEER_HEADER, PeerInfo};
+
+/// Errors that can occur while parsing a NOTIFY message.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum NotifyError {
+    /// The message ended before the header section was complete.
     #[error("The request is incomplete and cannot be parsed.")]
     Incomplete,
+    /// The message has more headers than the parser accepts, see `ParseOptions::max_headers`.
+    #[error("The message has too many headers.")]
+    TooManyHeaders,
+    /// The message is not a valid HTTP request.
     #[error("ParseError: {0}")]
-    ParseError(#[from] httparse::Error),
-    #[error("IoError: {0}")]
-    IoError(#[from] std::io::Error),
+    ParseError(httparse::Error),
+}
+
+impl From<httparse::Error> for NotifyError {
+    fn from(e: httparse::Error) -> Self {
+        match e {
+            httparse::Error::TooManyHeaders => NotifyError::TooManyHeaders,
+            e => NotifyError::ParseError(e),
+        }
+    }
+}
+
+/// The number of headers parsed unless `ParseOptions::max_headers` says otherwise.
+pub(crate) const MAX_HEADERS: usize = 64;
+
+/// A header buffer for `data`, sized to its header lines but at most `max_headers`.
+pub(crate) fn header_buf<'b>(data: &'b [u8], max_headers: usize) -> Vec<httparse::Header<'b>> {
+    let lines = data
+        .split(|b| *b == b'\n')
+        .skip(1)
+        .take_while(|line| !matches!(line, [] | [b'\r']))
+        .count();
+    vec![httparse::EMPTY_HEADER; lines.min(max_headers)]
 }
 
 /// Represents a NOTIFY request in the SSDP protocol.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NotifyMessage {
     /// The remote address of the client that sent the NOTIFY request.
     pub remote_addr: SocketAddr,
@@ -26,14 +60,268 @@ impl NotifyMessage {
         Self { remote_addr, data }
     }
 
+    /// The trimmed value of the first header called `name`, compared case-insensitively, read
+    /// straight from the raw data without parsing the message.
+    ///
+    /// Cheap enough to filter messages before `parse`. Folded continuation lines are not merged
+    /// and lines that are not valid UTF-8 are skipped.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{NotifyMessage, test_vectors};
+    ///
+    /// let msg = NotifyMessage::new("192.0.2.1:1900".parse().unwrap(), test_vectors::FRITZBOX_ALIVE.to_vec());
+    /// assert_eq!(msg.header("nts"), Some("ssdp:alive"));
+    /// assert_eq!(msg.headers_iter().next(), Some(("HOST", "239.255.255.250:1900")));
+    /// ```
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers_iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// All headers of the raw data as trimmed name/value pairs, see `header`.
+    pub fn headers_iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.data
+            .split(|b| *b == b'\n')
+            .skip(1)
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .take_while(|line| !line.is_empty())
+            .filter_map(|line| {
+                let line = std::str::from_utf8(line).ok()?;
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim(), value.trim()))
+            })
+    }
+
     /// Parses the NOTIFY request from the raw data.
     pub fn parse(&self) -> Result<NotifyRequest, NotifyError> {
         NotifyRequest::parse(self.remote_addr, &self.data)
     }
+
+    /// Parses the NOTIFY request from the raw data using `options`.
+    pub fn parse_with(&self, options: &ParseOptions) -> Result<NotifyRequest, NotifyError> {
+        NotifyRequest::parse_with(self.remote_addr, &self.data, options)
+    }
+}
+
+/// What to do when a header appears more than once in a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum DuplicateHeaders {
+    /// Keep every occurrence, lookups match any of them.
+    #[default]
+    KeepAll,
+    /// Keep the first occurrence only.
+    FirstWins,
+    /// Keep the last occurrence only, at the position of the first one.
+    LastWins,
+    /// Merge all occurrences into the first one, joining values with `", "`.
+    Join,
+}
+
+/// Options controlling how tolerant parsing of incoming messages is.
+///
+/// Header names are always matched case-insensitively, and obsolete folded continuation lines
+/// (starting with a space or tab) are merged into the previous header value with a single space.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    allow_truncated: bool,
+    allow_missing_version: bool,
+    duplicate_headers: DuplicateHeaders,
+    max_headers: Option<usize>,
+}
+
+impl ParseOptions {
+    /// Create the default, strict options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept messages cut off inside the header section, defaults to `false`.
+    ///
+    /// Datagrams truncated at the UDP layer then yield the headers received so far, with
+    /// `NotifyRequest::truncated` set, instead of `NotifyError::Incomplete`.
+    pub fn allow_truncated(mut self, allow: bool) -> Self {
+        self.allow_truncated = allow;
+        self
+    }
+
+    /// Accept request lines without an HTTP version, such as `NOTIFY *`, defaults to `false`.
+    ///
+    /// `NotifyRequest::version` is `None` for such messages. `HTTP/1.0` is always accepted.
+    pub fn allow_missing_version(mut self, allow: bool) -> Self {
+        self.allow_missing_version = allow;
+        self
+    }
+
+    /// Set how repeated headers are handled, defaults to `DuplicateHeaders::KeepAll`.
+    pub fn duplicate_headers(mut self, policy: DuplicateHeaders) -> Self {
+        self.duplicate_headers = policy;
+        self
+    }
+
+    /// Set the most headers a message may have, defaults to 64.
+    ///
+    /// Messages with more fail with `NotifyError::TooManyHeaders`.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{NotifyError, NotifyRequest, ParseOptions};
+    ///
+    /// let mut data = b"NOTIFY * HTTP/1.1\r\n".to_vec();
+    /// for i in 0..24 {
+    ///     data.extend_from_slice(format!("X-VENDOR-{i}: {i}\r\n").as_bytes());
+    /// }
+    /// data.extend_from_slice(b"\r\n");
+    /// let addr = "192.168.1.2:1900".parse().unwrap();
+    ///
+    /// assert_eq!(NotifyRequest::parse(addr, &data).unwrap().headers.len(), 24);
+    ///
+    /// let options = ParseOptions::new().max_headers(16);
+    /// let res = NotifyRequest::parse_with(addr, &data, &options);
+    /// assert!(matches!(res, Err(NotifyError::TooManyHeaders)));
+    /// ```
+    pub fn max_headers(mut self, max: usize) -> Self {
+        self.max_headers = Some(max);
+        self
+    }
+}
+
+/// Merge obsolete folded header lines into the previous line, the body is left untouched.
+fn unfold(data: &[u8]) -> Cow<'_, [u8]> {
+    let header_end = data
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .unwrap_or(data.len());
+    let is_fold =
+        |i: usize| data[i..].starts_with(b"\r\n") && matches!(data.get(i + 2), Some(b' ' | b'\t'));
+
+    if !(0..header_end).any(is_fold) {
+        return Cow::Borrowed(data);
+    }
+
+    let mut unfolded = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < header_end {
+        if is_fold(i) {
+            i += 2;
+            while matches!(data.get(i), Some(b' ' | b'\t')) {
+                i += 1;
+            }
+            unfolded.push(b' ');
+        } else {
+            unfolded.push(data[i]);
+            i += 1;
+        }
+    }
+    unfolded.extend_from_slice(&data[header_end..]);
+
+    Cow::Owned(unfolded)
+}
+
+/// Append ` HTTP/1.0` to the request line if it lacks a version, `None` if it has one.
+fn add_missing_version(data: &[u8]) -> Option<Vec<u8>> {
+    let line_end = data.windows(2).position(|w| w == b"\r\n")?;
+    let tokens = data[..line_end]
+        .split(|b| *b == b' ')
+        .filter(|t| !t.is_empty())
+        .count();
+    if tokens != 2 {
+        return None;
+    }
+
+    let mut patched = Vec::with_capacity(data.len() + 9);
+    patched.extend_from_slice(data[..line_end].trim_ascii_end());
+    patched.extend_from_slice(b" HTTP/1.0");
+    patched.extend_from_slice(&data[line_end..]);
+    Some(patched)
+}
+
+/// Apply `policy` to `headers`, keeping the order of first occurrences.
+fn dedup_headers(
+    headers: Vec<(String, String)>,
+    policy: DuplicateHeaders,
+) -> Vec<(String, String)> {
+    if policy == DuplicateHeaders::KeepAll {
+        return headers;
+    }
+
+    let mut deduped: Vec<(String, String)> = Vec::with_capacity(headers.len());
+    for (name, value) in headers {
+        match deduped
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(&name))
+        {
+            None => deduped.push((name, value)),
+            Some((_, existing)) => match policy {
+                DuplicateHeaders::KeepAll | DuplicateHeaders::FirstWins => {}
+                DuplicateHeaders::LastWins => *existing = value,
+                DuplicateHeaders::Join => {
+                    existing.push_str(", ");
+                    existing.push_str(&value);
+                }
+            },
+        }
+    }
+
+    deduped
+}
+
+/// The notification sub type of a `NOTIFY` message (`NTS` header).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum Nts {
+    /// `ssdp:alive`, the device is available.
+    Alive,
+    /// `ssdp:byebye`, the device is leaving the network.
+    Byebye,
+    /// `ssdp:update`, the device changed its boot ID (UPnP 1.1).
+    Update,
+}
+
+impl Nts {
+    /// Parse an `NTS` value, compared case-insensitively. `None` for unknown sub types.
+    pub fn parse(value: &str) -> Option<Self> {
+        [Nts::Alive, Nts::Byebye, Nts::Update]
+            .into_iter()
+            .find(|nts| nts.as_str().eq_ignore_ascii_case(value.trim()))
+    }
+
+    /// The value as sent in the `NTS` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Nts::Alive => "ssdp:alive",
+            Nts::Byebye => "ssdp:byebye",
+            Nts::Update => "ssdp:update",
+        }
+    }
+}
+
+impl fmt::Display for Nts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// Represents a NOTIFY request with parsed information.
-#[derive(Debug, Clone)]
+///
+/// With the `serde` feature, requests can be logged or shipped as JSON:
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// use tokio_ssdp::NotifyRequest;
+///
+/// let data = b"NOTIFY * HTTP/1.1\r\nNT: upnp:rootdevice\r\nNTS: ssdp:alive\r\n\r\n";
+/// let request = NotifyRequest::parse("192.168.1.2:1900".parse().unwrap(), data).unwrap();
+///
+/// let json = serde_json::to_string(&request).unwrap();
+/// let back: NotifyRequest = serde_json::from_str(&json).unwrap();
+/// assert_eq!(back.nts(), request.nts());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NotifyRequest {
     /// The remote address of the client that sent the request.
     pub remote_addr: SocketAddr,
@@ -41,10 +329,16 @@ pub struct NotifyRequest {
     pub method: String,
     /// The path of the request (e.g., "/").
     pub path: String,
+    /// The HTTP minor version, `0` for `HTTP/1.0` and `1` for `HTTP/1.1`, `None` if the request
+    /// line had none (see `ParseOptions::allow_missing_version`).
+    pub version: Option<u8>,
     /// The headers of the request, represented as a vector of tuples (header name, header value).
     pub headers: Vec<(String, String)>,
     /// The body of the request, which can contain additional information.
     pub body: String,
+    /// Whether the message was cut off, either inside the headers or before `CONTENT-LENGTH`
+    /// bytes of body.
+    pub truncated: bool,
 }
 
 impl NotifyRequest {
@@ -58,7 +352,85 @@ impl NotifyRequest {
     /// # Errors
     /// * `NotifyError::Incomplete` if the request is incomplete.
     pub fn parse(remote_addr: SocketAddr, data: &[u8]) -> Result<Self, NotifyError> {
-        let mut headers = [httparse::EMPTY_HEADER; 16];
+        Self::parse_with(remote_addr, data, &ParseOptions::default())
+    }
+
+    /// Parse the HTTP request from the given byte slice using `options`.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{NotifyRequest, ParseOptions};
+    ///
+    /// let data = b"NOTIFY * HTTP/1.1\r\nNTS: ssdp:alive\r\nUSN: uuid:1234::upnp:ro";
+    /// let addr = "192.168.1.2:1900".parse().unwrap();
+    ///
+    /// assert!(NotifyRequest::parse(addr, data).is_err());
+    ///
+    /// let options = ParseOptions::new().allow_truncated(true);
+    /// let req = NotifyRequest::parse_with(addr, data, &options).unwrap();
+    /// assert!(req.truncated);
+    /// assert!(req.header_match("NTS", "ssdp:alive"));
+    ///
+    /// let data = b"NOTIFY *\r\nNTS: ssdp:alive\r\n\r\n";
+    /// assert!(NotifyRequest::parse(addr, data).is_err());
+    ///
+    /// let options = ParseOptions::new().allow_missing_version(true);
+    /// let req = NotifyRequest::parse_with(addr, data, &options).unwrap();
+    /// assert_eq!(req.version, None);
+    /// ```
+    pub fn parse_with(
+        remote_addr: SocketAddr,
+        data: &[u8],
+        options: &ParseOptions,
+    ) -> Result<Self, NotifyError> {
+        let data = unfold(data);
+        let patched = options
+            .allow_missing_version
+            .then(|| add_missing_version(&data))
+            .flatten();
+        let data = patched.as_deref().unwrap_or(&data);
+
+        let res = Self::parse_lenient(remote_addr, data, options);
+        match res {
+            Ok(req) if patched.is_some() => Ok(NotifyRequest {
+                version: None,
+                ..req
+            }),
+            res => res,
+        }
+    }
+
+    /// Parse `data`, tolerating truncation if `options` allow it.
+    fn parse_lenient(
+        remote_addr: SocketAddr,
+        data: &[u8],
+        options: &ParseOptions,
+    ) -> Result<Self, NotifyError> {
+        match Self::parse_complete(remote_addr, data, options) {
+            Err(NotifyError::Incomplete) if options.allow_truncated => {
+                // Drop the partial last line and terminate the header section before it
+                let end = data
+                    .windows(2)
+                    .rposition(|w| w == b"\r\n")
+                    .ok_or(NotifyError::Incomplete)?
+                    + 2;
+                let mut terminated = data[..end].to_vec();
+                terminated.extend_from_slice(b"\r\n");
+
+                let mut req = Self::parse_complete(remote_addr, &terminated, options)?;
+                req.truncated = true;
+                Ok(req)
+            }
+            res => res,
+        }
+    }
+
+    fn parse_complete(
+        remote_addr: SocketAddr,
+        data: &[u8],
+        options: &ParseOptions,
+    ) -> Result<Self, NotifyError> {
+        let mut headers = header_buf(data, options.max_headers.unwrap_or(MAX_HEADERS));
         let mut req = httparse::Request::new(&mut headers);
         let result = req.parse(data)?;
 
@@ -78,11 +450,18 @@ impl NotifyRequest {
                 } else {
                     String::new()
                 };
+                let parsed_headers = dedup_headers(parsed_headers, options.duplicate_headers);
+                let content_length = parsed_headers
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                    .and_then(|(_, value)| value.trim().parse::<usize>().ok());
                 Ok(NotifyRequest {
                     remote_addr,
                     method,
                     path,
+                    version: req.version,
                     headers: parsed_headers,
+                    truncated: content_length.is_some_and(|len| data.len() - n < len),
                     body,
                 })
             }
@@ -90,7 +469,172 @@ impl NotifyRequest {
         }
     }
 
+    /// The trimmed value of the first header called `name`, compared case-insensitively.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{NotifyRequest, test_vectors};
+    ///
+    /// let req = NotifyRequest::parse("192.0.2.20:1900".parse().unwrap(), test_vectors::HUE_ALIVE).unwrap();
+    /// assert_eq!(req.header("HUE-BRIDGEID"), Some("001788FFFE000000"));
+    /// assert_eq!(req.headers_iter().count(), 8);
+    /// ```
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers_iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// All headers as trimmed name/value pairs, in order of appearance.
+    pub fn headers_iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(n, v)| (n.as_str(), v.trim()))
+    }
+
+    /// The notification type (`NT` header).
+    pub fn nt(&self) -> Option<&str> {
+        self.header("NT")
+    }
+
+    /// The parsed notification sub type (`NTS` header), `None` if missing or unknown.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::{NotifyRequest, Nts, test_vectors};
+    ///
+    /// let req = NotifyRequest::parse("192.0.2.10:1900".parse().unwrap(), test_vectors::SONOS_ALIVE).unwrap();
+    /// assert_eq!(req.nts(), Some(Nts::Alive));
+    /// assert_eq!(req.nt(), Some("upnp:rootdevice"));
+    /// assert_eq!(req.usn(), Some("uuid:RINCON_000000000000001400::upnp:rootdevice"));
+    /// assert_eq!(req.location(), Some("http://192.0.2.10:1400/xml/device_description.xml"));
+    /// assert_eq!(req.max_age(), Some(Duration::from_secs(1800)));
+    /// assert_eq!(req.boot_id(), Some(87));
+    /// assert_eq!(req.config_id(), None);
+    /// ```
+    pub fn nts(&self) -> Option<Nts> {
+        self.header("NTS").and_then(Nts::parse)
+    }
+
+    /// The unique service name (`USN` header).
+    pub fn usn(&self) -> Option<&str> {
+        self.header("USN")
+    }
+
+    /// The URL of the device description (`LOCATION` header), not validated.
+    pub fn location(&self) -> Option<&str> {
+        self.header("LOCATION")
+    }
+
+    /// The `SERVER` header, describing the device's OS and UPnP stack.
+    pub fn server(&self) -> Option<&str> {
+        self.header("SERVER")
+    }
+
+    /// How long the announcement is valid, from the `CACHE-CONTROL` header.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.cache_control()?.effective_max_age()
+    }
+
+    /// The boot instance of the device (`BOOTID.UPNP.ORG` header, UPnP 1.1).
+    pub fn boot_id(&self) -> Option<u32> {
+        self.header("BOOTID.UPNP.ORG")?.parse().ok()
+    }
+
+    /// The boot instance announced by `ssdp:update` (`NEXTBOOTID.UPNP.ORG` header, UPnP 1.1).
+    pub fn next_boot_id(&self) -> Option<u32> {
+        self.header("NEXTBOOTID.UPNP.ORG")?.parse().ok()
+    }
+
+    /// The version of the device description (`CONFIGID.UPNP.ORG` header, UPnP 1.1).
+    pub fn config_id(&self) -> Option<u32> {
+        self.header("CONFIGID.UPNP.ORG")?.parse().ok()
+    }
+
+    /// The port the device answers unicast searches on (`SEARCHPORT.UPNP.ORG` header, UPnP 1.1).
+    pub fn search_port(&self) -> Option<u16> {
+        self.header("SEARCHPORT.UPNP.ORG")?.parse().ok()
+    }
+
+    /// The version and features of the sender if it runs this crate with `Server::identify`.
+    pub fn peer_info(&self) -> Option<PeerInfo> {
+        self.header(PEER_HEADER).and_then(PeerInfo::parse)
+    }
+
+    /// Serialize the request as it goes on the wire, e.g. to send one built or rewritten by hand.
+    ///
+    /// The request line is followed by the headers in their order, each ended by CRLF, an empty
+    /// line and the body; nothing is added. A request without a version is written as HTTP/1.1.
+    /// Line breaks in header names or values are replaced by spaces so they cannot inject
+    /// headers. Parsing and serializing gives back the bytes received, apart from folded header
+    /// lines, which are joined, and whitespace around header values.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{NotifyRequest, test_vectors};
+    ///
+    /// let addr = "192.0.2.20:1900".parse().unwrap();
+    /// let req = NotifyRequest::parse(addr, test_vectors::HUE_ALIVE).unwrap();
+    /// assert_eq!(req.to_bytes(), test_vectors::HUE_ALIVE);
+    ///
+    /// let mut byebye = req.clone();
+    /// byebye.headers.retain(|(name, _)| !name.eq_ignore_ascii_case("CACHE-CONTROL"));
+    /// for (name, value) in byebye.headers.iter_mut() {
+    ///     if name.eq_ignore_ascii_case("NTS") {
+    ///         *value = "ssdp:byebye".into();
+    ///     }
+    /// }
+    /// let text = String::from_utf8(byebye.to_bytes()).unwrap();
+    /// assert!(text.starts_with("NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\n"));
+    /// assert!(text.contains("\r\nNTS: ssdp:byebye\r\n") && text.ends_with("\r\n\r\n"));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = format!(
+            "{} {} HTTP/1.{}\r\n",
+            single_line(&self.method),
+            single_line(&self.path),
+            self.version.unwrap_or(1)
+        );
+        write_headers(&mut buf, &self.headers);
+        buf.push_str("\r\n");
+        buf.push_str(&self.body);
+
+        buf.into_bytes()
+    }
+
+    /// The parsed `CACHE-CONTROL` header, if present.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::NotifyRequest;
+    /// use std::time::Duration;
+    ///
+    /// let data = b"NOTIFY * HTTP/1.1\r\nCache-Control: max-age = 1800\r\n\r\n";
+    /// let req = NotifyRequest::parse("192.168.1.2:1900".parse().unwrap(), data).unwrap();
+    /// assert_eq!(req.cache_control().unwrap().max_age, Some(Duration::from_secs(1800)));
+    /// ```
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        self.header("CACHE-CONTROL").map(CacheControl::parse)
+    }
+
+    /// The kind of the announcing device, guessed from its `SERVER` header.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{DeviceKind, NotifyRequest};
+    ///
+    /// let data = b"NOTIFY * HTTP/1.1\r\nSERVER: Linux/5.10 UPnP/1.0 MiniDLNA/1.3.0\r\n\r\n";
+    /// let req = NotifyRequest::parse("192.168.1.2:1900".parse().unwrap(), data).unwrap();
+    /// assert_eq!(req.device_kind(), DeviceKind::MediaServer);
+    /// ```
+    pub fn device_kind(&self) -> DeviceKind {
+        self.server()
+            .map_or(DeviceKind::Unknown, DeviceKind::from_server)
+    }
+
     /// Check if the request header contains the given name and value.
+    ///
+    /// Names are compared case-insensitively. If the header appears several times (see
+    /// `DuplicateHeaders`), any occurrence may match.
     /// # Arguments
     /// * `name` - The name of the header to check.
     /// * `value` - The value to check for in the header.
@@ -104,6 +648,25 @@ impl NotifyRequest {
 
     /// Check if the request header matches the given name and value.
     ///
+    /// Names and values are compared case-insensitively. If the header appears several times (see
+    /// `DuplicateHeaders`), any occurrence may match.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{DuplicateHeaders, NotifyRequest, ParseOptions};
+    ///
+    /// let data = b"NOTIFY * HTTP/1.1\r\nNT: upnp:rootdevice\r\nNT: ssdp:all\r\nX-Long: a\r\n b\r\n\r\n";
+    /// let addr = "192.168.1.2:1900".parse().unwrap();
+    ///
+    /// let req = NotifyRequest::parse(addr, data).unwrap();
+    /// assert!(req.header_match("nt", "ssdp:all"));
+    /// assert!(req.header_match("x-long", "a b"));
+    ///
+    /// let options = ParseOptions::new().duplicate_headers(DuplicateHeaders::FirstWins);
+    /// let req = NotifyRequest::parse_with(addr, data, &options).unwrap();
+    /// assert!(!req.header_match("nt", "ssdp:all"));
+    /// ```
+    ///
     /// # Arguments
     /// * `name` - The name of the header to match.
     /// * `value` - The value of the header to match.
@@ -116,8 +679,131 @@ impl NotifyRequest {
     }
 }
 
+/// A `NOTIFY` request borrowing its method, path, headers and body from the received data.
+///
+/// Parsing allocates only the list of headers, which makes it suited to filtering many messages
+/// before keeping a few with `to_owned`. Unlike [`NotifyRequest::parse`], folded header lines are
+/// rejected and headers whose value is not valid UTF-8 are skipped.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{NotifyRequestRef, Nts, test_vectors};
+///
+/// let addr = "192.0.2.10:1900".parse().unwrap();
+/// let req = NotifyRequestRef::parse(addr, test_vectors::SONOS_ALIVE).unwrap();
+/// assert_eq!(req.nts(), Some(Nts::Alive));
+/// assert_eq!(req.usn(), Some("uuid:RINCON_000000000000001400::upnp:rootdevice"));
+///
+/// let owned = req.to_owned();
+/// assert_eq!(owned.usn(), req.usn());
+/// ```
+#[derive(Debug, Clone)]
+pub struct NotifyRequestRef<'a> {
+    /// The remote address of the client that sent the request.
+    pub remote_addr: SocketAddr,
+    /// The HTTP method of the request (e.g., "NOTIFY").
+    pub method: &'a str,
+    /// The path of the request (e.g., "*").
+    pub path: &'a str,
+    /// The HTTP minor version, `0` for `HTTP/1.0` and `1` for `HTTP/1.1`.
+    pub version: Option<u8>,
+    /// The headers of the request as (name, value) pairs, values untrimmed.
+    pub headers: Vec<(&'a str, &'a str)>,
+    /// The body of the request.
+    pub body: &'a [u8],
+    /// Whether the body is shorter than `CONTENT-LENGTH`.
+    pub truncated: bool,
+}
+
+impl<'a> NotifyRequestRef<'a> {
+    /// Parse the request in `data` without copying it.
+    ///
+    /// # Errors
+    /// * `NotifyError::Incomplete` if the header section is incomplete.
+    /// * `NotifyError::TooManyHeaders` if `data` has more than 64 headers.
+    /// * `NotifyError::ParseError` if `data` is not a valid HTTP request.
+    pub fn parse(remote_addr: SocketAddr, data: &'a [u8]) -> Result<Self, NotifyError> {
+        let mut headers = header_buf(data, MAX_HEADERS);
+        let mut req = httparse::Request::new(&mut headers);
+        let httparse::Status::Complete(n) = req.parse(data)? else {
+            return Err(NotifyError::Incomplete);
+        };
+
+        let headers: Vec<(&'a str, &'a str)> = req
+            .headers
+            .iter()
+            .filter_map(|h| Some((h.name, std::str::from_utf8(h.value).ok()?)))
+            .collect();
+        let body = &data[n..];
+        let content_length = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+
+        Ok(Self {
+            remote_addr,
+            method: req.method.unwrap_or(""),
+            path: req.path.unwrap_or(""),
+            version: req.version,
+            headers,
+            body,
+            truncated: content_length.is_some_and(|len| body.len() < len),
+        })
+    }
+
+    /// The trimmed value of the first header called `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&'a str> {
+        self.headers_iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// All headers as trimmed name/value pairs, in order of appearance.
+    pub fn headers_iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + '_ {
+        self.headers.iter().map(|&(n, v)| (n, v.trim()))
+    }
+
+    /// The notification type (`NT` header).
+    pub fn nt(&self) -> Option<&'a str> {
+        self.header("NT")
+    }
+
+    /// The parsed notification sub type (`NTS` header), `None` if missing or unknown.
+    pub fn nts(&self) -> Option<Nts> {
+        self.header("NTS").and_then(Nts::parse)
+    }
+
+    /// The unique service name (`USN` header).
+    pub fn usn(&self) -> Option<&'a str> {
+        self.header("USN")
+    }
+
+    /// The URL of the device description (`LOCATION` header), not validated.
+    pub fn location(&self) -> Option<&'a str> {
+        self.header("LOCATION")
+    }
+
+    /// Copy the request into an owned [`NotifyRequest`].
+    pub fn to_owned(&self) -> NotifyRequest {
+        NotifyRequest {
+            remote_addr: self.remote_addr,
+            method: self.method.to_owned(),
+            path: self.path.to_owned(),
+            version: self.version,
+            headers: self
+                .headers
+                .iter()
+                .map(|&(n, v)| (n.to_owned(), v.to_owned()))
+                .collect(),
+            body: String::from_utf8_lossy(self.body).into_owned(),
+            truncated: self.truncated,
+        }
+    }
+}
+
 /// Represents a NOTIFY response sent to the client.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NotifyResponse {
     /// The remote address of the client that sent the request.
     pub remote_addr: SocketAddr,
@@ -128,3 +814,195 @@ pub struct NotifyResponse {
     /// The body of the response, which can contain additional information.
     pub body: String,
 }
+
+impl NotifyResponse {
+    /// Create a response to the message from `remote_addr`, without headers or body.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::NotifyResponse;
+    ///
+    /// let res = NotifyResponse::new("192.0.2.1:50000".parse().unwrap(), 200)
+    ///     .header("ST", "upnp:rootdevice")
+    ///     .header("USN", "uuid:1234::upnp:rootdevice");
+    /// assert_eq!(
+    ///     res.to_bytes(),
+    ///     b"HTTP/1.1 200 OK\r\nST: upnp:rootdevice\r\nUSN: uuid:1234::upnp:rootdevice\r\nEXT:\r\n\r\n"
+    /// );
+    /// ```
+    pub fn new(remote_addr: SocketAddr, status_code: u16) -> Self {
+        Self {
+            remote_addr,
+            status_code,
+            headers: vec![],
+            body: String::new(),
+        }
+    }
+
+    /// Add a header, the order of headers is kept.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Parse an HTTP response, e.g. one relayed by a proxy or captured in a test.
+    ///
+    /// Folded header lines are unfolded; the rest of `data` after the headers is the body.
+    /// Parsing what `to_bytes` wrote gives the response back, with the headers it added.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::NotifyResponse;
+    ///
+    /// let addr = "192.0.2.1:50000".parse().unwrap();
+    /// let res = NotifyResponse::new(addr, 200)
+    ///     .header("CACHE-CONTROL", "max-age=1800")
+    ///     .header("ST", "upnp:rootdevice")
+    ///     .header("USN", "uuid:1234::upnp:rootdevice");
+    ///
+    /// let parsed = NotifyResponse::parse(addr, &res.to_bytes()).unwrap();
+    /// assert_eq!(parsed.status_code, 200);
+    /// assert_eq!(parsed.st(), Some("upnp:rootdevice"));
+    /// assert_eq!(parsed.max_age(), Some(std::time::Duration::from_secs(1800)));
+    /// assert_eq!(parsed.to_bytes(), res.to_bytes());
+    /// ```
+    pub fn parse(remote_addr: SocketAddr, data: &[u8]) -> Result<Self, NotifyError> {
+        let data = unfold(data);
+        let mut headers = header_buf(&data, MAX_HEADERS);
+        let mut res = httparse::Response::new(&mut headers);
+        let httparse::Status::Complete(n) = res.parse(&data)? else {
+            return Err(NotifyError::Incomplete);
+        };
+
+        Ok(Self {
+            remote_addr,
+            status_code: res.code.unwrap_or_default(),
+            headers: res
+                .headers
+                .iter()
+                .map(|h| {
+                    (
+                        h.name.to_owned(),
+                        String::from_utf8_lossy(h.value).into_owned(),
+                    )
+                })
+                .collect(),
+            body: String::from_utf8_lossy(&data[n..]).into_owned(),
+        })
+    }
+
+    /// The trimmed value of the first header called `name`, compared case-insensitively.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim())
+    }
+
+    /// The search target answered for (`ST` header).
+    pub fn st(&self) -> Option<&str> {
+        self.header_value("ST")
+    }
+
+    /// The unique service name (`USN` header).
+    pub fn usn(&self) -> Option<&str> {
+        self.header_value("USN")
+    }
+
+    /// The URL of the device description (`LOCATION` header), not validated.
+    pub fn location(&self) -> Option<&str> {
+        self.header_value("LOCATION")
+    }
+
+    /// The `SERVER` header, describing the device's OS and UPnP stack.
+    pub fn server(&self) -> Option<&str> {
+        self.header_value("SERVER")
+    }
+
+    /// The parsed `CACHE-CONTROL` header.
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        self.header_value("CACHE-CONTROL").map(CacheControl::parse)
+    }
+
+    /// How long the response is valid, from the `CACHE-CONTROL` header.
+    pub fn max_age(&self) -> Option<Duration> {
+        self.cache_control()?.effective_max_age()
+    }
+
+    /// The boot instance of the device (`BOOTID.UPNP.ORG` header, UPnP 1.1).
+    pub fn boot_id(&self) -> Option<u32> {
+        self.header_value("BOOTID.UPNP.ORG")?.parse().ok()
+    }
+
+    /// The version of the device description (`CONFIGID.UPNP.ORG` header, UPnP 1.1).
+    pub fn config_id(&self) -> Option<u32> {
+        self.header_value("CONFIGID.UPNP.ORG")?.parse().ok()
+    }
+
+    /// Serialize the response as an HTTP/1.1 message.
+    ///
+    /// The empty `EXT` header search responses require is appended to `200 OK` responses lacking
+    /// it, as is `CONTENT-LENGTH` when there is a body. Line breaks in header names or values are
+    /// replaced by spaces so they cannot inject headers. `DATE` and `SERVER` are not added, see
+    /// `ServerHandle::send`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let has = |name: &str| {
+            self.headers
+                .iter()
+                .any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+        let mut buf = format!(
+            "HTTP/1.1 {} {}\r\n",
+            self.status_code,
+            reason_phrase(self.status_code)
+        );
+        write_headers(&mut buf, &self.headers);
+        if self.status_code == 200 && !has("EXT") {
+            buf.push_str("EXT:\r\n");
+        }
+        if !self.body.is_empty() && !has("CONTENT-LENGTH") {
+            let _ = write!(buf, "CONTENT-LENGTH: {}\r\n", self.body.len());
+        }
+        buf.push_str("\r\n");
+        buf.push_str(&self.body);
+
+        buf.into_bytes()
+    }
+}
+
+/// Append `headers` in order, each as `name: value` and CRLF.
+pub(crate) fn write_headers(buf: &mut String, headers: &[(String, String)]) {
+    for (name, value) in headers.iter() {
+        // Empty headers such as `EXT` are written without the space, like peers expect
+        match single_line(value) {
+            value if value.is_empty() => {
+                let _ = write!(buf, "{}:\r\n", single_line(name));
+            }
+            value => {
+                let _ = write!(buf, "{}: {value}\r\n", single_line(name));
+            }
+        }
+    }
+}
+
+/// `value` with its line breaks replaced by spaces, so it cannot end the line it is written on.
+pub(crate) fn single_line(value: &str) -> Cow<'_, str> {
+    if value.contains(['\r', '\n']) {
+        Cow::Owned(value.replace(['\r', '\n'], " "))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// The reason phrase of the status codes SSDP peers use, empty for others.
+fn reason_phrase(status_code: u16) -> &'static str {
+    match status_code {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        412 => "Precondition Failed",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
@@ -0,0 +1,79 @@
+use crate::header::{self, HeaderValue};
+
+/// The header instances of this crate identify themselves with, see `Server::identify`.
+pub const PEER_HEADER: &str = "X-TOKIO-SSDP";
+
+/// What another instance of this crate reported about itself in its `X-TOKIO-SSDP` header.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{NotifyRequest, PeerInfo};
+///
+/// let data = b"NOTIFY * HTTP/1.1\r\nX-TOKIO-SSDP: 1.0.0; features=\"server,client\"\r\n\r\n";
+/// let req = NotifyRequest::parse("192.168.1.2:1900".parse().unwrap(), data).unwrap();
+///
+/// let peer = req.peer_info().unwrap();
+/// assert_eq!(peer.version, "1.0.0");
+/// assert_eq!(peer.features, ["server", "client"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct PeerInfo {
+    /// The crate version.
+    pub version: String,
+    /// The cargo features the crate was built with.
+    pub features: Vec<String>,
+}
+
+impl PeerInfo {
+    /// The information this build sends.
+    pub fn local() -> Self {
+        // Every feature of Cargo.toml, so peers see exactly what this build supports
+        macro_rules! features {
+            ($($name:literal),*) => {
+                [$(($name, cfg!(feature = $name))),*]
+            };
+        }
+        let features = features!(
+            "server",
+            "client",
+            "signal",
+            "description",
+            "dial",
+            "tracing",
+            "serde",
+            "testing"
+        );
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            features: features
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_owned())
+                .collect(),
+        }
+    }
+
+    /// Parse the value of an `X-TOKIO-SSDP` header, `None` if it has no version.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = HeaderValue::parse(value);
+        if value.value().is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            version: value.value().to_owned(),
+            features: value
+                .param("features")
+                .map(|features| header::split_list(features).map(str::to_owned).collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The value of the `X-TOKIO-SSDP` header.
+    #[cfg(feature = "server")]
+    pub(crate) fn header_value(&self) -> String {
+        format!("{}; features=\"{}\"", self.version, self.features.join(","))
+    }
+}
@@ -0,0 +1,134 @@
+use std::{fmt, net::IpAddr};
+
+use crate::{Device, Requester, Subnet};
+
+/// Whether a configured [`Server`](crate::Server) would answer searches from the internet, see
+/// `Server::exposure_report`.
+///
+/// The server binds the SSDP port on all addresses, so a unicast `M-SEARCH` sent to any public
+/// address of the host reaches it unless a firewall drops it. Answering such searches is what
+/// turned many routers into reflection amplifiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExposureReport {
+    /// The UDP port the server listens on.
+    pub port: u16,
+    /// Addresses of this host that are reachable from the internet, i.e. neither private
+    /// (RFC 1918, unique local), shared (RFC 6598), loopback nor link-local.
+    pub public_addresses: Vec<IpAddr>,
+    /// The `USN`s of the devices a requester with a public address would be told about.
+    ///
+    /// Only subnet visibility rules hide a device, `CPUUID.UPNP.ORG` is chosen by the requester.
+    pub exposed_devices: Vec<String>,
+    /// The search targets of response templates, which are answered for any requester.
+    pub exposed_templates: Vec<String>,
+    /// Whether a response policy or request handlers are set. They may refuse requests from
+    /// public addresses, but cannot be evaluated here.
+    pub custom_filters: bool,
+}
+
+impl ExposureReport {
+    /// Whether searches from the internet could reach the server and would be answered.
+    pub fn is_exposed(&self) -> bool {
+        !self.public_addresses.is_empty()
+            && (!self.exposed_devices.is_empty() || !self.exposed_templates.is_empty())
+    }
+}
+
+impl fmt::Display for ExposureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.public_addresses.is_empty() {
+            return write!(f, "not exposed: no public address on this host");
+        }
+        if !self.is_exposed() {
+            return write!(f, "not exposed: nothing is revealed to public requesters");
+        }
+
+        write!(f, "exposed on UDP port {} of", self.port)?;
+        for ip in self.public_addresses.iter() {
+            write!(f, " {ip}")?;
+        }
+        write!(
+            f,
+            ": {} device(s) and {} template(s) answer public requesters",
+            self.exposed_devices.len(),
+            self.exposed_templates.len()
+        )?;
+        if self.custom_filters {
+            write!(f, " unless the custom filters refuse them")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `ip` is routable on the internet.
+pub(crate) fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            let shared = a == 100 && (64..128).contains(&b);
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || shared)
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            let unique_local = first & 0xfe00 == 0xfc00;
+            let link_local = first & 0xffc0 == 0xfe80;
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || unique_local
+                || link_local)
+        }
+    }
+}
+
+/// The ranges of the addresses `is_public` rejects.
+const NON_PUBLIC_RANGES: [&str; 14] = [
+    "0.0.0.0/32",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "224.0.0.0/4",
+    "255.255.255.255/32",
+    "::/128",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// Whether no address of `subnet` is routable on the internet.
+fn is_private_subnet(subnet: &Subnet) -> bool {
+    NON_PUBLIC_RANGES
+        .iter()
+        .filter_map(|range| range.parse::<Subnet>().ok())
+        .any(|range| range.prefix_len() <= subnet.prefix_len() && range.contains(subnet.addr()))
+}
+
+/// Whether `device` may be revealed to some requester with a public address.
+///
+/// Requesters choose their `CPUUID.UPNP.ORG`, so only subnet rules restrict them: a device is
+/// hidden if it is only allowed to private subnets, or denied to everyone. An allowed subnet
+/// with any public address, such as `0.0.0.0/0`, reveals it.
+pub(crate) fn revealed_to_public(device: &Device) -> bool {
+    let denied_to_all = device
+        .deny
+        .iter()
+        .any(|r| matches!(r, Requester::Subnet(subnet) if subnet.prefix_len() == 0));
+    let private_only = !device.allow.is_empty()
+        && device
+            .allow
+            .iter()
+            .all(|r| matches!(r, Requester::Subnet(subnet) if is_private_subnet(subnet)));
+
+    !(denied_to_all || private_only)
+}
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+/// How eagerly the server trades CPU wake-ups for announcement timeliness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum PowerProfile {
+    /// Re-announce every half `max-age` so caches never lapse, pacing packets 50ms apart.
+    Aggressive,
+    /// Re-announce every `max-age`, pacing packets 50ms apart.
+    #[default]
+    Balanced,
+    /// Re-announce every `max-age` in a single burst and round timers to whole seconds, so the
+    /// CPU wakes up once per round instead of once per device.
+    LowPower,
+}
+
+impl PowerProfile {
+    /// Delay between two packets of the same announcement round.
+    pub(crate) fn pacing(&self) -> Duration {
+        match self {
+            PowerProfile::Aggressive | PowerProfile::Balanced => Duration::from_millis(50),
+            PowerProfile::LowPower => Duration::ZERO,
+        }
+    }
+
+    /// Delay between two announcement rounds.
+    pub(crate) fn announce_interval(&self, max_age: u64) -> Duration {
+        match self {
+            PowerProfile::Aggressive => Duration::from_secs(max_age) / 2,
+            PowerProfile::Balanced | PowerProfile::LowPower => Duration::from_secs(max_age),
+        }
+    }
+
+    /// Granularity timers are rounded up to when none was configured.
+    pub(crate) fn timer_granularity(&self) -> Duration {
+        match self {
+            PowerProfile::Aggressive | PowerProfile::Balanced => Duration::ZERO,
+            PowerProfile::LowPower => Duration::from_secs(1),
+        }
+    }
+}
+
+/// Round `delay` up to a multiple of `granularity`, so nearby timers fire together.
+pub(crate) fn coarsen(delay: Duration, granularity: Duration) -> Duration {
+    if granularity.is_zero() {
+        return delay;
+    }
+
+    let steps = delay.as_nanos().div_ceil(granularity.as_nanos());
+    granularity * u32::try_from(steps).unwrap_or(u32::MAX)
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+
+use tokio::sync::{Notify, futures::Notified};
+
+use crate::Device;
+
+/// Contributes devices to a running server, see `Server::device_provider`.
+///
+/// The server asks for the devices when it starts and again whenever `changed` resolves. Devices
+/// that appeared or changed are announced with `ssdp:alive` right away, those that disappeared
+/// with `ssdp:byebye`. Root devices are expanded like the ones given to `Server::new`.
+///
+/// # Examples
+/// ```
+/// use std::{future::Future, pin::Pin, sync::Mutex};
+/// use tokio::sync::Notify;
+/// use tokio_ssdp::{Device, DeviceProvider, Server};
+///
+/// /// Cameras found by some bridge, which calls `set` when they change.
+/// #[derive(Debug, Default)]
+/// struct Cameras {
+///     devices: Mutex<Vec<Device>>,
+///     changed: Notify,
+/// }
+///
+/// impl Cameras {
+///     fn set(&self, devices: Vec<Device>) {
+///         *self.devices.lock().unwrap() = devices;
+///         self.changed.notify_one();
+///     }
+/// }
+///
+/// impl DeviceProvider for Cameras {
+///     fn devices(&self) -> Pin<Box<dyn Future<Output = Vec<Device>> + Send + '_>> {
+///         Box::pin(async { self.devices.lock().unwrap().clone() })
+///     }
+///
+///     fn changed(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+///         Box::pin(self.changed.notified())
+///     }
+/// }
+///
+/// Server::new([])
+///   .device_provider(Cameras::default());
+/// ```
+pub trait DeviceProvider: Debug + Send + Sync {
+    /// The devices to advertise now.
+    fn devices(&self) -> Pin<Box<dyn Future<Output = Vec<Device>> + Send + '_>>;
+
+    /// Resolves once `devices` may return something else. Never resolves by default, so the
+    /// devices are only asked for once; sleep in it to poll periodically.
+    fn changed(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(std::future::pending())
+    }
+}
+
+/// The devices a server advertises, gathered from several sources.
+///
+/// Source `0` holds the devices given to `Server::new` and added through a `ServerHandle`.
+/// Readers take a snapshot, so sources can change while an announcement round is going on.
+#[derive(Debug)]
+pub(crate) struct DeviceSet {
+    inner: Arc<RwLock<Sources>>,
+    changed: Notify,
+}
+
+#[derive(Debug)]
+struct Sources {
+    /// The root devices of source `0`, before expanding them.
+    roots: Vec<Device>,
+    sources: Vec<Vec<Device>>,
+    snapshot: Arc<[Device]>,
+    /// UUIDs of the lazy devices that were searched for.
+    awake: HashSet<String>,
+}
+
+impl DeviceSet {
+    pub(crate) fn new(roots: Vec<Device>) -> Self {
+        let devices: Vec<Device> = roots.iter().flat_map(|d| d.expand()).collect();
+        Self {
+            inner: Arc::new(RwLock::new(Sources {
+                roots,
+                snapshot: devices.clone().into(),
+                sources: vec![devices],
+                awake: HashSet::new(),
+            })),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Resolves when the devices of a source change after this was called.
+    pub(crate) fn changed(&self) -> Notified<'_> {
+        self.changed.notified()
+    }
+
+    /// The devices of all sources, deduplicated by USN.
+    pub(crate) fn snapshot(&self) -> Arc<[Device]> {
+        let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(&inner.snapshot)
+    }
+
+    /// Whether `device` may be announced, i.e. it is not lazy or was searched for.
+    pub(crate) fn is_awake(&self, device: &Device) -> bool {
+        !device.lazy || {
+            let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+            inner.awake.contains(&device.uuid)
+        }
+    }
+
+    /// Mark the lazy devices among `found` as searched for, returning the advertisements of the
+    /// UUIDs that were not before.
+    pub(crate) fn wake<'a>(&self, found: impl IntoIterator<Item = &'a Device>) -> Vec<Device> {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let woken: Vec<String> = found
+            .into_iter()
+            .filter(|d| d.lazy && inner.awake.insert(d.uuid.clone()))
+            .map(|d| d.uuid.clone())
+            .collect();
+        if woken.is_empty() {
+            return vec![];
+        }
+
+        inner
+            .snapshot
+            .iter()
+            .filter(|d| woken.contains(&d.uuid))
+            .cloned()
+            .collect()
+    }
+
+    /// Add an empty source, returning its index.
+    pub(crate) fn add_source(&self) -> usize {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        inner.sources.push(vec![]);
+        inner.sources.len() - 1
+    }
+
+    /// Replace the devices of `source`, returning the devices that were removed from, added to
+    /// and changed in the set.
+    pub(crate) fn replace(&self, source: usize, devices: Vec<Device>) -> Changes {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        inner.sources[source] = devices;
+        let changes = inner.rebuild();
+        self.changed.notify_waiters();
+        changes
+    }
+
+    /// Add the root device `device` to source `0`, replacing the one with its UUID, returning
+    /// the changes like `replace`.
+    pub(crate) fn insert_root(&self, device: Device) -> Changes {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        match inner.roots.iter_mut().find(|d| d.uuid == device.uuid) {
+            Some(root) => *root = device,
+            None => inner.roots.push(device),
+        }
+        inner.expand_roots();
+        let changes = inner.rebuild();
+        self.changed.notify_waiters();
+        changes
+    }
+
+    /// Remove the root device `uuid` from source `0` with its embedded devices, returning the
+    /// changes like `replace`, or `None` if there is no such root device.
+    pub(crate) fn remove_root(&self, uuid: &str) -> Option<Changes> {
+        let mut inner = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        let count = inner.roots.len();
+        inner.roots.retain(|d| d.uuid != uuid);
+        if inner.roots.len() == count {
+            return None;
+        }
+        inner.expand_roots();
+        let changes = inner.rebuild();
+        self.changed.notify_waiters();
+        Some(changes)
+    }
+}
+
+/// The devices removed from, added to and changed in a [`DeviceSet`].
+pub(crate) type Changes = (Vec<Device>, Vec<Device>, Vec<Device>);
+
+impl Sources {
+    /// Set source `0` to the advertisements of the root devices.
+    fn expand_roots(&mut self) {
+        self.sources[0] = self.roots.iter().flat_map(|d| d.expand()).collect();
+    }
+
+    /// Gather the devices of all sources again, returning the changes to the snapshot.
+    fn rebuild(&mut self) -> Changes {
+        let mut all: Vec<Device> = Vec::new();
+        for device in self.sources.iter().flatten() {
+            if !all.iter().any(|d| d.usn == device.usn) {
+                all.push(device.clone());
+            }
+        }
+        let before = std::mem::replace(&mut self.snapshot, all.into());
+
+        let after = &self.snapshot;
+        let removed = before
+            .iter()
+            .filter(|d| !after.iter().any(|a| a.usn == d.usn))
+            .cloned()
+            .collect();
+        let added = after
+            .iter()
+            .filter(|d| !before.iter().any(|b| b.usn == d.usn))
+            .cloned()
+            .collect();
+        let updated = after
+            .iter()
+            .filter(|d| before.iter().any(|b| b.usn == d.usn && b != *d))
+            .cloned()
+            .collect();
+
+        (removed, added, updated)
+    }
+}
+
+impl Clone for DeviceSet {
+    /// A set with the same devices, changing independently of this one.
+    fn clone(&self) -> Self {
+        let inner = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        Self {
+            inner: Arc::new(RwLock::new(Sources {
+                roots: inner.roots.clone(),
+                sources: inner.sources.clone(),
+                snapshot: Arc::clone(&inner.snapshot),
+                awake: inner.awake.clone(),
+            })),
+            changed: Notify::new(),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+/// Headers a message cannot do without, never pruned. `OPT` and `01-NLS` go together, a lone
+/// one would announce an extension the message does not follow.
+const REQUIRED: [&str; 18] = [
+    "HOST",
+    "CACHE-CONTROL",
+    "DATE",
+    "EXT",
+    "LOCATION",
+    "NT",
+    "NTS",
+    "SERVER",
+    "ST",
+    "USN",
+    "BOOTID.UPNP.ORG",
+    "CONFIGID.UPNP.ORG",
+    "NEXTBOOTID.UPNP.ORG",
+    "SEARCHPORT.UPNP.ORG",
+    "OPT",
+    "01-NLS",
+    "CONTENT-TYPE",
+    "CONTENT-LENGTH",
+];
+
+/// Remove optional headers from `message` until it is at most `max` bytes long.
+///
+/// The headers named in `order` go first, in that order, then the remaining optional ones from
+/// the last to the first. Returns the names of the pruned headers, or `Err` with them if the
+/// message is still too long.
+pub(crate) fn prune(
+    message: &mut String,
+    max: usize,
+    order: &[String],
+) -> Result<Vec<String>, Vec<String>> {
+    if message.len() <= max {
+        return Ok(vec![]);
+    }
+
+    let Some(end) = message.find("\r\n\r\n") else {
+        return Err(vec![]);
+    };
+    let body = message.split_off(end);
+    let mut lines: Vec<&str> = message.split("\r\n").collect();
+    let name = |line: &str| line.split(':').next().unwrap_or_default().trim().to_owned();
+
+    // Indices of the lines to remove, most expendable first
+    let optional: Vec<usize> = (1..lines.len())
+        .rev()
+        .filter(|&i| {
+            let name = name(lines[i]);
+            !REQUIRED.iter().any(|r| r.eq_ignore_ascii_case(&name))
+        })
+        .collect();
+    let mut candidates: Vec<usize> = order
+        .iter()
+        .flat_map(|o| {
+            optional
+                .iter()
+                .copied()
+                .filter(|&i| name(lines[i]).eq_ignore_ascii_case(o))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    let rest: Vec<usize> = optional
+        .into_iter()
+        .filter(|i| !candidates.contains(i))
+        .collect();
+    candidates.extend(rest);
+
+    let mut len = message.len() + body.len();
+    let mut pruned = vec![];
+    for i in candidates {
+        if len <= max {
+            break;
+        }
+        len -= lines[i].len() + 2;
+        pruned.push(name(lines[i]));
+        lines[i] = "";
+    }
+
+    let head = lines
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    *message = head + &body;
+
+    if message.len() <= max {
+        Ok(pruned)
+    } else {
+        Err(pruned)
+    }
+}
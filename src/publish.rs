@@ -0,0 +1,111 @@
+use std::{future::Future, io, path::Path};
+
+use log::info;
+
+use crate::{DescriptionServer, Device, DeviceDescription, Error, HttpHandler, Result, Server};
+
+/// Make a device discoverable on the local network in one call.
+///
+/// Advertises the root device `uuid` of type `device_type` with SSDP, generates its description
+/// and serves it over HTTP on a free port, where `handler` answers every other request. The
+/// description names the device after its type; use [`DescriptionServer`] and [`Server`]
+/// directly for anything more. Pair it with [`persistent_uuid`] so the device keeps its identity
+/// across restarts.
+///
+/// Returns a future that needs to be `await`ed to keep the device published.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::{HttpHandler, persistent_uuid, publish_device};
+///
+/// #[derive(Debug)]
+/// struct Pages;
+///
+/// impl HttpHandler for Pages {
+///     fn get(&self, _path: &str) -> Option<(String, Vec<u8>)> {
+///         None
+///     }
+/// }
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// let uuid = persistent_uuid("device.uuid")?;
+/// publish_device(&uuid, "urn:schemas-upnp-org:device:Basic:1", Pages)?.await
+/// # }
+/// ```
+pub fn publish_device<H: HttpHandler + 'static>(
+    uuid: &str,
+    device_type: &str,
+    handler: H,
+) -> Result<impl Future<Output = Result<()>> + use<H>> {
+    let device = Device::new(uuid, device_type, "");
+    device.validate()?;
+
+    let name = type_name(device_type);
+    let descriptions = DescriptionServer::bind(0)?
+        .describe(&device, DeviceDescription::new(name, "tokio-ssdp", name))
+        .http_handler(handler);
+    info!(
+        "Publishing {device_type} {uuid}, described on port {}",
+        descriptions.port()
+    );
+
+    let server = Server::new([
+        Device::new(uuid, "upnp:rootdevice", ""),
+        Device::new(uuid, "", ""),
+        device,
+    ])
+    .location_resolver(descriptions.locations());
+    let descriptions = descriptions.serve()?;
+    let server = server.serve()?;
+
+    Ok(async move {
+        tokio::select! {
+            res = descriptions => res,
+            res = server => res,
+        }
+    })
+}
+
+/// The UUID stored in the file at `path`, or a new random one stored there if it does not exist.
+///
+/// Devices must keep their UUID across restarts, or control points see a new device each time.
+pub fn persistent_uuid(path: impl AsRef<Path>) -> Result<String> {
+    let path = path.as_ref();
+    match std::fs::read_to_string(path) {
+        Ok(uuid) if !uuid.trim().is_empty() => return Ok(uuid.trim().to_owned()),
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(Error::from(e)),
+    }
+
+    let uuid = random_uuid();
+    std::fs::write(path, format!("{uuid}\n"))?;
+    info!("Stored the new device UUID {uuid} in {}", path.display());
+    Ok(uuid)
+}
+
+/// A random (version 4) UUID.
+fn random_uuid() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// The name of a device type, e.g. `MediaServer` for
+/// `urn:schemas-upnp-org:device:MediaServer:1`.
+fn type_name(device_type: &str) -> &str {
+    match device_type.split(':').collect::<Vec<_>>()[..] {
+        ["urn", _, "device", name, _] => name,
+        _ => device_type,
+    }
+}
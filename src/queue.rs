@@ -0,0 +1,68 @@
+use std::{collections::VecDeque, net::SocketAddr, sync::Mutex};
+
+use tokio::{sync::Notify, time::Instant};
+
+/// Which datagram a full receive queue gives up, see `Server::recv_queue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropPolicy {
+    /// Drop the oldest queued datagram to make room, favoring fresh traffic.
+    DropOldest,
+    /// Drop the datagram that just arrived, favoring what was queued first.
+    DropNewest,
+}
+
+/// A received datagram: its bytes, sender, the index of the channel it arrived on and when.
+pub(crate) type Datagram = (Vec<u8>, SocketAddr, usize, Instant);
+
+/// The datagrams received and not yet processed, at most `capacity` of them.
+#[derive(Debug)]
+pub(crate) struct RecvQueue {
+    capacity: usize,
+    policy: DropPolicy,
+    datagrams: Mutex<VecDeque<Datagram>>,
+    ready: Notify,
+}
+
+impl RecvQueue {
+    pub(crate) fn new(capacity: usize, policy: DropPolicy) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            policy,
+            datagrams: Mutex::new(VecDeque::new()),
+            ready: Notify::new(),
+        }
+    }
+
+    /// Queue `datagram`, returning the sender of the datagram dropped to respect the capacity.
+    pub(crate) fn push(&self, datagram: Datagram) -> Option<SocketAddr> {
+        let mut datagrams = self.datagrams.lock().unwrap_or_else(|e| e.into_inner());
+        let dropped = if datagrams.len() < self.capacity {
+            None
+        } else {
+            match self.policy {
+                DropPolicy::DropOldest => datagrams.pop_front().map(|(_, addr, _, _)| addr),
+                DropPolicy::DropNewest => return Some(datagram.1),
+            }
+        };
+        datagrams.push_back(datagram);
+        drop(datagrams);
+
+        self.ready.notify_one();
+        dropped
+    }
+
+    /// Wait for the oldest queued datagram.
+    pub(crate) async fn pop(&self) -> Datagram {
+        loop {
+            let next = self
+                .datagrams
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .pop_front();
+            if let Some(datagram) = next {
+                return datagram;
+            }
+            self.ready.notified().await;
+        }
+    }
+}
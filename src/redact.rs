@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+
+/// What to hide from the server's logs and audit records, see `Server::redaction`.
+///
+/// Logs of a home network name its devices and addresses. Redacting them lets users attach logs
+/// to support requests.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::Redaction;
+///
+/// let redaction = Redaction::new().uuid_suffix(true).location_host(true);
+/// assert_eq!(
+///     redaction.apply("USN: uuid:2fac1234-31f8-11b4-a222-08002b34c003::upnp:rootdevice"),
+///     "USN: uuid:2fac1234-***::upnp:rootdevice"
+/// );
+/// assert_eq!(
+///     redaction.apply("LOCATION: http://192.168.1.20:8080/desc.xml"),
+///     "LOCATION: http://***:8080/desc.xml"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Redaction {
+    uuid_suffix: bool,
+    location_host: bool,
+}
+
+impl Redaction {
+    /// Redact nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep only the first group of `uuid:` identifiers, e.g. `uuid:2fac1234-***`.
+    pub fn uuid_suffix(mut self, enabled: bool) -> Self {
+        self.uuid_suffix = enabled;
+        self
+    }
+
+    /// Mask the host of `http://` and `https://` URLs, keeping scheme, port and path.
+    pub fn location_host(mut self, enabled: bool) -> Self {
+        self.location_host = enabled;
+        self
+    }
+
+    /// Whether any rule is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.uuid_suffix || self.location_host
+    }
+
+    /// Apply the enabled rules to `text`.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        if self.uuid_suffix {
+            text = redact_uuids(&text).map_or(text, Cow::Owned);
+        }
+        if self.location_host {
+            text = redact_hosts(&text).map_or(text, Cow::Owned);
+        }
+        text
+    }
+}
+
+/// Replace what follows the first group of every UUID, `None` if there is none.
+fn redact_uuids(text: &str) -> Option<String> {
+    const PREFIX: &str = "uuid:";
+    let is_uuid_char = |c: char| c.is_ascii_hexdigit() || c == '-';
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut found = false;
+    while let Some(start) = find_ignore_case(rest, PREFIX) {
+        let (head, tail) = rest.split_at(start + PREFIX.len());
+        out.push_str(head);
+        let len = tail.find(|c| !is_uuid_char(c)).unwrap_or(tail.len());
+        let uuid = &tail[..len];
+        match uuid.find('-') {
+            Some(dash) if dash + 1 < uuid.len() => {
+                out.push_str(&uuid[..=dash]);
+                out.push_str("***");
+                found = true;
+            }
+            _ => out.push_str(uuid),
+        }
+        rest = &tail[len..];
+    }
+    out.push_str(rest);
+
+    found.then_some(out)
+}
+
+/// Replace the host of every HTTP URL, `None` if there is none.
+fn redact_hosts(text: &str) -> Option<String> {
+    const SEPARATOR: &str = "://";
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut found = false;
+    while let Some(start) = rest.find(SEPARATOR) {
+        let (head, tail) = rest.split_at(start + SEPARATOR.len());
+        out.push_str(head);
+        if !(head.to_ascii_lowercase().ends_with("http://")
+            || head.to_ascii_lowercase().ends_with("https://"))
+        {
+            rest = tail;
+            continue;
+        }
+
+        // IPv6 hosts are bracketed and contain colons
+        let len = if tail.starts_with('[') {
+            tail.find(']').map_or(tail.len(), |end| end + 1)
+        } else {
+            tail.find([':', '/', '?', '#', ' ', '\r', '\n', '"', '>'])
+                .unwrap_or(tail.len())
+        };
+        if len > 0 {
+            out.push_str("***");
+            found = true;
+        }
+        rest = &tail[len..];
+    }
+    out.push_str(rest);
+
+    found.then_some(out)
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use log::debug;
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{CacheControl, NotifyRequest, Nts, SearchResponse};
+
+/// How long advertisements without a usable `CACHE-CONTROL` stay valid, the minimum the UPnP
+/// Device Architecture allows.
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(1800);
+
+/// The longest an advertisement stays valid, whatever its `CACHE-CONTROL` claims.
+const MAX_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The headers whose change makes a known device [`RegistryEvent::Updated`].
+const TRACKED_HEADERS: [&str; 3] = ["SERVER", "BOOTID.UPNP.ORG", "CONFIGID.UPNP.ORG"];
+
+/// A device or service seen on the network, see [`DeviceRegistry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RemoteDevice {
+    /// The unique service name (`USN` header).
+    pub usn: String,
+    /// The notification type or search target it was seen with (`NT` or `ST` header).
+    pub search_target: String,
+    /// The URL of the device description (`LOCATION` header).
+    pub location: String,
+    /// The address the last message came from.
+    pub remote_addr: SocketAddr,
+    /// The headers of the last message, in order of appearance.
+    pub headers: Vec<(String, String)>,
+    /// When the advertisement expires unless renewed.
+    pub expires: Instant,
+}
+
+impl RemoteDevice {
+    /// The value of the first header called `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A change of the devices known to a [`DeviceRegistry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RegistryEvent {
+    /// A device was seen for the first time.
+    Added(RemoteDevice),
+    /// A known device changed its `LOCATION`, `SERVER`, boot ID or configuration ID.
+    Updated(RemoteDevice),
+    /// A device was not renewed before its `CACHE-CONTROL` max-age ran out.
+    Expired(RemoteDevice),
+    /// A device left with `ssdp:byebye`.
+    Removed(RemoteDevice),
+}
+
+/// Tracks the devices present on the network from their announcements and search responses.
+///
+/// Feed it the `NOTIFY` messages and search responses received, e.g. from a
+/// [`Server::event_stream`](crate::Server::event_stream) or a [`Search`](crate::Search), and call
+/// `expired` to retire devices that were not renewed in time. Changes are reported on the
+/// [`RegistryEvents`] stream.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{DeviceRegistry, NotifyRequest, RegistryEvent};
+///
+/// let (mut registry, mut events) = DeviceRegistry::new(16);
+///
+/// let alive = b"NOTIFY * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nCACHE-CONTROL: max-age=1800\r\n\
+///     LOCATION: http://192.168.1.2/desc.xml\r\nNT: upnp:rootdevice\r\nNTS: ssdp:alive\r\n\
+///     USN: uuid:1234::upnp:rootdevice\r\n\r\n";
+/// let addr = "192.168.1.2:1900".parse().unwrap();
+/// registry.observe_notify(&NotifyRequest::parse(addr, alive).unwrap());
+///
+/// assert_eq!(registry.len(), 1);
+/// assert!(matches!(events.try_next(), Some(RegistryEvent::Added(d)) if d.usn == "uuid:1234::upnp:rootdevice"));
+/// ```
+#[derive(Debug)]
+pub struct DeviceRegistry {
+    devices: HashMap<String, RemoteDevice>,
+    tx: mpsc::Sender<RegistryEvent>,
+}
+
+impl DeviceRegistry {
+    /// Create an empty registry and the stream of its events, buffering at most `capacity`.
+    ///
+    /// When the stream lags behind by `capacity` events, new ones are dropped rather than
+    /// stalling the caller. A `capacity` of 0 buffers one event.
+    pub fn new(capacity: usize) -> (Self, RegistryEvents) {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        let registry = Self {
+            devices: HashMap::new(),
+            tx,
+        };
+        (registry, RegistryEvents { rx })
+    }
+
+    /// Take a `NOTIFY` message into account.
+    ///
+    /// `ssdp:alive` adds or renews the device, `ssdp:byebye` removes it and `ssdp:update` updates
+    /// a known one. Messages without `USN` or an `NTS` are ignored.
+    pub fn observe_notify(&mut self, request: &NotifyRequest) {
+        let (Some(usn), Some(nts)) = (request.usn(), request.nts()) else {
+            debug!(
+                "Registry ignores NOTIFY without USN or NTS from {}",
+                request.remote_addr
+            );
+            return;
+        };
+
+        match nts {
+            Nts::Byebye => {
+                if let Some(device) = self.devices.remove(usn) {
+                    self.emit(RegistryEvent::Removed(device));
+                }
+            }
+            Nts::Alive | Nts::Update => {
+                let (Some(nt), Some(location)) = (request.nt(), request.location()) else {
+                    debug!("Registry ignores NOTIFY without NT or LOCATION for {usn}");
+                    return;
+                };
+                if nts == Nts::Update && !self.devices.contains_key(usn) {
+                    return;
+                }
+                // Updates do not renew the advertisement
+                let max_age = (nts == Nts::Alive).then(|| request.cache_control());
+                let device = RemoteDevice {
+                    usn: usn.to_owned(),
+                    search_target: nt.to_owned(),
+                    location: location.to_owned(),
+                    remote_addr: request.remote_addr,
+                    headers: request.headers.clone(),
+                    expires: Instant::now(),
+                };
+                self.upsert(device, max_age);
+            }
+        }
+    }
+
+    /// Take a response to an `M-SEARCH` into account, adding or renewing the device.
+    pub fn observe_response(&mut self, response: &SearchResponse) {
+        let device = RemoteDevice {
+            usn: response.usn.clone(),
+            search_target: response.search_target.clone(),
+            location: response.location.clone(),
+            remote_addr: response.remote_addr,
+            headers: response.headers.clone(),
+            expires: Instant::now(),
+        };
+        self.upsert(device, Some(response.cache_control()));
+    }
+
+    /// Take an event of a server into account, searches are ignored.
+    #[cfg(feature = "server")]
+    pub fn observe(&mut self, event: &crate::SsdpEvent) {
+        use crate::SsdpEvent;
+
+        match event {
+            SsdpEvent::Alive(request) | SsdpEvent::Byebye(request) | SsdpEvent::Update(request) => {
+                self.observe_notify(request)
+            }
+            SsdpEvent::SearchResponse(response) => self.observe_response(response),
+            _ => {}
+        }
+    }
+
+    /// Remove the devices whose advertisement ran out, reporting them as expired.
+    pub fn expire(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<String> = self
+            .devices
+            .values()
+            .filter(|d| d.expires <= now)
+            .map(|d| d.usn.clone())
+            .collect();
+        for usn in expired {
+            if let Some(device) = self.devices.remove(&usn) {
+                self.emit(RegistryEvent::Expired(device));
+            }
+        }
+    }
+
+    /// Wait until the next advertisement runs out and `expire` it, never resolves while the
+    /// registry is empty.
+    ///
+    /// This is cancellation safe, so it can be raced against incoming messages:
+    /// ```no_run
+    /// # use tokio::sync::mpsc::Receiver;
+    /// # use tokio_ssdp::{DeviceRegistry, NotifyRequest};
+    /// # async fn run(mut registry: DeviceRegistry, mut notifies: Receiver<NotifyRequest>) {
+    /// loop {
+    ///     tokio::select! {
+    ///         Some(request) = notifies.recv() => registry.observe_notify(&request),
+    ///         _ = registry.expired() => {}
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn expired(&mut self) {
+        match self.devices.values().map(|d| d.expires).min() {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+        self.expire();
+    }
+
+    /// The device with the unique service name `usn`, if known.
+    pub fn get(&self, usn: &str) -> Option<&RemoteDevice> {
+        self.devices.get(usn)
+    }
+
+    /// The known devices, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &RemoteDevice> {
+        self.devices.values()
+    }
+
+    /// The number of known devices.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Whether no device is known.
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Add or refresh `device`, renewing it for the max-age of `cache_control` unless `None`.
+    ///
+    /// The max-age is capped to a day, as it comes from the network.
+    fn upsert(&mut self, mut device: RemoteDevice, cache_control: Option<Option<CacheControl>>) {
+        let renewal = cache_control.map(|cc| {
+            let max_age = cc
+                .and_then(|cc| cc.effective_max_age())
+                .unwrap_or(DEFAULT_MAX_AGE)
+                .min(MAX_MAX_AGE);
+            Instant::now() + max_age
+        });
+
+        match self.devices.get_mut(&device.usn) {
+            Some(known) => {
+                device.expires = renewal.unwrap_or(known.expires);
+                let updated = known.location != device.location
+                    || TRACKED_HEADERS
+                        .iter()
+                        .any(|name| known.header(name) != device.header(name));
+                *known = device;
+                if updated {
+                    let device = known.clone();
+                    self.emit(RegistryEvent::Updated(device));
+                }
+            }
+            None => {
+                device.expires = renewal.unwrap_or_else(|| Instant::now() + DEFAULT_MAX_AGE);
+                self.devices.insert(device.usn.clone(), device.clone());
+                self.emit(RegistryEvent::Added(device));
+            }
+        }
+    }
+
+    fn emit(&self, event: RegistryEvent) {
+        if let Err(mpsc::error::TrySendError::Full(event)) = self.tx.try_send(event) {
+            debug!("Registry event stream is full, dropping {event:?}");
+        }
+    }
+}
+
+/// The changes of a [`DeviceRegistry`], see `DeviceRegistry::new`.
+#[derive(Debug)]
+pub struct RegistryEvents {
+    rx: mpsc::Receiver<RegistryEvent>,
+}
+
+impl RegistryEvents {
+    /// The next event, `None` once the registry was dropped.
+    pub async fn next(&mut self) -> Option<RegistryEvent> {
+        self.rx.recv().await
+    }
+
+    /// The next event if one is ready.
+    pub fn try_next(&mut self) -> Option<RegistryEvent> {
+        self.rx.try_recv().ok()
+    }
+}
+
+impl Stream for RegistryEvents {
+    type Item = RegistryEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
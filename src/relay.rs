@@ -0,0 +1,351 @@
+use std::{
+    future::Future,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use log::{debug, info, warn};
+use tokio::{
+    net::UdpSocket,
+    sync::{OwnedSemaphorePermit, Semaphore},
+};
+
+use crate::{
+    Error, Result, SSDP_PORT, SearchRequest, ServerError, Subnet,
+    channel::{self, Channel, ChannelOptions, Interface},
+    interface, location, notify,
+    server::{MAX_RECEIVE_FAILURES, RECEIVE_BACKOFF},
+};
+
+/// How long responses to a relayed search are forwarded at most, besides its `MX`.
+const MAX_MX: u32 = 5;
+
+/// How many relayed searches may wait for responses at once, each holding a socket of its own.
+const MAX_RELAYED_SEARCHES: usize = 64;
+
+/// The header marking messages sent by a relay, which relays do not forward again, so two of
+/// them on the same links cannot bounce a message back and forth.
+const RELAY_HEADER: &str = "X-TOKIO-SSDP-RELAY";
+
+/// Forwards SSDP traffic between networks, so devices on one are found from the others.
+///
+/// `NOTIFY` messages and `M-SEARCH` requests received on one interface are sent to the multicast
+/// group of every other interface. Searches are sent from a socket of their own, the responses
+/// arriving there within `MX` seconds are passed back to the control point. Interfaces are told
+/// apart by the subnets of their addresses, messages from other sources are not relayed.
+///
+/// Relayed messages carry an `X-TOKIO-SSDP-RELAY` header and are not relayed again, so relays
+/// sharing links do not loop. At most 64 searches wait for responses at once, others are dropped.
+///
+/// This only makes the devices visible: control points still need to reach the `LOCATION` URLs,
+/// through routing or, with `rewrite_location`, a proxy on this host.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::SsdpRelay;
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// // The LAN and the IoT VLAN
+/// SsdpRelay::new(["192.168.1.2".parse().unwrap(), "10.0.20.1".parse().unwrap()])
+///     .serve()?
+///     .await
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SsdpRelay {
+    interfaces: Vec<Ipv4Addr>,
+    port: u16,
+    ttl: Option<u32>,
+    rewrite_location: bool,
+}
+
+impl SsdpRelay {
+    /// Create a relay between the interfaces with addresses `ips`.
+    pub fn new(ips: impl IntoIterator<Item = Ipv4Addr>) -> Self {
+        Self {
+            interfaces: ips.into_iter().collect(),
+            port: SSDP_PORT,
+            ttl: None,
+            rewrite_location: false,
+        }
+    }
+
+    /// Listen and relay on `port`, see `Server::port`.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Set the multicast TTL of relayed messages, see `Server::ttl`.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Point the `LOCATION` of relayed messages and search responses to this host, keeping the
+    /// scheme, port and path, defaults to `false`.
+    ///
+    /// The host becomes the relay's address on the interface the message is forwarded to. Enable
+    /// it when the description servers are reached through a proxy on this host instead of being
+    /// routed to.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::SsdpRelay;
+    ///
+    /// SsdpRelay::new(["192.168.1.2".parse().unwrap(), "10.0.20.1".parse().unwrap()])
+    ///   .rewrite_location(true);
+    /// ```
+    pub fn rewrite_location(mut self, enabled: bool) -> Self {
+        self.rewrite_location = enabled;
+        self
+    }
+
+    /// Start relaying, returns a future that needs to be `await`ed to keep relaying.
+    ///
+    /// Fails with `Error::Config` if less than two distinct interfaces were given.
+    pub fn serve(self) -> Result<impl Future<Output = Result<()>> + use<>> {
+        let mut ips = self.interfaces;
+        ips.sort_unstable();
+        ips.dedup();
+        if ips.len() < 2 || ips.iter().any(|ip| ip.is_unspecified()) {
+            return Err(Error::Config(
+                "the relay needs the addresses of two interfaces or more".into(),
+            ));
+        }
+
+        let options = ChannelOptions {
+            port: self.port,
+            ttl: self.ttl,
+            response_ttl: None,
+            source: Default::default(),
+            reuse_port: false,
+            port_sharing: cfg!(windows),
+            // Relayed messages must not come back to the relay
+            multicast_loop: Some(false),
+            unicast_search: false,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            bind_device: None,
+        };
+        let channel = Arc::new(Channel::v4(&ips, &options)?);
+        let links: Arc<[Link]> = ips
+            .iter()
+            .map(|&ip| Link {
+                ip,
+                subnets: interface::link_subnets(&[Interface::V4(ip)]).unwrap_or_default(),
+            })
+            .collect();
+        for link in links.iter() {
+            info!("Relaying SSDP on {} ({:?})", link.ip, link.subnets);
+        }
+        let relay = Arc::new(Relay {
+            channel,
+            links,
+            ttl: self.ttl,
+            rewrite_location: self.rewrite_location,
+            searches: Arc::new(Semaphore::new(MAX_RELAYED_SEARCHES)),
+        });
+
+        Ok(async move {
+            let mut buf = [0u8; 4096];
+            let mut failures = 0;
+            loop {
+                match channel::recv_any(std::slice::from_ref(&*relay.channel), &mut buf).await {
+                    Ok((n, addr, _)) => {
+                        failures = 0;
+                        relay.forward(&buf[..n], addr).await;
+                    }
+                    // Like a server, keep relaying through errors that clear up
+                    Err(e) => {
+                        failures += 1;
+                        if ServerError::is_socket_failure(&e) || failures >= MAX_RECEIVE_FAILURES {
+                            return Err(e.into());
+                        }
+                        warn!("Relay failed to receive: {e}");
+                        tokio::time::sleep(RECEIVE_BACKOFF).await;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// An interface relayed and the subnets reached through it.
+#[derive(Debug)]
+struct Link {
+    ip: Ipv4Addr,
+    subnets: Vec<Subnet>,
+}
+
+/// A running relay.
+#[derive(Debug)]
+struct Relay {
+    channel: Arc<Channel>,
+    links: Arc<[Link]>,
+    ttl: Option<u32>,
+    rewrite_location: bool,
+    /// Bounds the searches waiting for responses, see `MAX_RELAYED_SEARCHES`.
+    searches: Arc<Semaphore>,
+}
+
+impl Relay {
+    /// Send the message `data` from `remote_addr` to the other interfaces.
+    async fn forward(self: &Arc<Self>, data: &[u8], remote_addr: SocketAddr) {
+        let ip = remote_addr.ip();
+        if self.links.iter().any(|link| IpAddr::V4(link.ip) == ip) {
+            return;
+        }
+        let Some(from) = self
+            .links
+            .iter()
+            .position(|link| link.subnets.iter().any(|s| s.contains(ip)))
+        else {
+            debug!("Not relaying from {remote_addr}, it is on no relayed interface");
+            return;
+        };
+
+        let mut headers = notify::header_buf(data, notify::MAX_HEADERS);
+        let mut req = httparse::Request::new(&mut headers);
+        if !matches!(req.parse(data), Ok(httparse::Status::Complete(_))) {
+            debug!("Not relaying an invalid message from {remote_addr}");
+            return;
+        }
+        if req
+            .headers
+            .iter()
+            .any(|h| h.name.eq_ignore_ascii_case(RELAY_HEADER))
+        {
+            debug!("Not relaying a message from {remote_addr} another relay sent");
+            return;
+        }
+        let search = match req.method {
+            Some("NOTIFY") => None,
+            Some("M-SEARCH") => match SearchRequest::from_request(&req, remote_addr) {
+                Ok(search) => Some(search),
+                Err(e) => {
+                    debug!("Not relaying the search from {remote_addr}: {e}");
+                    return;
+                }
+            },
+            _ => return,
+        };
+
+        for (i, link) in self.links.iter().enumerate().filter(|(i, _)| *i != from) {
+            let message = mark(self.rewrite(data, link.ip), link.ip);
+            let res = match &search {
+                None => {
+                    debug!("Relaying NOTIFY from {remote_addr} to {}", link.ip);
+                    self.send(&message, i).await
+                }
+                Some(search) => {
+                    let Ok(permit) = Arc::clone(&self.searches).try_acquire_owned() else {
+                        warn!("Too many searches relayed at once, dropping one from {remote_addr}");
+                        return;
+                    };
+                    debug!("Relaying M-SEARCH from {remote_addr} to {}", link.ip);
+                    self.search(message, search, i, from, permit).await
+                }
+            };
+            if let Err(e) = res {
+                warn!("Relaying to {} failed: {e}", link.ip);
+            }
+        }
+    }
+
+    /// Multicast `message` on the interface `link`.
+    async fn send(&self, message: &[u8], link: usize) -> std::io::Result<()> {
+        let group = self.channel.groups[0];
+        self.channel
+            .send_multicast(message, group, Interface::V4(self.links[link].ip))
+            .await
+    }
+
+    /// Multicast the search `message` on the interface `link` from a socket of its own, and pass
+    /// the responses on to the control point behind the interface `back`, holding `permit` until
+    /// done.
+    async fn search(
+        self: &Arc<Self>,
+        message: Vec<u8>,
+        search: &SearchRequest,
+        link: usize,
+        back: usize,
+        permit: OwnedSemaphorePermit,
+    ) -> std::io::Result<()> {
+        let socket = UdpSocket::bind((self.links[link].ip, 0)).await?;
+        socket.set_multicast_loop_v4(false)?;
+        if let Some(ttl) = self.ttl {
+            socket.set_multicast_ttl_v4(ttl)?;
+        }
+        socket.send_to(&message, self.channel.groups[0]).await?;
+
+        let this = Arc::clone(self);
+        let control_point = search.remote_addr;
+        let window = Duration::from_secs(u64::from(search.mx.clamp(1, MAX_MX)) + 1);
+        tokio::spawn(async move {
+            let pass_back = this.pass_back(&socket, control_point, back);
+            if let Ok(Err(e)) = tokio::time::timeout(window, pass_back).await {
+                warn!("Relaying responses to {control_point} failed: {e}");
+            }
+            drop(permit);
+        });
+        Ok(())
+    }
+
+    /// Send the responses arriving on `socket` to `control_point`, with their `LOCATION` on the
+    /// interface `back`.
+    async fn pass_back(
+        &self,
+        socket: &UdpSocket,
+        control_point: SocketAddr,
+        back: usize,
+    ) -> std::io::Result<()> {
+        let mut buf = [0u8; 4096];
+        loop {
+            let (n, addr) = socket.recv_from(&mut buf).await?;
+            debug!("Relaying a search response from {addr} to {control_point}");
+            let response = self.rewrite(&buf[..n], self.links[back].ip);
+            self.channel
+                .responder
+                .send_to(&response, control_point)
+                .await?;
+        }
+    }
+
+    /// `message` with its `LOCATION` on `ip` if enabled.
+    fn rewrite(&self, message: &[u8], ip: Ipv4Addr) -> Vec<u8> {
+        if !self.rewrite_location {
+            return message.to_vec();
+        }
+        let Ok(message) = std::str::from_utf8(message) else {
+            return message.to_vec();
+        };
+
+        let (head, body) = message.split_once("\r\n\r\n").unwrap_or((message, ""));
+        let mut out = String::with_capacity(message.len());
+        for line in head.split("\r\n") {
+            match line.split_once(':') {
+                Some((name, url)) if name.trim().eq_ignore_ascii_case("LOCATION") => {
+                    out.push_str(&format!(
+                        "{name}: {}",
+                        location::rehost(url.trim(), ip.into())
+                    ));
+                }
+                _ => out.push_str(line),
+            }
+            out.push_str("\r\n");
+        }
+        out.push_str("\r\n");
+        out.push_str(body);
+        out.into_bytes()
+    }
+}
+
+/// `message` with the relay header naming the relay's address `ip` after its other headers.
+fn mark(mut message: Vec<u8>, ip: Ipv4Addr) -> Vec<u8> {
+    if let Some(end) = message.windows(4).position(|w| w == b"\r\n\r\n") {
+        let header = format!("{RELAY_HEADER}: {ip}\r\n");
+        message.splice(end + 2..end + 2, header.into_bytes());
+    }
+    message
+}
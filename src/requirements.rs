@@ -0,0 +1,55 @@
+use std::{fmt, net::SocketAddr};
+
+/// The direction of traffic a [`NetworkRule`] allows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Datagrams received by this host.
+    Inbound,
+    /// Datagrams sent by this host.
+    Outbound,
+}
+
+/// A single firewall rule the configured server needs. All rules are for UDP.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct NetworkRule {
+    /// Whether the traffic is received or sent.
+    pub direction: Direction,
+    /// The local UDP port, `None` for an OS-assigned ephemeral port.
+    pub local_port: Option<u16>,
+    /// The remote address, `None` for any address and port.
+    pub remote: Option<SocketAddr>,
+    /// What the rule is for, suitable as a rule description.
+    pub purpose: &'static str,
+}
+
+impl fmt::Display for NetworkRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (verb, prep) = match self.direction {
+            Direction::Inbound => ("inbound", "from"),
+            Direction::Outbound => ("outbound", "to"),
+        };
+        write!(f, "{verb} UDP on ")?;
+        match self.local_port {
+            Some(port) => write!(f, "port {port}")?,
+            None => f.write_str("an ephemeral port")?,
+        }
+        match self.remote {
+            Some(remote) => write!(f, " {prep} {remote}")?,
+            None => write!(f, " {prep} any address")?,
+        }
+        write!(f, " ({})", self.purpose)
+    }
+}
+
+/// The traffic a configured [`Server`](crate::Server) sends and receives, see
+/// `Server::network_requirements`.
+///
+/// Installers can turn the rules into firewall rules instead of guessing. Joining the multicast
+/// group additionally requires IGMP, which host firewalls usually allow.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct NetworkRequirements {
+    /// The rules, one per kind of traffic.
+    pub rules: Vec<NetworkRule>,
+}
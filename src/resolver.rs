@@ -0,0 +1,32 @@
+use std::{fmt::Debug, net::IpAddr};
+
+use crate::Device;
+
+/// Computes the `LOCATION` advertised for a device from the local address traffic uses.
+///
+/// On a multi-homed host each interface has its own address, so a description URL hard-coded to
+/// one of them is unreachable from the other networks. The server calls the resolver with the
+/// address of the interface an announcement is sent through, or the address it answers a search
+/// from. Subnet-based `Server::location_override`s take precedence.
+///
+/// # Examples
+/// ```
+/// use std::net::IpAddr;
+/// use tokio_ssdp::{Device, LocationResolver, Server};
+///
+/// #[derive(Debug)]
+/// struct Port8080;
+///
+/// impl LocationResolver for Port8080 {
+///     fn location(&self, _device: &Device, local_addr: IpAddr) -> String {
+///         format!("http://{local_addr}:8080/desc.xml")
+///     }
+/// }
+///
+/// Server::new([])
+///   .location_resolver(Port8080);
+/// ```
+pub trait LocationResolver: Debug + Send + Sync {
+    /// The `LOCATION` of `device` for traffic leaving through the address `local_addr`.
+    fn location(&self, device: &Device, local_addr: IpAddr) -> String;
+}
@@ -0,0 +1,270 @@
+use std::net::SocketAddr;
+
+use crate::{CacheControl, DeviceKind, Error, NotifyError, PEER_HEADER, PeerInfo, Result, notify};
+
+/// A response to an `M-SEARCH`, announcing a device or service found by the search.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResponse {
+    /// The address the response came from.
+    pub remote_addr: SocketAddr,
+    /// The search target the device answered for (`ST` header).
+    pub search_target: String,
+    /// The unique service name (`USN` header).
+    pub usn: String,
+    /// The URL of the device description (`LOCATION` header).
+    pub location: String,
+    /// All headers in order of appearance.
+    pub headers: Vec<(String, String)>,
+}
+
+impl SearchResponse {
+    /// Parse a `200 OK` response to an `M-SEARCH`.
+    ///
+    /// Fails with `Error::Protocol` for other status codes or if `ST`, `USN` or `LOCATION` is
+    /// missing.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::SearchResponse;
+    ///
+    /// let data = b"HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nEXT:\r\n\
+    ///     LOCATION: http://192.168.1.2:80/desc.xml\r\nSERVER: Linux UPnP/1.0 Sonos/70.3\r\n\
+    ///     ST: upnp:rootdevice\r\nUSN: uuid:1234::upnp:rootdevice\r\n\r\n";
+    /// let res = SearchResponse::parse("192.168.1.2:1900".parse().unwrap(), data).unwrap();
+    /// assert_eq!(res.location, "http://192.168.1.2:80/desc.xml");
+    /// assert_eq!(res.usn, "uuid:1234::upnp:rootdevice");
+    /// assert_eq!(res.server(), Some("Linux UPnP/1.0 Sonos/70.3"));
+    /// ```
+    pub fn parse(remote_addr: SocketAddr, data: &[u8]) -> Result<Self> {
+        let mut headers = notify::header_buf(data, notify::MAX_HEADERS);
+        let mut res = httparse::Response::new(&mut headers);
+        if res.parse(data).map_err(NotifyError::from)?.is_partial() {
+            return Err(NotifyError::Incomplete.into());
+        }
+
+        let code = res.code.unwrap_or_default();
+        if code != 200 {
+            return Err(Error::Protocol(format!("unexpected status {code}")));
+        }
+
+        let headers: Vec<(String, String)> = res
+            .headers
+            .iter()
+            .map(|h| {
+                (
+                    h.name.to_owned(),
+                    String::from_utf8_lossy(h.value).trim().to_owned(),
+                )
+            })
+            .collect();
+        let find = |name: &'static str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone())
+                .ok_or_else(|| Error::Protocol(format!("{name} header not found")))
+        };
+
+        Ok(Self {
+            remote_addr,
+            search_target: find("ST")?,
+            usn: find("USN")?,
+            location: find("LOCATION")?,
+            headers,
+        })
+    }
+
+    /// The value of the first header called `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The `SERVER` header, describing the device's OS and UPnP stack.
+    pub fn server(&self) -> Option<&str> {
+        self.header("SERVER")
+    }
+
+    /// The parsed `CACHE-CONTROL` header, telling how long the result stays valid.
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        self.header("CACHE-CONTROL").map(CacheControl::parse)
+    }
+
+    /// The version and features of the responder if it runs this crate with `Server::identify`.
+    pub fn peer_info(&self) -> Option<PeerInfo> {
+        self.header(PEER_HEADER).and_then(PeerInfo::parse)
+    }
+
+    /// The kind of the responding device, guessed from its `SERVER` header.
+    pub fn device_kind(&self) -> DeviceKind {
+        self.server()
+            .map_or(DeviceKind::Unknown, DeviceKind::from_server)
+    }
+}
+
+/// A response to an `M-SEARCH` crafted by a [`RequestHandler`](crate::RequestHandler), see
+/// `HandlerDecision::Reply`.
+///
+/// The server sends it like its own responses: within the `MX` delay of the search, from the
+/// response socket, with `EXT`, the server-wide headers and the boot and config IDs. The
+/// `CACHE-CONTROL`, `DATE` and `SERVER` headers default to the server's. Responses failing
+/// `validate` are not sent.
+///
+/// # Examples
+/// ```
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use tokio_ssdp::{HandlerDecision, MSearchResponse, NotifyRequest, RequestHandler, Server};
+///
+/// /// Advertise the description server through the port forwarded to it on the router.
+/// #[derive(Debug)]
+/// struct Forwarded(IpAddr);
+///
+/// impl RequestHandler for Forwarded {
+///     fn handle(&self, request: &mut NotifyRequest) -> HandlerDecision {
+///         if request.method != "M-SEARCH" || request.remote_addr.ip().is_loopback() {
+///             return HandlerDecision::Continue;
+///         }
+///         HandlerDecision::Reply(vec![
+///             MSearchResponse::new(
+///                 "upnp:rootdevice",
+///                 "uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043::upnp:rootdevice",
+///                 format!("http://{}:18080/desc.xml", self.0),
+///             )
+///             .server("Linux/6.1 UPnP/1.1 Acme/2.0"),
+///         ])
+///     }
+/// }
+///
+/// Server::new([])
+///   .request_handler(Forwarded(Ipv4Addr::new(203, 0, 113, 7).into()));
+/// ```
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MSearchResponse {
+    pub(crate) search_target: String,
+    pub(crate) usn: String,
+    pub(crate) location: String,
+    pub(crate) max_age: Option<u64>,
+    pub(crate) date: Option<std::time::SystemTime>,
+    pub(crate) server: Option<String>,
+    pub(crate) headers: Vec<(String, String)>,
+}
+
+#[cfg(feature = "server")]
+impl MSearchResponse {
+    /// A response for the search target `search_target` of the service `usn`, described at
+    /// `location`.
+    pub fn new(
+        search_target: impl Into<String>,
+        usn: impl Into<String>,
+        location: impl Into<String>,
+    ) -> Self {
+        Self {
+            search_target: search_target.into(),
+            usn: usn.into(),
+            location: location.into(),
+            max_age: None,
+            date: None,
+            server: None,
+            headers: vec![],
+        }
+    }
+
+    /// The response the server would send for `device`, with its extra headers, to adjust e.g.
+    /// the `LOCATION`.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, MSearchResponse};
+    ///
+    /// let device = Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "upnp:rootdevice",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// );
+    /// let response = MSearchResponse::for_device(&device)
+    ///     .location("http://203.0.113.7:18080/desc.xml");
+    /// assert_eq!(response.usn(), device.usn());
+    /// ```
+    pub fn for_device(device: &crate::Device) -> Self {
+        Self {
+            headers: device.headers.clone(),
+            ..Self::new(&device.search_target, &device.usn, &device.location)
+        }
+    }
+
+    /// Set the URL of the description (`LOCATION` header).
+    pub fn location(mut self, url: impl Into<String>) -> Self {
+        self.location = url.into();
+        self
+    }
+
+    /// Set the `max-age` of the `CACHE-CONTROL` header in seconds, defaults to `Server::max_age`.
+    pub fn max_age(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Set the `DATE` header, defaults to the time the response is built.
+    pub fn date(mut self, date: std::time::SystemTime) -> Self {
+        self.date = Some(date);
+        self
+    }
+
+    /// Set the `SERVER` header, defaults to the server's, see `Server::server_name`.
+    pub fn server(mut self, server: impl Into<String>) -> Self {
+        self.server = Some(server.into());
+        self
+    }
+
+    /// Add a header, after the standard ones.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The search target (`ST` header).
+    pub fn search_target(&self) -> &str {
+        &self.search_target
+    }
+
+    /// The unique service name (`USN` header).
+    pub fn usn(&self) -> &str {
+        &self.usn
+    }
+
+    /// Check that no header contains a line break, which would end it early and let its value
+    /// inject headers of its own.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::MSearchResponse;
+    ///
+    /// let response = MSearchResponse::new("upnp:rootdevice", "uuid:1234::upnp:rootdevice", "http://192.0.2.10/desc.xml");
+    /// assert!(response.clone().validate().is_ok());
+    /// assert!(response.header("X-NAME", "a\r\nLOCATION: http://evil/").validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let standard = [
+            ("ST", Some(&self.search_target)),
+            ("USN", Some(&self.usn)),
+            ("LOCATION", Some(&self.location)),
+            ("SERVER", self.server.as_ref()),
+        ];
+        let broken = standard
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?.as_str())))
+            .chain(self.headers.iter().map(|(n, v)| (n.as_str(), v.as_str())))
+            .find(|(name, value)| name.contains(['\r', '\n']) || value.contains(['\r', '\n']));
+        match broken {
+            Some((name, _)) => Err(Error::Config(format!(
+                "header {name:?} of the response for {} contains a line break",
+                self.usn
+            ))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fmt::Debug, sync::Mutex};
+
+use rand::{Rng as _, SeedableRng, rngs::StdRng};
+
+/// A source of randomness for response and announcement jitter.
+///
+/// Install a custom implementation with `Server::rng` to make the server's timing reproducible.
+pub trait Rng: Debug + Send + Sync {
+    /// Returns a uniformly distributed value in `0..upper`, `upper` is never `0`.
+    fn below(&self, upper: u64) -> u64;
+}
+
+/// The default [`Rng`], backed by the thread-local generator of `rand`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRng;
+
+impl Rng for ThreadRng {
+    fn below(&self, upper: u64) -> u64 {
+        rand::thread_rng().gen_range(0..upper)
+    }
+}
+
+/// A deterministic [`Rng`] producing the same sequence for the same seed.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Rng, SeededRng};
+///
+/// let a = SeededRng::new(42);
+/// let b = SeededRng::new(42);
+/// assert_eq!(a.below(1000), b.below(1000));
+/// ```
+#[derive(Debug)]
+pub struct SeededRng(Mutex<StdRng>);
+
+impl SeededRng {
+    /// Create a generator from `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self(Mutex::new(StdRng::seed_from_u64(seed)))
+    }
+}
+
+impl Rng for SeededRng {
+    fn below(&self, upper: u64) -> u64 {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .gen_range(0..upper)
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    sync::OnceLock,
+    time::Duration,
+};
+
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+
+/// A response waiting for its turn until the deadline, granted through the sender.
+type Ticket = (IpAddr, Instant, oneshot::Sender<()>);
+
+/// Hands out turns to send search responses, round-robin between requesters.
+///
+/// At most one turn is granted per `interval`. Each requester with responses waiting gets one
+/// turn per round, so a host with many pending responses cannot hold up the others. Turns not
+/// granted by their deadline are dropped. Turns are granted by a task started on first use, which
+/// ends with the scheduler.
+#[derive(Debug)]
+pub(crate) struct FairScheduler {
+    interval: Duration,
+    tickets: OnceLock<mpsc::UnboundedSender<Ticket>>,
+}
+
+impl FairScheduler {
+    pub(crate) fn new(limit: u32, period: Duration) -> Self {
+        Self {
+            interval: period / limit.max(1),
+            tickets: OnceLock::new(),
+        }
+    }
+
+    /// Wait until a response to `ip` may be sent, returning `false` if that is not before
+    /// `deadline`.
+    ///
+    /// Dropping the future gives the turn up, the next requester is served right away.
+    pub(crate) async fn turn(&self, ip: IpAddr, deadline: Instant) -> bool {
+        let tickets = self.tickets.get_or_init(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(grant(rx, self.interval));
+            tx
+        });
+
+        let (tx, rx) = oneshot::channel();
+        if tickets.send((ip, deadline, tx)).is_err() {
+            // The granting task only goes away with the scheduler
+            return true;
+        }
+        tokio::time::timeout_at(deadline, rx).await.is_ok()
+    }
+}
+
+/// The turns requested per requester, and the order requesters are served in.
+#[derive(Debug, Default)]
+struct Waiting {
+    queues: HashMap<IpAddr, VecDeque<(Instant, oneshot::Sender<()>)>>,
+    rotation: VecDeque<IpAddr>,
+}
+
+impl Waiting {
+    fn push(&mut self, (ip, deadline, tx): Ticket) {
+        let queue = self.queues.entry(ip).or_default();
+        if queue.is_empty() {
+            self.rotation.push_back(ip);
+        }
+        queue.push_back((deadline, tx));
+    }
+}
+
+/// Grant the turns requested on `tickets` one per `interval`, taking requesters in rotation.
+async fn grant(mut tickets: mpsc::UnboundedReceiver<Ticket>, interval: Duration) {
+    let mut waiting = Waiting::default();
+
+    loop {
+        if waiting.queues.is_empty() {
+            match tickets.recv().await {
+                Some(ticket) => waiting.push(ticket),
+                None => return,
+            }
+        }
+        while let Ok(ticket) = tickets.try_recv() {
+            waiting.push(ticket);
+        }
+
+        let Some(ip) = waiting.rotation.pop_front() else {
+            continue;
+        };
+        let Some(queue) = waiting.queues.get_mut(&ip) else {
+            continue;
+        };
+        // Skip the turns given up or expired in the meantime
+        let mut granted = false;
+        let now = Instant::now();
+        while let Some((deadline, tx)) = queue.pop_front() {
+            if deadline > now && tx.send(()).is_ok() {
+                granted = true;
+                break;
+            }
+        }
+        if queue.is_empty() {
+            waiting.queues.remove(&ip);
+        } else {
+            waiting.rotation.push_back(ip);
+        }
+
+        if granted && !interval.is_zero() {
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
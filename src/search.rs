@@ -0,0 +1,327 @@
+use std::{
+    fmt::Debug,
+    net::SocketAddr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{Device, DeviceKind, Error, Result, header::HeaderValue, notify};
+
+/// An incoming `M-SEARCH` request.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchRequest {
+    /// Identifies this search in logs and [`AuditRecord`](crate::AuditRecord)s, unique within the
+    /// process.
+    pub id: u64,
+    /// The address the request came from, responses are sent there, to its actual source port
+    /// whether or not it is 1900, unless `ResponsePolicy::destination` says otherwise.
+    pub remote_addr: SocketAddr,
+    /// The search target (`ST` header).
+    pub search_target: String,
+    /// The maximum response delay in seconds (`MX` header), `0` if missing.
+    pub mx: u32,
+    /// The unquoted `MAN` header, e.g. `ssdp:discover`.
+    pub man: String,
+    /// All headers in order of appearance.
+    pub headers: Vec<(String, String)>,
+}
+
+impl SearchRequest {
+    /// Parse the `M-SEARCH` request `data` sent from `remote_addr`.
+    ///
+    /// Fails with `Error::Protocol` if `data` is not a complete `M-SEARCH` or lacks `MAN` or `ST`,
+    /// and with `Error::InvalidHeader` if `MX` is not a number.
+    pub fn parse(remote_addr: SocketAddr, data: &[u8]) -> Result<Self> {
+        let mut headers = notify::header_buf(data, notify::MAX_HEADERS);
+        let mut req = httparse::Request::new(&mut headers);
+        match req.parse(data) {
+            Ok(httparse::Status::Complete(_)) if req.method == Some("M-SEARCH") => {
+                Self::from_request(&req, remote_addr)
+            }
+            Ok(httparse::Status::Complete(_)) => Err(Error::Protocol("not an M-SEARCH".into())),
+            Ok(httparse::Status::Partial) => Err(Error::Protocol("incomplete request".into())),
+            Err(e) => Err(Error::Protocol(e.to_string())),
+        }
+    }
+
+    /// Extract the search from a parsed `M-SEARCH` request.
+    pub(crate) fn from_request(
+        req: &httparse::Request<'_, '_>,
+        remote_addr: SocketAddr,
+    ) -> Result<Self> {
+        let headers: Vec<(String, String)> = req
+            .headers
+            .iter()
+            .map(|h| {
+                (
+                    h.name.to_owned(),
+                    String::from_utf8_lossy(h.value).into_owned(),
+                )
+            })
+            .collect();
+        let find = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+        };
+
+        let Some(man) = find("man") else {
+            return Err(Error::Protocol("MAN header not found".into()));
+        };
+        let man = HeaderValue::parse(man).value().to_owned();
+
+        let Some(search_target) = find("st") else {
+            return Err(Error::Protocol("ST header not found".into()));
+        };
+        let search_target = search_target.trim().to_owned();
+
+        let mx = match find("mx") {
+            Some(mx) => mx.trim().parse().map_err(|e| Error::InvalidHeader {
+                name: "MX",
+                source: Box::new(e),
+            })?,
+            None => 0,
+        };
+
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            remote_addr,
+            search_target,
+            mx,
+            man,
+            headers,
+        })
+    }
+
+    /// Serialize the search as it goes on the wire, e.g. to forward it.
+    ///
+    /// The headers are written in their order, with the values of `ST`, `MAN` and `MX` taken
+    /// from `search_target`, `man` and `mx`, so changing those changes the request. They are
+    /// written as received when unchanged, and appended if missing, `MX` only if not 0. Line
+    /// breaks in header names or values are replaced by spaces so they cannot inject headers.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{SearchRequest, test_vectors};
+    ///
+    /// let addr = "192.0.2.30:50000".parse().unwrap();
+    /// let mut search = SearchRequest::parse(addr, test_vectors::WINDOWS_MSEARCH).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(search.to_bytes()).unwrap(),
+    ///     "M-SEARCH * HTTP/1.1\r\nHost: 239.255.255.250:1900\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\nMan: \"ssdp:discover\"\r\nMX: 3\r\n\r\n",
+    /// );
+    ///
+    /// search.search_target = "ssdp:all".into();
+    /// search.mx = 1;
+    /// let text = String::from_utf8(search.to_bytes()).unwrap();
+    /// assert!(text.contains("\r\nST: ssdp:all\r\nMan: \"ssdp:discover\"\r\nMX: 1\r\n"));
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut headers = self.headers.clone();
+        let (mut st, mut man, mut mx) = (false, false, false);
+        for (name, value) in headers.iter_mut() {
+            if name.eq_ignore_ascii_case("ST") {
+                st = true;
+                if value.trim() != self.search_target {
+                    *value = self.search_target.clone();
+                }
+            } else if name.eq_ignore_ascii_case("MAN") {
+                man = true;
+                if HeaderValue::parse(value).value() != self.man {
+                    *value = format!("\"{}\"", self.man);
+                }
+            } else if name.eq_ignore_ascii_case("MX") {
+                mx = true;
+                if value.trim().parse() != Ok(self.mx) {
+                    *value = self.mx.to_string();
+                }
+            }
+        }
+        if !man {
+            headers.push(("MAN".into(), format!("\"{}\"", self.man)));
+        }
+        if !mx && self.mx > 0 {
+            headers.push(("MX".into(), self.mx.to_string()));
+        }
+        if !st {
+            headers.push(("ST".into(), self.search_target.clone()));
+        }
+
+        let mut buf = String::from("M-SEARCH * HTTP/1.1\r\n");
+        notify::write_headers(&mut buf, &headers);
+        buf.push_str("\r\n");
+        buf.into_bytes()
+    }
+
+    /// The value of the first header called `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The `USER-AGENT` header, naming the OS, UPnP version and product of the control point.
+    pub fn user_agent(&self) -> Option<&str> {
+        self.header("USER-AGENT").map(str::trim)
+    }
+
+    /// The friendly name of the control point (`CPFN.UPNP.ORG` header, UPnP 1.1).
+    pub fn cpfn(&self) -> Option<&str> {
+        self.header("CPFN.UPNP.ORG").map(str::trim)
+    }
+
+    /// The UUID of the control point (`CPUUID.UPNP.ORG` header, UPnP 1.1).
+    pub fn cpuuid(&self) -> Option<&str> {
+        self.header("CPUUID.UPNP.ORG").map(str::trim)
+    }
+
+    /// The kind of the searching control point, guessed from its `USER-AGENT` header.
+    pub fn device_kind(&self) -> DeviceKind {
+        self.header("USER-AGENT")
+            .map_or(DeviceKind::Unknown, DeviceKind::from_server)
+    }
+}
+
+/// Whether the search target `pattern` selects `search_target`, compared case-insensitively.
+///
+/// With `wildcards`, a pattern ending in `*` (or `**`, as used by some ecosystems) matches every
+/// target starting with the text before it.
+pub(crate) fn target_matches(pattern: &str, search_target: &str, wildcards: bool) -> bool {
+    let prefix = pattern.trim_end_matches('*');
+    if wildcards && prefix.len() < pattern.len() {
+        return search_target
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+    }
+    pattern.eq_ignore_ascii_case(search_target)
+}
+
+/// Whether `pattern` is a wildcard search target, see [`target_matches`].
+pub(crate) fn is_wildcard(pattern: &str, wildcards: bool) -> bool {
+    wildcards && pattern.ends_with('*')
+}
+
+/// Decides, per incoming search, whether and how the server answers.
+///
+/// Every method has a default matching the server's built-in behavior, so implementations only
+/// override what they need.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Device, ResponsePolicy, SearchRequest, Server};
+///
+/// /// Hide admin devices from requesters outside 10.0.0.0/8.
+/// #[derive(Debug)]
+/// struct HideAdmin;
+///
+/// impl ResponsePolicy for HideAdmin {
+///     fn reveal(&self, search: &SearchRequest, device: &Device) -> bool {
+///         let lan: tokio_ssdp::Subnet = "10.0.0.0/8".parse().unwrap();
+///         !device.location().contains("/admin/") || lan.contains(search.remote_addr.ip())
+///     }
+/// }
+///
+/// Server::new([])
+///   .response_policy(HideAdmin);
+/// ```
+pub trait ResponsePolicy: Debug + Send + Sync {
+    /// Whether to answer `search` at all, defaults to `true`.
+    fn respond(&self, search: &SearchRequest) -> bool {
+        let _ = search;
+        true
+    }
+
+    /// Whether `device` may be revealed to the requester of `search`, defaults to `true`.
+    fn reveal(&self, search: &SearchRequest, device: &Device) -> bool {
+        let _ = (search, device);
+        true
+    }
+
+    /// The delay before answering, `default` being the random delay derived from `MX`.
+    fn delay(&self, search: &SearchRequest, default: Duration) -> Duration {
+        let _ = search;
+        default
+    }
+
+    /// Where to send the responses, `default` being the source address and port of `search`.
+    ///
+    /// Some devices listen for responses on a port they name in a vendor header rather than the
+    /// one they sent from.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::net::SocketAddr;
+    /// use tokio_ssdp::{ResponsePolicy, SearchRequest, Server};
+    ///
+    /// /// Answer to the port in `X-REPLY-PORT` when there is one.
+    /// #[derive(Debug)]
+    /// struct ReplyPort;
+    ///
+    /// impl ResponsePolicy for ReplyPort {
+    ///     fn destination(&self, search: &SearchRequest, default: SocketAddr) -> SocketAddr {
+    ///         match search.header("X-REPLY-PORT").and_then(|p| p.trim().parse().ok()) {
+    ///             Some(port) => SocketAddr::new(default.ip(), port),
+    ///             None => default,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// Server::new([])
+    ///   .response_policy(ReplyPort);
+    /// ```
+    fn destination(&self, search: &SearchRequest, default: SocketAddr) -> SocketAddr {
+        let _ = search;
+        default
+    }
+
+    /// Headers appended to the response revealing `device` to the requester of `search`, none
+    /// by default. Pairs with a line break in the name or value are skipped with a warning.
+    ///
+    /// Together with `delay`, this lets applications work around the quirks of some control
+    /// points, told apart by `SearchRequest::user_agent`, `cpfn` or `cpuuid`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::{Device, ResponsePolicy, SearchRequest, Server};
+    ///
+    /// /// Some players only list devices announcing DLNA support, and miss early responses.
+    /// #[derive(Debug)]
+    /// struct PlayerQuirks;
+    ///
+    /// impl PlayerQuirks {
+    ///     fn is_player(search: &SearchRequest) -> bool {
+    ///         search.user_agent().is_some_and(|ua| ua.contains("AcmePlayer"))
+    ///     }
+    /// }
+    ///
+    /// impl ResponsePolicy for PlayerQuirks {
+    ///     fn delay(&self, search: &SearchRequest, default: Duration) -> Duration {
+    ///         if Self::is_player(search) {
+    ///             default.max(Duration::from_millis(500))
+    ///         } else {
+    ///             default
+    ///         }
+    ///     }
+    ///
+    ///     fn headers(&self, search: &SearchRequest, _device: &Device) -> Vec<(String, String)> {
+    ///         match Self::is_player(search) {
+    ///             true => vec![("X-DLNADOC".into(), "DMS-1.50".into())],
+    ///             false => vec![],
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// Server::new([])
+    ///   .response_policy(PlayerQuirks);
+    /// ```
+    fn headers(&self, search: &SearchRequest, device: &Device) -> Vec<(String, String)> {
+        let _ = (search, device);
+        vec![]
+    }
+}
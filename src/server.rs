@@ -1,39 +1,191 @@
 use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt::{self, Write as _},
     future::Future,
-    net::{Ipv4Addr, SocketAddr},
-    sync::Arc,
-    time::{Duration, SystemTime},
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    pin::Pin,
+    sync::{
+        Arc, Mutex, MutexGuard, OnceLock, RwLock, Weak,
+        atomic::{AtomicU32, Ordering},
+    },
+    task::{Context, Poll},
+    time::Duration,
 };
 
-use log::{debug, error, info};
-use rand::Rng;
-use std::io::Result as IoResult;
-use tokio::{net::UdpSocket, sync::oneshot};
+use futures_core::Stream;
+use log::{debug, error, info, warn};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Notify, oneshot, watch},
+    task::JoinSet,
+    time::Instant,
+};
 
-use crate::{Device, NotifyMessage};
+use crate::{
+    AnnounceStrategy, AuditKind, AuditRecord, AuditSink, Clock, Device, DeviceLifecycle,
+    DeviceProvider, Direction, Error, ExposureReport, HandlerDecision, LocationResolver,
+    MSearchResponse, Metrics, NetworkRequirements, NetworkRule, NetworkWatcher, NotifyMessage,
+    NotifyRequest, NotifyRequestRef, NotifyResponse, Nts, PEER_HEADER, PeerInfo, PowerProfile,
+    Redaction, RequestHandler, ResponseLatency, ResponsePolicy, ResponseTemplate, Result, Rng,
+    SSDP_ADDR, SSDP_PORT, SearchRequest, SearchResponse, ServerError, ServerHeader, SsdpEvent,
+    SsdpSocket, Subnet, SystemClock, ThreadRng, UpnpVersion,
+    capture::{Capture, CapturingSocket},
+    channel::{self, Channel, ChannelOptions, Interface, SSDP_ADDR_V6_LINK, SSDP_ADDR_V6_SITE},
+    header, interface,
+    journal::{DeviceChange, Journal, JournalEntry},
+    lifecycle::Lifecycle,
+    location,
+    metrics::Timing,
+    notify, posture, power,
+    provider::DeviceSet,
+    prune,
+    queue::{DropPolicy, RecvQueue},
+    schedule::FairScheduler,
+    search,
+    storm::{SearchDedup, SearchLimiter, StormGuard},
+    trace::{self, PacketCounters},
+    validate_request,
+};
 
-#[allow(dead_code)]
 type NotifyRx = tokio::sync::mpsc::Receiver<Arc<NotifyMessage>>;
 type NotifyTx = tokio::sync::mpsc::Sender<Arc<NotifyMessage>>;
 
-const SSDP_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
-const SSDP_PORT: u16 = 1900;
-const DEFAULT_SERVER_NAME: &str = "Tokio-SSDP/1.0 UPnP/1.0";
+/// Initial buffer size for outgoing messages, enough for the standard headers.
+const MESSAGE_CAPACITY: usize = 512;
+
+/// How many changes the journal keeps by default, see `Server::journal_capacity`.
+const JOURNAL_CAPACITY: usize = 1024;
+
+/// How long to pause receiving after a recoverable error, see `Server::receive_failed`.
+pub(crate) const RECEIVE_BACKOFF: Duration = Duration::from_millis(50);
+
+/// How many receive errors in a row stop the server, see `Server::receive_failed`.
+pub(crate) const MAX_RECEIVE_FAILURES: u32 = 100;
+
+/// The socket unicast `M-SEARCH` responses are sent from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum ResponseSource {
+    /// The socket bound to port 1900, the way most devices answer.
+    #[default]
+    Multicast,
+    /// A dedicated socket bound to an ephemeral port, for control points or firewalls that expect
+    /// responses not to originate from port 1900.
+    Ephemeral,
+}
+
+/// A `NOTIFY` announcement sent on demand, see `ServerHandle::announce`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AnnouncementKind {
+    /// `ssdp:alive`, as in the periodic announcements.
+    Alive,
+    /// `ssdp:byebye`, without stopping the server.
+    Byebye,
+    /// `ssdp:update` announcing the next boot ID, then `ssdp:alive` with it, see
+    /// `ServerHandle::update`.
+    Update,
+    /// A message shaped like `ssdp:alive` with this `NTS` value instead, for protocols such as
+    /// DIAL or proprietary casting ones that define their own.
+    Custom(String),
+}
+
+/// What happens to scheduled `M-SEARCH` responses when the server shuts down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum PendingResponses {
+    /// Send them before the `ssdp:byebye` messages, waiting at most the longest `MX` delay.
+    #[default]
+    Flush,
+    /// Drop them, nothing is sent after the `ssdp:byebye` messages.
+    Cancel,
+}
 
 /// A server providing SSDP functionalities.
 /// The server will respond to `M-SEARCH` requests, send `alive` and `byebye` messages when needed.
+///
+/// # Containers
+/// SSDP relies on multicast, which does not leave the bridge networks container runtimes create by
+/// default. Run the container with host networking (e.g. `docker run --network host`) so the server
+/// sees the host's interfaces; `serve` then skips bridges such as `docker0` and `veth*`.
 #[derive(Debug, Clone)]
 pub struct Server {
     server_name: Option<String>,
+    upnp_version: UpnpVersion,
+    bridge_interfaces: bool,
+    interfaces: Vec<Ipv4Addr>,
     max_age: u64,
-    devices: Vec<Device>,
+    announce_interval: Option<Duration>,
+    port: u16,
+    ttl: Option<u32>,
+    response_ttl: Option<u32>,
+    reuse_port: bool,
+    port_sharing: bool,
+    multicast_loop: Option<bool>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    bind_device: Option<String>,
+    honor_mx: bool,
+    tcp_responses: bool,
+    boot_id: Option<Arc<AtomicU32>>,
+    /// The `01-NLS` value, picked on first use and kept for the life of the server.
+    nls: Arc<OnceLock<String>>,
+    config_id: Option<u32>,
+    devices: DeviceSet,
+    providers: Vec<Arc<dyn DeviceProvider>>,
+    journal: Arc<Journal>,
     headers: Vec<(String, String)>,
+    location_overrides: Vec<(Subnet, String)>,
+    location_resolver: Option<Arc<dyn LocationResolver>>,
     notify_req_tx: Option<NotifyTx>,
+    event_tx: Option<tokio::sync::mpsc::Sender<SsdpEvent>>,
+    error_tx: Option<tokio::sync::mpsc::Sender<ServerError>>,
+    rng: Arc<dyn Rng>,
+    clock: Arc<dyn Clock>,
+    watchdog: Option<Duration>,
+    recv_queue: Option<(usize, DropPolicy)>,
+    storm_guard: Option<Arc<StormGuard>>,
+    search_limiter: Option<Arc<SearchLimiter>>,
+    search_dedup: Option<Arc<SearchDedup>>,
+    response_scheduler: Option<Arc<FairScheduler>>,
+    max_datagram_size: Option<usize>,
+    prune_order: Vec<String>,
+    allowed_sources: Vec<Subnet>,
+    denied_sources: Vec<Subnet>,
+    on_link_searches: bool,
+    strict: bool,
+    unicast_search: bool,
+    lifecycle: Option<Arc<Lifecycle>>,
+    network_watcher: Option<Arc<dyn NetworkWatcher>>,
+    packets: Arc<PacketCounters>,
+    metrics: Option<Arc<dyn Metrics>>,
+    search_man: Vec<String>,
+    response_policy: Option<Arc<dyn ResponsePolicy>>,
+    request_handlers: Vec<Arc<dyn RequestHandler>>,
+    templates: Vec<ResponseTemplate>,
+    wildcard_search: bool,
+    search_all_limit: Option<usize>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    capture: Option<Arc<Capture>>,
+    redaction: Redaction,
+    identify: bool,
+    response_source: Option<ResponseSource>,
+    pending_responses: PendingResponses,
+    ipv6: bool,
+    power_profile: PowerProfile,
+    announce_strategy: AnnounceStrategy,
+    timer_granularity: Option<Duration>,
+    control: Option<Control>,
+    #[cfg(feature = "signal")]
+    shutdown_on_signal: bool,
 }
 
 impl Server {
     /// Create a new SSDP server
     ///
+    /// Devices with services or embedded devices are replaced by their advertisements, see
+    /// `Device::expand`.
+    ///
     /// # Examples
     /// ```
     /// use tokio_ssdp::{Server, Device};
@@ -48,415 +200,3859 @@ impl Server {
     pub fn new(devices: impl IntoIterator<Item = Device>) -> Self {
         Self {
             server_name: None,
+            upnp_version: UpnpVersion::default(),
+            bridge_interfaces: false,
+            interfaces: vec![],
             max_age: 100,
-            devices: devices.into_iter().collect(),
+            announce_interval: None,
+            port: SSDP_PORT,
+            ttl: None,
+            response_ttl: None,
+            reuse_port: false,
+            port_sharing: cfg!(windows),
+            multicast_loop: None,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            bind_device: None,
+            honor_mx: true,
+            tcp_responses: false,
+            boot_id: None,
+            nls: Arc::new(OnceLock::new()),
+            config_id: None,
+            devices: DeviceSet::new(devices.into_iter().collect()),
+            providers: vec![],
+            journal: Arc::new(Journal::new(JOURNAL_CAPACITY)),
             headers: vec![],
+            location_overrides: vec![],
+            location_resolver: None,
             notify_req_tx: None,
+            event_tx: None,
+            error_tx: None,
+            rng: Arc::new(ThreadRng),
+            clock: Arc::new(SystemClock),
+            watchdog: None,
+            recv_queue: None,
+            storm_guard: None,
+            search_limiter: None,
+            search_dedup: None,
+            response_scheduler: None,
+            max_datagram_size: None,
+            prune_order: vec![],
+            allowed_sources: vec![],
+            denied_sources: vec![],
+            on_link_searches: false,
+            strict: false,
+            unicast_search: false,
+            lifecycle: None,
+            network_watcher: None,
+            packets: Arc::default(),
+            metrics: None,
+            search_man: vec![],
+            response_policy: None,
+            request_handlers: vec![],
+            templates: vec![],
+            wildcard_search: false,
+            search_all_limit: None,
+            audit_sink: None,
+            capture: None,
+            redaction: Redaction::new(),
+            identify: false,
+            response_source: None,
+            pending_responses: PendingResponses::default(),
+            ipv6: false,
+            power_profile: PowerProfile::default(),
+            announce_strategy: AnnounceStrategy::default(),
+            timer_granularity: None,
+            control: None,
+            #[cfg(feature = "signal")]
+            shutdown_on_signal: false,
         }
     }
 
-    /// Set the name of the `Server` response header, defaults to `Tokio-SSDP/1.0 UPnP/1.0`.
+    /// Set the `SERVER` header, defaults to `<OS>/- UPnP/<version> tokio-ssdp/<crate version>`,
+    /// e.g. `linux/- UPnP/1.0 tokio-ssdp/1.0.0`.
+    ///
+    /// The UPnP Device Architecture requires `<OS>/<version> UPnP/<version> <product>/<version>`,
+    /// which certification suites and some routers check; build it as a [`ServerHeader`] to have
+    /// the form checked. `serve` warns about values of another form. Devices may override it
+    /// with `Device::server_name`.
     /// # Examples
     /// ```
-    /// use tokio_ssdp::Server;
+    /// use tokio_ssdp::{Server, ServerHeader, UpnpVersion};
     ///
     /// Server::new([])
     ///   .server_name("SomeRandomDevice/1.0 UPnP/1.0");
+    ///
+    /// Server::new([])
+    ///   .server_name(ServerHeader::new("Linux/6.1", UpnpVersion::V1_0, "AcmeHub/1.2").unwrap());
     /// ```
     pub fn server_name(mut self, server_name: impl Into<String>) -> Self {
         self.server_name = Some(server_name.into());
         self
     }
 
+    /// Set the UPnP Device Architecture version to conform to, defaults to `UpnpVersion::V1_0`.
+    ///
+    /// The headers the server generates that a later version introduced, e.g.
+    /// `SECURELOCATION.UPNP.ORG` for 2.0, are not sent, and `boot_id` and `config_id` need 1.1.
+    /// Headers set with `extra_header` or `Device::extra_header` are always sent as given.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Server, UpnpVersion};
+    ///
+    /// Server::new([])
+    ///   .upnp_version(UpnpVersion::V1_1)
+    ///   .extra_header("CONFIGID.UPNP.ORG", "1");
+    /// ```
+    pub fn upnp_version(mut self, version: UpnpVersion) -> Self {
+        self.upnp_version = version;
+        self
+    }
+
     /// Set the value of `Cache-Control: max-age=`, which is the valid time for the message, defaults to 100.
     pub fn max_age(mut self, max_age: u64) -> Self {
         self.max_age = max_age;
         self
     }
 
-    /// Add an extra header to search responses
+    /// Re-announce the devices every `interval`, defaults to a value derived from the shortest
+    /// `max-age` of the devices, see `Device::max_age`, and the power profile.
+    ///
+    /// Should be shorter than `max_age`, or control points drop the devices between rounds.
     /// # Examples
     /// ```
+    /// use std::time::Duration;
     /// use tokio_ssdp::Server;
     ///
     /// Server::new([])
-    ///   .extra_header("CONFIGID.UPNP.ORG", "1");
+    ///   .max_age(1800)
+    ///   .announce_interval(Duration::from_secs(900));
     /// ```
-    pub fn extra_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
-        self.headers.push((name.into(), value.into()));
+    pub fn announce_interval(mut self, interval: Duration) -> Self {
+        self.announce_interval = Some(interval);
         self
     }
 
-    /// Add a sender for `NotifyRequest` messages.
-    /// This is useful if you want to handle `NOTIFY` messages in your application.
-    pub fn notify_req_tx(mut self, tx: NotifyTx) -> Self {
-        self.notify_req_tx = Some(tx);
+    /// Set the time-to-live (IPv4) and hop limit (IPv6) of multicast announcements, defaults to
+    /// the OS default of 1.
+    ///
+    /// The specification recommends 2, raise it to reach devices behind multicast routers.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
         self
     }
 
-    /// Start serving on all interfaces, see `serve_addr` for details.
-    pub fn serve(self) -> IoResult<impl Future<Output = IoResult<()>>> {
-        self.serve_addr(Ipv4Addr::new(0, 0, 0, 0))
+    /// Set the time-to-live (IPv4) and hop limit (IPv6) of unicast search responses, defaults to
+    /// the OS default, usually 64.
+    ///
+    /// Independent of `ttl`: routed control points may need responses to travel further than
+    /// announcements should.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .ttl(2)
+    ///   .response_ttl(16);
+    /// ```
+    pub fn response_ttl(mut self, ttl: u32) -> Self {
+        self.response_ttl = Some(ttl);
+        self
     }
 
-    /// Start serving on `ip`, returns a future that needs to be `await`ed to keep the server running.
+    /// Listen for `M-SEARCH` requests sent straight to the interface addresses, disabled by
+    /// default.
+    ///
+    /// UPnP 1.1 control points and probing tools may search a known device by unicast to its
+    /// address and SSDP port. When another program shares the port, such a datagram reaches only
+    /// one of the sockets bound to it, possibly not the server's. A second socket bound to each
+    /// interface address, not the wildcard one, takes precedence and hands the searches to the
+    /// same handling as multicast ones; unicast searches carry no `MX` and are answered right
+    /// away. Only applies to IPv4 interfaces given by address, see `interfaces`, and binding
+    /// failures are logged without failing the server. Windows also delivers multicast traffic
+    /// to such sockets, so searches would be answered twice.
     /// # Examples
-    /// ```no_run
+    /// ```
     /// use tokio_ssdp::Server;
-    /// use std::net::Ipv4Addr;
     ///
     /// Server::new([])
-    ///   .serve_addr(Ipv4Addr::new(192, 168, 1, 100));
+    ///   .unicast_search(true);
     /// ```
-    pub fn serve_addr(self, ip: Ipv4Addr) -> IoResult<impl Future<Output = IoResult<()>>> {
-        let this = Arc::new(self);
-        let s = {
-            use socket2::{Domain, Protocol, Socket, Type};
-            let s = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
-            s.set_reuse_address(true)?;
-            s.set_nonblocking(true)?;
-            s.bind(&SocketAddr::from((ip, SSDP_PORT)).into())?;
-            s.join_multicast_v4(&SSDP_ADDR, &ip)?;
-            s
-        };
-        let socket = Arc::new(UdpSocket::from_std(s.into())?);
+    pub fn unicast_search(mut self, enabled: bool) -> Self {
+        self.unicast_search = enabled;
+        self
+    }
 
-        info!("Listening on {}", socket.local_addr()?);
+    /// Set `SO_REUSEPORT` on the SSDP socket from the start, defaults to only setting it when
+    /// another program already holds the port.
+    ///
+    /// Lets several SSDP-aware processes on one host, or containers sharing the host network,
+    /// all bind the port in any order. Has no effect where the option does not exist.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .reuse_port(true);
+    /// ```
+    pub fn reuse_port(mut self, enabled: bool) -> Self {
+        self.reuse_port = enabled;
+        self
+    }
 
-        // Pre-concat headers
-        let extra_headers = Arc::new(
-            this.headers
-                .iter()
-                .map(|(name, value)| format!("{name}: {value}\r\n"))
-                .collect::<Vec<_>>()
-                .join("")
-        );
+    /// Expect another program to hold the SSDP port too, defaults to `true` on Windows, where the
+    /// SSDP Discovery service usually does.
+    ///
+    /// Search responses are then sent from a socket of their own unless `response_source` is set,
+    /// since the OS delivers unicast datagrams to a shared port to one of the programs only.
+    /// On Windows, should binding the port fail although it is shared, the server binds it on the
+    /// address of its interface instead if it has a single one (see `interfaces`), which still
+    /// receives the multicast traffic. Searches sent to the port by unicast may still be missed.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .interfaces(["192.168.1.100".parse().unwrap()])
+    ///   .port_sharing(true);
+    /// ```
+    pub fn port_sharing(mut self, enabled: bool) -> Self {
+        self.port_sharing = enabled;
+        self
+    }
 
-        let server_fut = async move {
-            let mut buf = [0u8; 4096];
+    /// Bind the sockets to the network device `name` (`SO_BINDTODEVICE`), e.g. `eth0`.
+    ///
+    /// Datagrams are then only received from and sent through that device, whatever the
+    /// routing table says. Usually requires the `CAP_NET_RAW` capability.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .bind_device("eth0");
+    /// ```
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn bind_device(mut self, name: impl Into<String>) -> Self {
+        self.bind_device = Some(name.into());
+        self
+    }
 
-            let (_notify_alive_tx, mut notify_alive_rx) = oneshot::channel::<()>();
-            tokio::spawn({
-                let this = Arc::clone(&this);
-                let socket = Arc::clone(&socket);
-                let extra_headers = Arc::clone(&extra_headers);
+    /// Whether the multicast messages sent are looped back to this host, defaults to the OS
+    /// default (enabled).
+    ///
+    /// Disable it when other SSDP processes on the host should not see the announcements, or
+    /// to keep the server from receiving its own.
+    pub fn multicast_loop(mut self, enabled: bool) -> Self {
+        self.multicast_loop = Some(enabled);
+        self
+    }
 
-                async move {
-                    loop {
-                        if let Err(e) = this.broadcast_alive(&socket, &extra_headers).await {
-                            error!("Send alive messages failed: {e}");
-                        }
+    /// Listen on and announce to `port` instead of the standard SSDP port 1900.
+    ///
+    /// Regular control points only talk to port 1900, this is meant for tests and closed
+    /// deployments where another SSDP stack holds the port.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
 
-                        tokio::select! {
-                            _ = tokio::time::sleep(Duration::from_secs(this.max_age)) => {
-                                // It's time to send alive messages
-                            }
-                            _ = Pin::new(&mut notify_alive_rx) => {
-                                // We should shut down
-                                debug!("notify_alive shutdown");
-                                return IoResult::Ok(());
-                            }
-                        }
-                    }
-                }
-            });
+    /// Wait a random delay within the `MX` of searches before responding, defaults to `true`.
+    ///
+    /// With `false` responses are sent right away, which is faster on small networks but lets
+    /// devices answering together flood the control point.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .honor_mx(false);
+    /// ```
+    pub fn honor_mx(mut self, enabled: bool) -> Self {
+        self.honor_mx = enabled;
+        self
+    }
 
-            let (_notify_byebye_tx, notify_byebye_rx) = oneshot::channel::<()>();
-            tokio::spawn({
-                let this = Arc::clone(&this);
-                let socket = Arc::clone(&socket);
-                let extra_headers = Arc::clone(&extra_headers);
+    /// Answer searches carrying a `TCPPORT.UPNP.ORG` header over TCP, defaults to `false`.
+    ///
+    /// UPnP 2.0 control points use the header to receive responses too large for a datagram on
+    /// a TCP port. The server connects to that port on the requester and writes the response,
+    /// falling back to UDP if this fails. Outbound TCP must be allowed by the firewall.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .tcp_responses(true);
+    /// ```
+    pub fn tcp_responses(mut self, enabled: bool) -> Self {
+        self.tcp_responses = enabled;
+        self
+    }
 
-                async move {
-                    let _ = notify_byebye_rx.await;
+    /// Send `BOOTID.UPNP.ORG` with announcements and search responses, starting at `boot_id`.
+    ///
+    /// UPnP 1.1 requires the boot ID to increase every time the device (re)joins the network,
+    /// persist it across restarts. `ServerHandle::update` moves to the next one at runtime.
+    /// Requires `UpnpVersion::V1_1` or later, starting the server fails with `Error::Config`
+    /// otherwise.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Server, UpnpVersion};
+    ///
+    /// Server::new([])
+    ///   .upnp_version(UpnpVersion::V1_1)
+    ///   .boot_id(42)
+    ///   .config_id(7);
+    /// ```
+    pub fn boot_id(mut self, boot_id: u32) -> Self {
+        self.boot_id = Some(Arc::new(AtomicU32::new(boot_id)));
+        self
+    }
 
-                    if let Err(e) = this.broadcast_byebye(&socket, &extra_headers).await {
-                        error!("Send byebye messages failed: {e}");
-                    }
-                }
-            });
+    /// Send `CONFIGID.UPNP.ORG` with announcements and search responses.
+    ///
+    /// The configuration ID identifies the version of the device and service descriptions,
+    /// change it whenever they change. Requires `UpnpVersion::V1_1` or later, like `boot_id`.
+    pub fn config_id(mut self, config_id: u32) -> Self {
+        self.config_id = Some(config_id);
+        self
+    }
 
-            loop {
-                let (n, addr) = socket.recv_from(&mut buf).await?;
+    /// Add an extra header to search responses
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .extra_header("CONFIGID.UPNP.ORG", "1");
+    /// ```
+    pub fn extra_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
 
-                let mut headers = [httparse::EMPTY_HEADER; 16];
-                let mut req = httparse::Request::new(&mut headers);
+    /// Rewrite the `LOCATION` of search responses sent to requesters in `subnet`.
+    ///
+    /// The scheme, host and port of every device's location are replaced by `origin`, which helps
+    /// when the devices are only reachable from that subnet through NAT or a port forward.
+    /// The first matching subnet wins.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .location_override("10.8.0.0/16".parse().unwrap(), "http://203.0.113.5:18080");
+    /// ```
+    pub fn location_override(mut self, subnet: Subnet, origin: impl Into<String>) -> Self {
+        self.location_overrides.push((subnet, origin.into()));
+        self
+    }
 
-                if let Ok(httparse::Status::Complete(_)) = req.parse(&buf[..n]) {
-                    let method = if let Some(m) = req.method {
-                        m
-                    } else {
-                        continue;
-                    };
+    /// Compute the `LOCATION` of devices from the local address of each interface, see
+    /// [`LocationResolver`].
+    pub fn location_resolver(mut self, resolver: impl LocationResolver + 'static) -> Self {
+        self.location_resolver = Some(Arc::new(resolver));
+        self
+    }
 
-                    let path = if let Some(m) = req.path {
-                        m
-                    } else {
-                        continue;
-                    };
+    /// Also answer `M-SEARCH` requests whose `MAN` header is `value`, besides `"ssdp:discover"`.
+    ///
+    /// Searches with other `MAN` values are ignored rather than treated as errors.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .search_man("ssdp:discoveronly");
+    /// ```
+    pub fn search_man(mut self, value: impl Into<String>) -> Self {
+        self.search_man.push(value.into());
+        self
+    }
 
-                    match (method, path) {
-                        ("M-SEARCH", "*") => {
-                            let socket = Arc::clone(&socket);
-                            let res = this.handle_search(&req, socket, addr, &extra_headers).await;
-                            if let Err(e) = res {
-                                error!("Handle search failed: {e}");
-                            }
-                        }
-                        ("NOTIFY", _) => {
-                            let nr = NotifyMessage {
-                                remote_addr: addr,
-                                data: buf[..n].to_vec(),
-                            };
-                            let _res = this.handle_notify(Arc::new(nr)).await;
-                        }
-                        _ => debug!("Unknown SSDP request {method} {path} from {addr}"),
-                    }
-                }
-            }
-        };
+    /// Install a policy deciding per incoming search whether to answer, which devices to reveal
+    /// and how long to wait, see [`ResponsePolicy`].
+    pub fn response_policy(mut self, policy: impl ResponsePolicy + 'static) -> Self {
+        self.response_policy = Some(Arc::new(policy));
+        self
+    }
 
-        Ok(server_fut)
+    /// Run `handler` on every incoming message before the built-in processing, see
+    /// [`RequestHandler`]. Handlers run in the order they were added.
+    pub fn request_handler(mut self, handler: impl RequestHandler + 'static) -> Self {
+        self.request_handlers.push(Arc::new(handler));
+        self
     }
 
-    async fn handle_notify(&self, nr: Arc<NotifyMessage>) -> IoResult<()> {
-        debug!(
-            "Received NOTIFY from {} with {} bytes",
-            nr.remote_addr,
-            nr.data.len()
-        );
+    /// Advertise the devices of `provider` too, tracking their changes while the server runs,
+    /// see [`DeviceProvider`].
+    pub fn device_provider(mut self, provider: impl DeviceProvider + 'static) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
 
-        if let Some(tx) = &self.notify_req_tx {
-            if tx.send(nr).await.is_err() {
-                error!("Failed to send notify request");
-            }
-        } else {
-            debug!("No notify request sender configured, ignoring NOTIFY");
-        }
+    /// Answer searches for the template's search target with its canned response instead of a
+    /// device, see [`ResponseTemplate`].
+    pub fn response_template(mut self, template: ResponseTemplate) -> Self {
+        self.templates.push(template);
+        self
+    }
 
-        Ok(())
+    /// Treat a trailing `*` in the `ST` of searches as a wildcard, defaults to `false`.
+    ///
+    /// `urn:Belkin:device:**` then matches every device or [`ResponseTemplate`] whose search target
+    /// starts with `urn:Belkin:device:`, and each of them is answered, up to `search_all_limit`.
+    /// Templates take precedence: devices are only answered if no template matches.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .wildcard_search(true);
+    /// ```
+    pub fn wildcard_search(mut self, enabled: bool) -> Self {
+        self.wildcard_search = enabled;
+        self
     }
 
-    async fn handle_search(
-        &self,
-        req: &httparse::Request<'_, '_>,
-        socket: Arc<UdpSocket>,
-        remote_addr: SocketAddr,
-        extra_headers: &str,
-    ) -> std::io::Result<()> {
-        let mut st = None;
-        let mut mx = 0u32;
-        let mut man_found = false;
+    /// Also serve over IPv6 on the link-local (`FF02::C`) and site-local (`FF05::C`) groups,
+    /// defaults to `false`.
+    ///
+    /// Messages then carry the `OPT` and `01-NLS` headers dual-stack control points use to match
+    /// the announcements of both address families, with the same `01-NLS` value for the life of
+    /// the server, also in the messages of `alive_messages` and `byebye_messages`. Use
+    /// `location_override` with IPv6 subnets to hand IPv6 requesters an IPv6 `LOCATION`.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .ipv6(true);
+    /// ```
+    pub fn ipv6(mut self, enabled: bool) -> Self {
+        self.ipv6 = enabled;
+        self
+    }
 
-        for header in req.headers.iter() {
-            let v = Some(String::from_utf8_lossy(header.value));
+    /// Answer `ssdp:all` and wildcard searches with at most `limit` responses, defaults to one per
+    /// device.
+    ///
+    /// The responses to `ssdp:all` are spread over the requester's `MX` window either way.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .search_all_limit(16);
+    /// ```
+    pub fn search_all_limit(mut self, limit: usize) -> Self {
+        self.search_all_limit = Some(limit);
+        self
+    }
 
-            if header.name.eq_ignore_ascii_case("st") {
-                st = v;
-                continue;
-            }
+    /// Set the socket search responses are sent from, defaults to [`ResponseSource::Multicast`], or
+    /// [`ResponseSource::Ephemeral`] with `port_sharing`.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{ResponseSource, Server};
+    ///
+    /// Server::new([])
+    ///   .response_source(ResponseSource::Ephemeral);
+    /// ```
+    pub fn response_source(mut self, source: ResponseSource) -> Self {
+        self.response_source = Some(source);
+        self
+    }
 
-            if header.name.eq_ignore_ascii_case("mx") {
-                let val = String::from_utf8_lossy(header.value);
+    /// Set what happens to responses still waiting for their `MX` delay on shutdown, defaults to
+    /// [`PendingResponses::Flush`].
+    ///
+    /// Either way no response is sent after the `ssdp:byebye` messages.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{PendingResponses, Server};
+    ///
+    /// Server::new([])
+    ///   .pending_responses(PendingResponses::Cancel);
+    /// ```
+    pub fn pending_responses(mut self, policy: PendingResponses) -> Self {
+        self.pending_responses = policy;
+        self
+    }
 
-                mx = match val.parse() {
-                    Ok(v) => v,
-                    Err(e) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
-                };
+    /// Write every datagram the server sends and receives to `writer`, with its time and peer,
+    /// defaults to capturing nothing.
+    ///
+    /// The capture format is described at [`CaptureRecord`], read it back with
+    /// [`CaptureReader`] or run it through a request handler with [`replay`](crate::replay), to
+    /// reproduce what a device sent. Datagrams are captured in full, including what `redaction`
+    /// hides from the logs, by a thread of their own, so `writer` may block. It is flushed
+    /// whenever it caught up, so a `BufWriter` over a file saves system calls during bursts.
+    /// Servers run by an `SsdpMux` share its sockets and are not captured.
+    /// # Examples
+    /// ```no_run
+    /// use std::{fs::File, io::BufWriter};
+    /// use tokio_ssdp::Server;
+    ///
+    /// # fn run() -> std::io::Result<()> {
+    /// Server::new([])
+    ///   .capture(BufWriter::new(File::create("ssdp.capture")?));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn capture<W>(mut self, writer: W) -> Self
+    where
+        W: std::io::Write + fmt::Debug + Send + 'static,
+    {
+        self.capture = Some(Arc::new(Capture::new(writer)));
+        self
+    }
 
-                continue;
+    /// Record every datagram the server sends, see [`AuditSink`].
+    pub fn audit_sink(mut self, sink: impl AuditSink + 'static) -> Self {
+        self.audit_sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Hide identifiers and addresses from the messages logged and the audit records, see
+    /// [`Redaction`]. Nothing is redacted by default.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Redaction, Server};
+    ///
+    /// Server::new([])
+    ///   .redaction(Redaction::new().uuid_suffix(true).location_host(true));
+    /// ```
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Send the crate version and features in an `X-TOKIO-SSDP` header, defaults to `false`.
+    ///
+    /// Other instances of this crate read it with `peer_info`, which helps telling a fleet of
+    /// devices built on it apart during diagnostics. See [`PeerInfo`].
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .identify(true);
+    /// ```
+    pub fn identify(mut self, enabled: bool) -> Self {
+        self.identify = enabled;
+        self
+    }
+
+    /// Add a sender for `NotifyRequest` messages.
+    /// This is useful if you want to handle `NOTIFY` messages in your application.
+    ///
+    /// See `notify_stream` for a receiver that does not depend on tokio's channel types.
+    pub fn notify_req_tx(mut self, tx: NotifyTx) -> Self {
+        self.notify_req_tx = Some(tx);
+        self
+    }
+
+    /// Receive `NOTIFY` messages as a [`NotifyStream`], buffering at most `capacity` of them.
+    ///
+    /// Replaces a sender set with `notify_req_tx`. The stream ends once the server stopped.
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::Server;
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let mut notifies = server.notify_stream(16);
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// while let Some(msg) = notifies.next().await {
+    ///     println!("NOTIFY from {}", msg.remote_addr);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn notify_stream(&mut self, capacity: usize) -> NotifyStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+        self.notify_req_tx = Some(tx);
+        NotifyStream { rx }
+    }
+
+    /// Receive every incoming SSDP message as an [`SsdpEvent`], buffering at most `capacity`.
+    ///
+    /// Events are delivered alongside the server's own processing, after request handlers and
+    /// the alive rate limit. When the stream lags behind by `capacity` events, new ones are
+    /// dropped rather than stalling the server. The stream ends once the server stopped.
+    /// # Examples
+    /// ```no_run
+    /// use std::collections::HashMap;
+    /// use tokio_ssdp::{Server, SsdpEvent};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let mut events = server.event_stream(64);
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// let mut present = HashMap::new();
+    /// while let Some(event) = events.next().await {
+    ///     match event {
+    ///         SsdpEvent::Alive(req) => {
+    ///             present.insert(req.usn().unwrap_or_default().to_owned(), req.remote_addr);
+    ///         }
+    ///         SsdpEvent::Byebye(req) => {
+    ///             present.remove(req.usn().unwrap_or_default());
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn event_stream(&mut self, capacity: usize) -> EventStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+        self.event_tx = Some(tx);
+        EventStream { rx }
+    }
+
+    /// Receive the errors of the running server as [`ServerError`]s, buffering at most
+    /// `capacity`.
+    ///
+    /// The server keeps running through recoverable errors, e.g. a failed send or an ICMP error
+    /// reported by the socket, and only stops on a fatal one, the last item of the stream. Every
+    /// error is logged too; when the stream lags behind by `capacity` errors, new ones are only
+    /// logged. The stream ends once the server stopped.
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::{Server, ServerError};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let mut errors = server.error_stream(16);
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// while let Some(error) = errors.next().await {
+    ///     if let ServerError::Respond { destination, .. } = &error {
+    ///         eprintln!("{destination} missed a response");
+    ///     }
+    ///     if error.is_fatal() {
+    ///         eprintln!("Server stopped: {error}");
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn error_stream(&mut self, capacity: usize) -> ErrorStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(capacity);
+        self.error_tx = Some(tx);
+        ErrorStream { rx }
+    }
+
+    /// Create a [`ServerHandle`] to shut the server down gracefully or send custom responses.
+    ///
+    /// Replaces a handle created before. The handle also stops clones of this server.
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::Server;
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let handle = server.handle();
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// // Sends `ssdp:byebye` for every device before returning
+    /// handle.shutdown().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn handle(&mut self) -> ServerHandle {
+        let (requested_tx, requested_rx) = watch::channel(false);
+        let (done_tx, done_rx) = watch::channel(false);
+        let update = Arc::new(Notify::new());
+        let schedule = Arc::new(Schedule::default());
+        let outlet = Arc::new(OnceLock::new());
+        self.control = Some(Control {
+            requested: requested_rx,
+            done: Arc::new(done_tx),
+            update: Arc::clone(&update),
+            schedule: Arc::clone(&schedule),
+            outlet: Arc::clone(&outlet),
+        });
+        ServerHandle {
+            requested: requested_tx,
+            done: done_rx,
+            update,
+            schedule,
+            outlet,
+            journal: Arc::clone(&self.journal),
+        }
+    }
+
+    /// Keep the latest `capacity` changes to the advertised devices for
+    /// `ServerHandle::changes_since`, defaults to 1024.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .journal_capacity(64);
+    /// ```
+    pub fn journal_capacity(self, capacity: usize) -> Self {
+        self.journal.set_capacity(capacity);
+        self
+    }
+
+    /// Set the power profile, trading CPU wake-ups for announcement timeliness, defaults to
+    /// `PowerProfile::Balanced`.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{PowerProfile, Server};
+    ///
+    /// Server::new([])
+    ///   .power_profile(PowerProfile::LowPower);
+    /// ```
+    pub fn power_profile(mut self, profile: PowerProfile) -> Self {
+        self.power_profile = profile;
+        self
+    }
+
+    /// Set how the `ssdp:alive` rounds are scheduled: copies of each message, jitter and
+    /// refreshing before half the `max-age`, see [`AnnounceStrategy`].
+    pub fn announce_strategy(mut self, strategy: AnnounceStrategy) -> Self {
+        self.announce_strategy = strategy;
+        self
+    }
+
+    /// Round timers (announcement rounds and search response delays) up to multiples of
+    /// `granularity` so they fire together, defaults to the power profile's granularity.
+    pub fn timer_granularity(mut self, granularity: Duration) -> Self {
+        self.timer_granularity = Some(granularity);
+        self
+    }
+
+    /// Re-join the multicast group when nothing was received for `period`, disabled by default.
+    ///
+    /// This heals sockets silently dropped from the group, e.g. by a switch or a network restart,
+    /// on long-running appliances. The server's own announcements are looped back, so `period`
+    /// should be longer than `max_age`.
+    pub fn watchdog(mut self, period: Duration) -> Self {
+        self.watchdog = Some(period);
+        self
+    }
+
+    /// Drop `ssdp:alive` messages a device repeats within `min_interval` instead of forwarding them
+    /// to the [`notify_req_tx`](Self::notify_req_tx) channel, defaults to forwarding everything.
+    ///
+    /// Devices stuck in an alive storm are reported once with a warning, until they stay quiet for
+    /// `min_interval`.
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .alive_rate_limit(Duration::from_secs(10));
+    /// ```
+    pub fn alive_rate_limit(mut self, min_interval: Duration) -> Self {
+        self.storm_guard = Some(Arc::new(StormGuard::new(min_interval)));
+        self
+    }
+
+    /// Receive datagrams on a task of their own, queueing up to `capacity` of them while earlier
+    /// ones are processed, defaults to receiving the next datagram once the last was processed.
+    ///
+    /// Keeps the sockets drained when processing is slow, e.g. because of a request handler,
+    /// without buffering without bound: once the queue is full, `policy` decides which datagram
+    /// is dropped. Dropped datagrams are counted like filtered ones, see `Metrics::dropped`.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{DropPolicy, Server};
+    ///
+    /// Server::new([])
+    ///   .recv_queue(256, DropPolicy::DropOldest);
+    /// ```
+    pub fn recv_queue(mut self, capacity: usize, policy: DropPolicy) -> Self {
+        self.recv_queue = Some((capacity, policy));
+        self
+    }
+
+    /// Answer at most `limit` `M-SEARCH` requests per `period` from each source address, defaults
+    /// to answering every search.
+    ///
+    /// Addresses may send `limit` searches in a burst and regain the allowance gradually. Excess
+    /// searches are dropped before they are handled, without events, so a host flooding the
+    /// server cannot make it burn CPU or flood the network with responses. Flooding hosts are
+    /// reported once with a warning, until they stay quiet for a whole `period`. A `limit` of 0
+    /// fails to start the server with `Error::Config`.
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .search_rate_limit(10, Duration::from_secs(1));
+    /// ```
+    pub fn search_rate_limit(mut self, limit: u32, period: Duration) -> Self {
+        self.search_limiter = Some(Arc::new(SearchLimiter::new(limit, period)));
+        self
+    }
+
+    /// Handle a search only once per `window` when its sender repeats it, and send each response
+    /// to a requester only once per `window`, defaults to answering every search in full.
+    ///
+    /// Control points commonly send a search several times in a row in case a datagram is lost,
+    /// Windows three times. With many devices, answering each copy in full floods the network
+    /// with identical responses. Searches with the same source address and `ST` are dropped
+    /// within the window, without events; responses to other searches of the requester, e.g. to
+    /// `ssdp:all` after `upnp:rootdevice`, are left out if they carry a `USN` it was sent already.
+    /// Keep the window shorter than the interval at which control points search again on purpose.
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .search_dedup(Duration::from_secs(3));
+    /// ```
+    pub fn search_dedup(mut self, window: Duration) -> Self {
+        self.search_dedup = Some(Arc::new(SearchDedup::new(window)));
+        self
+    }
+
+    /// Send at most `limit` search responses per `period` in total, taking turns between the
+    /// requesters, defaults to sending each response when its delay is over.
+    ///
+    /// When many control points search at once, e.g. after a power outage, responses whose delay
+    /// is over queue up per requester and each requester gets one response out per round, so a
+    /// host that asked for many (`ssdp:all`) cannot starve the others. Responses still waiting
+    /// `MX` seconds (one at least) after their delay is over are dropped, the requester has
+    /// likely stopped listening by then. Combine with `search_rate_limit` to also bound the
+    /// searches each host gets answered.
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .fair_responses(100, Duration::from_secs(1));
+    /// ```
+    pub fn fair_responses(mut self, limit: u32, period: Duration) -> Self {
+        self.response_scheduler = Some(Arc::new(FairScheduler::new(limit, period)));
+        self
+    }
+
+    /// Only process datagrams from addresses in `subnets`, defaults to every address.
+    ///
+    /// Datagrams from other sources are dropped before request handlers see them. Calling this
+    /// again adds to the allowed subnets.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .allow_subnets(&["192.168.0.0/16".parse().unwrap(), "fe80::/10".parse().unwrap()]);
+    /// ```
+    pub fn allow_subnets(mut self, subnets: &[Subnet]) -> Self {
+        self.allowed_sources.extend_from_slice(subnets);
+        self
+    }
+
+    /// Drop datagrams from addresses in `subnets`, this takes precedence over `allow_subnets`.
+    pub fn deny_subnets(mut self, subnets: &[Subnet]) -> Self {
+        self.denied_sources.extend_from_slice(subnets);
+        self
+    }
+
+    /// Ignore `M-SEARCH` requests from sources outside the subnets of the served interfaces,
+    /// defaults to `false`.
+    ///
+    /// Control points search from the link they share with the device, so off-link searches are
+    /// likely spoofed to use the server as a reflection amplifier. The subnets are read when the
+    /// server starts and on network changes; IPv6 link-local sources are always on-link. If the
+    /// interfaces cannot be listed, searches are answered from every source with a warning.
+    ///
+    /// Only the source address is checked, not the interface a search arrived on; use
+    /// `bind_device` where available to only receive from one interface.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .on_link_searches_only(true);
+    /// ```
+    pub fn on_link_searches_only(mut self, enabled: bool) -> Self {
+        self.on_link_searches = enabled;
+        self
+    }
+
+    /// Drop `NOTIFY` and `M-SEARCH` requests that do not follow the UPnP Device Architecture to
+    /// the letter, defaults to `false`.
+    ///
+    /// Requests are checked with [`validate_request`](crate::validate_request). Failing ones are
+    /// logged with every violation and counted as parse errors, see `Metrics::parse_error`,
+    /// instead of being answered or forwarded, before the request handlers see them. Lenient
+    /// parsing is the default because many devices get details like the quotes around `MAN`
+    /// wrong.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .strict(true);
+    /// ```
+    pub fn strict(mut self, enabled: bool) -> Self {
+        self.strict = enabled;
+        self
+    }
+
+    /// Announce the devices anew whenever `watcher` reports a network change, disabled by
+    /// default.
+    ///
+    /// The server re-joins the multicast groups, sends `ssdp:byebye` for every device, moves to
+    /// the next boot ID if `boot_id` is set and sends `ssdp:alive`. Announcements and responses
+    /// carry the new address in `LOCATION` if a `location_resolver` derives it from the local
+    /// address, and the interfaces given by address must still exist: with the default one the
+    /// OS picks, the server follows e.g. a laptop switching Wi-Fi networks.
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use tokio_ssdp::{InterfacePoller, Server, UpnpVersion};
+    ///
+    /// Server::new([])
+    ///   .upnp_version(UpnpVersion::V1_1)
+    ///   .boot_id(1)
+    ///   .network_watcher(InterfacePoller::new(Duration::from_secs(5)));
+    /// ```
+    pub fn network_watcher(mut self, watcher: impl NetworkWatcher + 'static) -> Self {
+        self.network_watcher = Some(Arc::new(watcher));
+        self
+    }
+
+    /// Call `hook` on the discovery activity of each device, see [`DeviceLifecycle`].
+    pub fn lifecycle_hook(mut self, hook: impl DeviceLifecycle + 'static) -> Self {
+        self.lifecycle = Some(Arc::new(Lifecycle::new(hook)));
+        self
+    }
+
+    /// Report the traffic of the server to `metrics`, see [`Metrics`].
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Metrics, Server};
+    ///
+    /// #[derive(Debug)]
+    /// struct Prometheus;
+    ///
+    /// impl Metrics for Prometheus {}
+    ///
+    /// Server::new([])
+    ///   .metrics(Prometheus);
+    /// ```
+    pub fn metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = Some(Arc::new(metrics));
+        self
+    }
+
+    /// Count a datagram from `remote_addr` that was ignored.
+    fn dropped(&self, remote_addr: SocketAddr, reason: &'static str) {
+        self.packets.dropped(remote_addr, reason);
+        if let Some(metrics) = &self.metrics {
+            metrics.dropped(remote_addr, reason);
+        }
+    }
+
+    /// Count a datagram from `remote_addr` that could not be parsed.
+    fn invalid(&self, remote_addr: SocketAddr, error: &dyn fmt::Display) {
+        self.packets.invalid(remote_addr, error);
+        if let Some(metrics) = &self.metrics {
+            metrics.parse_error(remote_addr);
+        }
+    }
+
+    /// Count a `NOTIFY` message of type `nts` sent to `destination`.
+    fn notify_sent(&self, nts: Nts, destination: SocketAddr) {
+        if let Some(metrics) = &self.metrics {
+            metrics.notify_sent(nts, destination);
+        }
+    }
+
+    /// Whether datagrams from `ip` pass the source subnet filters.
+    fn source_allowed(&self, ip: IpAddr) -> bool {
+        !self.denied_sources.iter().any(|s| s.contains(ip))
+            && (self.allowed_sources.is_empty()
+                || self.allowed_sources.iter().any(|s| s.contains(ip)))
+    }
+
+    /// Keep the messages sent at most `bytes` long, defaults to no limit.
+    ///
+    /// Datagrams larger than the path MTU are fragmented, and some networks drop the fragments.
+    /// Messages that would exceed `bytes`, e.g. because of a long `LOCATION` plus vendor headers,
+    /// lose optional headers (see `prune_order`) until they fit. The headers the specification
+    /// requires, `SEARCHPORT.UPNP.ORG`, the `OPT`/`01-NLS` pair and the body are always kept; a
+    /// warning is logged when they alone are too long.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    ///
+    /// Server::new([])
+    ///   .max_datagram_size(1400)
+    ///   .prune_order(["X-VENDOR-DEBUG", "SECURELOCATION.UPNP.ORG"]);
+    /// ```
+    pub fn max_datagram_size(mut self, bytes: usize) -> Self {
+        self.max_datagram_size = Some(bytes);
+        self
+    }
+
+    /// Set the headers removed first from messages exceeding `max_datagram_size`, in that
+    /// order. Other optional headers follow, from the last of the message to the first.
+    pub fn prune_order<S: Into<String>>(mut self, headers: impl IntoIterator<Item = S>) -> Self {
+        self.prune_order = headers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Stop serving and send `ssdp:byebye` when the process receives Ctrl-C, `SIGTERM` or a Windows
+    /// console close/shutdown event, defaults to `false`.
+    ///
+    /// The future returned by `serve` resolves once the byebye messages are sent, so the
+    /// application can exit right after awaiting it.
+    #[cfg(feature = "signal")]
+    pub fn shutdown_on_signal(mut self, enabled: bool) -> Self {
+        self.shutdown_on_signal = enabled;
+        self
+    }
+
+    /// Allow `serve` to pick container bridge interfaces (`docker0`, `veth*`, `cni*`, ...),
+    /// defaults to `false`.
+    pub fn bridge_interfaces(mut self, allow: bool) -> Self {
+        self.bridge_interfaces = allow;
+        self
+    }
+
+    /// Serve on the interfaces with these addresses instead of a single automatically selected
+    /// one when calling `serve`.
+    ///
+    /// Announcements are sent through every interface. Combine with `location_resolver` so each
+    /// network is handed a `LOCATION` reachable from it.
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Server;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// Server::new([])
+    ///   .interfaces([Ipv4Addr::new(192, 168, 1, 100), Ipv4Addr::new(10, 0, 0, 100)]);
+    /// ```
+    pub fn interfaces(mut self, ips: impl IntoIterator<Item = Ipv4Addr>) -> Self {
+        self.interfaces = ips.into_iter().collect();
+        self
+    }
+
+    /// Set the source of randomness for the `MX` response delay, defaults to [`ThreadRng`].
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Server, SeededRng};
+    ///
+    /// Server::new([])
+    ///   .rng(SeededRng::new(42));
+    /// ```
+    pub fn rng(mut self, rng: impl Rng + 'static) -> Self {
+        self.rng = Arc::new(rng);
+        self
+    }
+
+    /// Set the clock used for the `DATE` header, defaults to [`SystemClock`].
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// The ports and addresses the server will use with the current configuration.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Direction, ResponseSource, Server};
+    ///
+    /// let requirements = Server::new([])
+    ///   .response_source(ResponseSource::Ephemeral)
+    ///   .network_requirements();
+    ///
+    /// for rule in &requirements.rules {
+    ///     println!("{rule}");
+    /// }
+    /// assert_eq!(requirements.rules[0].direction, Direction::Inbound);
+    /// assert_eq!(requirements.rules[0].local_port, Some(1900));
+    /// ```
+    pub fn network_requirements(&self) -> NetworkRequirements {
+        let mut groups = vec![SocketAddr::from((SSDP_ADDR, self.port))];
+        if self.ipv6 {
+            groups.push((SSDP_ADDR_V6_LINK, self.port).into());
+            groups.push((SSDP_ADDR_V6_SITE, self.port).into());
+        }
+        let response_port = match self.responder() {
+            ResponseSource::Multicast => Some(self.port),
+            ResponseSource::Ephemeral => None,
+        };
+
+        let mut rules = vec![NetworkRule {
+            direction: Direction::Inbound,
+            local_port: Some(self.port),
+            remote: None,
+            purpose: "M-SEARCH requests and NOTIFY messages",
+        }];
+        rules.extend(groups.into_iter().map(|group| NetworkRule {
+            direction: Direction::Outbound,
+            local_port: Some(self.port),
+            remote: Some(group),
+            purpose: "ssdp:alive and ssdp:byebye announcements",
+        }));
+        rules.push(NetworkRule {
+            direction: Direction::Outbound,
+            local_port: response_port,
+            remote: None,
+            purpose: "M-SEARCH responses",
+        });
+
+        NetworkRequirements { rules }
+    }
+
+    /// Check whether the server would answer searches from the internet with the current
+    /// configuration, see [`ExposureReport`].
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, Requester, Server};
+    ///
+    /// let device = Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "upnp:rootdevice",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// )
+    /// .allow(Requester::Subnet("192.168.1.0/24".parse().unwrap()));
+    ///
+    /// let report = Server::new([device]).exposure_report();
+    /// assert!(report.exposed_devices.is_empty());
+    /// assert!(!report.is_exposed());
+    /// println!("{report}");
+    /// ```
+    pub fn exposure_report(&self) -> ExposureReport {
+        let public_addresses = interface::local_addrs()
+            .into_iter()
+            .filter(|ip| self.ipv6 || ip.is_ipv4())
+            .filter(|ip| posture::is_public(*ip))
+            .collect();
+        let exposed_devices = self
+            .devices
+            .snapshot()
+            .iter()
+            .filter(|d| posture::revealed_to_public(d))
+            .map(|d| d.usn.clone())
+            .collect();
+
+        ExposureReport {
+            port: self.port,
+            public_addresses,
+            exposed_devices,
+            exposed_templates: self
+                .templates
+                .iter()
+                .map(|t| t.search_target.clone())
+                .collect(),
+            custom_filters: self.response_policy.is_some() || !self.request_handlers.is_empty(),
+        }
+    }
+
+    /// The `ssdp:alive` messages announcing the configured devices to `destination`, one per
+    /// device.
+    ///
+    /// `HOST` is set to `destination` and `LOCATION` is resolved for the local address towards it.
+    /// Devices that are not multicast either are left out: those with an allow list and lazy
+    /// devices nobody searched for yet. Use `ServerHandle::notify_alive_to` to send them from a
+    /// running server.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::{Device, Server};
+    ///
+    /// let server = Server::new([Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:1",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// )]);
+    ///
+    /// let messages = server.alive_messages("192.168.1.20:1900".parse().unwrap());
+    /// assert_eq!(messages.len(), 1);
+    /// assert!(messages[0].contains("HOST: 192.168.1.20:1900\r\n"));
+    /// assert!(messages[0].contains("NTS: ssdp:alive\r\n"));
+    /// ```
+    pub fn alive_messages(&self, destination: SocketAddr) -> Vec<String> {
+        let extra_headers = self.extra_headers(destination.is_ipv6());
+        self.directed_messages(
+            &self.devices.snapshot(),
+            AuditKind::Alive,
+            destination,
+            &extra_headers,
+        )
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+    }
+
+    /// The `ssdp:byebye` messages retiring the configured devices at `destination`, one per
+    /// device, see `alive_messages`.
+    pub fn byebye_messages(&self, destination: SocketAddr) -> Vec<String> {
+        let extra_headers = self.extra_headers(destination.is_ipv6());
+        self.directed_messages(
+            &self.devices.snapshot(),
+            AuditKind::Byebye,
+            destination,
+            &extra_headers,
+        )
+        .into_iter()
+        .map(|(_, message)| message)
+        .collect()
+    }
+
+    /// Start serving on the interfaces set with `interfaces`, or an automatically selected one,
+    /// see `serve_addr` for details.
+    ///
+    /// Without `interfaces`, loopback, link-local-only, down and container bridge interfaces are
+    /// skipped and the interface the OS routes SSDP multicast traffic through is preferred. The
+    /// decision is logged at `info` level, use `serve_addr` to override it.
+    pub fn serve(self) -> Result<impl Future<Output = Result<()>>> {
+        let ips = match self.interfaces[..] {
+            [] => vec![
+                interface::select_interface(self.bridge_interfaces)
+                    .unwrap_or(Ipv4Addr::UNSPECIFIED),
+            ],
+            _ => self.interfaces.clone(),
+        };
+        self.serve_on(&ips)
+    }
+
+    /// Start serving on the interface with address `ip`, returns a future that needs to be
+    /// `await`ed to keep the server running.
+    ///
+    /// Passing `Ipv4Addr::UNSPECIFIED` lets the OS pick the interface. Fails if a device is invalid
+    /// (see `Device::validate`) or the socket cannot be set up.
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::Server;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// Server::new([])
+    ///   .serve_addr(Ipv4Addr::new(192, 168, 1, 100));
+    /// ```
+    pub fn serve_addr(self, ip: Ipv4Addr) -> Result<impl Future<Output = Result<()>>> {
+        self.serve_on(&[ip])
+    }
+
+    /// Start serving through `socket` instead of binding UDP sockets, returns a future that needs
+    /// to be `await`ed to keep the server running.
+    ///
+    /// Announcements are sent to the IPv4 group on `port`, through the interface the transport
+    /// picks. Responses are sent from `socket` too. Fails if a device is invalid, see
+    /// `Device::validate`.
+    /// # Examples
+    /// ```no_run
+    /// use std::net::Ipv4Addr;
+    /// use tokio::net::UdpSocket;
+    /// use tokio_ssdp::Server;
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 1900)).await?;
+    /// socket.join_multicast_v4(Ipv4Addr::new(239, 255, 255, 250), Ipv4Addr::UNSPECIFIED)?;
+    ///
+    /// Server::new([])
+    ///   .serve_socket(socket)?
+    ///   .await
+    /// # }
+    /// ```
+    pub fn serve_socket<S: SsdpSocket + 'static>(
+        self,
+        socket: S,
+    ) -> Result<impl Future<Output = Result<()>> + use<S>> {
+        self.prepare()?;
+
+        let channel = Channel::from_socket(Arc::new(socket), self.port);
+        self.serve_channels(vec![channel])
+    }
+
+    fn serve_on(self, ips: &[Ipv4Addr]) -> Result<impl Future<Output = Result<()>> + use<>> {
+        self.prepare()?;
+
+        let options = ChannelOptions {
+            port: self.port,
+            ttl: self.ttl,
+            response_ttl: self.response_ttl,
+            source: self.responder(),
+            reuse_port: self.reuse_port,
+            port_sharing: self.port_sharing,
+            multicast_loop: self.multicast_loop,
+            unicast_search: self.unicast_search,
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            bind_device: self.bind_device.clone(),
+        };
+        let channels = channel::bind(ips, &options, self.ipv6)?;
+        self.serve_channels(channels)
+    }
+
+    fn serve_channels(
+        self,
+        mut channels: Vec<Channel>,
+    ) -> Result<impl Future<Output = Result<()>> + use<>> {
+        if let Some(capture) = &self.capture {
+            for channel in channels.iter_mut() {
+                channel.wrap_sockets(|socket| CapturingSocket::wrap(socket, capture, &self.clock));
+            }
+        }
+        let channels: Arc<[Channel]> = channels.into();
+        let extra_headers = self.extra_headers(self.ipv6);
+
+        #[cfg(feature = "signal")]
+        let signaled = crate::signal::terminated(self.shutdown_on_signal);
+        #[cfg(not(feature = "signal"))]
+        let signaled = std::future::pending::<()>();
+        let watchdog = self.watchdog;
+        let queue = self
+            .recv_queue
+            .map(|(capacity, policy)| Arc::new(RecvQueue::new(capacity, policy)));
+        let this = Arc::new(self);
+
+        let server_fut = async move {
+            let mut buf = [0u8; 4096];
+            let mut tenant = Tenant::start(this, channels, extra_headers);
+            let mut receiver = JoinSet::new();
+            if let Some(queue) = &queue {
+                receiver.spawn(receive(
+                    Arc::clone(&tenant.server),
+                    Arc::clone(&tenant.channels),
+                    Arc::clone(queue),
+                    watchdog,
+                ));
+            }
+
+            let requested = tenant.requested();
+            let terminated = async {
+                tokio::select! {
+                    _ = signaled => {}
+                    _ = requested => {}
+                }
+            };
+            let mut terminated = std::pin::pin!(terminated);
+            let mut failures = 0;
+
+            loop {
+                let next = async {
+                    let Some(queue) = &queue else {
+                        let (n, addr, channel) =
+                            channel::recv_watched(&tenant.channels, &mut buf, watchdog).await?;
+                        return Ok((n, addr, channel, Instant::now()));
+                    };
+                    tokio::select! {
+                        (data, addr, channel, received) = queue.pop() => {
+                            buf[..data.len()].copy_from_slice(&data);
+                            Ok((data.len(), addr, channel, received))
+                        }
+                        Some(res) = receiver.join_next() => match res {
+                            Ok(Err(e)) => Err(e),
+                            Ok(Ok(())) | Err(_) => std::future::pending().await,
+                        },
+                    }
+                };
+                let (n, addr, channel, received) = tokio::select! {
+                    res = next => match res {
+                        Ok(datagram) => {
+                            failures = 0;
+                            datagram
+                        }
+                        Err(e) => {
+                            tenant.server.receive_failed(e, &mut failures).await?;
+                            continue;
+                        }
+                    },
+                    _ = &mut terminated => {
+                        info!("Termination requested, shutting down");
+                        tenant.stop().await;
+                        return Ok(());
+                    }
+                };
+
+                tenant.dispatch(addr, &buf[..n], channel, received).await;
+            }
+        };
+
+        Ok(server_fut)
+    }
+
+    /// Check the configuration before serving, logging what will not work as expected.
+    pub(crate) fn prepare(&self) -> Result<()> {
+        for device in self.devices.snapshot().iter() {
+            device.validate()?;
+        }
+
+        self.check_locations();
+        let devices = self.devices.snapshot();
+        let mut server_names: Vec<&String> = (self.server_name.iter())
+            .chain(devices.iter().filter_map(|d| d.server.as_ref()))
+            .collect();
+        server_names.sort();
+        server_names.dedup();
+        for name in server_names {
+            match name.parse::<ServerHeader>() {
+                Ok(header) if header.upnp_version() != self.upnp_version => warn!(
+                    "SERVER header {name:?} announces another version than UPnP/{}",
+                    self.upnp_version
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Sending a nonconforming SERVER header: {e}"),
+            }
+        }
+        let period = self.announce_period();
+        if let Some(device) = self.devices.snapshot().iter().find(|d| {
+            d.max_age
+                .is_some_and(|max_age| Duration::from_secs(max_age) < period)
+        }) {
+            warn!(
+                "The max-age of {} is shorter than the announcement interval of {period:?}",
+                device.usn
+            );
+        }
+        if (self.boot_id.is_some() || self.config_id.is_some())
+            && !self.upnp_version.allows_header("BOOTID.UPNP.ORG")
+        {
+            return Err(Error::Config(format!(
+                "boot and configuration IDs are not part of UPnP/{}, use UPnP 1.1 or later",
+                self.upnp_version
+            )));
+        }
+        if self.search_limiter.as_ref().is_some_and(|l| l.limit() == 0) {
+            return Err(Error::Config(
+                "a search rate limit of 0 would leave every search unanswered".into(),
+            ));
+        }
+        if let Some(device) = self.devices.snapshot().iter().find(|d| {
+            d.secure_location.is_some()
+                && !self.upnp_version.allows_header("SECURELOCATION.UPNP.ORG")
+        }) {
+            warn!(
+                "Not sending SECURELOCATION.UPNP.ORG of {}, it is not part of UPnP/{}",
+                device.usn, self.upnp_version
+            );
+        }
+        if self.port_sharing && self.response_source == Some(ResponseSource::Multicast) {
+            warn!(
+                "Answering searches from the shared SSDP port, follow-ups may reach the other program"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// The headers added to every message, pre-concatenated.
+    pub(crate) fn extra_headers(&self, ipv6: bool) -> String {
+        self.headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .chain(
+                self.identify
+                    .then(|| format!("{PEER_HEADER}: {}\r\n", PeerInfo::local().header_value())),
+            )
+            .chain(ipv6.then(|| {
+                // Lets dual-stack control points recognize the same device on both families, so
+                // the same in every message of this boot
+                let nls = self.nls.get_or_init(|| {
+                    format!(
+                        "{:016x}{:016x}",
+                        self.rng.below(u64::MAX),
+                        self.rng.below(u64::MAX)
+                    )
+                });
+                format!("OPT: \"http://schemas.upnp.org/upnp/1/0/\"; ns=01\r\n01-NLS: {nls}\r\n")
+            }))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// The socket search responses are sent from, see `response_source`.
+    fn responder(&self) -> ResponseSource {
+        match (self.response_source, self.port_sharing) {
+            (Some(source), _) => source,
+            // Unicast datagrams to a shared port reach one of the sockets only, so follow-ups to
+            // the responses could go to the other program
+            (None, true) => ResponseSource::Ephemeral,
+            (None, false) => ResponseSource::Multicast,
+        }
+    }
+
+    /// Granularity timers are rounded up to.
+    fn granularity(&self) -> Duration {
+        self.timer_granularity
+            .unwrap_or_else(|| self.power_profile.timer_granularity())
+    }
+
+    /// The shortest `max-age` the devices are advertised with.
+    fn shortest_max_age(&self) -> u64 {
+        self.devices
+            .snapshot()
+            .iter()
+            .map(|d| d.max_age.unwrap_or(self.max_age))
+            .min()
+            .unwrap_or(self.max_age)
+    }
+
+    /// The `max-age` to send for a device with `max_age`, extended past a postponed round, see
+    /// `ServerHandle::reschedule_announcement`.
+    fn max_age_of(&self, max_age: Option<u64>) -> u64 {
+        let max_age = max_age.unwrap_or(self.max_age);
+        match self.schedule().and_then(|s| s.held_for(Instant::now())) {
+            Some(held) => max_age.saturating_add(held.as_secs()),
+            None => max_age,
+        }
+    }
+
+    /// The announcement schedule shared with the handle, if there is one.
+    fn schedule(&self) -> Option<&Schedule> {
+        self.control.as_ref().map(|control| &*control.schedule)
+    }
+
+    /// Delay between two rounds of `ssdp:alive` messages, derived from the shortest `max-age`.
+    fn announce_period(&self) -> Duration {
+        let max_age = self.shortest_max_age();
+        let interval = self
+            .announce_interval
+            .unwrap_or_else(|| self.power_profile.announce_interval(max_age));
+        let strategy = &self.announce_strategy;
+        let interval = match strategy.half_max_age {
+            true => {
+                // The round itself counts, from the first message of a device to its last copy
+                let devices = u32::try_from(self.devices.snapshot().len()).unwrap_or(u32::MAX);
+                let messages = devices.saturating_mul(strategy.copies);
+                let round = strategy
+                    .round_length()
+                    .saturating_add(self.power_profile.pacing().saturating_mul(messages));
+                interval.min(
+                    (Duration::from_secs(max_age) / 2)
+                        .saturating_sub(round)
+                        .max(Duration::from_secs(1)),
+                )
+            }
+            false => interval,
+        };
+        power::coarsen(interval, self.granularity())
+    }
+
+    /// Append the secure location of `device`, if part of the UPnP version, and its extra
+    /// headers.
+    fn write_device_headers(&self, buf: &mut String, device: &Device) {
+        if let Some(url) = &device.secure_location {
+            if self.upnp_version.allows_header("SECURELOCATION.UPNP.ORG") {
+                let _ = write!(buf, "SECURELOCATION.UPNP.ORG: {url}\r\n");
+            }
+        }
+        for (name, value) in device.headers.iter() {
+            let _ = write!(buf, "{name}: {value}\r\n");
+        }
+    }
+
+    /// Prune optional headers from `message` to respect `max_datagram_size`.
+    fn fit(&self, message: &mut String) {
+        let Some(max) = self.max_datagram_size else {
+            return;
+        };
+        match prune::prune(message, max, &self.prune_order) {
+            Ok(pruned) if pruned.is_empty() => {}
+            Ok(pruned) => debug!("Pruned {pruned:?} to keep a message within {max} bytes"),
+            Err(_) => warn!(
+                "Message of {} bytes exceeds the maximum of {max} bytes without optional headers",
+                message.len()
+            ),
+        }
+    }
+
+    /// Whether `device` is announced: it has no allow list and, if lazy, was searched for.
+    fn announces(&self, device: &Device) -> bool {
+        !device.is_restricted() && self.devices.is_awake(device)
+    }
+
+    /// Append the `BOOTID.UPNP.ORG` and `CONFIGID.UPNP.ORG` headers, if configured and part of
+    /// the UPnP version.
+    fn write_ids(&self, buf: &mut String) {
+        if !self.upnp_version.allows_header("BOOTID.UPNP.ORG") {
+            return;
+        }
+        if let Some(boot_id) = &self.boot_id {
+            let _ = write!(
+                buf,
+                "BOOTID.UPNP.ORG: {}\r\n",
+                boot_id.load(Ordering::Relaxed)
+            );
+        }
+        if let Some(config_id) = self.config_id {
+            let _ = write!(buf, "CONFIGID.UPNP.ORG: {config_id}\r\n");
+        }
+    }
+
+    /// The value of the `SERVER` header.
+    fn server_header(&self) -> Cow<'_, str> {
+        match &self.server_name {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(ServerHeader::tokio_ssdp(self.upnp_version).to_string()),
+        }
+    }
+
+    /// Warn about devices whose `LOCATION` host is not an address of this host.
+    fn check_locations(&self) {
+        let local_addrs = interface::local_addrs();
+        if local_addrs.is_empty() {
+            return;
+        }
+
+        for device in self.devices.snapshot().iter() {
+            let Some(ip) = location::host_ip(&device.location) else {
+                continue;
+            };
+
+            if !local_addrs.contains(&ip) {
+                if self.location_overrides.is_empty() {
+                    warn!(
+                        "LOCATION {} of {} is not on a local interface (NAT or port forward?), consider `Server::location_override`",
+                        self.redaction.apply(&device.location),
+                        self.redaction.apply(&device.usn)
+                    );
+                } else {
+                    debug!(
+                        "LOCATION {} of {} is not on a local interface",
+                        self.redaction.apply(&device.location),
+                        self.redaction.apply(&device.usn)
+                    );
+                }
+            }
+        }
+    }
+
+    /// The `LOCATION` of `device` as seen by a requester at `remote_addr`.
+    fn location_for<'a>(&self, device: &'a Device, remote_addr: SocketAddr) -> Cow<'a, str> {
+        match self
+            .location_overrides
+            .iter()
+            .find(|(subnet, _)| subnet.contains(remote_addr.ip()))
+        {
+            Some((_, origin)) => Cow::Owned(location::rebase(&device.location, origin)),
+            None => self.resolve_location(device, || interface::local_addr_towards(remote_addr)),
+        }
+    }
+
+    /// The `LOCATION` of `device` in announcements sent to `group` through `interface`.
+    fn announced_location<'a>(
+        &self,
+        device: &'a Device,
+        group: SocketAddr,
+        interface: Interface,
+    ) -> Cow<'a, str> {
+        self.resolve_location(device, || {
+            interface
+                .addr()
+                .or_else(|| interface::local_addr_towards(group))
+        })
+    }
+
+    /// Ask the `LocationResolver` for the `LOCATION` of `device`, if there is one and the local
+    /// address is known.
+    fn resolve_location<'a>(
+        &self,
+        device: &'a Device,
+        local_addr: impl FnOnce() -> Option<IpAddr>,
+    ) -> Cow<'a, str> {
+        let resolved = self
+            .location_resolver
+            .as_ref()
+            .and_then(|resolver| Some(resolver.location(device, local_addr()?)));
+        match resolved {
+            Some(location) => Cow::Owned(location),
+            None => Cow::Borrowed(&device.location),
+        }
+    }
+
+    /// Run the request handlers on `data`, `None` if one of them dropped or answered it, the
+    /// request as they left it otherwise, borrowed if they did not change it.
+    async fn run_handlers<'a>(
+        &self,
+        remote_addr: SocketAddr,
+        data: &'a [u8],
+        channel: &Channel,
+        extra_headers: &str,
+        pending: &mut JoinSet<()>,
+        timing: Timing,
+    ) -> Option<Cow<'a, [u8]>> {
+        if self.request_handlers.is_empty() {
+            return Some(Cow::Borrowed(data));
+        }
+        let Ok(mut request) = NotifyRequest::parse(remote_addr, data) else {
+            return Some(Cow::Borrowed(data));
+        };
+        let received = request.clone();
+
+        for handler in self.request_handlers.iter() {
+            match handler.handle(&mut request) {
+                HandlerDecision::Continue => {}
+                HandlerDecision::Drop => {
+                    debug!(
+                        "Request handler dropped {} from {remote_addr}",
+                        request.method
+                    );
+                    self.dropped(remote_addr, "request handler");
+                    return None;
+                }
+                HandlerDecision::Respond(response) => {
+                    let response =
+                        with_default_headers(&response, &self.server_header(), &*self.clock);
+                    if let Err(e) = channel
+                        .responder
+                        .send_to(&response.to_bytes(), response.remote_addr)
+                        .await
+                    {
+                        self.report(ServerError::Respond {
+                            destination: response.remote_addr,
+                            source: e,
+                        });
+                    }
+                    return None;
+                }
+                HandlerDecision::Reply(replies) => {
+                    match SearchRequest::parse(remote_addr, &request.to_bytes()) {
+                        Ok(search) => {
+                            debug!(
+                                "Request handler answered M-SEARCH #{} from {remote_addr}",
+                                search.id
+                            );
+                            if self.event_tx.is_some() {
+                                self.emit(SsdpEvent::Search(search.clone()));
+                            }
+                            let timing = timing.serializing();
+                            let responses = replies
+                                .iter()
+                                .filter(|reply| match reply.validate() {
+                                    Ok(()) => true,
+                                    Err(e) => {
+                                        warn!("Not sending the reply of a request handler: {e}");
+                                        false
+                                    }
+                                })
+                                .map(|reply| {
+                                    let message = self.reply_message(reply, extra_headers);
+                                    self.response(
+                                        &search,
+                                        message,
+                                        &reply.search_target,
+                                        Some(&reply.usn),
+                                        timing.received,
+                                    )
+                                })
+                                .collect::<Vec<_>>();
+                            if !responses.is_empty() {
+                                let socket = Arc::clone(&channel.responder);
+                                self.respond_later(&search, socket, pending, responses, timing);
+                            }
+                        }
+                        Err(e) => {
+                            debug!("Not replying to {remote_addr}: {e}");
+                            self.dropped(remote_addr, "request handler");
+                        }
+                    }
+                    return None;
+                }
+            }
+        }
+
+        // Serializing an untouched request could still differ from what was received
+        if request == received {
+            Some(Cow::Borrowed(data))
+        } else {
+            Some(Cow::Owned(request.to_bytes()))
+        }
+    }
+
+    /// Log `error` and deliver it to the error stream, see [`report`].
+    fn report(&self, error: ServerError) {
+        report(self.error_tx.as_ref(), error);
+    }
+
+    /// Handle the failure to receive `error`, the `failures`th in a row: recoverable errors are
+    /// reported and the caller receives again after a short pause, so an error repeating until
+    /// the network is back does not spin; fatal ones, and the last of `MAX_RECEIVE_FAILURES` in
+    /// a row, are reported and returned, stopping the server.
+    async fn receive_failed(
+        &self,
+        error: std::io::Error,
+        failures: &mut u32,
+    ) -> std::io::Result<()> {
+        *failures += 1;
+        self.receive_error(error, *failures)?;
+        tokio::time::sleep(RECEIVE_BACKOFF).await;
+        Ok(())
+    }
+
+    /// Report the failure to receive `error`, the `failures`th in a row, returning it if it
+    /// stops the server, see `receive_failed`.
+    pub(crate) fn receive_error(
+        &self,
+        error: std::io::Error,
+        failures: u32,
+    ) -> std::io::Result<()> {
+        match ServerError::receive(error) {
+            ServerError::Receive(e) if failures < MAX_RECEIVE_FAILURES => {
+                self.report(ServerError::Receive(e));
+                Ok(())
+            }
+            ServerError::Receive(e) | ServerError::Socket(e) => {
+                if failures >= MAX_RECEIVE_FAILURES {
+                    warn!("Receiving failed {failures} times in a row, giving up");
+                }
+                self.report(ServerError::Socket(copy_io_error(&e)));
+                Err(e)
+            }
+            error => {
+                self.report(error);
+                Ok(())
+            }
+        }
+    }
+
+    /// Deliver `event` to the event stream, if any, dropping it if the stream lags behind.
+    fn emit(&self, event: SsdpEvent) {
+        if let Some(tx) = &self.event_tx {
+            if let Err(tokio::sync::mpsc::error::TrySendError::Full(event)) = tx.try_send(event) {
+                debug!("Event stream is full, dropping {event:?}");
+            }
+        }
+    }
+
+    async fn handle_notify(&self, nr: Arc<NotifyMessage>) -> Result<()> {
+        debug!(
+            "Received NOTIFY from {} with {} bytes",
+            nr.remote_addr,
+            nr.data.len()
+        );
+
+        if let Some(tx) = &self.notify_req_tx {
+            if tx.send(nr).await.is_err() {
+                error!("Failed to send notify request");
+            }
+        } else {
+            debug!("No notify request sender configured, ignoring NOTIFY");
+        }
+
+        Ok(())
+    }
+
+    /// Answer `search`, returning the lazy devices it woke up, see `Device::lazy`.
+    async fn handle_search(
+        &self,
+        search: &SearchRequest,
+        socket: Arc<dyn SsdpSocket>,
+        extra_headers: &str,
+        pending: &mut JoinSet<()>,
+        timing: Timing,
+    ) -> Result<Vec<Device>> {
+        let remote_addr = search.remote_addr;
+
+        if !search.man.eq_ignore_ascii_case("ssdp:discover")
+            && !self
+                .search_man
+                .iter()
+                .any(|m| header::unquote(m).eq_ignore_ascii_case(&search.man))
+        {
+            debug!(
+                "Ignoring M-SEARCH #{} from {remote_addr} with MAN {}",
+                search.id, search.man
+            );
+            return Ok(vec![]);
+        }
+
+        debug!(
+            "M-SEARCH #{} from {remote_addr}: ST={:?}, MX={:?}",
+            search.id,
+            self.redaction.apply(&search.search_target),
+            search.mx
+        );
+
+        if let Some(policy) = &self.response_policy {
+            if !policy.respond(search) {
+                debug!(
+                    "Response policy declined M-SEARCH #{} from {remote_addr}",
+                    search.id
+                );
+                return Ok(vec![]);
+            }
+        }
+
+        // Every match is answered, once per USN; only sweeps are capped by `search_all_limit`
+        let search_all = search.search_target.eq_ignore_ascii_case("ssdp:all");
+        let wildcard = search::is_wildcard(&search.search_target, self.wildcard_search);
+        let limit = if search_all || wildcard {
+            self.search_all_limit.unwrap_or(usize::MAX)
+        } else {
+            usize::MAX
+        };
+        let templates: Vec<&ResponseTemplate> = self
+            .templates
+            .iter()
+            .filter(|t| {
+                search::target_matches(
+                    &search.search_target,
+                    &t.search_target,
+                    self.wildcard_search,
+                )
+            })
+            .take(limit)
+            .collect();
+        if !templates.is_empty() {
+            let timing = timing.serializing();
+            let responses = templates
+                .into_iter()
+                .map(|template| {
+                    debug!(
+                        "Answering M-SEARCH #{} with the template for {}",
+                        search.id, template.search_target
+                    );
+                    self.response(
+                        search,
+                        template.render(),
+                        &template.search_target,
+                        None,
+                        timing.received,
+                    )
+                })
+                .collect();
+            self.respond_later(search, socket, pending, responses, timing);
+            return Ok(vec![]);
+        }
+
+        let all = self.devices.snapshot();
+        let mut usns = HashSet::new();
+        let mut devices: Vec<&Device> = all
+            .iter()
+            .filter(|d| {
+                (search_all
+                    || search::target_matches(
+                        &search.search_target,
+                        &d.search_target,
+                        self.wildcard_search,
+                    ))
+                    && d.visible_to(search)
+                    && d.on_family_of(search.remote_addr.ip())
+                    && self
+                        .response_policy
+                        .as_ref()
+                        .is_none_or(|policy| policy.reveal(search, d))
+            })
+            .filter(|d| usns.insert(d.usn.as_str()))
+            .take(limit)
+            .collect();
+
+        // Devices are found by their UUID even if they do not advertise the `uuid:` target
+        let by_uuid;
+        if devices.is_empty() {
+            by_uuid = self.find_by_uuid(search);
+            devices.extend(by_uuid.iter());
+        }
+
+        // A sweep for everything does not count as interest in the lazy devices
+        let woken = if search_all {
+            vec![]
+        } else {
+            self.devices.wake(devices.iter().copied())
+        };
+
+        let timing = timing.serializing();
+        let date = httpdate::HttpDate::from(self.clock.now());
+        let mut responses = Vec::with_capacity(devices.len());
+        for device in devices {
+            debug!(
+                "M-SEARCH #{} matched {}",
+                search.id,
+                self.redaction.apply(&format!("{device:?}"))
+            );
+            trace::matched(device, &self.redaction);
+            if let Some(lifecycle) = &self.lifecycle {
+                lifecycle.searched(device, search);
+            }
+
+            let mut message = String::with_capacity(
+                MESSAGE_CAPACITY
+                    + extra_headers.len()
+                    + device.body.as_ref().map_or(0, String::len),
+            );
+            let _ = write!(
+                message,
+                concat!(
+                    "HTTP/1.1 200 OK\r\n",
+                    "CACHE-CONTROL: max-age={max_age}\r\n",
+                    "DATE: {date}\r\n",
+                    "EXT:\r\n",
+                    "LOCATION: {loc}\r\n",
+                    "SERVER: {server}\r\n",
+                    "ST: {st}\r\n",
+                    "USN: {usn}\r\n",
+                    "{headers}",
+                ),
+                max_age = self.max_age_of(device.max_age),
+                date = date,
+                loc = self.location_for(device, remote_addr),
+                server = device.server.as_deref().unwrap_or(&self.server_header()),
+                st = device.search_target,
+                usn = device.usn,
+                headers = extra_headers
+            );
+            self.write_ids(&mut message);
+            self.write_device_headers(&mut message, device);
+            if let Some(policy) = &self.response_policy {
+                for (name, value) in policy.headers(search, device) {
+                    if name.contains(['\r', '\n']) || value.contains(['\r', '\n']) {
+                        warn!("Skipping invalid response policy header {name:?}: {value:?}");
+                        continue;
+                    }
+                    let _ = write!(message, "{name}: {value}\r\n");
+                }
+            }
+
+            write_body(&mut message, device);
+            self.fit(&mut message);
+
+            responses.push(self.response(
+                search,
+                message,
+                &device.search_target,
+                Some(&device.usn),
+                timing.received,
+            ));
+        }
+
+        if !responses.is_empty() {
+            self.respond_later(search, socket, pending, responses, timing);
+        }
+
+        Ok(woken)
+    }
+
+    /// The message of a response a request handler crafted.
+    fn reply_message(&self, reply: &MSearchResponse, extra_headers: &str) -> String {
+        let mut message = String::with_capacity(MESSAGE_CAPACITY + extra_headers.len());
+        let _ = write!(
+            message,
+            concat!(
+                "HTTP/1.1 200 OK\r\n",
+                "CACHE-CONTROL: max-age={max_age}\r\n",
+                "DATE: {date}\r\n",
+                "EXT:\r\n",
+                "LOCATION: {loc}\r\n",
+                "SERVER: {server}\r\n",
+                "ST: {st}\r\n",
+                "USN: {usn}\r\n",
+                "{headers}",
+            ),
+            max_age = reply.max_age.unwrap_or_else(|| self.max_age_of(None)),
+            date = httpdate::HttpDate::from(reply.date.unwrap_or_else(|| self.clock.now())),
+            loc = reply.location,
+            server = reply.server.as_deref().unwrap_or(&self.server_header()),
+            st = reply.search_target,
+            usn = reply.usn,
+            headers = extra_headers
+        );
+        self.write_ids(&mut message);
+        for (name, value) in reply.headers.iter() {
+            let _ = write!(message, "{name}: {value}\r\n");
+        }
+        message.push_str("\r\n");
+        self.fit(&mut message);
+        message
+    }
+
+    /// The `uuid:` advertisement of the device a `ST: uuid:<uuid>` search looks for, if any.
+    fn find_by_uuid(&self, search: &SearchRequest) -> Option<Device> {
+        let st = &search.search_target;
+        let uuid = st
+            .get(..5)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("uuid:"))
+            .map(|_| &st[5..])?;
+
+        self.devices
+            .snapshot()
+            .iter()
+            .find(|d| {
+                d.uuid.eq_ignore_ascii_case(uuid)
+                    && d.visible_to(search)
+                    && d.on_family_of(search.remote_addr.ip())
+                    && self
+                        .response_policy
+                        .as_ref()
+                        .is_none_or(|policy| policy.reveal(search, d))
+            })
+            .map(|d| d.advertisement(&d.uuid, ""))
+    }
+
+    /// A response to `search`, received at `received`, with its audit record if there is an
+    /// audit sink.
+    fn response(
+        &self,
+        search: &SearchRequest,
+        message: String,
+        search_target: &str,
+        usn: Option<&str>,
+        received: Instant,
+    ) -> Response {
+        let audit = self.audit_sink.as_ref().map(|_| {
+            let now = self.clock.now();
+            let received = now.checked_sub(received.elapsed()).unwrap_or(now);
+            AuditRecord {
+                kind: AuditKind::SearchResponse,
+                search_target: self.redaction.apply(search_target).into_owned(),
+                usn: usn.map(|usn| self.redaction.apply(usn).into_owned()),
+                destination: search.remote_addr,
+                timestamp: now,
+                search_received: Some(received),
+                search_id: Some(search.id),
+            }
+        });
+
+        Response {
+            message,
+            usn: usn.map(str::to_owned),
+            audit,
+        }
+    }
+
+    /// Send `responses` to the requester within the delay its `MX` allows.
+    ///
+    /// A single response is sent after a random delay below `MX` seconds. Several responses (to
+    /// `ssdp:all`) are spread evenly over the `MX` window, so the requester's socket buffer is not
+    /// flooded.
+    fn respond_later(
+        &self,
+        search: &SearchRequest,
+        socket: Arc<dyn SsdpSocket>,
+        pending: &mut JoinSet<()>,
+        mut responses: Vec<Response>,
+        timing: Timing,
+    ) {
+        if let Some(dedup) = &self.search_dedup {
+            let count = responses.len();
+            responses.retain(|response| {
+                response
+                    .usn
+                    .as_deref()
+                    .is_none_or(|usn| !dedup.responded(search.remote_addr, usn))
+            });
+            if responses.len() < count {
+                debug!(
+                    "Leaving out {} responses to M-SEARCH #{} sent to {} already",
+                    count - responses.len(),
+                    search.id,
+                    search.remote_addr
+                );
+            }
+            if responses.is_empty() {
+                return;
+            }
+        }
+        let scheduled = Instant::now();
+        let remote_addr = match &self.response_policy {
+            Some(policy) => policy.destination(search, search.remote_addr),
+            None => search.remote_addr,
+        };
+        if remote_addr != search.remote_addr {
+            debug!(
+                "Sending the responses to M-SEARCH #{} from {} to {remote_addr}",
+                search.id, search.remote_addr
+            );
+        }
+        let search_id = search.id;
+        let tcp_addr = self
+            .tcp_responses
+            .then(|| {
+                search
+                    .header("TCPPORT.UPNP.ORG")?
+                    .trim()
+                    .parse::<u16>()
+                    .ok()
+            })
+            .flatten()
+            .map(|port| SocketAddr::new(remote_addr.ip(), port));
+        let audit = self
+            .audit_sink
+            .clone()
+            .map(|sink| (sink, Arc::clone(&self.clock)));
+        let metrics = self.metrics.clone();
+        let scheduler = self.response_scheduler.clone();
+        // The requester is unlikely to still listen MX seconds after a response was due
+        let turn_timeout = Duration::from_secs(search.mx.clamp(1, 5) as u64);
+        let dedup = self.search_dedup.clone();
+        let requester = search.remote_addr;
+        let error_tx = self.error_tx.clone();
+        let redaction = self.redaction;
+
+        for response in responses.iter() {
+            debug!(
+                "Response to M-SEARCH #{search_id}: {}",
+                self.redaction.apply(&response.message)
+            );
+        }
+
+        // upnp specification advises to use a number less than 5 if it is bigger than 5
+        let mx = if self.honor_mx { search.mx.min(5) } else { 0 };
+        let count = responses.len() as u32;
+        let spacing = if count > 1 {
+            (Duration::from_secs(mx as u64) / count).max(self.power_profile.pacing())
+        } else {
+            Duration::ZERO
+        };
+        // wait a random time up to mx, or within the first slot when spreading
+        let mut wait = if mx == 0 {
+            Duration::ZERO
+        } else if count > 1 {
+            Duration::from_millis(self.rng.below((spacing.as_millis() as u64).max(1)))
+        } else {
+            // Millisecond jitter, so devices answering the same search do not collide on whole
+            // seconds
+            let wait = Duration::from_millis(self.rng.below(mx as u64 * 1000));
+            power::coarsen(wait, self.granularity()).min(Duration::from_secs(mx as u64))
+        };
+        if let Some(policy) = &self.response_policy {
+            wait = policy.delay(search, wait);
+        }
+
+        // Reap the responses sent in the meantime
+        while pending.try_join_next().is_some() {}
+        let send = async move {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            for (i, response) in responses.into_iter().enumerate() {
+                if i > 0 && !spacing.is_zero() {
+                    tokio::time::sleep(spacing).await;
+                }
+                if let Some(scheduler) = &scheduler {
+                    let deadline = Instant::now() + turn_timeout;
+                    if !scheduler.turn(remote_addr.ip(), deadline).await {
+                        debug!(
+                            "Dropping a response to M-SEARCH #{search_id}, its turn came too late"
+                        );
+                        continue;
+                    }
+                }
+                // Another search of the requester may have been answered in the meantime
+                let deduplicated = dedup.as_ref().zip(response.usn.as_deref());
+                if deduplicated.is_some_and(|(dedup, usn)| dedup.responded(requester, usn)) {
+                    continue;
+                }
+                let sending = Instant::now();
+                let sent = match tcp_addr {
+                    Some(tcp_addr) => match send_tcp(tcp_addr, response.message.as_bytes()).await {
+                        Ok(()) => Ok(()),
+                        Err(e) => {
+                            debug!("TCP response to {tcp_addr} failed, falling back to UDP: {e}");
+                            socket
+                                .send_to(response.message.as_bytes(), remote_addr)
+                                .await
+                                .map(drop)
+                        }
+                    },
+                    None => socket
+                        .send_to(response.message.as_bytes(), remote_addr)
+                        .await
+                        .map(drop),
+                };
+                if let Err(e) = sent {
+                    debug!("Failed to send response to M-SEARCH #{search_id}");
+                    report(
+                        error_tx.as_ref(),
+                        ServerError::Respond {
+                            destination: remote_addr,
+                            source: e,
+                        },
+                    );
+                    continue;
+                }
+                if let Some((dedup, usn)) = deduplicated {
+                    dedup.record_response(requester, usn);
+                }
+                trace::responded(response.usn.as_deref(), &redaction);
+                if let Some(metrics) = &metrics {
+                    metrics.response_sent(remote_addr);
+                    let latency = ResponseLatency {
+                        queued: timing.dispatched - timing.received,
+                        processing: timing.serializing - timing.dispatched,
+                        serialization: scheduled - timing.serializing,
+                        delay: sending - scheduled,
+                        send: sending.elapsed(),
+                    };
+                    metrics.response_latency(remote_addr, latency);
+                }
+                if let (Some((sink, clock)), Some(mut record)) = (&audit, response.audit) {
+                    record.timestamp = clock.now();
+                    sink.record(&record);
+                }
+            }
+        };
+        pending.spawn(trace::respond(search_id, remote_addr, send));
+    }
+
+    /// Record an announcement of `device` with the audit sink, if any.
+    fn audit(&self, kind: AuditKind, device: &Device, destination: SocketAddr) {
+        if let Some(sink) = &self.audit_sink {
+            sink.record(&AuditRecord {
+                kind,
+                search_target: self.redaction.apply(&device.search_target).into_owned(),
+                usn: Some(self.redaction.apply(&device.usn).into_owned()),
+                destination,
+                timestamp: self.clock.now(),
+                search_received: None,
+                search_id: None,
+            });
+        }
+    }
+
+    /// Broadcast `ssdp:alive`
+    async fn broadcast_alive(
+        &self,
+        devices: &[Device],
+        channels: &[Channel],
+        extra_headers: &str,
+    ) -> Result<()> {
+        debug!("Sending alive messages");
+
+        // Each device is sent at a random offset below the jitter, and its copies follow
+        let strategy = &self.announce_strategy;
+        let mut schedule: Vec<(Duration, u32, &Device)> = vec![];
+        for device in devices.iter().filter(|d| self.announces(d)) {
+            let offset = match strategy.jitter.as_millis() as u64 {
+                0 => Duration::ZERO,
+                max => Duration::from_millis(self.rng.below(max)),
+            };
+            for copy in 0..strategy.copies {
+                let at = offset.saturating_add(strategy.copy_spacing.saturating_mul(copy));
+                schedule.push((at, copy, device));
+            }
+        }
+        schedule.sort_by_key(|(at, copy, _)| (*at, *copy));
+
+        let start = tokio::time::Instant::now();
+        let mut message = String::with_capacity(MESSAGE_CAPACITY + extra_headers.len());
+        let mut failed = None;
+        for (at, copy, device) in schedule {
+            tokio::time::sleep_until(start + at).await;
+            if let Err(e) = self
+                .send_alive(
+                    device,
+                    channels,
+                    extra_headers,
+                    Nts::Alive.as_str(),
+                    &mut message,
+                )
+                .await
+            {
+                failed.get_or_insert(e);
+            }
+            if copy == 0 {
+                if let Some(lifecycle) = &self.lifecycle {
+                    lifecycle.hook.on_announced(device);
+                }
+            }
+
+            // Avoid congestion
+            let pacing = self.power_profile.pacing();
+            if !pacing.is_zero() {
+                tokio::time::sleep(pacing).await;
+            }
+        }
+
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Send the `ssdp:alive` messages of `device`, or alive-shaped ones with the `NTS` value
+    /// `nts`, on each group of `channels`, failing with the first failed send once all were
+    /// attempted.
+    async fn send_alive(
+        &self,
+        device: &Device,
+        channels: &[Channel],
+        extra_headers: &str,
+        nts: &str,
+        message: &mut String,
+    ) -> Result<()> {
+        let mut failed = None;
+        for channel in channels
+            .iter()
+            .filter(|c| device.on_family_of(c.groups[0].ip()))
+        {
+            for (&group, &interface) in groups_by_interface(channel) {
+                message.clear();
+                let location = self.announced_location(device, group, interface);
+                self.write_notify(message, device, group, &location, nts, extra_headers);
+
+                debug!("{nts} message: {}", self.redaction.apply(message));
+
+                match self
+                    .send_announcement(channel, message, group, interface)
+                    .await
+                {
+                    Ok(()) if nts == Nts::Alive.as_str() => {
+                        self.audit(AuditKind::Alive, device, group);
+                        self.notify_sent(Nts::Alive, group);
+                    }
+                    Ok(()) => {
+                        self.audit(AuditKind::Custom, device, group);
+                        if let Some(metrics) = &self.metrics {
+                            metrics.custom_notify_sent(nts, group);
+                        }
+                    }
+                    Err(e) => {
+                        failed.get_or_insert(e);
+                    }
+                }
+            }
+        }
+
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Send the announcement `message` to `group` on `interface`, reporting a failure on the
+    /// error stream, so the caller can go on with the other devices and groups.
+    async fn send_announcement(
+        &self,
+        channel: &Channel,
+        message: &str,
+        group: SocketAddr,
+        interface: Interface,
+    ) -> Result<()> {
+        let sent = channel
+            .send_multicast(message.as_bytes(), group, interface)
+            .await;
+        if let Err(e) = &sent {
+            self.report(ServerError::Announce(Error::Io(copy_io_error(e))));
+        }
+        Ok(sent?)
+    }
+
+    /// Broadcast the alive-shaped `NOTIFY` with the `NTS` value `nts`.
+    async fn broadcast_custom(
+        &self,
+        devices: &[Device],
+        channels: &[Channel],
+        extra_headers: &str,
+        nts: &str,
+    ) -> Result<()> {
+        debug!("Sending {nts} messages");
+
+        let mut message = String::with_capacity(MESSAGE_CAPACITY + extra_headers.len());
+        let mut failed = None;
+        for device in devices.iter().filter(|d| self.announces(d)) {
+            if let Err(e) = self
+                .send_alive(device, channels, extra_headers, nts, &mut message)
+                .await
+            {
+                failed.get_or_insert(e);
+            }
+
+            // Avoid congestion
+            let pacing = self.power_profile.pacing();
+            if !pacing.is_zero() {
+                tokio::time::sleep(pacing).await;
+            }
+        }
+
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Broadcast `ssdp:update` announcing the next boot ID, then switch to it.
+    async fn broadcast_update(&self, channels: &[Channel], extra_headers: &str) -> Result<()> {
+        let Some(boot_id) = &self.boot_id else {
+            warn!("ssdp:update requested without a boot ID, see `Server::boot_id`");
+            return Ok(());
+        };
+        let next_boot_id = next_boot_id(boot_id.load(Ordering::Relaxed));
+        debug!("Sending update messages for boot ID {next_boot_id}");
+
+        let mut message = String::with_capacity(MESSAGE_CAPACITY + extra_headers.len());
+        let mut failed = None;
+        for device in self.devices.snapshot().iter().filter(|d| self.announces(d)) {
+            for channel in channels
+                .iter()
+                .filter(|c| device.on_family_of(c.groups[0].ip()))
+            {
+                for (&group, &interface) in groups_by_interface(channel) {
+                    message.clear();
+                    let _ = write!(
+                        message,
+                        concat!(
+                            "NOTIFY * HTTP/1.1\r\n",
+                            "HOST: {group}\r\n",
+                            "LOCATION: {loc}\r\n",
+                            "NT: {st}\r\n",
+                            "NTS: ssdp:update\r\n",
+                            "USN: {usn}\r\n",
+                            "NEXTBOOTID.UPNP.ORG: {next_boot_id}\r\n",
+                            "{headers}",
+                        ),
+                        group = group,
+                        loc = self.announced_location(device, group, interface),
+                        st = device.search_target,
+                        usn = device.usn,
+                        next_boot_id = next_boot_id,
+                        headers = extra_headers
+                    );
+                    self.write_ids(&mut message);
+                    self.write_device_headers(&mut message, device);
+
+                    write_body(&mut message, device);
+                    self.fit(&mut message);
+
+                    debug!("Update message: {}", self.redaction.apply(&message));
+
+                    match self
+                        .send_announcement(channel, &message, group, interface)
+                        .await
+                    {
+                        Ok(()) => {
+                            self.audit(AuditKind::Update, device, group);
+                            self.notify_sent(Nts::Update, group);
+                        }
+                        Err(e) => {
+                            failed.get_or_insert(e);
+                        }
+                    }
+                }
+            }
+
+            // Avoid congestion
+            let pacing = self.power_profile.pacing();
+            if !pacing.is_zero() {
+                tokio::time::sleep(pacing).await;
+            }
+        }
+
+        self.set_boot_id(boot_id, next_boot_id);
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Switch to `next_boot_id`, see `Server::boot_id`.
+    fn set_boot_id(&self, boot_id: &AtomicU32, next_boot_id: u32) {
+        boot_id.store(next_boot_id, Ordering::Relaxed);
+        self.journal.record([DeviceChange::BootId(next_boot_id)]);
+    }
+
+    /// Re-join the multicast groups after the network changed and announce the devices anew:
+    /// `ssdp:byebye` so control points drop addresses that may be gone, then `ssdp:alive` with
+    /// the next boot ID, if there is one.
+    async fn network_changed(&self, channels: &[Channel], extra_headers: &str) -> Result<()> {
+        for channel in channels {
+            channel.rejoin();
+        }
+
+        let devices = self.devices.snapshot();
+        // Likely to fail for the interface that went away, which must not keep the devices from
+        // being announced on the others. Failed sends were reported already.
+        let _ = self
+            .broadcast_byebye(&devices, channels, extra_headers)
+            .await;
+        if let Some(boot_id) = &self.boot_id {
+            self.set_boot_id(boot_id, next_boot_id(boot_id.load(Ordering::Relaxed)));
+        }
+        self.broadcast_alive(&devices, channels, extra_headers)
+            .await
+    }
+
+    /// Record changes to the advertised devices in the journal.
+    fn record_changes(&self, removed: &[Device], added: &[Device], updated: &[Device]) {
+        self.journal.record(
+            (removed.iter().cloned().map(DeviceChange::Removed))
+                .chain(added.iter().cloned().map(DeviceChange::Added))
+                .chain(updated.iter().cloned().map(DeviceChange::Updated)),
+        );
+    }
+
+    /// Write the `ssdp:alive` message announcing `device` to `host`, or one with another `nts`.
+    fn write_notify(
+        &self,
+        message: &mut String,
+        device: &Device,
+        host: SocketAddr,
+        location: &str,
+        nts: &str,
+        extra_headers: &str,
+    ) {
+        let _ = write!(
+            message,
+            concat!(
+                "NOTIFY * HTTP/1.1\r\n",
+                "HOST: {host}\r\n",
+                "CACHE-CONTROL: max-age={max_age}\r\n",
+                "LOCATION: {loc}\r\n",
+                "NT: {st}\r\n",
+                "NTS: {nts}\r\n",
+                "SERVER: {server}\r\n",
+                "USN: {usn}\r\n",
+                "{headers}",
+            ),
+            max_age = self.max_age_of(device.max_age),
+            host = host,
+            loc = location,
+            nts = nts,
+            server = device.server.as_deref().unwrap_or(&self.server_header()),
+            st = device.search_target,
+            usn = device.usn,
+            headers = extra_headers
+        );
+        self.write_ids(message);
+        self.write_device_headers(message, device);
+
+        write_body(message, device);
+        self.fit(message);
+    }
+
+    /// Write the `ssdp:byebye` message retiring `device` to `host`.
+    fn write_byebye(
+        &self,
+        message: &mut String,
+        device: &Device,
+        host: SocketAddr,
+        extra_headers: &str,
+    ) {
+        let _ = write!(
+            message,
+            concat!(
+                "NOTIFY * HTTP/1.1\r\n",
+                "HOST: {host}\r\n",
+                "NT: {st}\r\n",
+                "NTS: ssdp:byebye\r\n",
+                "USN: {usn}\r\n",
+                "{headers}",
+            ),
+            host = host,
+            st = device.search_target,
+            usn = device.usn,
+            headers = extra_headers
+        );
+        self.write_ids(message);
+        self.write_device_headers(message, device);
+
+        write_body(message, device);
+        self.fit(message);
+    }
+
+    /// Write the message of `kind` directed to `destination` for each unrestricted device.
+    fn directed_messages<'d>(
+        &self,
+        devices: &'d [Device],
+        kind: AuditKind,
+        destination: SocketAddr,
+        extra_headers: &str,
+    ) -> Vec<(&'d Device, String)> {
+        devices
+            .iter()
+            .filter(|d| self.announces(d) && d.on_family_of(destination.ip()))
+            .map(|device| {
+                let mut message = String::with_capacity(MESSAGE_CAPACITY + extra_headers.len());
+                if kind == AuditKind::Byebye {
+                    self.write_byebye(&mut message, device, destination, extra_headers);
+                } else {
+                    let location = self
+                        .resolve_location(device, || interface::local_addr_towards(destination));
+                    self.write_notify(
+                        &mut message,
+                        device,
+                        destination,
+                        &location,
+                        "ssdp:alive",
+                        extra_headers,
+                    );
+                }
+                (device, message)
+            })
+            .collect()
+    }
+
+    /// Broadcast `ssdp:byebye`
+    async fn broadcast_byebye(
+        &self,
+        devices: &[Device],
+        channels: &[Channel],
+        extra_headers: &str,
+    ) -> Result<()> {
+        debug!("Sending byebye messages");
+
+        let mut message = String::with_capacity(MESSAGE_CAPACITY + extra_headers.len());
+        let mut failed = None;
+        for device in devices.iter().filter(|d| self.announces(d)) {
+            for channel in channels
+                .iter()
+                .filter(|c| device.on_family_of(c.groups[0].ip()))
+            {
+                for (&group, &interface) in groups_by_interface(channel) {
+                    message.clear();
+                    self.write_byebye(&mut message, device, group, extra_headers);
+
+                    debug!("Byebye message: {}", self.redaction.apply(&message));
+
+                    match self
+                        .send_announcement(channel, &message, group, interface)
+                        .await
+                    {
+                        Ok(()) => {
+                            self.audit(AuditKind::Byebye, device, group);
+                            self.notify_sent(Nts::Byebye, group);
+                        }
+                        Err(e) => {
+                            failed.get_or_insert(e);
+                        }
+                    }
+                }
+            }
+            if let Some(lifecycle) = &self.lifecycle {
+                lifecycle.hook.on_byebye_sent(device);
+            }
+
+            // Avoid congestion
+            let pacing = self.power_profile.pacing();
+            if !pacing.is_zero() {
+                tokio::time::sleep(pacing).await;
+            }
+        }
+
+        failed.map_or(Ok(()), Err)
+    }
+}
+
+/// Controls a running [`Server`], see `Server::handle`.
+#[derive(Debug)]
+pub struct ServerHandle {
+    requested: watch::Sender<bool>,
+    done: watch::Receiver<bool>,
+    update: Arc<Notify>,
+    schedule: Arc<Schedule>,
+    outlet: Arc<OnceLock<Outlet>>,
+    journal: Arc<Journal>,
+}
+
+impl ServerHandle {
+    /// Announce that the device changed its boot ID, e.g. after a network change.
+    ///
+    /// The server sends `ssdp:update` with `NEXTBOOTID.UPNP.ORG` for every device, switches to
+    /// that boot ID and re-announces the devices with it. Requires `Server::boot_id`, returns
+    /// without waiting for the messages to be sent.
+    pub fn update(&self) {
+        self.update.notify_one();
+    }
+
+    /// When the next round of periodic `ssdp:alive` messages is due, `None` if the server is not
+    /// running.
+    pub fn next_announcement(&self) -> Option<Instant> {
+        self.running().ok()?;
+        self.schedule.state().next
+    }
+
+    /// Move the next round of periodic `ssdp:alive` messages to `at`, after which the usual
+    /// interval resumes.
+    ///
+    /// Lets applications hold the announcements back, e.g. while flashing firmware, or bring them
+    /// forward. Postponing the round sends one right away whose `CACHE-CONTROL` outlasts the
+    /// delay, as do the search responses until `at`, so control points keep the devices
+    /// meanwhile. Fails with `Error::Config` if the server is not running.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use tokio::time::Instant;
+    /// use tokio_ssdp::Server;
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let handle = server.handle();
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// // Stay quiet while the firmware is flashed
+    /// handle.reschedule_announcement(Instant::now() + Duration::from_secs(600))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reschedule_announcement(&self, at: Instant) -> Result<()> {
+        self.running()?;
+        let mut state = self.schedule.state();
+        let postponed = state.next.is_none_or(|next| at > next);
+        state.postponed |= postponed;
+        state.covering |= postponed;
+        state.requested = Some(at);
+        state.next = Some(at);
+        drop(state);
+        self.schedule.moved.notify_one();
+        Ok(())
+    }
+
+    /// Send the announcement `kind` for every device to the multicast groups now, besides the
+    /// periodic `ssdp:alive` messages.
+    ///
+    /// Waits until the messages were sent. Fails with `Error::Config` if the server is not running
+    /// or a custom `NTS` value is empty or contains a line break, or with the first failed send
+    /// once every message was attempted.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::{AnnouncementKind, Server};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let handle = server.handle();
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// handle.announce(AnnouncementKind::Custom("dial:refresh".into())).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn announce(&self, kind: AnnouncementKind) -> Result<()> {
+        if let AnnouncementKind::Custom(nts) = &kind {
+            if nts.trim().is_empty() || nts.contains(['\r', '\n']) {
+                return Err(Error::Config(format!("invalid NTS value {nts:?}")));
+            }
+        }
+        let Running {
+            outlet,
+            server,
+            channels,
+        } = self.running()?;
+
+        let devices = server.devices.snapshot();
+        let (channels, extra_headers) = (&*channels, &outlet.extra_headers);
+        match kind {
+            AnnouncementKind::Alive => {
+                server
+                    .broadcast_alive(&devices, channels, extra_headers)
+                    .await
+            }
+            AnnouncementKind::Byebye => {
+                server
+                    .broadcast_byebye(&devices, channels, extra_headers)
+                    .await
+            }
+            AnnouncementKind::Update => {
+                server.broadcast_update(channels, extra_headers).await?;
+                server
+                    .broadcast_alive(&devices, channels, extra_headers)
+                    .await
+            }
+            AnnouncementKind::Custom(nts) => {
+                server
+                    .broadcast_custom(&devices, channels, extra_headers, &nts)
+                    .await
+            }
+        }
+    }
+
+    /// Advertise `device` too while the server runs, or replace the root device with its UUID.
+    ///
+    /// `ssdp:alive` is sent right away for its new and changed advertisements, `ssdp:byebye` for
+    /// those of the replaced device it no longer has, e.g. a service it dropped. Waits until the
+    /// messages were sent. Fails with `Error::Config` if the server is not running, or like
+    /// `Device::validate` if the device is invalid.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::{Device, Server};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let handle = server.handle();
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// let camera = Device::new(
+    ///     "3c5d2f4e-8a1b-4f6c-9d7e-0a1b2c3d4e5f",
+    ///     "urn:schemas-upnp-org:device:DigitalSecurityCamera:1",
+    ///     "http://192.168.1.100:8080/camera.xml",
+    /// );
+    /// handle.add_device(camera).await?;
+    /// handle.remove_device("3c5d2f4e-8a1b-4f6c-9d7e-0a1b2c3d4e5f").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn add_device(&self, device: Device) -> Result<()> {
+        for advertisement in device.expand() {
+            advertisement.validate()?;
+        }
+        let Running {
+            outlet,
+            server,
+            channels,
+        } = self.running()?;
+
+        let (removed, added, updated) = server.devices.insert_root(device);
+        server.record_changes(&removed, &added, &updated);
+        let (channels, extra_headers) = (&*channels, &outlet.extra_headers);
+        let mut sent = Ok(());
+        if !removed.is_empty() {
+            sent = server
+                .broadcast_byebye(&removed, channels, extra_headers)
+                .await;
+        }
+        let alive: Vec<Device> = added.into_iter().chain(updated).collect();
+        if !alive.is_empty() {
+            sent = sent.and(
+                server
+                    .broadcast_alive(&alive, channels, extra_headers)
+                    .await,
+            );
+        }
+        sent
+    }
+
+    /// Stop advertising the root device `uuid` and its embedded devices while the server runs,
+    /// sending `ssdp:byebye` for them right away.
+    ///
+    /// Only removes devices given to `Server::new` or `add_device`, not those of a
+    /// [`DeviceProvider`]. Waits until the messages were sent. Fails with `Error::Config` if the
+    /// server is not running or has no such root device.
+    pub async fn remove_device(&self, uuid: &str) -> Result<()> {
+        let uuid = uuid.strip_prefix("uuid:").unwrap_or(uuid);
+        let Running {
+            outlet,
+            server,
+            channels,
+        } = self.running()?;
+
+        let (removed, added, updated) = server
+            .devices
+            .remove_root(uuid)
+            .ok_or_else(|| Error::Config(format!("there is no root device uuid:{uuid}")))?;
+        server.record_changes(&removed, &added, &updated);
+        server
+            .broadcast_byebye(&removed, &channels, &outlet.extra_headers)
+            .await
+    }
+
+    /// The outlet of the running server, the server and its channels.
+    fn running(&self) -> Result<Running<'_>> {
+        let not_running = || Error::Config("the server is not running".into());
+        let outlet = self.outlet.get().ok_or_else(not_running)?;
+        Ok(Running {
+            outlet,
+            server: outlet.server.upgrade().ok_or_else(not_running)?,
+            channels: outlet.channels.upgrade().ok_or_else(not_running)?,
+        })
+    }
+
+    /// Send `response` to its `remote_addr` from the socket the server answers searches from.
+    ///
+    /// Lets applications reply to messages they intercepted, e.g. from a [`NotifyStream`].
+    /// `DATE` and `SERVER` headers are added unless present. Fails with `Error::Config` if the
+    /// server was not started yet.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::{NotifyResponse, Server};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let handle = server.handle();
+    /// let mut notifies = server.notify_stream(16);
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// while let Some(msg) = notifies.next().await {
+    ///     handle.send(&NotifyResponse::new(msg.remote_addr, 200)).await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send(&self, response: &NotifyResponse) -> Result<()> {
+        let (Running { outlet, .. }, responder) = self.responder_to(response.remote_addr)?;
+
+        let response = with_default_headers(response, &outlet.server_header, &*outlet.clock);
+        responder
+            .send_to(&response.to_bytes(), response.remote_addr)
+            .await?;
+        Ok(())
+    }
+
+    /// Send `ssdp:alive` for every device to `addr` only, e.g. a control point that asked to be
+    /// told about the devices again or a proxy forwarding them.
+    ///
+    /// The messages are those of `Server::alive_messages`, sent from the socket the server
+    /// answers searches from. Fails with `Error::Config` if the server is not running.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::{Device, Server};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([Device::new(
+    ///     "ad8782a0-9e28-422b-a6ae-670fe7c4c043",
+    ///     "urn:schemas-upnp-org:device:MediaServer:1",
+    ///     "http://192.168.1.100:8080/desc.xml",
+    /// )]);
+    /// let handle = server.handle();
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// handle.notify_alive_to("192.168.1.20:1900".parse().unwrap()).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn notify_alive_to(&self, addr: SocketAddr) -> Result<()> {
+        self.notify_to(AuditKind::Alive, addr).await
+    }
+
+    /// Send `ssdp:byebye` for every device to `addr` only, see `notify_alive_to`.
+    pub async fn notify_byebye_to(&self, addr: SocketAddr) -> Result<()> {
+        self.notify_to(AuditKind::Byebye, addr).await
+    }
+
+    async fn notify_to(&self, kind: AuditKind, addr: SocketAddr) -> Result<()> {
+        let (Running { outlet, server, .. }, responder) = self.responder_to(addr)?;
+
+        let devices = server.devices.snapshot();
+        for (device, message) in
+            server.directed_messages(&devices, kind, addr, &outlet.extra_headers)
+        {
+            debug!("Directed message: {}", server.redaction.apply(&message));
+            responder.send_to(message.as_bytes(), addr).await?;
+            server.audit(kind, device, addr);
+            let nts = if kind == AuditKind::Byebye {
+                Nts::Byebye
+            } else {
+                Nts::Alive
+            };
+            server.notify_sent(nts, addr);
+        }
+        Ok(())
+    }
+
+    /// The running server and the socket it answers searches of the address family of `addr`
+    /// from.
+    fn responder_to(&self, addr: SocketAddr) -> Result<(Running<'_>, Arc<dyn SsdpSocket>)> {
+        let running = self.running()?;
+        let channel = running
+            .channels
+            .iter()
+            .find(|c| c.groups[0].is_ipv4() == addr.is_ipv4())
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "the server does not serve the address family of {addr}"
+                ))
+            })?;
+        let responder = Arc::clone(&channel.responder);
+        Ok((running, responder))
+    }
+
+    /// The sequence number of the latest change to the advertised devices, `0` before the server
+    /// started.
+    pub fn journal_seq(&self) -> u64 {
+        self.journal.last_seq()
+    }
+
+    /// The random identifier of the journal of this server, which tells its sequence numbers
+    /// apart from those of another server or of an earlier run, see `changes_since`.
+    pub fn journal_instance(&self) -> u64 {
+        self.journal.instance()
+    }
+
+    /// The changes to the advertised devices after the one numbered `seq` in the journal
+    /// `instance`, oldest first.
+    ///
+    /// Lets other systems keep their view of the devices in sync: they apply the changes and
+    /// remember the instance and last sequence number, then ask for what they missed after
+    /// reconnecting. Starting at `0`, the devices the server started with come first. Returns
+    /// `None` if `instance` is not `journal_instance`, e.g. because the server restarted, if the
+    /// changes were dropped already, see `Server::journal_capacity`, or if `seq` is ahead of the
+    /// journal; start over from `0` with the current instance then.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tokio_ssdp::{DeviceChange, Server};
+    ///
+    /// # async fn run() -> tokio_ssdp::Result<()> {
+    /// let mut server = Server::new([]);
+    /// let handle = server.handle();
+    /// tokio::spawn(server.serve()?);
+    ///
+    /// let (instance, mut seq) = (handle.journal_instance(), 0);
+    /// for entry in handle.changes_since(instance, seq).unwrap_or_default() {
+    ///     if let DeviceChange::Added(device) = &entry.change {
+    ///         println!("{} advertised", device.usn());
+    ///     }
+    ///     seq = entry.seq;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn changes_since(&self, instance: u64, seq: u64) -> Option<Vec<JournalEntry>> {
+        self.journal.since(instance, seq)
+    }
+
+    /// Stop answering searches and announcing, send `ssdp:byebye` for every device and wait until
+    /// the server stopped.
+    ///
+    /// Pending search responses are handled according to `Server::pending_responses`. Returns
+    /// immediately if the server was dropped.
+    pub async fn shutdown(mut self) {
+        self.requested.send_replace(true);
+        let _ = self.done.wait_for(|done| *done).await;
+    }
+}
+
+/// A started [`Server`], with its announcement tasks and the responses it has yet to send.
+pub(crate) struct Tenant {
+    pub(crate) server: Arc<Server>,
+    pub(crate) channels: Arc<[Channel]>,
+    extra_headers: Arc<String>,
+    pending: JoinSet<()>,
+    /// The provider and network watcher tasks, stopped before saying goodbye or aborted when the
+    /// tenant is dropped.
+    background: JoinSet<()>,
+    _notify_alive_tx: oneshot::Sender<()>,
+    notify_byebye_tx: oneshot::Sender<()>,
+    /// The subnets searches must come from, see `Server::on_link_searches_only`, updated when
+    /// the network changes.
+    link_subnets: Option<Arc<RwLock<Vec<Subnet>>>>,
+}
+
+impl Tenant {
+    /// Start announcing the devices of `server` on `channels`.
+    pub(crate) fn start(
+        server: Arc<Server>,
+        channels: Arc<[Channel]>,
+        extra_headers: String,
+    ) -> Self {
+        let this = server;
+        let extra_headers = Arc::new(extra_headers);
+        if let Some(control) = &this.control {
+            let _ = control.outlet.set(Outlet {
+                server: Arc::downgrade(&this),
+                channels: Arc::downgrade(&channels),
+                extra_headers: Arc::clone(&extra_headers),
+                server_header: this.server_header().into_owned(),
+                clock: Arc::clone(&this.clock),
+            });
+        }
+
+        let (notify_alive_tx, mut notify_alive_rx) = oneshot::channel::<()>();
+        tokio::spawn({
+            let this = Arc::clone(&this);
+            let channels = Arc::clone(&channels);
+            let extra_headers = Arc::clone(&extra_headers);
+
+            async move {
+                loop {
+                    // Failed sends are reported on the error stream as they happen
+                    let _ = this
+                        .broadcast_alive(&this.devices.snapshot(), &channels, &extra_headers)
+                        .await;
+                    if let Some(schedule) = this.schedule() {
+                        schedule.sent(Instant::now());
+                    }
+
+                    let update = async {
+                        match &this.control {
+                            Some(control) => control.update.notified().await,
+                            None => std::future::pending().await,
+                        }
+                    };
+
+                    let period = async {
+                        // Devices added meanwhile may call for an earlier round
+                        let start = Instant::now();
+                        loop {
+                            let changed = this.devices.changed();
+                            let moved = async {
+                                match this.schedule() {
+                                    Some(schedule) => schedule.moved.notified().await,
+                                    None => std::future::pending().await,
+                                }
+                            };
+                            let regular = start + this.announce_period();
+                            let deadline = match this.schedule() {
+                                Some(schedule) => schedule.next_round(regular),
+                                None => regular,
+                            };
+                            tokio::select! {
+                                _ = tokio::time::sleep_until(deadline) => return,
+                                _ = changed => {}
+                                _ = moved => {}
+                            }
+                            // Announce now what outlasts the delay
+                            if this.schedule().is_some_and(Schedule::take_covering) {
+                                return;
+                            }
+                        }
+                    };
+
+                    tokio::select! {
+                        _ = period => {
+                            // It's time to send alive messages
+                        }
+                        _ = update => {
+                            // Announce the new boot ID, then alive messages carrying it
+                            let _ = this.broadcast_update(&channels, &extra_headers).await;
+                        }
+                        _ = Pin::new(&mut notify_alive_rx) => {
+                            // We should shut down
+                            debug!("notify_alive shutdown");
+                            return;
+                        }
+                    }
+                }
             }
+        });
 
-            if header.name.eq_ignore_ascii_case("man") {
-                if header.value != b"\"ssdp:discover\"" {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        format!(
-                            "MAN != \"ssdp:discover\" ({})",
-                            String::from_utf8_lossy(header.value)
-                        ),
-                    ));
+        let (notify_byebye_tx, notify_byebye_rx) = oneshot::channel::<()>();
+        tokio::spawn({
+            let this = Arc::clone(&this);
+            let channels = Arc::clone(&channels);
+            let extra_headers = Arc::clone(&extra_headers);
+
+            async move {
+                // A message means the byebye messages were already sent
+                if notify_byebye_rx.await.is_ok() {
+                    return;
                 }
-                man_found = true;
+
+                let _ = this
+                    .broadcast_byebye(&this.devices.snapshot(), &channels, &extra_headers)
+                    .await;
             }
-        }
+        });
 
-        if !man_found {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "MAN header not found",
+        this.journal.record(
+            this.devices
+                .snapshot()
+                .iter()
+                .cloned()
+                .map(DeviceChange::Added),
+        );
+
+        let mut background = JoinSet::new();
+        for provider in this.providers.iter() {
+            let source = this.devices.add_source();
+            background.spawn(provide(
+                Arc::clone(&this),
+                Arc::clone(provider),
+                source,
+                Arc::clone(&channels),
+                Arc::clone(&extra_headers),
             ));
         }
 
-        let st = if let Some(st) = st {
-            st
-        } else {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "ST header not found",
+        let link_subnets = this
+            .on_link_searches
+            .then(|| Arc::new(RwLock::new(on_link_subnets(&channels))));
+
+        if let Some(watcher) = &this.network_watcher {
+            background.spawn(watch_network(
+                Arc::clone(&this),
+                Arc::clone(watcher),
+                Arc::clone(&channels),
+                Arc::clone(&extra_headers),
+                link_subnets.clone(),
             ));
-        };
+        }
 
-        debug!("ST={st:?}, MX={mx:?}");
+        Self {
+            server: this,
+            channels,
+            extra_headers,
+            pending: JoinSet::new(),
+            background,
+            _notify_alive_tx: notify_alive_tx,
+            notify_byebye_tx,
+            link_subnets,
+        }
+    }
 
-        let device = if let Some(s) = self
-            .devices
-            .iter()
-            .find(|d| d.search_target.eq_ignore_ascii_case(&st))
-        {
-            s
-        } else {
-            return Ok(());
+    /// Resolves once shutdown was requested through a [`ServerHandle`], never without one or if
+    /// it was dropped.
+    pub(crate) fn requested(&self) -> impl Future<Output = ()> + Send + 'static {
+        let requested = self.server.control.as_ref().map(|c| c.requested.clone());
+        async move {
+            match requested {
+                Some(mut requested) => {
+                    if requested.wait_for(|requested| *requested).await.is_err() {
+                        std::future::pending().await
+                    }
+                }
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    /// Handle the datagram `data` received from `addr` on the channel with index `channel`.
+    pub(crate) async fn dispatch(
+        &mut self,
+        addr: SocketAddr,
+        data: &[u8],
+        channel: usize,
+        received: Instant,
+    ) {
+        let dispatched = Instant::now();
+        let timing = Timing {
+            received,
+            dispatched,
+            serializing: dispatched,
         };
+        trace::packet(addr, data.len(), self.receive(addr, data, channel, timing)).await
+    }
 
-        debug!("Matched {device:?}");
+    async fn receive(&mut self, addr: SocketAddr, data: &[u8], channel: usize, timing: Timing) {
+        let this = &self.server;
+        let channels = &self.channels;
+        if let Some(metrics) = &this.metrics {
+            metrics.packet_received(addr, data.len());
+        }
+        if !this.source_allowed(addr.ip()) {
+            debug!("Dropping datagram from filtered source {addr}");
+            this.dropped(addr, "filtered source");
+            return;
+        }
+        // Before the handlers, so none answers or forwards a request strict mode drops
+        if this.strict && !data.starts_with(b"HTTP/") {
+            let port = channels[channel].groups[0].port();
+            if let Err(e) = validate_request(data, port) {
+                debug!("Dropping request from {addr}: {e}");
+                this.invalid(addr, &e);
+                return;
+            }
+        }
+        // Before the handlers too, so their replies cannot be turned against other hosts
+        if data.starts_with(b"M-SEARCH ") {
+            if let Some(metrics) = &this.metrics {
+                metrics.msearch_received(addr);
+            }
+            if !self.admit_search(addr) {
+                return;
+            }
+        }
+        let Some(data) = this
+            .run_handlers(
+                addr,
+                data,
+                &channels[channel],
+                &self.extra_headers,
+                &mut self.pending,
+                timing,
+            )
+            .await
+        else {
+            return;
+        };
 
-        let mut response = format!(
-            concat!(
-                "HTTP/1.1 200 OK\r\n",
-                "CACHE-CONTROL: max-age={max_age}\r\n",
-                "DATE: {date}\r\n",
-                "EXT:\r\n",
-                "LOCATION: {loc}\r\n",
-                "SERVER: {server}\r\n",
-                "ST: {st}\r\n",
-                "USN: {usn}\r\n",
-                "{headers}",
-            ),
-            max_age = self.max_age,
-            date = httpdate::fmt_http_date(SystemTime::now()),
-            loc = device.location,
-            server = self.server_name.as_deref().unwrap_or(DEFAULT_SERVER_NAME),
-            st = device.search_target,
-            usn = device.usn,
-            headers = extra_headers
-        );
+        if data.starts_with(b"HTTP/") {
+            if this.event_tx.is_some() {
+                match SearchResponse::parse(addr, &data) {
+                    Ok(res) => this.emit(SsdpEvent::SearchResponse(res)),
+                    Err(e) => {
+                        debug!("Ignoring invalid response from {addr}: {e}");
+                        this.invalid(addr, &e);
+                    }
+                }
+            }
+            return;
+        }
+
+        let mut headers = notify::header_buf(&data, notify::MAX_HEADERS);
+        let mut req = httparse::Request::new(&mut headers);
 
-        if let Some(content_type) = &device.content_type {
-            response.push_str(&format!("CONTENT-TYPE: {content_type}\r\n"));
+        match req.parse(&data) {
+            Ok(httparse::Status::Complete(_)) => {}
+            Ok(httparse::Status::Partial) => {
+                this.invalid(addr, &"incomplete request");
+                return;
+            }
+            Err(e) => {
+                this.invalid(addr, &e);
+                return;
+            }
         }
-        if let Some(body) = &device.body {
-            response.push_str(&format!("CONTENT-LENGTH: {}\r\n", body.len()));
-            response.push_str("\r\n");
-            response.push_str(body);
+
+        let method = if let Some(m) = req.method {
+            m
         } else {
-            response.push_str("\r\n");
-        }
+            return;
+        };
 
-        debug!("Response: {response}");
+        let path = if let Some(m) = req.path {
+            m
+        } else {
+            return;
+        };
 
-        tokio::spawn(async move {
-            if mx > 0 {
-                // upnp specification advises to use a number less than 5 if it is bigger than 5
-                mx = mx.min(5);
-                // wait a random time up to mx
-                let wait = {
-                    let mut rng = rand::thread_rng();
-                    rng.gen_range(0..mx)
+        match (method, path) {
+            ("M-SEARCH", "*") => {
+                if let Some(dedup) = &this.search_dedup {
+                    if !dedup.admit_search(&req, addr) {
+                        this.dropped(addr, "repeated search");
+                        return;
+                    }
+                }
+                let socket = Arc::clone(&channels[channel].responder);
+                let res = match SearchRequest::from_request(&req, addr) {
+                    Ok(search) => {
+                        if this.event_tx.is_some() {
+                            this.emit(SsdpEvent::Search(search.clone()));
+                        }
+                        let handle = this.handle_search(
+                            &search,
+                            socket,
+                            &self.extra_headers,
+                            &mut self.pending,
+                            timing,
+                        );
+                        trace::search(&search, &self.server.redaction, handle).await
+                    }
+                    Err(e) => {
+                        this.invalid(addr, &e);
+                        Err(e)
+                    }
+                };
+                match res {
+                    Ok(woken) if !woken.is_empty() => {
+                        let this = Arc::clone(this);
+                        let channels = Arc::clone(channels);
+                        let extra_headers = Arc::clone(&self.extra_headers);
+                        self.pending.spawn(async move {
+                            let _ = this
+                                .broadcast_alive(&woken, &channels, &extra_headers)
+                                .await;
+                        });
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("Handle search failed: {e}"),
+                }
+            }
+            ("NOTIFY", _) => {
+                if let Some(metrics) = &this.metrics {
+                    metrics.notify_received(addr);
+                }
+                if let Some(guard) = &this.storm_guard {
+                    if !guard.admit(&req, addr.ip()) {
+                        this.dropped(addr, "notify storm");
+                        return;
+                    }
+                }
+                if this.event_tx.is_some() {
+                    // Only copy the messages that make an event
+                    let request = match NotifyRequestRef::parse(addr, &data) {
+                        Ok(req) => req.nts().is_some().then(|| req.to_owned()),
+                        // Folded header lines need the copying parser
+                        Err(_) => NotifyRequest::parse(addr, &data).ok(),
+                    };
+                    if let Some(event) = request.and_then(SsdpEvent::from_notify) {
+                        this.emit(event);
+                    }
+                }
+                let nr = NotifyMessage {
+                    remote_addr: addr,
+                    data: data.to_vec(),
                 };
-                tokio::time::sleep(Duration::from_secs(wait as u64)).await;
+                let _res = this.handle_notify(Arc::new(nr)).await;
             }
-            if let Err(e) = socket.send_to(response.as_bytes(), remote_addr).await {
-                error!("Failed to send search response: {e}");
+            _ => {
+                debug!("Unknown SSDP request {method} {path} from {addr}");
+                this.dropped(addr, "unknown request");
             }
-        });
-
-        Ok(())
+        }
     }
 
-    /// Broadcast `ssdp:alive`
-    async fn broadcast_alive(&self, socket: &UdpSocket, extra_headers: &str) -> IoResult<()> {
-        debug!("Sending alive messages");
-
-        for device in self.devices.iter() {
-            let mut message = format!(
-                concat!(
-                    "NOTIFY * HTTP/1.1\r\n",
-                    "HOST: {ssdp_addr}:{ssdp_port}\r\n",
-                    "CACHE-CONTROL: max-age={max_age}\r\n",
-                    "LOCATION: {loc}\r\n",
-                    "NT: {st}\r\n",
-                    "NTS: ssdp:alive\r\n",
-                    "SERVER: {server}\r\n",
-                    "USN: {usn}\r\n",
-                    "{headers}",
-                ),
-                max_age = self.max_age,
-                ssdp_addr = SSDP_ADDR,
-                ssdp_port = SSDP_PORT,
-                loc = device.location,
-                server = self.server_name.as_deref().unwrap_or(DEFAULT_SERVER_NAME),
-                st = device.search_target,
-                usn = device.usn,
-                headers = extra_headers
-            );
+    /// Whether the `M-SEARCH` from `addr` comes from a link of the server, with
+    /// `Server::on_link_searches_only`, and within the `Server::search_rate_limit`.
+    fn admit_search(&self, addr: SocketAddr) -> bool {
+        let this = &self.server;
+        if let Some(subnets) = &self.link_subnets {
+            let subnets = subnets.read().unwrap_or_else(|e| e.into_inner());
+            let link_local =
+                matches!(addr.ip(), IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80);
+            if !link_local && !subnets.iter().any(|s| s.contains(addr.ip())) {
+                debug!("Ignoring M-SEARCH from off-link source {addr}");
+                this.dropped(addr, "off-link source");
+                return false;
+            }
+        }
+        if let Some(limiter) = &this.search_limiter {
+            if !limiter.admit(addr.ip()) {
+                this.dropped(addr, "search rate limit");
+                return false;
+            }
+        }
+        true
+    }
 
-            if let Some(content_type) = &device.content_type {
-                message.push_str(&format!("CONTENT-TYPE: {content_type}\r\n"));
+    /// Settle the pending responses and say goodbye.
+    pub(crate) async fn stop(mut self) {
+        let this = &self.server;
+        match this.pending_responses {
+            PendingResponses::Flush => {
+                debug!("Flushing {} pending responses", self.pending.len());
+                while self.pending.join_next().await.is_some() {}
             }
-            if let Some(body) = &device.body {
-                message.push_str(&format!("CONTENT-LENGTH: {}\r\n", body.len()));
-                message.push_str("\r\n");
-                message.push_str(body);
-            } else {
-                message.push_str("\r\n");
+            PendingResponses::Cancel => {
+                debug!("Cancelling {} pending responses", self.pending.len());
+                self.pending.shutdown().await;
             }
+        }
+        // A provider could otherwise add devices after the goodbye, or announce them during it
+        self.background.shutdown().await;
+        let _ = this
+            .broadcast_byebye(
+                &this.devices.snapshot(),
+                &self.channels,
+                &self.extra_headers,
+            )
+            .await;
+        let _ = self.notify_byebye_tx.send(());
+        if let Some(control) = &this.control {
+            control.done.send_replace(true);
+        }
+    }
+}
 
-            debug!("Alive message: {message}");
+/// The server's end of a [`ServerHandle`].
+#[derive(Debug, Clone)]
+struct Control {
+    requested: watch::Receiver<bool>,
+    done: Arc<watch::Sender<bool>>,
+    update: Arc<Notify>,
+    schedule: Arc<Schedule>,
+    outlet: Arc<OnceLock<Outlet>>,
+}
 
-            socket
-                .send_to(message.as_bytes(), (SSDP_ADDR, SSDP_PORT))
-                .await?;
+/// When the next round of periodic `ssdp:alive` messages is due, shared with the
+/// [`ServerHandle`], see `ServerHandle::reschedule_announcement`.
+#[derive(Debug, Default)]
+struct Schedule {
+    state: Mutex<ScheduleState>,
+    /// Wakes the announcer when the application moved the next round.
+    moved: Notify,
+}
 
-            // Avoid congestion
-            tokio::time::sleep(Duration::from_millis(50)).await;
+#[derive(Debug, Default)]
+struct ScheduleState {
+    /// When the next round is due, `None` until the first round was sent.
+    next: Option<Instant>,
+    /// When the application wants the next round, instead of after the interval.
+    requested: Option<Instant>,
+    /// Whether the requested round is later than the scheduled one it replaced.
+    postponed: bool,
+    /// Whether a round covering the postponement is due right away.
+    covering: bool,
+}
+
+impl Schedule {
+    fn state(&self) -> MutexGuard<'_, ScheduleState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The next round, the requested one or `regular`, recorded for `ServerHandle`.
+    fn next_round(&self, regular: Instant) -> Instant {
+        let mut state = self.state();
+        let next = state.requested.unwrap_or(regular);
+        state.next = Some(next);
+        next
+    }
+
+    /// Whether the next round was postponed since last asked, calling for a round right away.
+    fn take_covering(&self) -> bool {
+        std::mem::take(&mut self.state().covering)
+    }
+
+    /// Note that a round was sent at `now`, which may have been the requested one.
+    fn sent(&self, now: Instant) {
+        let mut state = self.state();
+        if state.requested.is_some_and(|at| at <= now) {
+            state.requested = None;
+            state.postponed = false;
         }
+    }
 
-        Ok(())
+    /// How long until the postponed round, which the devices must not expire before.
+    fn held_for(&self, now: Instant) -> Option<Duration> {
+        let state = self.state();
+        let held = state.requested.filter(|_| state.postponed)?;
+        Some(held.saturating_duration_since(now))
     }
+}
 
-    /// Broadcast `ssdp:byebye`
-    async fn broadcast_byebye(&self, socket: &UdpSocket, extra_headers: &str) -> IoResult<()> {
-        debug!("Sending byebye messages");
+/// What a [`ServerHandle`] needs to send messages, set once the server started.
+#[derive(Debug)]
+struct Outlet {
+    server: Weak<Server>,
+    /// Weak so the sockets close when the server stops.
+    channels: Weak<[Channel]>,
+    extra_headers: Arc<String>,
+    server_header: String,
+    clock: Arc<dyn Clock>,
+}
 
-        for device in self.devices.iter() {
-            let mut message = format!(
-                concat!(
-                    "NOTIFY * HTTP/1.1\r\n",
-                    "HOST: {ssdp_addr}:{ssdp_port}\r\n",
-                    "NT: {st}\r\n",
-                    "NTS: ssdp:byebye\r\n",
-                    "USN: {usn}\r\n",
-                    "{headers}",
-                ),
-                ssdp_addr = SSDP_ADDR,
-                ssdp_port = SSDP_PORT,
-                st = device.search_target,
-                usn = device.usn,
-                headers = extra_headers
+/// A running server as seen from a [`ServerHandle`].
+struct Running<'a> {
+    outlet: &'a Outlet,
+    server: Arc<Server>,
+    channels: Arc<[Channel]>,
+}
+
+/// The `NOTIFY` messages received by a [`Server`], see `Server::notify_stream`.
+#[derive(Debug)]
+pub struct NotifyStream {
+    rx: NotifyRx,
+}
+
+impl NotifyStream {
+    /// The next message, `None` once the server stopped.
+    pub async fn next(&mut self) -> Option<Arc<NotifyMessage>> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for NotifyStream {
+    type Item = Arc<NotifyMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
+
+/// The boot ID following `boot_id`, which must stay below 2^31.
+fn next_boot_id(boot_id: u32) -> u32 {
+    boot_id.wrapping_add(1) & 0x7fff_ffff
+}
+
+/// Keep the devices of `provider` in the source `source` of the server's set, announcing the
+/// changes.
+async fn provide(
+    server: Arc<Server>,
+    provider: Arc<dyn DeviceProvider>,
+    source: usize,
+    channels: Arc<[Channel]>,
+    extra_headers: Arc<String>,
+) {
+    loop {
+        let devices = provider
+            .devices()
+            .await
+            .into_iter()
+            .flat_map(|d| d.expand())
+            .filter(|d| match d.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Ignoring device from {provider:?}: {e}");
+                    false
+                }
+            })
+            .collect();
+
+        let (removed, added, updated) = server.devices.replace(source, devices);
+        server.record_changes(&removed, &added, &updated);
+        if !removed.is_empty() {
+            debug!("{} devices removed by {provider:?}", removed.len());
+            let _ = server
+                .broadcast_byebye(&removed, &channels, &extra_headers)
+                .await;
+        }
+        if !added.is_empty() || !updated.is_empty() {
+            debug!(
+                "{} devices added and {} changed by {provider:?}",
+                added.len(),
+                updated.len()
             );
+            let alive: Vec<Device> = added.into_iter().chain(updated).collect();
+            let _ = server
+                .broadcast_alive(&alive, &channels, &extra_headers)
+                .await;
+        }
+
+        provider.changed().await;
+    }
+}
+
+/// The subnets reachable through the interfaces of `channels`, every address if the interfaces
+/// cannot be listed.
+fn on_link_subnets(channels: &[Channel]) -> Vec<Subnet> {
+    let interfaces: Vec<Interface> = channels
+        .iter()
+        .flat_map(|c| c.interfaces.iter().copied())
+        .collect();
+    let Some(subnets) = interface::link_subnets(&interfaces) else {
+        // Failing closed would leave the devices undiscoverable
+        warn!("Answering searches from every source, the on-link subnets are unknown");
+        return ["0.0.0.0/0", "::/0"]
+            .iter()
+            .filter_map(|s| s.parse().ok())
+            .collect();
+    };
+    debug!("Answering searches from {subnets:?}");
+    subnets
+}
 
-            if let Some(content_type) = &device.content_type {
-                message.push_str(&format!("CONTENT-TYPE: {content_type}\r\n"));
+/// Re-announce the devices whenever `watcher` reports a network change, refreshing the on-link
+/// subnets if searches are restricted to them.
+async fn watch_network(
+    server: Arc<Server>,
+    watcher: Arc<dyn NetworkWatcher>,
+    channels: Arc<[Channel]>,
+    extra_headers: Arc<String>,
+    link_subnets: Option<Arc<RwLock<Vec<Subnet>>>>,
+) {
+    loop {
+        watcher.changed().await;
+        info!("Network changed, announcing the devices again");
+
+        if let Some(link_subnets) = &link_subnets {
+            let subnets = on_link_subnets(&channels);
+            *link_subnets.write().unwrap_or_else(|e| e.into_inner()) = subnets;
+        }
+        let _ = server.network_changed(&channels, &extra_headers).await;
+    }
+}
+
+/// Receive datagrams from `channels` into `queue`, counting those the queue drops.
+async fn receive(
+    server: Arc<Server>,
+    channels: Arc<[Channel]>,
+    queue: Arc<RecvQueue>,
+    watchdog: Option<Duration>,
+) -> std::io::Result<()> {
+    let mut buf = [0u8; 4096];
+    let mut failures = 0;
+    loop {
+        let (n, addr, channel) = match channel::recv_watched(&channels, &mut buf, watchdog).await {
+            Ok(received) => {
+                failures = 0;
+                received
             }
-            if let Some(body) = &device.body {
-                message.push_str(&format!("CONTENT-LENGTH: {}\r\n", body.len()));
-                message.push_str("\r\n");
-                message.push_str(body);
-            } else {
-                message.push_str("\r\n");
+            Err(e) => {
+                server.receive_failed(e, &mut failures).await?;
+                continue;
             }
+        };
+        let datagram = (buf[..n].to_vec(), addr, channel, Instant::now());
+        if let Some(dropped) = queue.push(datagram) {
+            server.dropped(dropped, "receive queue full");
+        }
+    }
+}
 
-            debug!("Byebye message: {message}");
-
-            socket
-                .send_to(message.as_bytes(), (SSDP_ADDR, SSDP_PORT))
-                .await?;
+/// A copy of `error` for the error stream, keeping its OS error code if it has one.
+pub(crate) fn copy_io_error(error: &std::io::Error) -> std::io::Error {
+    match error.raw_os_error() {
+        Some(code) => std::io::Error::from_raw_os_error(code),
+        None => std::io::Error::new(error.kind(), error.to_string()),
+    }
+}
 
-            // Avoid congestion
-            tokio::time::sleep(Duration::from_millis(50)).await;
+/// Log `error` and deliver it to the error stream, if any, unless the stream lags behind.
+fn report(error_tx: Option<&tokio::sync::mpsc::Sender<ServerError>>, error: ServerError) {
+    error!("{error}");
+    if let Some(tx) = error_tx {
+        if let Err(tokio::sync::mpsc::error::TrySendError::Full(error)) = tx.try_send(error) {
+            debug!("Error stream is full, dropping {error:?}");
         }
+    }
+}
 
-        Ok(())
+/// Connect to `addr` and write `message`, giving up after 5 seconds.
+async fn send_tcp(addr: SocketAddr, message: &[u8]) -> std::io::Result<()> {
+    let send = async {
+        let mut stream = tokio::net::TcpStream::connect(addr).await?;
+        stream.write_all(message).await?;
+        stream.shutdown().await
+    };
+    tokio::time::timeout(Duration::from_secs(5), send)
+        .await
+        .unwrap_or_else(|_| Err(std::io::ErrorKind::TimedOut.into()))
+}
+
+/// `response` with the `DATE` and `SERVER` headers added unless present.
+fn with_default_headers(
+    response: &NotifyResponse,
+    server_header: &str,
+    clock: &dyn Clock,
+) -> NotifyResponse {
+    let has = |name: &str| {
+        response
+            .headers
+            .iter()
+            .any(|(n, _)| n.eq_ignore_ascii_case(name))
+    };
+    let mut response = response.clone();
+    if !has("DATE") {
+        let date = httpdate::HttpDate::from(clock.now());
+        response.headers.push(("DATE".into(), date.to_string()));
+    }
+    if !has("SERVER") {
+        response
+            .headers
+            .push(("SERVER".into(), server_header.to_owned()));
+    }
+    response
+}
+
+/// The errors of a running [`Server`], see `Server::error_stream`.
+#[derive(Debug)]
+pub struct ErrorStream {
+    rx: tokio::sync::mpsc::Receiver<ServerError>,
+}
+
+impl ErrorStream {
+    /// The next error, `None` once the server stopped.
+    pub async fn next(&mut self) -> Option<ServerError> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for ErrorStream {
+    type Item = ServerError;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
+
+/// The SSDP messages received by a [`Server`], see `Server::event_stream`.
+#[derive(Debug)]
+pub struct EventStream {
+    rx: tokio::sync::mpsc::Receiver<SsdpEvent>,
+}
+
+impl EventStream {
+    /// The next event, `None` once the server stopped.
+    pub async fn next(&mut self) -> Option<SsdpEvent> {
+        self.rx.recv().await
+    }
+}
+
+impl Stream for EventStream {
+    type Item = SsdpEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().rx.poll_recv(cx)
+    }
+}
+
+/// Every multicast group of `channel` paired with every interface it is joined on.
+fn groups_by_interface(channel: &Channel) -> impl Iterator<Item = (&SocketAddr, &Interface)> {
+    channel
+        .groups
+        .iter()
+        .flat_map(|group| channel.interfaces.iter().map(move |iface| (group, iface)))
+}
+
+/// A search response waiting to be sent.
+struct Response {
+    message: String,
+    usn: Option<String>,
+    audit: Option<AuditRecord>,
+}
+
+/// Append the `CONTENT-TYPE`/`CONTENT-LENGTH` headers, the empty line and the body of `device`.
+fn write_body(buf: &mut String, device: &Device) {
+    if let Some(content_type) = &device.content_type {
+        let _ = write!(buf, "CONTENT-TYPE: {content_type}\r\n");
+    }
+    if let Some(body) = &device.body {
+        let _ = write!(buf, "CONTENT-LENGTH: {}\r\n\r\n{body}", body.len());
+    } else {
+        buf.push_str("\r\n");
     }
 }
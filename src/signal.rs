@@ -0,0 +1,58 @@
+use log::error;
+
+/// Resolves once the process is asked to terminate, never if `enabled` is `false`.
+///
+/// Listens for Ctrl-C everywhere, `SIGTERM` on Unix and console close/shutdown events on Windows.
+pub(crate) async fn terminated(enabled: bool) {
+    if !enabled {
+        return std::future::pending().await;
+    }
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut term = match signal(SignalKind::terminate()) {
+            Ok(term) => term,
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {e}");
+                return ctrl_c().await;
+            }
+        };
+
+        tokio::select! {
+            _ = ctrl_c() => {}
+            _ = term.recv() => {}
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        use tokio::signal::windows::{ctrl_close, ctrl_shutdown};
+
+        let (mut close, mut shutdown) = match (ctrl_close(), ctrl_shutdown()) {
+            (Ok(close), Ok(shutdown)) => (close, shutdown),
+            (Err(e), _) | (_, Err(e)) => {
+                error!("Failed to listen for console events: {e}");
+                return ctrl_c().await;
+            }
+        };
+
+        tokio::select! {
+            _ = ctrl_c() => {}
+            _ = close.recv() => {}
+            _ = shutdown.recv() => {}
+        }
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    ctrl_c().await
+}
+
+/// Resolves on Ctrl-C, never if the handler cannot be installed.
+async fn ctrl_c() {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("Failed to listen for Ctrl-C: {e}");
+        std::future::pending().await
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    fmt::Debug,
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    task::{Context, Poll},
+};
+
+use socket2::SockRef;
+use tokio::{io::ReadBuf, net::UdpSocket};
+
+/// The datagram transport of a server: sends and receives SSDP messages and manages multicast
+/// group membership.
+///
+/// `tokio::net::UdpSocket` implements it and is what `Server::serve` binds. Implement it to run a
+/// server over another network stack, e.g. an embedded one, or an in-memory network in tests,
+/// and serve with `Server::serve_socket`.
+///
+/// The multicast methods default to doing nothing, for transports delivering the multicast
+/// traffic without joining groups.
+///
+/// # Examples
+/// ```
+/// use std::{
+///     io,
+///     net::SocketAddr,
+///     sync::Mutex,
+///     task::{Context, Poll},
+/// };
+/// use tokio::io::ReadBuf;
+/// use tokio_ssdp::SsdpSocket;
+///
+/// /// Records what is sent and never receives anything.
+/// #[derive(Debug, Default)]
+/// struct Recorder(Mutex<Vec<(SocketAddr, Vec<u8>)>>);
+///
+/// impl SsdpSocket for Recorder {
+///     fn poll_recv_from(
+///         &self,
+///         _: &mut Context<'_>,
+///         _: &mut ReadBuf<'_>,
+///     ) -> Poll<io::Result<SocketAddr>> {
+///         Poll::Pending
+///     }
+///
+///     fn poll_send_to(
+///         &self,
+///         _: &mut Context<'_>,
+///         buf: &[u8],
+///         target: SocketAddr,
+///     ) -> Poll<io::Result<usize>> {
+///         self.0.lock().unwrap().push((target, buf.to_vec()));
+///         Poll::Ready(Ok(buf.len()))
+///     }
+///
+///     fn local_addr(&self) -> io::Result<SocketAddr> {
+///         Ok("192.0.2.10:1900".parse().unwrap())
+///     }
+/// }
+/// ```
+pub trait SsdpSocket: Debug + Send + Sync {
+    /// Receive a datagram into `buf`, returning its sender.
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<SocketAddr>>;
+
+    /// Send the datagram `buf` to `target`, returning the number of bytes sent.
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>>;
+
+    /// The address the socket is bound to.
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+
+    /// Join the IPv4 multicast `group` on the interface with address `interface`.
+    fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        let _ = (group, interface);
+        Ok(())
+    }
+
+    /// Leave the IPv4 multicast `group` on the interface with address `interface`.
+    fn leave_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        let _ = (group, interface);
+        Ok(())
+    }
+
+    /// Join the IPv6 multicast `group` on the interface with index `index`.
+    fn join_multicast_v6(&self, group: Ipv6Addr, index: u32) -> io::Result<()> {
+        let _ = (group, index);
+        Ok(())
+    }
+
+    /// Leave the IPv6 multicast `group` on the interface with index `index`.
+    fn leave_multicast_v6(&self, group: Ipv6Addr, index: u32) -> io::Result<()> {
+        let _ = (group, index);
+        Ok(())
+    }
+
+    /// Send the following IPv4 multicast datagrams through the interface with address
+    /// `interface`.
+    fn set_multicast_if_v4(&self, interface: Ipv4Addr) -> io::Result<()> {
+        let _ = interface;
+        Ok(())
+    }
+
+    /// Send the following IPv6 multicast datagrams through the interface with index `index`.
+    fn set_multicast_if_v6(&self, index: u32) -> io::Result<()> {
+        let _ = index;
+        Ok(())
+    }
+}
+
+impl dyn SsdpSocket {
+    /// Send the datagram `buf` to `target`.
+    pub async fn send_to(&self, buf: &[u8], target: SocketAddr) -> io::Result<usize> {
+        std::future::poll_fn(|cx| self.poll_send_to(cx, buf, target)).await
+    }
+
+    /// Receive a datagram into `buf`, returning its length and sender.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let mut buf = ReadBuf::new(buf);
+        let addr = std::future::poll_fn(|cx| self.poll_recv_from(cx, &mut buf)).await?;
+        Ok((buf.filled().len(), addr))
+    }
+}
+
+impl SsdpSocket for UdpSocket {
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<SocketAddr>> {
+        UdpSocket::poll_recv_from(self, cx, buf)
+    }
+
+    fn poll_send_to(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        UdpSocket::poll_send_to(self, cx, buf, target)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::local_addr(self)
+    }
+
+    fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        UdpSocket::join_multicast_v4(self, group, interface)
+    }
+
+    fn leave_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        UdpSocket::leave_multicast_v4(self, group, interface)
+    }
+
+    fn join_multicast_v6(&self, group: Ipv6Addr, index: u32) -> io::Result<()> {
+        UdpSocket::join_multicast_v6(self, &group, index)
+    }
+
+    fn leave_multicast_v6(&self, group: Ipv6Addr, index: u32) -> io::Result<()> {
+        UdpSocket::leave_multicast_v6(self, &group, index)
+    }
+
+    fn set_multicast_if_v4(&self, interface: Ipv4Addr) -> io::Result<()> {
+        SockRef::from(self).set_multicast_if_v4(&interface)
+    }
+
+    fn set_multicast_if_v6(&self, index: u32) -> io::Result<()> {
+        SockRef::from(self).set_multicast_if_v6(index)
+    }
+}
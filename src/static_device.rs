@@ -0,0 +1,325 @@
+use crate::Device;
+
+/// A device known at compile time, checked by the compiler when declared in a `const` or
+/// `static`.
+///
+/// The constructors are `const fn`s panicking on what `Device::validate` would reject at runtime,
+/// and more: the UUID must be in the canonical `8-4-4-4-12` hexadecimal form, with or without the
+/// `uuid:` prefix, `urn:` search targets must follow
+/// `urn:<domain>:device|service:<type>:<version>`, services must be `urn:` service types and the
+/// `max-age` must be at least one second. Evaluated in a constant, such a panic fails the build,
+/// so a typo never ships. Convert into a [`Device`] to serve it.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Device, Server, StaticDevice};
+///
+/// static PRINTER: StaticDevice = StaticDevice::new(
+///     "6f1b0f1e-3c3a-4c7e-9a07-1d2b3c4d5e6f",
+///     "urn:schemas-upnp-org:device:Printer:1",
+///     "http://192.0.2.10/desc.xml",
+/// )
+/// .with_services(&["urn:schemas-upnp-org:service:PrintBasic:1"])
+/// .max_age(1800);
+///
+/// let server = Server::new([Device::from(&PRINTER)]);
+/// ```
+///
+/// A malformed type does not compile:
+/// ```compile_fail
+/// use tokio_ssdp::StaticDevice;
+///
+/// static PRINTER: StaticDevice = StaticDevice::new(
+///     "6f1b0f1e-3c3a-4c7e-9a07-1d2b3c4d5e6f",
+///     "urn:schemas-upnp-org:device:Printer",
+///     "http://192.0.2.10/desc.xml",
+/// );
+/// ```
+///
+/// Neither does one with whitespace, which `Urn` rejects:
+/// ```compile_fail
+/// use tokio_ssdp::StaticDevice;
+///
+/// static PRINTER: StaticDevice = StaticDevice::new(
+///     "6f1b0f1e-3c3a-4c7e-9a07-1d2b3c4d5e6f",
+///     "urn:schemas-upnp-org:device:Laser Printer:1",
+///     "http://192.0.2.10/desc.xml",
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StaticDevice {
+    uuid: &'static str,
+    search_target: &'static str,
+    location: &'static str,
+    services: &'static [&'static str],
+    max_age: Option<u64>,
+}
+
+impl StaticDevice {
+    /// The device `uuid` advertising `search_target` at `location`, like `Device::new`.
+    ///
+    /// # Panics
+    /// If `uuid` is not a canonical UUID, `search_target` is a malformed `urn:` type or contains
+    /// a line break, or `location` contains a line break.
+    pub const fn new(
+        uuid: &'static str,
+        search_target: &'static str,
+        location: &'static str,
+    ) -> Self {
+        assert!(is_uuid(uuid), "the UUID is not of the form 8-4-4-4-12");
+        assert!(
+            is_target(search_target.as_bytes()),
+            "the search target is not of the form urn:<domain>:device|service:<type>:<version>"
+        );
+        assert!(
+            !has_line_break(location.as_bytes()),
+            "the location contains a line break"
+        );
+        Self {
+            uuid,
+            search_target,
+            location,
+            services: &[],
+            max_age: None,
+        }
+    }
+
+    /// Add the services of the device by their types, like `Device::with_service`.
+    ///
+    /// # Panics
+    /// If one is not of the form `urn:<domain>:service:<type>:<version>`.
+    pub const fn with_services(mut self, services: &'static [&'static str]) -> Self {
+        let mut i = 0;
+        while i < services.len() {
+            assert!(
+                matches!(urn_kind(services[i].as_bytes()), Some(Kind::Service)),
+                "a service is not of the form urn:<domain>:service:<type>:<version>"
+            );
+            i += 1;
+        }
+        self.services = services;
+        self
+    }
+
+    /// Set the `max-age` of the device in seconds, like `Device::max_age`.
+    ///
+    /// # Panics
+    /// If `max_age` is 0, which would expire the device as soon as it is announced.
+    pub const fn max_age(mut self, max_age: u64) -> Self {
+        assert!(max_age > 0, "the max-age must be at least one second");
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// The UUID of the device, as given.
+    pub const fn uuid(&self) -> &'static str {
+        self.uuid
+    }
+
+    /// The search target of the device.
+    pub const fn search_target(&self) -> &'static str {
+        self.search_target
+    }
+
+    /// The location of the device.
+    pub const fn location(&self) -> &'static str {
+        self.location
+    }
+}
+
+impl From<&StaticDevice> for Device {
+    fn from(device: &StaticDevice) -> Self {
+        let mut converted = Device::new(device.uuid, device.search_target, device.location);
+        for service in device.services {
+            converted = converted.with_service(*service);
+        }
+        if let Some(max_age) = device.max_age {
+            converted = converted.max_age(max_age);
+        }
+        converted
+    }
+}
+
+impl From<StaticDevice> for Device {
+    fn from(device: StaticDevice) -> Self {
+        Device::from(&device)
+    }
+}
+
+/// The kind of a `urn:` type.
+enum Kind {
+    Device,
+    Service,
+}
+
+/// Whether `uuid` is `8-4-4-4-12` hexadecimal digits, optionally prefixed with `uuid:`.
+const fn is_uuid(uuid: &str) -> bool {
+    let mut uuid = uuid.as_bytes();
+    if let Some(rest) = strip_prefix(uuid, b"uuid:") {
+        uuid = rest;
+    }
+    if uuid.len() != 36 {
+        return false;
+    }
+    let mut i = 0;
+    while i < uuid.len() {
+        let valid = match i {
+            8 | 13 | 18 | 23 => uuid[i] == b'-',
+            _ => uuid[i].is_ascii_hexdigit(),
+        };
+        if !valid {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Whether `nt` is a notification type `Device::validate` accepts, without line breaks.
+const fn is_target(nt: &[u8]) -> bool {
+    if has_line_break(nt) {
+        return false;
+    }
+    strip_prefix(nt, b"urn:").is_none() || urn_kind(nt).is_some()
+}
+
+/// The kind of the `urn:<domain>:device|service:<type>:<version>` type `urn`, `None` if it is
+/// malformed.
+const fn urn_kind(urn: &[u8]) -> Option<Kind> {
+    let Some(rest) = strip_prefix(urn, b"urn:") else {
+        return None;
+    };
+    let Some((domain, rest)) = split_colon(rest) else {
+        return None;
+    };
+    let Some((kind, rest)) = split_colon(rest) else {
+        return None;
+    };
+    let Some((name, version)) = split_colon(rest) else {
+        return None;
+    };
+    let kind = if eq(kind, b"device") {
+        Kind::Device
+    } else if eq(kind, b"service") {
+        Kind::Service
+    } else {
+        return None;
+    };
+    if domain.is_empty()
+        || name.is_empty()
+        || has_whitespace(domain)
+        || has_whitespace(name)
+        || !is_version(version)
+        || has_line_break(urn)
+    {
+        return None;
+    }
+    Some(kind)
+}
+
+/// Whether `version` is a decimal `u32`, as `Urn` parses it.
+const fn is_version(version: &[u8]) -> bool {
+    if version.is_empty() {
+        return false;
+    }
+    let mut value: u32 = 0;
+    let mut i = 0;
+    while i < version.len() {
+        if !version[i].is_ascii_digit() {
+            return false;
+        }
+        value = match value.checked_mul(10) {
+            Some(value) => match value.checked_add((version[i] - b'0') as u32) {
+                Some(value) => value,
+                None => return false,
+            },
+            None => return false,
+        };
+        i += 1;
+    }
+    true
+}
+
+/// Split `s` at its first `:`, `None` without one.
+const fn split_colon(s: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] == b':' {
+            let (head, tail) = s.split_at(i);
+            let (_, tail) = tail.split_at(1);
+            return Some((head, tail));
+        }
+        i += 1;
+    }
+    None
+}
+
+const fn strip_prefix<'a>(s: &'a [u8], prefix: &[u8]) -> Option<&'a [u8]> {
+    if s.len() < prefix.len() {
+        return None;
+    }
+    let (head, tail) = s.split_at(prefix.len());
+    if eq(head, prefix) { Some(tail) } else { None }
+}
+
+const fn eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn has_line_break(s: &[u8]) -> bool {
+    let mut i = 0;
+    while i < s.len() {
+        if s[i] == b'\r' || s[i] == b'\n' {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Whether the UTF-8 `s` contains whitespace, as `char::is_whitespace` and so `Urn` see it.
+const fn has_whitespace(s: &[u8]) -> bool {
+    let mut i = 0;
+    while i < s.len() {
+        let (c, len) = match s[i] {
+            b if b < 0x80 => (b as u32, 1),
+            b if b < 0xe0 => (((b & 0x1f) as u32) << 6 | (s[i + 1] & 0x3f) as u32, 2),
+            b if b < 0xf0 => (
+                ((b & 0x0f) as u32) << 12
+                    | ((s[i + 1] & 0x3f) as u32) << 6
+                    | (s[i + 2] & 0x3f) as u32,
+                3,
+            ),
+            // There is no whitespace outside the Basic Multilingual Plane
+            _ => (0, 4),
+        };
+        if matches!(
+            c,
+            0x09..=0x0d
+                | 0x20
+                | 0x85
+                | 0xa0
+                | 0x1680
+                | 0x2000..=0x200a
+                | 0x2028
+                | 0x2029
+                | 0x202f
+                | 0x205f
+                | 0x3000
+        ) {
+            return true;
+        }
+        i += len;
+    }
+    false
+}
@@ -0,0 +1,342 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::Duration,
+};
+
+use log::{debug, warn};
+use tokio::time::Instant;
+
+/// Number of entries a [`WindowMap`] keeps at most.
+const MAX_ENTRIES: usize = 1024;
+
+#[derive(Debug)]
+struct Announcer {
+    last_forwarded: Instant,
+    dropped: u64,
+    noisy: bool,
+}
+
+/// Rate-limits `ssdp:alive` messages of devices re-announcing faster than `min_interval`.
+///
+/// Devices are identified by their source address and `USN`, so each advertised target of a device
+/// is limited on its own. Other notification types are never dropped. Devices silent for
+/// `min_interval` are forgotten.
+#[derive(Debug)]
+pub(crate) struct StormGuard {
+    min_interval: Duration,
+    announcers: Mutex<WindowMap<(IpAddr, String), Announcer>>,
+}
+
+impl StormGuard {
+    pub(crate) fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            announcers: Mutex::new(WindowMap::new(min_interval)),
+        }
+    }
+
+    /// Whether the `NOTIFY` in `req` sent from `ip` should be forwarded.
+    pub(crate) fn admit(&self, req: &httparse::Request, ip: IpAddr) -> bool {
+        let header = |name: &str| {
+            req.headers
+                .iter()
+                .find(|h| h.name.eq_ignore_ascii_case(name))
+                .and_then(|h| std::str::from_utf8(h.value).ok())
+                .map(str::trim)
+        };
+        let (Some(nts), Some(usn)) = (header("NTS"), header("USN")) else {
+            return true;
+        };
+
+        let mut announcers = self.announcers.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (ip, usn.to_owned());
+
+        if !nts.eq_ignore_ascii_case("ssdp:alive") {
+            announcers.remove(&key);
+            return true;
+        }
+
+        let now = Instant::now();
+        let mut first = false;
+        let announcer = announcers.touch_or_insert_with(key, now, || {
+            first = true;
+            Announcer {
+                last_forwarded: now,
+                dropped: 0,
+                noisy: false,
+            }
+        });
+        if first {
+            return true;
+        }
+
+        if now.duration_since(announcer.last_forwarded) < self.min_interval {
+            announcer.dropped += 1;
+            if !announcer.noisy {
+                announcer.noisy = true;
+                warn!(
+                    "Noisy device {usn} at {ip} re-announces within {:?}, rate-limiting its ssdp:alive messages",
+                    self.min_interval
+                );
+            }
+            return false;
+        }
+
+        if announcer.dropped > 0 {
+            debug!(
+                "Dropped {} ssdp:alive messages of noisy device {usn} at {ip}",
+                announcer.dropped
+            );
+        }
+        announcer.last_forwarded = now;
+        announcer.dropped = 0;
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled: Instant,
+    dropped: u64,
+    flooding: bool,
+}
+
+/// Limits the `M-SEARCH` requests answered per source address with a token bucket.
+///
+/// Each address may send `limit` searches in a burst, and regains `limit` per `period`. Buckets
+/// idle for a whole period are full again, so they are forgotten then.
+#[derive(Debug)]
+pub(crate) struct SearchLimiter {
+    limit: u32,
+    period: Duration,
+    buckets: Mutex<WindowMap<IpAddr, Bucket>>,
+}
+
+impl SearchLimiter {
+    pub(crate) fn new(limit: u32, period: Duration) -> Self {
+        Self {
+            limit,
+            period,
+            buckets: Mutex::new(WindowMap::new(period)),
+        }
+    }
+
+    /// The searches allowed per period.
+    pub(crate) fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Whether a search from `ip` should be handled.
+    pub(crate) fn admit(&self, ip: IpAddr) -> bool {
+        let capacity = self.limit as f64;
+        let rate = capacity / self.period.as_secs_f64().max(f64::EPSILON);
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let bucket = buckets.touch_or_insert_with(ip, now, || Bucket {
+            tokens: capacity,
+            refilled: now,
+            dropped: 0,
+            flooding: false,
+        });
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.refilled = now;
+
+        if bucket.tokens < 1.0 {
+            if !bucket.flooding {
+                bucket.flooding = true;
+                warn!(
+                    "{ip} sends more than {} M-SEARCH requests per {:?}, ignoring the excess",
+                    self.limit, self.period
+                );
+            }
+            bucket.dropped += 1;
+            return false;
+        }
+
+        if bucket.dropped > 0 {
+            debug!("Ignored {} M-SEARCH requests from {ip}", bucket.dropped);
+            bucket.dropped = 0;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+/// Coalesces the searches a control point repeats and the responses they would duplicate.
+///
+/// Searches are identified by their source address and `ST`, responses by their destination and
+/// `USN`. Either is let through once per `window`, counted from the first one; a response counts
+/// once it was sent.
+#[derive(Debug)]
+pub(crate) struct SearchDedup {
+    searches: Mutex<WindowMap<(SocketAddr, String), ()>>,
+    responses: Mutex<WindowMap<(SocketAddr, String), ()>>,
+}
+
+impl SearchDedup {
+    pub(crate) fn new(window: Duration) -> Self {
+        Self {
+            searches: Mutex::new(WindowMap::new(window)),
+            responses: Mutex::new(WindowMap::new(window)),
+        }
+    }
+
+    /// Whether the `M-SEARCH` in `req` sent from `addr` should be handled.
+    pub(crate) fn admit_search(&self, req: &httparse::Request, addr: SocketAddr) -> bool {
+        let st = req
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case("ST"))
+            .and_then(|h| std::str::from_utf8(h.value).ok())
+            .map_or("", str::trim);
+
+        let now = Instant::now();
+        let mut searches = self.searches.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (addr, st.to_owned());
+        if searches.get(&key, now).is_some() {
+            debug!("Ignoring repeated M-SEARCH for {st} from {addr}");
+            return false;
+        }
+        searches.insert(key, (), now);
+        true
+    }
+
+    /// Whether the response advertising `usn` was sent to `addr` within the window.
+    pub(crate) fn responded(&self, addr: SocketAddr, usn: &str) -> bool {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(addr, usn.to_owned()), Instant::now())
+            .is_some()
+    }
+
+    /// Remember that the response advertising `usn` was sent to `addr`.
+    pub(crate) fn record_response(&self, addr: SocketAddr, usn: &str) {
+        self.responses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert((addr, usn.to_owned()), (), Instant::now());
+    }
+}
+
+/// A map forgetting the entries not seen for `window`, and the least recently seen ones beyond
+/// `MAX_ENTRIES`, so hosts sending from many addresses cannot grow it without bounds.
+#[derive(Debug)]
+struct WindowMap<K, V> {
+    window: Duration,
+    /// The values with the stamp and time they were last seen at.
+    entries: HashMap<K, (u64, Instant, V)>,
+    /// When keys were seen, oldest first; a record is stale once its key was seen again.
+    seen: VecDeque<(K, u64, Instant)>,
+    next_stamp: u64,
+}
+
+impl<K: Clone + Eq + Hash, V> WindowMap<K, V> {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: HashMap::new(),
+            seen: VecDeque::new(),
+            next_stamp: 0,
+        }
+    }
+
+    /// The value for `key`, unless it was last seen `window` or longer before `now`.
+    fn get(&mut self, key: &K, now: Instant) -> Option<&mut V> {
+        self.expire(now);
+        self.entries.get_mut(key).map(|(_, _, value)| value)
+    }
+
+    /// Insert `value` for `key`, replacing the previous one and restarting its window.
+    fn insert(&mut self, key: K, value: V, now: Instant) {
+        let stamp = self.see(&key, now);
+        self.entries.insert(key, (stamp, now, value));
+    }
+
+    /// The value for `key`, inserting `default()` if there is none, restarting its window.
+    fn touch_or_insert_with(
+        &mut self,
+        key: K,
+        now: Instant,
+        default: impl FnOnce() -> V,
+    ) -> &mut V {
+        let stamp = self.see(&key, now);
+        let (last_stamp, last_seen, value) = self
+            .entries
+            .entry(key)
+            .or_insert_with(|| (stamp, now, default()));
+        (*last_stamp, *last_seen) = (stamp, now);
+        value
+    }
+
+    /// Record that `key` was seen at `now`, making room for it, and return the stamp of the record.
+    fn see(&mut self, key: &K, now: Instant) -> u64 {
+        self.expire(now);
+        self.compact();
+        if !self.entries.contains_key(key) {
+            while self.entries.len() >= MAX_ENTRIES {
+                self.evict_oldest();
+            }
+        }
+        let stamp = self.next_stamp;
+        self.next_stamp += 1;
+        self.seen.push_back((key.clone(), stamp, now));
+        stamp
+    }
+
+    fn remove(&mut self, key: &K) {
+        self.entries.remove(key);
+    }
+
+    /// Forget the entries last seen `window` or longer before `now`.
+    fn expire(&mut self, now: Instant) {
+        while self
+            .seen
+            .front()
+            .is_some_and(|(_, _, seen)| now.duration_since(*seen) >= self.window)
+        {
+            self.pop_front();
+        }
+    }
+
+    /// Forget the entry seen the longest ago.
+    fn evict_oldest(&mut self) {
+        while !self.seen.is_empty() {
+            if self.pop_front() {
+                return;
+            }
+        }
+    }
+
+    /// Drop the oldest record, along with its entry if not seen since. Returns whether it was.
+    fn pop_front(&mut self) -> bool {
+        let Some((key, stamp, _)) = self.seen.pop_front() else {
+            return false;
+        };
+        let live = self
+            .entries
+            .get(&key)
+            .is_some_and(|(last, _, _)| *last == stamp);
+        if live {
+            self.entries.remove(&key);
+        }
+        live
+    }
+
+    /// Drop the stale records once they make up half of them, so touching the same entries
+    /// over and over does not grow the records.
+    fn compact(&mut self) {
+        if self.seen.len() >= 2 * MAX_ENTRIES {
+            let entries = &self.entries;
+            self.seen.retain(|(key, stamp, _)| {
+                entries.get(key).is_some_and(|(last, _, _)| last == stamp)
+            });
+        }
+    }
+}
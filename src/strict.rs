@@ -0,0 +1,206 @@
+use std::{fmt, net::SocketAddr};
+
+use crate::{CacheControl, Error, Result, SSDP_ADDR, notify};
+
+/// A way an incoming request breaks the UPnP Device Architecture, see [`validate_request`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Violation {
+    /// The datagram is not a complete HTTP request.
+    #[error("not an HTTP request: {0}")]
+    Malformed(String),
+    /// The method is neither `NOTIFY` nor `M-SEARCH`.
+    #[error("method {0} is neither NOTIFY nor M-SEARCH")]
+    Method(String),
+    /// The request target is not `*`.
+    #[error("request target {0} is not *")]
+    Path(String),
+    /// The request is not HTTP/1.1.
+    #[error("the request is HTTP/1.0, not HTTP/1.1")]
+    Version,
+    /// A required header is missing.
+    #[error("missing {0} header")]
+    MissingHeader(&'static str),
+    /// A header has a value the specification does not allow.
+    #[error("invalid {name} header {value:?}")]
+    InvalidHeader {
+        /// The name of the header.
+        name: &'static str,
+        /// Its value as received.
+        value: String,
+    },
+    /// `HOST` is not the SSDP multicast address and port.
+    #[error("HOST {0} is not the SSDP multicast address and port")]
+    Host(String),
+    /// The `MAN` header of a search is not `"ssdp:discover"`, quotes included.
+    #[error("MAN {0} is not \"ssdp:discover\"")]
+    Man(String),
+}
+
+/// Check the `NOTIFY` or `M-SEARCH` request `data` against the UPnP Device Architecture, for a
+/// server on `port`.
+///
+/// Fails with `Error::Invalid`, listing every violation found:
+/// - the method must be `NOTIFY` or `M-SEARCH`, the request target `*` and the version HTTP/1.1
+/// - `HOST` must be the SSDP multicast address (`239.255.255.250`, or `[ff0X::c]` for IPv6) on
+///   `port`, except for unicast searches, which carry the address of the device
+/// - searches need `MAN: "ssdp:discover"`, with the quotes, and `ST`, multicast ones an integer
+///   `MX` of at least 1
+/// - notifications need `NT`, `USN` and an `NTS` of `ssdp:alive`, `ssdp:byebye` or
+///   `ssdp:update`, `ssdp:alive` a `CACHE-CONTROL` with `max-age` and a `LOCATION`
+///
+/// `Server::strict` drops the requests failing it.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{Error, Violation, validate_request};
+///
+/// let search = b"M-SEARCH * HTTP/1.1\r\nHOST: 239.255.255.250:1900\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: ssdp:all\r\n\r\n";
+/// assert!(validate_request(search, 1900).is_ok());
+///
+/// let sloppy = b"M-SEARCH * HTTP/1.1\r\nHost: 239.255.255.250\r\nMAN: ssdp:discover\r\nST: ssdp:all\r\n\r\n";
+/// let Err(Error::Invalid(violations)) = validate_request(sloppy, 1900) else {
+///     panic!("accepted");
+/// };
+/// assert_eq!(violations, [
+///     Violation::Host("239.255.255.250".into()),
+///     Violation::Man("ssdp:discover".into()),
+///     Violation::MissingHeader("MX"),
+/// ]);
+/// ```
+pub fn validate_request(data: &[u8], port: u16) -> Result<()> {
+    let violations = violations(data, port);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Invalid(violations))
+    }
+}
+
+fn violations(data: &[u8], port: u16) -> Vec<Violation> {
+    let mut headers = notify::header_buf(data, notify::MAX_HEADERS);
+    let mut req = httparse::Request::new(&mut headers);
+    match req.parse(data) {
+        Ok(httparse::Status::Complete(_)) => {}
+        Ok(httparse::Status::Partial) => {
+            return vec![Violation::Malformed("incomplete request".into())];
+        }
+        Err(e) => return vec![Violation::Malformed(e.to_string())],
+    }
+
+    let mut violations = vec![];
+    let method = req.method.unwrap_or_default();
+    let search = match method {
+        "M-SEARCH" => true,
+        "NOTIFY" => false,
+        _ => return vec![Violation::Method(method.to_owned())],
+    };
+    let path = req.path.unwrap_or_default();
+    if path != "*" {
+        violations.push(Violation::Path(path.to_owned()));
+    }
+    if req.version != Some(1) {
+        violations.push(Violation::Version);
+    }
+
+    let find = |name: &str| {
+        req.headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .map(|h| String::from_utf8_lossy(h.value).trim().to_owned())
+    };
+    let require = |violations: &mut Vec<Violation>, name: &'static str| {
+        let value = find(name);
+        if value.is_none() {
+            violations.push(Violation::MissingHeader(name));
+        }
+        value
+    };
+
+    let multicast = match require(&mut violations, "HOST") {
+        Some(host) => match host.parse::<SocketAddr>() {
+            Ok(addr) if addr.ip().is_multicast() => {
+                if !is_ssdp_group(addr) || addr.port() != port {
+                    violations.push(Violation::Host(host));
+                }
+                true
+            }
+            // Unicast searches are sent to the device's own address
+            Ok(_) if search => false,
+            _ => {
+                violations.push(Violation::Host(host));
+                true
+            }
+        },
+        None => true,
+    };
+
+    if search {
+        if let Some(man) = require(&mut violations, "MAN") {
+            if man != "\"ssdp:discover\"" {
+                violations.push(Violation::Man(man));
+            }
+        }
+        require(&mut violations, "ST");
+        if multicast {
+            if let Some(mx) = require(&mut violations, "MX") {
+                if !mx.parse::<u32>().is_ok_and(|mx| mx >= 1) {
+                    violations.push(Violation::InvalidHeader {
+                        name: "MX",
+                        value: mx,
+                    });
+                }
+            }
+        }
+    } else {
+        require(&mut violations, "NT");
+        require(&mut violations, "USN");
+        let nts = require(&mut violations, "NTS");
+        match nts.as_deref() {
+            Some("ssdp:alive") => {
+                if let Some(cc) = require(&mut violations, "CACHE-CONTROL") {
+                    if CacheControl::parse(&cc).max_age.is_none() {
+                        violations.push(Violation::InvalidHeader {
+                            name: "CACHE-CONTROL",
+                            value: cc,
+                        });
+                    }
+                }
+                require(&mut violations, "LOCATION");
+            }
+            Some("ssdp:byebye" | "ssdp:update") | None => {}
+            Some(_) => violations.push(Violation::InvalidHeader {
+                name: "NTS",
+                value: nts.unwrap_or_default(),
+            }),
+        }
+    }
+
+    violations
+}
+
+/// Whether `addr` is the IPv4 SSDP group or an IPv6 one, `ff0X::c` of any scope.
+fn is_ssdp_group(addr: SocketAddr) -> bool {
+    match addr {
+        SocketAddr::V4(addr) => *addr.ip() == SSDP_ADDR,
+        SocketAddr::V6(addr) => {
+            let segments = addr.ip().segments();
+            segments[0] & 0xfff0 == 0xff00 && segments[1..7] == [0; 6] && segments[7] == 0xc
+        }
+    }
+}
+
+/// Writes violations separated by semicolons, for `Error::Invalid`.
+pub(crate) struct List<'a>(pub(crate) &'a [Violation]);
+
+impl fmt::Display for List<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, violation) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
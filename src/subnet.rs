@@ -0,0 +1,124 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use crate::Error;
+
+/// An IP network in CIDR notation, such as `192.168.1.0/24` or `fe80::/10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subnet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl Subnet {
+    /// Create a subnet from a network address and a prefix length.
+    ///
+    /// Host bits of `addr` are cleared. Fails if `prefix_len` is longer than the address.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Subnet;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// let subnet = Subnet::new(Ipv4Addr::new(192, 168, 1, 7).into(), 24).unwrap();
+    /// assert_eq!(subnet.to_string(), "192.168.1.0/24");
+    /// assert!(Subnet::new(Ipv4Addr::UNSPECIFIED.into(), 33).is_err());
+    /// ```
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, Error> {
+        let max_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_len {
+            return Err(Error::Config(format!(
+                "prefix length {prefix_len} is longer than {max_len} bits"
+            )));
+        }
+
+        let addr = match addr {
+            IpAddr::V4(ip) => IpAddr::V4(Ipv4Addr::from(
+                u32::from(ip) & u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0),
+            )),
+            IpAddr::V6(ip) => IpAddr::V6(Ipv6Addr::from(
+                u128::from(ip) & u128::MAX.checked_shl(128 - prefix_len as u32).unwrap_or(0),
+            )),
+        };
+
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// The network address.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The prefix length in bits.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Check if `ip` belongs to this subnet. Addresses of the other family never match.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::Subnet;
+    ///
+    /// let subnet: Subnet = "10.0.0.0/8".parse().unwrap();
+    /// assert!(subnet.contains("10.20.30.40".parse().unwrap()));
+    /// assert!(!subnet.contains("192.168.1.1".parse().unwrap()));
+    /// ```
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match Self::new(ip, self.prefix_len) {
+            Ok(other) => other.addr == self.addr,
+            Err(_) => false,
+        }
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = Error;
+
+    /// Parse `addr/prefix_len`, a bare address is treated as a single host.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+
+        let invalid = |e: &dyn fmt::Display| Error::Config(format!("invalid subnet {s:?}: {e}"));
+
+        let addr: IpAddr = addr.trim().parse().map_err(|e| invalid(&e))?;
+        let prefix_len = match prefix_len {
+            Some(len) => len.trim().parse().map_err(|e| invalid(&e))?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+
+        Self::new(addr, prefix_len)
+    }
+}
+
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Serialized in CIDR notation, like `Display` writes and `FromStr` reads it.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Subnet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Subnet {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fmt, future::Future, pin::Pin};
+
+use log::info;
+
+use crate::ServerHandle;
+
+/// A step of an orchestrated shutdown, see [`Teardown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ShutdownPhase {
+    /// The SSDP servers stop answering searches and send `ssdp:byebye`.
+    Ssdp,
+    /// Event subscriptions (GENA) are ended.
+    Eventing,
+    /// HTTP servers, e.g. for device descriptions, stop.
+    Http,
+}
+
+impl fmt::Display for ShutdownPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShutdownPhase::Ssdp => "SSDP",
+            ShutdownPhase::Eventing => "eventing",
+            ShutdownPhase::Http => "HTTP",
+        })
+    }
+}
+
+/// Reported by `Teardown::shutdown_all` as it goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ShutdownProgress {
+    /// The phase started.
+    Started(ShutdownPhase),
+    /// Every part of the phase stopped.
+    Finished(ShutdownPhase),
+}
+
+type Stop = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Shuts the subsystems making up a UPnP device down in an order control points can follow.
+///
+/// Control points are told the devices are gone before the URLs they learned stop working: the
+/// SSDP servers stop answering searches and send `ssdp:byebye` first, then event subscriptions
+/// end, and the HTTP servers stop last. Phases without parts are skipped.
+///
+/// # Examples
+/// ```no_run
+/// use tokio_ssdp::{Server, Teardown};
+///
+/// # async fn run() -> tokio_ssdp::Result<()> {
+/// let mut server = Server::new([]);
+/// let handle = server.handle();
+/// tokio::spawn(server.serve()?);
+///
+/// Teardown::new()
+///     .ssdp(handle)
+///     .eventing(async { /* end the GENA subscriptions */ })
+///     .shutdown_all(|progress| println!("{progress:?}"))
+///     .await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Teardown {
+    ssdp: Vec<ServerHandle>,
+    eventing: Vec<Stop>,
+    http: Vec<Stop>,
+}
+
+impl fmt::Debug for Teardown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Teardown")
+            .field("ssdp", &self.ssdp)
+            .field("eventing", &self.eventing.len())
+            .field("http", &self.http.len())
+            .finish()
+    }
+}
+
+impl Teardown {
+    /// Create a teardown without parts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shut the server of `handle` down in the SSDP phase.
+    pub fn ssdp(mut self, handle: ServerHandle) -> Self {
+        self.ssdp.push(handle);
+        self
+    }
+
+    /// Run `stop` in the eventing phase.
+    pub fn eventing(mut self, stop: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.eventing.push(Box::pin(stop));
+        self
+    }
+
+    /// Run `stop` in the HTTP phase.
+    pub fn http(mut self, stop: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.http.push(Box::pin(stop));
+        self
+    }
+
+    /// Stop the [`DescriptionServer`](crate::DescriptionServer) of `handle` in the HTTP phase.
+    #[cfg(feature = "description")]
+    pub fn description_server(self, handle: crate::DescriptionHandle) -> Self {
+        self.http(handle.shutdown())
+    }
+
+    /// Run the phases one after the other, calling `progress` as each starts and finishes.
+    ///
+    /// The parts of a phase are stopped in the order they were added.
+    pub async fn shutdown_all(self, mut progress: impl FnMut(ShutdownProgress)) {
+        let ssdp = self
+            .ssdp
+            .into_iter()
+            .map(|handle| Box::pin(handle.shutdown()) as Stop)
+            .collect();
+        let phases = [
+            (ShutdownPhase::Ssdp, ssdp),
+            (ShutdownPhase::Eventing, self.eventing),
+            (ShutdownPhase::Http, self.http),
+        ];
+
+        for (phase, parts) in phases {
+            if parts.is_empty() {
+                continue;
+            }
+
+            info!("Shutting {phase} down");
+            progress(ShutdownProgress::Started(phase));
+            for part in parts {
+                part.await;
+            }
+            progress(ShutdownProgress::Finished(phase));
+        }
+    }
+}
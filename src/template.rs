@@ -0,0 +1,60 @@
+/// A canned `M-SEARCH` response sent verbatim for one search target.
+///
+/// Templates bypass the registered devices: the response consists of exactly the given headers, in
+/// order, with no `DATE`, `SERVER` or extra headers added. This allows impersonating arbitrary
+/// devices, e.g. for SSDP honeypots studying scanners.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{ResponseTemplate, Server};
+///
+/// Server::new([]).response_template(
+///     ResponseTemplate::new("urn:schemas-upnp-org:device:InternetGatewayDevice:1")
+///         .header("CACHE-CONTROL", "max-age=120")
+///         .header("ST", "urn:schemas-upnp-org:device:InternetGatewayDevice:1")
+///         .header(
+///             "USN",
+///             concat!(
+///                 "uuid:00000000-0000-0000-0000-000000000000",
+///                 "::urn:schemas-upnp-org:device:InternetGatewayDevice:1",
+///             ),
+///         )
+///         .header("EXT", "")
+///         .header("SERVER", "Linux/2.6 UPnP/1.0 miniupnpd/1.0")
+///         .header("LOCATION", "http://192.168.1.1:5431/igd.xml"),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResponseTemplate {
+    pub(crate) search_target: String,
+    headers: Vec<(String, String)>,
+}
+
+impl ResponseTemplate {
+    /// Create an empty template answering searches for `search_target`.
+    pub fn new(search_target: impl Into<String>) -> Self {
+        Self {
+            search_target: search_target.into(),
+            headers: vec![],
+        }
+    }
+
+    /// Append a header to the response.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// The complete response message.
+    pub(crate) fn render(&self) -> String {
+        let mut response = String::from("HTTP/1.1 200 OK\r\n");
+        for (name, value) in &self.headers {
+            response.push_str(name);
+            response.push_str(": ");
+            response.push_str(value);
+            response.push_str("\r\n");
+        }
+        response.push_str("\r\n");
+        response
+    }
+}
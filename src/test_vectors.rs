@@ -0,0 +1,111 @@
+//! SSDP messages as sent by common devices, for interoperability tests.
+//!
+//! The messages follow the formats these devices send on the wire, including their quirks (header
+//! case, missing spaces, vendor headers). Identifiers are anonymized and addresses come from the
+//! documentation range `192.0.2.0/24`.
+//!
+//! # Examples
+//! ```
+//! use tokio_ssdp::{NotifyRequest, test_vectors};
+//!
+//! for data in test_vectors::NOTIFY {
+//!     let req = NotifyRequest::parse("192.0.2.1:1900".parse().unwrap(), data).unwrap();
+//!     assert_eq!(req.method, "NOTIFY");
+//!     assert!(req.header_match("NTS", "ssdp:alive"));
+//! }
+//! ```
+
+/// `ssdp:alive` of a Sonos speaker, with its `X-RINCON-*` household headers.
+pub const SONOS_ALIVE: &[u8] = concat!(
+    "NOTIFY * HTTP/1.1\r\n",
+    "HOST: 239.255.255.250:1900\r\n",
+    "CACHE-CONTROL: max-age = 1800\r\n",
+    "LOCATION: http://192.0.2.10:1400/xml/device_description.xml\r\n",
+    "NT: upnp:rootdevice\r\n",
+    "NTS: ssdp:alive\r\n",
+    "SERVER: Linux UPnP/1.0 Sonos/70.3-35220 (ZPS1)\r\n",
+    "USN: uuid:RINCON_000000000000001400::upnp:rootdevice\r\n",
+    "X-RINCON-HOUSEHOLD: Sonos_0000000000000000000000000000\r\n",
+    "X-RINCON-BOOTSEQ: 87\r\n",
+    "BOOTID.UPNP.ORG: 87\r\n",
+    "X-RINCON-WIFIMODE: 0\r\n",
+    "X-RINCON-VARIANT: 1\r\n",
+    "HOUSEHOLD.SMARTSPEAKER.AUDIO: Sonos_0000000000000000000000000000.AAAAAAAAAAAAAAAAAAAA\r\n",
+    "\r\n",
+)
+.as_bytes();
+
+/// `ssdp:alive` of a Philips Hue bridge, with its `hue-bridgeid` header.
+pub const HUE_ALIVE: &[u8] = concat!(
+    "NOTIFY * HTTP/1.1\r\n",
+    "HOST: 239.255.255.250:1900\r\n",
+    "CACHE-CONTROL: max-age=100\r\n",
+    "LOCATION: http://192.0.2.20:80/description.xml\r\n",
+    "SERVER: Hue/1.0 UPnP/1.0 IpBridge/1.65.0\r\n",
+    "NTS: ssdp:alive\r\n",
+    "hue-bridgeid: 001788FFFE000000\r\n",
+    "NT: upnp:rootdevice\r\n",
+    "USN: uuid:2f402f80-da50-11e1-9b23-001788000000::upnp:rootdevice\r\n",
+    "\r\n",
+)
+.as_bytes();
+
+/// `ssdp:alive` of an AVM FRITZ!Box router for its internet gateway device.
+pub const FRITZBOX_ALIVE: &[u8] = concat!(
+    "NOTIFY * HTTP/1.1\r\n",
+    "HOST: 239.255.255.250:1900\r\n",
+    "LOCATION: http://192.0.2.1:49000/igd2desc.xml\r\n",
+    "SERVER: FRITZ!Box 7590 UPnP/1.0 AVM FRITZ!Box 7590 154.07.57\r\n",
+    "CACHE-CONTROL: max-age=1800\r\n",
+    "NT: urn:schemas-upnp-org:device:InternetGatewayDevice:2\r\n",
+    "NTS: ssdp:alive\r\n",
+    "USN: uuid:75802409-bccb-40e7-8e6c-000000000001::urn:schemas-upnp-org:device:InternetGatewayDevice:2\r\n",
+    "\r\n",
+)
+.as_bytes();
+
+/// `ssdp:alive` of a Windows host, with the `OPT`/`01-NLS` extension headers.
+pub const WINDOWS_ALIVE: &[u8] = concat!(
+    "NOTIFY * HTTP/1.1\r\n",
+    "Host:239.255.255.250:1900\r\n",
+    "NT:urn:schemas-upnp-org:device:MediaServer:1\r\n",
+    "NTS:ssdp:alive\r\n",
+    "Location:http://192.0.2.40:2869/upnphost/udhisapi.dll?content=uuid:00000000-0000-0000-0000-000000000040\r\n",
+    "USN:uuid:00000000-0000-0000-0000-000000000040::urn:schemas-upnp-org:device:MediaServer:1\r\n",
+    "Cache-Control:max-age=900\r\n",
+    "Server:Microsoft-Windows/10.0 UPnP/1.0 UPnP-Device-Host/1.0\r\n",
+    "OPT:\"http://schemas.upnp.org/upnp/1/0/\"; ns=01\r\n",
+    "01-NLS:00000000000000000000000000000040\r\n",
+    "\r\n",
+)
+.as_bytes();
+
+/// `M-SEARCH` of a Windows host looking for an internet gateway, without spaces after the colons.
+pub const WINDOWS_MSEARCH: &[u8] = concat!(
+    "M-SEARCH * HTTP/1.1\r\n",
+    "Host:239.255.255.250:1900\r\n",
+    "ST:urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n",
+    "Man:\"ssdp:discover\"\r\n",
+    "MX:3\r\n",
+    "\r\n",
+)
+.as_bytes();
+
+/// `M-SEARCH` response of a Samsung TV for its media renderer, with a `DATE` from an unset clock.
+pub const SAMSUNG_TV_RESPONSE: &[u8] = concat!(
+    "HTTP/1.1 200 OK\r\n",
+    "CACHE-CONTROL: max-age=1800\r\n",
+    "DATE: Thu, 01 Jan 1970 00:00:42 GMT\r\n",
+    "EXT:\r\n",
+    "LOCATION: http://192.0.2.30:9197/dmr\r\n",
+    "SERVER: SHP, UPnP/1.0, Samsung UPnP SDK/1.0\r\n",
+    "ST: urn:schemas-upnp-org:device:MediaRenderer:1\r\n",
+    "USN: uuid:00000000-0000-1000-8000-000000000030::urn:schemas-upnp-org:device:MediaRenderer:1\r\n",
+    "Content-Length: 0\r\n",
+    "BOOTID.UPNP.ORG: 0\r\n",
+    "\r\n",
+)
+.as_bytes();
+
+/// All `NOTIFY` messages of this module.
+pub const NOTIFY: &[&[u8]] = &[SONOS_ALIVE, HUE_ALIVE, FRITZBOX_ALIVE, WINDOWS_ALIVE];
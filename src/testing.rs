@@ -0,0 +1,353 @@
+//! An in-memory network for testing servers and clients without sockets or real time.
+//!
+//! [`MockNetwork`] hands out [`MockSocket`]s, [`SsdpSocket`] transports exchanging datagrams
+//! through it: multicast ones reach every socket on the destination port that joined the group,
+//! unicast ones the socket bound to the destination address. Serve them with
+//! `Server::serve_socket` and search through them with `Client::search_via`.
+//!
+//! Run the tests with the tokio clock paused (`#[tokio::test(start_paused = true)]`, needs tokio's
+//! `test-util` feature): the runtime then skips straight to the next timer whenever every task is
+//! idle, so advertisement intervals and `MX` delays elapse instantly, in the same order every run.
+//! [`MockNetwork::packets`] records everything sent, with the time since the network was created.
+//!
+//! Needs the `testing` feature.
+//!
+//! # Examples
+//! ```
+//! # #[cfg(all(feature = "server", feature = "client"))]
+//! use std::{net::SocketAddr, time::Duration};
+//! # #[cfg(all(feature = "server", feature = "client"))]
+//! use tokio_ssdp::{Client, Device, Server, testing::MockNetwork};
+//!
+//! # #[cfg(not(all(feature = "server", feature = "client")))]
+//! # fn main() {}
+//! # #[cfg(all(feature = "server", feature = "client"))]
+//! # #[tokio::main(flavor = "current_thread", start_paused = true)]
+//! # async fn main() -> tokio_ssdp::Result<()> {
+//! let network = MockNetwork::new();
+//!
+//! let printer = Device::new(
+//!     "6f1b0f1e-3c3a-4c7e-9a07-1d2b3c4d5e6f",
+//!     "urn:schemas-upnp-org:device:Printer:1",
+//!     "http://192.0.2.10/desc.xml",
+//! );
+//! let server = Server::new([printer])
+//!     .announce_interval(Duration::from_secs(60))
+//!     .serve_socket(network.bind_multicast("192.0.2.10:1900".parse().unwrap())?)?;
+//! tokio::spawn(server);
+//!
+//! let mut search = Client::new()
+//!     .search_via(network.bind("192.0.2.50:0".parse().unwrap())?, "ssdp:all")
+//!     .await?;
+//! let mut found = vec![];
+//! while let Some(response) = search.next().await {
+//!     found.push(response?.search_target);
+//! }
+//! assert!(found.contains(&"urn:schemas-upnp-org:device:Printer:1".to_owned()));
+//!
+//! // Two minutes pass instantly, with a round of announcements every minute
+//! tokio::time::sleep(Duration::from_secs(120)).await;
+//! let group: SocketAddr = "239.255.255.250:1900".parse().unwrap();
+//! let rounds: Vec<_> = network
+//!     .packets()
+//!     .into_iter()
+//!     .filter(|p| p.to == group && p.text().contains("ssdp:alive"))
+//!     .map(|p| p.at.as_secs())
+//!     .collect();
+//! assert_eq!(rounds.first(), Some(&0));
+//! assert!(rounds.contains(&60) && rounds.contains(&120));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use tokio::{
+    io::ReadBuf,
+    time::{Instant, Sleep},
+};
+
+use crate::{SSDP_ADDR, SsdpSocket};
+
+/// The first port handed out to sockets bound to port 0, the start of the dynamic range.
+const EPHEMERAL_PORTS: u16 = 49152;
+
+/// A datagram sent on a [`MockNetwork`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct Packet {
+    /// When it was sent, since the network was created.
+    pub at: Duration,
+    /// The address of the sending socket.
+    pub from: SocketAddr,
+    /// The destination, a multicast group or a unicast address.
+    pub to: SocketAddr,
+    /// The datagram.
+    pub data: Vec<u8>,
+}
+
+impl Packet {
+    /// The datagram as text, with invalid UTF-8 replaced.
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.data)
+    }
+}
+
+/// An in-memory network connecting [`MockSocket`]s, see the [module documentation](self).
+///
+/// Clones share the same network.
+#[derive(Debug, Clone)]
+pub struct MockNetwork {
+    inner: Arc<Mutex<Network>>,
+}
+
+#[derive(Debug)]
+struct Network {
+    created: Instant,
+    latency: Duration,
+    next_port: u16,
+    sockets: HashMap<SocketAddr, Mailbox>,
+    packets: Vec<Packet>,
+}
+
+/// The state of a bound socket: its multicast groups and the datagrams on their way to it.
+#[derive(Debug, Default)]
+struct Mailbox {
+    groups: HashSet<IpAddr>,
+    queue: VecDeque<(Instant, SocketAddr, Vec<u8>)>,
+    waker: Option<Waker>,
+}
+
+impl Default for MockNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockNetwork {
+    /// Create an empty network delivering datagrams instantly.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Network {
+                created: Instant::now(),
+                latency: Duration::ZERO,
+                next_port: EPHEMERAL_PORTS,
+                sockets: HashMap::new(),
+                packets: vec![],
+            })),
+        }
+    }
+
+    /// Deliver datagrams `latency` after they are sent, defaults to instantly.
+    pub fn latency(self, latency: Duration) -> Self {
+        self.lock().latency = latency;
+        self
+    }
+
+    /// Bind a socket to `addr`, picking a free port if its port is 0.
+    ///
+    /// Fails with `AddrNotAvailable` for an unspecified IP, as every socket needs the address of
+    /// its host to send from, and with `AddrInUse` if a socket is bound to `addr` already, or if
+    /// every ephemeral port is taken for port 0.
+    pub fn bind(&self, mut addr: SocketAddr) -> io::Result<MockSocket> {
+        if addr.ip().is_unspecified() || addr.ip().is_multicast() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("{} is not a host address", addr.ip()),
+            ));
+        }
+
+        let mut network = self.lock();
+        if addr.port() == 0 {
+            // One pass over the ephemeral range, starting after the port picked last
+            let mut tried = 0;
+            while network
+                .sockets
+                .contains_key(&(addr.ip(), network.next_port).into())
+            {
+                tried += 1;
+                if tried > u16::MAX - EPHEMERAL_PORTS {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("every ephemeral port of {} is in use", addr.ip()),
+                    ));
+                }
+                network.next_port = network.next_port.checked_add(1).unwrap_or(EPHEMERAL_PORTS);
+            }
+            addr.set_port(network.next_port);
+        }
+        if network.sockets.contains_key(&addr) {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{addr} is in use"),
+            ));
+        }
+        network.sockets.insert(addr, Mailbox::default());
+
+        Ok(MockSocket {
+            addr,
+            network: Arc::clone(&self.inner),
+            delay: Mutex::new(None),
+        })
+    }
+
+    /// Bind a socket to `addr` and join the IPv4 SSDP multicast group, as a server's socket.
+    pub fn bind_multicast(&self, addr: SocketAddr) -> io::Result<MockSocket> {
+        let socket = self.bind(addr)?;
+        socket.join_multicast_v4(SSDP_ADDR, Ipv4Addr::UNSPECIFIED)?;
+        Ok(socket)
+    }
+
+    /// Every datagram sent so far, in the order they were sent.
+    pub fn packets(&self) -> Vec<Packet> {
+        self.lock().packets.clone()
+    }
+
+    /// Forget the datagrams sent so far.
+    pub fn clear_packets(&self) {
+        self.lock().packets.clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Network> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A socket of a [`MockNetwork`], unbound when dropped.
+#[derive(Debug)]
+pub struct MockSocket {
+    addr: SocketAddr,
+    network: Arc<Mutex<Network>>,
+    /// The timer until the next datagram arrives, when the network has latency.
+    delay: Mutex<Option<Pin<Box<Sleep>>>>,
+}
+
+impl MockSocket {
+    fn lock(&self) -> MutexGuard<'_, Network> {
+        self.network.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn update_groups(&self, group: IpAddr, join: bool) -> io::Result<()> {
+        let mut network = self.lock();
+        let mailbox = network
+            .sockets
+            .get_mut(&self.addr)
+            .ok_or(io::ErrorKind::NotConnected)?;
+        if join {
+            mailbox.groups.insert(group);
+        } else {
+            mailbox.groups.remove(&group);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MockSocket {
+    fn drop(&mut self) {
+        self.lock().sockets.remove(&self.addr);
+    }
+}
+
+impl SsdpSocket for MockSocket {
+    fn poll_recv_from(
+        &self,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<SocketAddr>> {
+        let mut delay = self.delay.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            let mut network = self.lock();
+            let mailbox = network
+                .sockets
+                .get_mut(&self.addr)
+                .ok_or(io::ErrorKind::NotConnected)?;
+
+            let Some(&(arrival, _, _)) = mailbox.queue.front() else {
+                mailbox.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            };
+            if arrival <= Instant::now() {
+                let (_, from, data) = mailbox.queue.pop_front().expect("checked above");
+                let n = data.len().min(buf.remaining());
+                buf.put_slice(&data[..n]);
+                return Poll::Ready(Ok(from));
+            }
+
+            // Wake up when it arrives, or when an earlier one is queued
+            mailbox.waker = Some(cx.waker().clone());
+            drop(network);
+            let sleep = delay.get_or_insert_with(|| Box::pin(tokio::time::sleep_until(arrival)));
+            if sleep.deadline() != arrival {
+                sleep.as_mut().reset(arrival);
+            }
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    fn poll_send_to(
+        &self,
+        _: &mut Context<'_>,
+        buf: &[u8],
+        target: SocketAddr,
+    ) -> Poll<io::Result<usize>> {
+        let mut network = self.lock();
+        let now = Instant::now();
+        let packet = Packet {
+            at: now - network.created,
+            from: self.addr,
+            to: target,
+            data: buf.to_vec(),
+        };
+        let arrival = now + network.latency;
+
+        for (addr, mailbox) in network.sockets.iter_mut() {
+            let receives = if target.ip().is_multicast() {
+                *addr != self.addr
+                    && addr.port() == target.port()
+                    && mailbox.groups.contains(&target.ip())
+            } else {
+                *addr == target
+            };
+            if receives {
+                mailbox.queue.push_back((arrival, self.addr, buf.to_vec()));
+                if let Some(waker) = mailbox.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+        network.packets.push(packet);
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn join_multicast_v4(&self, group: Ipv4Addr, _: Ipv4Addr) -> io::Result<()> {
+        self.update_groups(group.into(), true)
+    }
+
+    fn leave_multicast_v4(&self, group: Ipv4Addr, _: Ipv4Addr) -> io::Result<()> {
+        self.update_groups(group.into(), false)
+    }
+
+    fn join_multicast_v6(&self, group: Ipv6Addr, _: u32) -> io::Result<()> {
+        self.update_groups(group.into(), true)
+    }
+
+    fn leave_multicast_v6(&self, group: Ipv6Addr, _: u32) -> io::Result<()> {
+        self.update_groups(group.into(), false)
+    }
+}
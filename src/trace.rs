@@ -0,0 +1,128 @@
+use std::{fmt::Display, future::Future, net::SocketAddr};
+
+#[cfg(feature = "tracing")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Device, Redaction, SearchRequest};
+
+/// Counts the datagrams a server drops or cannot parse, reported as `tracing` events.
+///
+/// Without the `tracing` feature this does nothing.
+#[derive(Debug, Default)]
+pub(crate) struct PacketCounters {
+    #[cfg(feature = "tracing")]
+    dropped: AtomicU64,
+    #[cfg(feature = "tracing")]
+    invalid: AtomicU64,
+}
+
+impl PacketCounters {
+    /// Count a well-formed datagram from `remote_addr` the server chose to ignore.
+    pub(crate) fn dropped(&self, remote_addr: SocketAddr, reason: &'static str) {
+        #[cfg(feature = "tracing")]
+        {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::debug!(%remote_addr, reason, dropped, "dropped datagram");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (remote_addr, reason);
+    }
+
+    /// Count a datagram from `remote_addr` that could not be parsed.
+    pub(crate) fn invalid(&self, remote_addr: SocketAddr, error: &dyn Display) {
+        #[cfg(feature = "tracing")]
+        {
+            let invalid = self.invalid.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::debug!(%remote_addr, %error, invalid, "invalid datagram");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = (remote_addr, error);
+    }
+}
+
+/// Run `receive` in a span for the datagram of `len` bytes from `remote_addr`.
+pub(crate) async fn packet<F: Future>(
+    remote_addr: SocketAddr,
+    len: usize,
+    receive: F,
+) -> F::Output {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+        receive
+            .instrument(tracing::debug_span!("ssdp_packet", %remote_addr, len))
+            .await
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (remote_addr, len);
+        receive.await
+    }
+}
+
+/// Run `handle` in a span for answering `search`, with `redaction` applied to its target.
+pub(crate) async fn search<F: Future>(
+    search: &SearchRequest,
+    redaction: &Redaction,
+    handle: F,
+) -> F::Output {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+        let span = tracing::debug_span!(
+            "ssdp_search",
+            search_id = search.id,
+            st = %redaction.apply(&search.search_target),
+            mx = search.mx,
+        );
+        handle.instrument(span).await
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (search, redaction);
+        handle.await
+    }
+}
+
+/// Run `send` in a span for sending the responses to search `search_id` to `remote_addr`.
+pub(crate) async fn respond<F: Future>(
+    search_id: u64,
+    remote_addr: SocketAddr,
+    send: F,
+) -> F::Output {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
+        send.instrument(tracing::debug_span!("ssdp_respond", search_id, %remote_addr))
+            .await
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (search_id, remote_addr);
+        send.await
+    }
+}
+
+/// Record that the search being answered matched `device`, with `redaction` applied.
+pub(crate) fn matched(device: &Device, redaction: &Redaction) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        st = %redaction.apply(&device.search_target),
+        usn = %redaction.apply(&device.usn),
+        "matched device"
+    );
+    #[cfg(not(feature = "tracing"))]
+    let _ = (device, redaction);
+}
+
+/// Record that a response was sent, for the device `usn` if not from a template, with
+/// `redaction` applied.
+pub(crate) fn responded(usn: Option<&str>, redaction: &Redaction) {
+    #[cfg(feature = "tracing")]
+    {
+        let usn = usn.map(|usn| redaction.apply(usn));
+        tracing::debug!(usn = usn.as_deref(), "sent response");
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = (usn, redaction);
+}
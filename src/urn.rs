@@ -0,0 +1,317 @@
+use std::{fmt, str::FromStr};
+
+use crate::{Error, Result};
+
+/// Check that `part` of a [`Urn`], its domain or type name, fits between the colons.
+fn check_part(what: &str, part: &str) -> Result<()> {
+    if part.is_empty() || part.contains(|c: char| c == ':' || c.is_whitespace()) {
+        return Err(Error::Config(format!(
+            "the {what} {part:?} of a URN must be non-empty, without colons or whitespace"
+        )));
+    }
+    Ok(())
+}
+
+/// Whether a [`Urn`] names a device type or a service type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UrnKind {
+    /// `urn:<domain>:device:<type>:<version>`
+    Device,
+    /// `urn:<domain>:service:<type>:<version>`
+    Service,
+}
+
+/// A device or service type, such as `urn:schemas-upnp-org:device:MediaServer:1`.
+///
+/// Parsing checks the form the UPnP Device Architecture requires, so a malformed type is caught
+/// when the device is created instead of silently never matching a search. A `Urn` converts into
+/// the `String` that `Device::new` and `Device::with_service` take.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::Urn;
+///
+/// let urn = Urn::device("schemas-upnp-org", "MediaServer", 1).unwrap();
+/// assert_eq!(urn.to_string(), "urn:schemas-upnp-org:device:MediaServer:1");
+/// assert_eq!("urn:schemas-upnp-org:device:MediaServer:1".parse::<Urn>().unwrap(), urn);
+/// assert!("urn:schemas-upnp-org:device:MediaServer".parse::<Urn>().is_err());
+/// assert!(Urn::device("schemas-upnp-org", "Media Server", 1).is_err());
+///
+/// # #[cfg(feature = "server")] {
+/// use tokio_ssdp::Device;
+///
+/// Device::new("ad8782a0-9e28-422b-a6ae-670fe7c4c043", urn, "http://192.168.1.100:8080/desc.xml")
+///     .with_service(Urn::service("schemas-upnp-org", "ContentDirectory", 1).unwrap());
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Urn {
+    domain: String,
+    kind: UrnKind,
+    name: String,
+    version: u32,
+}
+
+impl Urn {
+    /// The device type `name` of `domain`, e.g. `schemas-upnp-org`, in `version`.
+    ///
+    /// Fails with `Error::Config` if `domain` or `name` is empty or contains a colon or whitespace.
+    pub fn device(
+        domain: impl Into<String>,
+        name: impl Into<String>,
+        version: u32,
+    ) -> Result<Self> {
+        Self::new(domain.into(), UrnKind::Device, name.into(), version)
+    }
+
+    /// The service type `name` of `domain`, e.g. `schemas-upnp-org`, in `version`.
+    ///
+    /// Fails with `Error::Config` if `domain` or `name` is empty or contains a colon or whitespace.
+    pub fn service(
+        domain: impl Into<String>,
+        name: impl Into<String>,
+        version: u32,
+    ) -> Result<Self> {
+        Self::new(domain.into(), UrnKind::Service, name.into(), version)
+    }
+
+    fn new(domain: String, kind: UrnKind, name: String, version: u32) -> Result<Self> {
+        check_part("domain", &domain)?;
+        check_part("type name", &name)?;
+        Ok(Self {
+            domain,
+            kind,
+            name,
+            version,
+        })
+    }
+
+    /// The domain defining the type, `schemas-upnp-org` for standard types.
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Whether this is a device or a service type.
+    pub fn kind(&self) -> UrnKind {
+        self.kind
+    }
+
+    /// The name of the type, e.g. `MediaServer`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The version of the type.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The same type in `version`.
+    pub fn with_version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+}
+
+impl FromStr for Urn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            Error::Config(format!(
+                "{s} is not of the form urn:<domain>:device|service:<type>:<version>"
+            ))
+        };
+
+        let parts: Vec<&str> = s
+            .strip_prefix("urn:")
+            .ok_or_else(invalid)?
+            .split(':')
+            .collect();
+        let [domain, kind, name, version] = parts.as_slice() else {
+            return Err(invalid());
+        };
+        let kind = match *kind {
+            "device" => UrnKind::Device,
+            "service" => UrnKind::Service,
+            _ => return Err(invalid()),
+        };
+        if !version.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let version = version.parse().map_err(|_| invalid())?;
+
+        Self::new((*domain).to_owned(), kind, (*name).to_owned(), version).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for Urn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            UrnKind::Device => "device",
+            UrnKind::Service => "service",
+        };
+        write!(
+            f,
+            "urn:{}:{kind}:{}:{}",
+            self.domain, self.name, self.version
+        )
+    }
+}
+
+impl From<Urn> for String {
+    fn from(urn: Urn) -> Self {
+        urn.to_string()
+    }
+}
+
+/// A unique service name, the `USN` header: `uuid:<uuid>`, or `uuid:<uuid>::<nt>` for every
+/// notification type but the `uuid:` one.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{ErrorKind, Urn, Usn};
+///
+/// let usn = Usn::parse(
+///     "uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043::urn:schemas-upnp-org:device:MediaServer:1",
+/// )
+/// .unwrap();
+/// assert_eq!(usn.uuid(), "ad8782a0-9e28-422b-a6ae-670fe7c4c043");
+/// assert_eq!(usn.urn(), Urn::device("schemas-upnp-org", "MediaServer", 1).ok());
+///
+/// let usn = Usn::new("ad8782a0-9e28-422b-a6ae-670fe7c4c043", "upnp:rootdevice");
+/// assert_eq!(usn.to_string(), "uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043::upnp:rootdevice");
+///
+/// let err = Usn::parse("ad8782a0-9e28-422b-a6ae-670fe7c4c043").unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::Parse);
+/// assert!(Usn::parse("uuid:ad8782a0-9e28-422b-a6ae-670fe7c4c043::urn:acme:device").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Usn {
+    uuid: String,
+    target: Option<String>,
+}
+
+impl Usn {
+    /// The USN of the notification type `nt` of the device `uuid`, see `compose_usn`.
+    pub fn new(uuid: impl AsRef<str>, nt: impl Into<String>) -> Self {
+        let uuid = uuid.as_ref();
+        let uuid = uuid.strip_prefix("uuid:").unwrap_or(uuid).to_owned();
+        let nt = nt.into();
+        let target = if nt.is_empty() || nt.strip_prefix("uuid:") == Some(&uuid) {
+            None
+        } else {
+            Some(nt)
+        };
+        Self { uuid, target }
+    }
+
+    /// Parse `usn`, same as `usn.parse()`.
+    ///
+    /// Fails with `Error::InvalidHeader`, of kind `ErrorKind::Parse`, without the `uuid:` prefix,
+    /// with an empty UUID or notification type, or with a malformed `urn:` type.
+    pub fn parse(usn: &str) -> Result<Self> {
+        usn.parse()
+    }
+
+    /// The UUID of the device, without the `uuid:` prefix.
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    /// The notification type after `::`, `None` for the `uuid:` target.
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// The device or service type of the notification type, if it is one.
+    pub fn urn(&self) -> Option<Urn> {
+        self.target.as_deref()?.parse().ok()
+    }
+}
+
+impl FromStr for Usn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // A USN usually comes from a peer, so a malformed one is a parse error
+        let invalid = |why: &str| Error::InvalidHeader {
+            name: "USN",
+            source: format!("{s:?}: {why}").into(),
+        };
+
+        let rest = s
+            .strip_prefix("uuid:")
+            .ok_or_else(|| invalid("missing the uuid: prefix"))?;
+        let (uuid, target) = match rest.split_once("::") {
+            Some((uuid, target)) => (uuid, Some(target)),
+            None => (rest, None),
+        };
+        if uuid.is_empty() {
+            return Err(invalid("empty UUID"));
+        }
+        match target {
+            Some("") => return Err(invalid("empty notification type")),
+            Some(target) if target.starts_with("urn:") => {
+                target
+                    .parse::<Urn>()
+                    .map_err(|_| invalid("malformed urn: type"))?;
+            }
+            _ => {}
+        }
+
+        Ok(Self {
+            uuid: uuid.to_owned(),
+            target: target.map(str::to_owned),
+        })
+    }
+}
+
+impl fmt::Display for Usn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "uuid:{}", self.uuid)?;
+        if let Some(target) = &self.target {
+            write!(f, "::{target}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Usn> for String {
+    fn from(usn: Usn) -> Self {
+        usn.to_string()
+    }
+}
+
+/// Serialized as the `urn:` string, like `Display` writes and `FromStr` reads it.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Urn {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Urn {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Serialized as the `USN` header value, like `Display` writes and `FromStr` reads it.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Usn {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Usn {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
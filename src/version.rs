@@ -0,0 +1,194 @@
+use std::{fmt, str::FromStr};
+
+use crate::Error;
+
+/// A version of the UPnP Device Architecture the server conforms to.
+///
+/// The version is announced in the default `SERVER` header and decides which of the headers
+/// introduced by later versions may be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum UpnpVersion {
+    /// UPnP Device Architecture 1.0.
+    #[default]
+    V1_0,
+    /// UPnP Device Architecture 1.1, adds `BOOTID.UPNP.ORG`, `CONFIGID.UPNP.ORG` and friends.
+    V1_1,
+    /// UPnP Device Architecture 2.0, adds `SECURELOCATION.UPNP.ORG`.
+    V2_0,
+}
+
+impl UpnpVersion {
+    /// The first version defining the header `name`, `None` for headers not tied to a version.
+    ///
+    /// # Examples
+    /// ```
+    /// use tokio_ssdp::UpnpVersion;
+    ///
+    /// assert_eq!(UpnpVersion::introducing("bootid.upnp.org"), Some(UpnpVersion::V1_1));
+    /// assert_eq!(UpnpVersion::introducing("X-Vendor"), None);
+    /// ```
+    pub fn introducing(name: &str) -> Option<Self> {
+        const V1_1_HEADERS: &[&str] = &[
+            "BOOTID.UPNP.ORG",
+            "NEXTBOOTID.UPNP.ORG",
+            "CONFIGID.UPNP.ORG",
+            "SEARCHPORT.UPNP.ORG",
+        ];
+        const V2_0_HEADERS: &[&str] = &["SECURELOCATION.UPNP.ORG"];
+
+        if V1_1_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+            Some(Self::V1_1)
+        } else if V2_0_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name)) {
+            Some(Self::V2_0)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the header `name` may be sent by a server conforming to this version.
+    pub fn allows_header(&self, name: &str) -> bool {
+        Self::introducing(name).is_none_or(|v| v <= *self)
+    }
+}
+
+impl fmt::Display for UpnpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            UpnpVersion::V1_0 => "1.0",
+            UpnpVersion::V1_1 => "1.1",
+            UpnpVersion::V2_0 => "2.0",
+        })
+    }
+}
+
+/// A `SERVER` header of the form the UPnP Device Architecture requires:
+/// `<OS>/<version> UPnP/<UDA version> <product>/<version>`.
+///
+/// The OS and the product are HTTP product tokens, a name and a version separated by `/`, without
+/// spaces or separators such as `(`, `,` or `;`. Building or parsing one checks the form, so a
+/// header certification suites and strict routers reject is caught early. A `ServerHeader`
+/// converts into the `String` that `Server::server_name` and `Device::server_name` take.
+///
+/// # Examples
+/// ```
+/// use tokio_ssdp::{ServerHeader, UpnpVersion};
+///
+/// let header = ServerHeader::new("Linux/6.1", UpnpVersion::V1_1, "AcmePrinter/2.4").unwrap();
+/// assert_eq!(header.to_string(), "Linux/6.1 UPnP/1.1 AcmePrinter/2.4");
+/// assert_eq!("Linux/6.1 UPnP/1.1 AcmePrinter/2.4".parse::<ServerHeader>().unwrap(), header);
+///
+/// assert!(ServerHeader::new("Linux", UpnpVersion::V1_1, "AcmePrinter/2.4").is_err());
+/// assert!("SHP, UPnP/1.0, Samsung UPnP SDK/1.0".parse::<ServerHeader>().is_err());
+///
+/// # #[cfg(feature = "server")] {
+/// use tokio_ssdp::Server;
+///
+/// Server::new([])
+///     .upnp_version(UpnpVersion::V1_1)
+///     .server_name(header);
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServerHeader {
+    os: String,
+    upnp: UpnpVersion,
+    product: String,
+}
+
+impl ServerHeader {
+    /// The header for the OS token `os`, e.g. `Linux/6.1`, conforming to `upnp`, and the product
+    /// token `product`, e.g. `AcmePrinter/2.4`.
+    ///
+    /// Fails with `Error::Config` if `os` or `product` is not a `<name>/<version>` token.
+    pub fn new(
+        os: impl Into<String>,
+        upnp: UpnpVersion,
+        product: impl Into<String>,
+    ) -> Result<Self, Error> {
+        let (os, product) = (os.into(), product.into());
+        for (what, token) in [("OS", &os), ("product", &product)] {
+            if !is_product_token(token) {
+                return Err(Error::Config(format!(
+                    "{what} {token:?} is not of the form <name>/<version>"
+                )));
+            }
+        }
+        Ok(Self { os, upnp, product })
+    }
+
+    /// The header sent when none is configured, `<OS>/- UPnP/<version> tokio-ssdp/<version>`,
+    /// without the OS version, which the standard library does not tell.
+    #[cfg(feature = "server")]
+    pub(crate) fn tokio_ssdp(upnp: UpnpVersion) -> Self {
+        Self {
+            os: format!("{}/-", std::env::consts::OS),
+            upnp,
+            product: concat!("tokio-ssdp/", env!("CARGO_PKG_VERSION")).to_owned(),
+        }
+    }
+
+    /// The OS token, e.g. `Linux/6.1`.
+    pub fn os(&self) -> &str {
+        &self.os
+    }
+
+    /// The version of the UPnP Device Architecture announced.
+    pub fn upnp_version(&self) -> UpnpVersion {
+        self.upnp
+    }
+
+    /// The product token, e.g. `AcmePrinter/2.4`.
+    pub fn product(&self) -> &str {
+        &self.product
+    }
+}
+
+/// Whether `token` is `<name>/<version>`, both HTTP tokens.
+fn is_product_token(token: &str) -> bool {
+    fn is_token(s: &str) -> bool {
+        !s.is_empty()
+            && s.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+    }
+
+    token
+        .split_once('/')
+        .is_some_and(|(name, version)| is_token(name) && is_token(version))
+}
+
+impl FromStr for ServerHeader {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            Error::Config(format!(
+                "{s:?} is not of the form <OS>/<version> UPnP/<version> <product>/<version>"
+            ))
+        };
+
+        let [os, upnp, product] = s.split(' ').collect::<Vec<_>>()[..] else {
+            return Err(invalid());
+        };
+        let upnp = match upnp {
+            "UPnP/1.0" => UpnpVersion::V1_0,
+            "UPnP/1.1" => UpnpVersion::V1_1,
+            "UPnP/2.0" => UpnpVersion::V2_0,
+            _ => return Err(invalid()),
+        };
+        Self::new(os, upnp, product).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for ServerHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} UPnP/{} {}", self.os, self.upnp, self.product)
+    }
+}
+
+impl From<ServerHeader> for String {
+    fn from(header: ServerHeader) -> Self {
+        header.to_string()
+    }
+}
@@ -0,0 +1,109 @@
+use std::{fmt::Debug, future::Future, net::IpAddr, pin::Pin, sync::Mutex, time::Duration};
+
+use if_addrs::IfOperStatus;
+use log::{info, warn};
+
+/// Tells a running server that the network changed, see `Server::network_watcher`.
+///
+/// Implement it on top of the OS notifications (netlink, `NotifyAddrChange`,
+/// `SCNetworkReachability`) where latency matters; [`InterfacePoller`] polls the interface list
+/// instead.
+///
+/// # Examples
+/// ```
+/// use std::{future::Future, pin::Pin};
+/// use tokio::sync::Notify;
+/// use tokio_ssdp::{NetworkWatcher, Server};
+///
+/// /// Notified by the application's own connectivity monitoring.
+/// #[derive(Debug, Default)]
+/// struct Roaming(Notify);
+///
+/// impl NetworkWatcher for Roaming {
+///     fn changed(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+///         Box::pin(self.0.notified())
+///     }
+/// }
+///
+/// Server::new([])
+///   .network_watcher(Roaming::default());
+/// ```
+pub trait NetworkWatcher: Debug + Send + Sync {
+    /// Resolves once an interface came up, went down or changed its address since the server
+    /// started or since it last resolved.
+    fn changed(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+/// Watches the network by listing the interfaces periodically.
+///
+/// A change of the name, address or operational state of any non-loopback interface counts.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use tokio_ssdp::{InterfacePoller, Server};
+///
+/// Server::new([])
+///   .network_watcher(InterfacePoller::new(Duration::from_secs(5)));
+/// ```
+#[derive(Debug)]
+pub struct InterfacePoller {
+    period: Duration,
+    last: Mutex<Option<Vec<(String, IpAddr, bool)>>>,
+}
+
+impl InterfacePoller {
+    /// List the interfaces every `period`.
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            last: Mutex::new(None),
+        }
+    }
+}
+
+impl NetworkWatcher for InterfacePoller {
+    fn changed(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async move {
+            let mut interval = tokio::time::interval(self.period);
+            loop {
+                interval.tick().await;
+                let Some(current) = interfaces() else {
+                    continue;
+                };
+                let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+                match last.replace(current.clone()) {
+                    Some(previous) if previous != current => {
+                        info!("Network interfaces changed: {current:?}");
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        })
+    }
+}
+
+/// The non-loopback interfaces, their addresses and whether they are up, sorted, `None` if they
+/// cannot be listed.
+fn interfaces() -> Option<Vec<(String, IpAddr, bool)>> {
+    let mut interfaces: Vec<_> = match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces
+            .into_iter()
+            .filter(|iface| !iface.is_loopback())
+            .map(|iface| {
+                let up = !matches!(
+                    iface.oper_status,
+                    IfOperStatus::Down | IfOperStatus::NotPresent | IfOperStatus::LowerLayerDown
+                );
+                (iface.name.clone(), iface.ip(), up)
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to list network interfaces: {e}");
+            return None;
+        }
+    };
+    interfaces.sort();
+    Some(interfaces)
+}
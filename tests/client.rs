@@ -0,0 +1,84 @@
+//! Searches on a `MockNetwork`, with the tokio clock paused so timers elapse instantly.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::time::Instant;
+use tokio_ssdp::{Client, SsdpSocket, Timeouts, testing::MockNetwork};
+
+const DEVICE: &str = "192.0.2.10:1900";
+const CONTROL_POINT: &str = "192.0.2.50:5000";
+
+/// A device on `network` answering the first search with `count` responses, `every` apart.
+fn respond(network: &MockNetwork, count: u32, every: Duration) {
+    let device: Arc<dyn SsdpSocket> =
+        Arc::new(network.bind_multicast(DEVICE.parse().unwrap()).unwrap());
+    tokio::spawn(async move {
+        let mut buf = [0; 1024];
+        let (_, requester) = device.recv_from(&mut buf).await.unwrap();
+        for i in 0..count {
+            tokio::time::sleep(every).await;
+            let response = format!(
+                concat!(
+                    "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nEXT:\r\n",
+                    "LOCATION: http://192.0.2.10/{i}.xml\r\nST: upnp:rootdevice\r\n",
+                    "USN: uuid:6f1b0f1e-3c3a-4c7e-9a07-1d2b3c4d5e{i:02x}::upnp:rootdevice\r\n\r\n"
+                ),
+                i = i
+            );
+            device
+                .send_to(response.as_bytes(), requester)
+                .await
+                .unwrap();
+        }
+    });
+}
+
+/// Search for root devices through `client`, returning how many answered and how long it took.
+async fn search(network: &MockNetwork, client: Client) -> (usize, Duration) {
+    let start = Instant::now();
+    let socket = network.bind(CONTROL_POINT.parse().unwrap()).unwrap();
+    let mut search = client.search_via(socket, "upnp:rootdevice").await.unwrap();
+    let mut found = 0;
+    while let Some(response) = search.next().await {
+        response.unwrap();
+        found += 1;
+    }
+    (found, start.elapsed())
+}
+
+#[tokio::test(start_paused = true)]
+async fn searches_collect_for_mx_plus_one_second_by_default() {
+    let network = MockNetwork::new();
+    respond(&network, 3, Duration::from_millis(200));
+
+    let (found, took) = search(&network, Client::new().mx(2)).await;
+    assert_eq!(found, 3);
+    assert_eq!(took, Duration::from_secs(3));
+}
+
+#[tokio::test(start_paused = true)]
+async fn read_timeout_ends_a_search_once_responses_dry_up() {
+    let network = MockNetwork::new();
+    respond(&network, 3, Duration::from_millis(200));
+
+    // Each response restarts the read timeout, so all three arrive
+    let timeouts = Timeouts::new()
+        .search(Duration::from_secs(5))
+        .read(Duration::from_millis(300));
+    let (found, took) = search(&network, Client::new().timeouts(timeouts)).await;
+    assert_eq!(found, 3);
+    assert_eq!(took, Duration::from_millis(900));
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_timeout_bounds_a_search_with_a_read_timeout() {
+    let network = MockNetwork::new();
+    respond(&network, 10, Duration::from_millis(200));
+
+    let timeouts = Timeouts::new()
+        .search(Duration::from_secs(1))
+        .read(Duration::from_millis(300));
+    let (found, took) = search(&network, Client::new().timeouts(timeouts)).await;
+    assert_eq!(found, 4);
+    assert_eq!(took, Duration::from_secs(1));
+}
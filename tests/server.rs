@@ -0,0 +1,416 @@
+//! Servers on a `MockNetwork`, with the tokio clock paused so timers elapse instantly.
+
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::Instant};
+use tokio_ssdp::{Device, DeviceProvider, Server, SsdpSocket, UpnpVersion, testing::MockNetwork};
+
+const SERVER: &str = "192.0.2.10:1900";
+const GROUP: &str = "239.255.255.250:1900";
+const PRINTER: &str = "urn:schemas-upnp-org:device:Printer:1";
+const PRINT_SERVICE: &str = "urn:schemas-upnp-org:service:PrintBasic:1";
+
+/// The root printer `i`, advertised as `upnp:rootdevice`, its UUID, type and service.
+fn printer(i: u8) -> Device {
+    Device::new(
+        format!("6f1b0f1e-3c3a-4c7e-9a07-1d2b3c4d5e{i:02x}"),
+        PRINTER,
+        format!("http://192.0.2.10/{i}.xml"),
+    )
+    .with_service(PRINT_SERVICE)
+}
+
+/// Serve `server` on `network`, letting it start.
+async fn serve(network: &MockNetwork, server: Server) {
+    let socket = network.bind_multicast(SERVER.parse().unwrap()).unwrap();
+    tokio::spawn(server.serve_socket(socket).unwrap());
+    tokio::time::sleep(Duration::from_millis(10)).await;
+}
+
+/// A control point socket on `addr`.
+fn control_point(network: &MockNetwork, addr: &str) -> Arc<dyn SsdpSocket> {
+    Arc::new(network.bind(addr.parse().unwrap()).unwrap())
+}
+
+/// Multicast an `M-SEARCH` for `st` from `socket`.
+async fn search(socket: &Arc<dyn SsdpSocket>, st: &str, mx: u32) {
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {GROUP}\r\nMAN: \"ssdp:discover\"\r\nMX: {mx}\r\nST: {st}\r\n\r\n"
+    );
+    socket
+        .send_to(request.as_bytes(), GROUP.parse().unwrap())
+        .await
+        .unwrap();
+}
+
+/// The value of `name` in `message`.
+fn header(message: &str, name: &str) -> Option<String> {
+    message.lines().find_map(|line| {
+        let (n, value) = line.split_once(':')?;
+        n.eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_owned())
+    })
+}
+
+/// The time and `ST` of the responses sent to `to`.
+fn responses(network: &MockNetwork, to: &str) -> Vec<(Duration, String)> {
+    let to: SocketAddr = to.parse().unwrap();
+    network
+        .packets()
+        .into_iter()
+        .filter(|p| p.to == to)
+        .map(|p| (p.at, header(&p.text(), "ST").unwrap_or_default()))
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn announces_on_start_and_every_interval() {
+    let network = MockNetwork::new();
+    let server = Server::new([printer(1)]).announce_interval(Duration::from_secs(60));
+    serve(&network, server).await;
+    tokio::time::sleep(Duration::from_secs(130)).await;
+
+    let group: SocketAddr = GROUP.parse().unwrap();
+    let alive: Vec<_> = network
+        .packets()
+        .into_iter()
+        .filter(|p| p.to == group && p.text().contains("ssdp:alive"))
+        .collect();
+    let mut rounds: Vec<u64> = alive.iter().map(|p| p.at.as_secs()).collect();
+    rounds.dedup();
+    assert_eq!(rounds, [0, 60, 120]);
+
+    // Each round covers every advertisement of the tree
+    let mut first: Vec<String> = alive
+        .iter()
+        .filter(|p| p.at.as_secs() == 0)
+        .filter_map(|p| header(&p.text(), "NT"))
+        .collect();
+    first.sort();
+    first.dedup();
+    assert_eq!(first.len(), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn boot_ids_require_upnp_1_1() {
+    let network = MockNetwork::new();
+    let socket = || network.bind_multicast(SERVER.parse().unwrap()).unwrap();
+    assert!(Server::new([]).boot_id(1).serve_socket(socket()).is_err());
+    assert!(Server::new([]).config_id(1).serve_socket(socket()).is_err());
+
+    let server = Server::new([printer(1)])
+        .upnp_version(UpnpVersion::V1_1)
+        .boot_id(1)
+        .config_id(7);
+    serve(&network, server).await;
+    let group: SocketAddr = GROUP.parse().unwrap();
+    let alive = network
+        .packets()
+        .into_iter()
+        .find(|p| p.to == group)
+        .unwrap();
+    assert_eq!(
+        header(&alive.text(), "BOOTID.UPNP.ORG").as_deref(),
+        Some("1")
+    );
+    assert_eq!(
+        header(&alive.text(), "CONFIGID.UPNP.ORG").as_deref(),
+        Some("7")
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn matches_exact_and_wildcard_search_targets() {
+    let network = MockNetwork::new();
+    serve(&network, Server::new([printer(1)]).wildcard_search(true)).await;
+
+    let searches = [
+        ("192.0.2.50:5000", PRINTER, 1),
+        (
+            "192.0.2.50:5001",
+            "urn:schemas-upnp-org:device:Printer:2",
+            0,
+        ),
+        ("192.0.2.50:5002", "urn:schemas-upnp-org:service:*", 1),
+        ("192.0.2.50:5003", "urn:schemas-upnp-org:**", 2),
+        ("192.0.2.50:5004", "ssdp:all", 4),
+    ];
+    for (addr, st, _) in searches {
+        search(&control_point(&network, addr), st, 1).await;
+    }
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    for (addr, st, expected) in searches {
+        assert_eq!(responses(&network, addr).len(), expected, "ST {st}");
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn exact_search_targets_answer_every_matching_device() {
+    let network = MockNetwork::new();
+    serve(&network, Server::new([printer(1), printer(2)])).await;
+
+    let roots = "192.0.2.50:5000";
+    let printers = "192.0.2.50:5001";
+    search(&control_point(&network, roots), "upnp:rootdevice", 1).await;
+    search(&control_point(&network, printers), PRINTER, 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert_eq!(responses(&network, roots).len(), 2);
+    assert_eq!(responses(&network, printers).len(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn wildcards_are_literal_unless_enabled() {
+    let network = MockNetwork::new();
+    serve(&network, Server::new([printer(1)])).await;
+
+    let addr = "192.0.2.50:5000";
+    search(&control_point(&network, addr), "urn:schemas-upnp-org:**", 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert!(responses(&network, addr).is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_dedup_drops_repeated_searches_within_the_window() {
+    let network = MockNetwork::new();
+    let server = Server::new([printer(1)]).search_dedup(Duration::from_secs(3));
+    serve(&network, server).await;
+
+    let addr = "192.0.2.50:5000";
+    let socket = control_point(&network, addr);
+    search(&socket, PRINTER, 1).await;
+    search(&socket, PRINTER, 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(responses(&network, addr).len(), 1);
+
+    // Responses already sent are left out of the answer to a broader search
+    search(&socket, "ssdp:all", 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    let sent = responses(&network, addr);
+    assert_eq!(sent.len(), 4);
+    assert_eq!(sent.iter().filter(|(_, st)| st == PRINTER).count(), 1);
+
+    // Once the window is over, the search is answered again
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    search(&socket, PRINTER, 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(responses(&network, addr).len(), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_rate_limit_bounds_the_searches_answered_per_host() {
+    let network = MockNetwork::new();
+    let server = Server::new([printer(1)]).search_rate_limit(2, Duration::from_secs(1));
+    serve(&network, server).await;
+
+    let addr = "192.0.2.50:5000";
+    let socket = control_point(&network, addr);
+    for _ in 0..5 {
+        search(&socket, PRINTER, 1).await;
+    }
+    // Another host has an allowance of its own
+    let other = "192.0.2.51:5000";
+    search(&control_point(&network, other), PRINTER, 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(responses(&network, addr).len(), 2);
+    assert_eq!(responses(&network, other).len(), 1);
+
+    // The allowance is regained over the period
+    search(&socket, PRINTER, 1).await;
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(responses(&network, addr).len(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn search_rate_limit_of_zero_is_rejected() {
+    let network = MockNetwork::new();
+    let socket = network.bind_multicast(SERVER.parse().unwrap()).unwrap();
+    let server = Server::new([printer(1)]).search_rate_limit(0, Duration::from_secs(1));
+    assert!(server.serve_socket(socket).is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn alive_rate_limit_drops_repeated_announcements() {
+    let network = MockNetwork::new();
+    let mut server = Server::new([]).alive_rate_limit(Duration::from_secs(10));
+    let mut notifications = server.notify_stream(16);
+    serve(&network, server).await;
+
+    let device = control_point(&network, "192.0.2.60:1900");
+    let notify = |nts: &str| {
+        format!(
+            concat!(
+                "NOTIFY * HTTP/1.1\r\nHOST: {}\r\nCACHE-CONTROL: max-age=1800\r\n",
+                "LOCATION: http://192.0.2.60/desc.xml\r\nNT: upnp:rootdevice\r\nNTS: {}\r\n",
+                "USN: uuid:0b9b9c36-3a0c-4c40-8b34-9cf1f4fdc1a1::upnp:rootdevice\r\n\r\n"
+            ),
+            GROUP, nts
+        )
+    };
+    let group: SocketAddr = GROUP.parse().unwrap();
+    for nts in ["ssdp:alive", "ssdp:alive", "ssdp:alive", "ssdp:byebye"] {
+        device.send_to(notify(nts).as_bytes(), group).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    // Quiet for longer than the interval, so the next alive goes through
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    device
+        .send_to(notify("ssdp:alive").as_bytes(), group)
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let mut received = vec![];
+    while let Ok(Some(message)) =
+        tokio::time::timeout(Duration::from_millis(1), notifications.next()).await
+    {
+        received.push(header(&String::from_utf8_lossy(&message.data), "NTS").unwrap());
+    }
+    assert_eq!(received, ["ssdp:alive", "ssdp:byebye", "ssdp:alive"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn fair_responses_take_turns_between_requesters() {
+    let network = MockNetwork::new();
+    let server = Server::new([printer(1)]).fair_responses(5, Duration::from_secs(1));
+    serve(&network, server).await;
+
+    // The first host asks for everything, the second for one device right after
+    let greedy = "192.0.2.50:5000";
+    let modest = "192.0.2.51:5000";
+    search(&control_point(&network, greedy), "ssdp:all", 0).await;
+    search(&control_point(&network, modest), PRINTER, 0).await;
+    tokio::time::sleep(Duration::from_secs(3)).await;
+
+    let greedy = responses(&network, greedy);
+    let modest = responses(&network, modest);
+    assert_eq!(greedy.len(), 4);
+    assert_eq!(modest.len(), 1);
+    // The second host does not wait for every response to the first
+    assert!(modest[0].0 < greedy[1].0);
+    // And responses go out one turn apart
+    for pair in greedy.windows(2) {
+        assert!(pair[1].0 - pair[0].0 >= Duration::from_millis(200));
+    }
+}
+
+/// Devices that a test swaps while the server runs, shared with the server.
+#[derive(Debug, Clone, Default)]
+struct Swappable(Arc<(Mutex<Vec<Device>>, Notify)>);
+
+impl Swappable {
+    fn set(&self, devices: Vec<Device>) {
+        *self.0.0.lock().unwrap() = devices;
+        self.0.1.notify_one();
+    }
+}
+
+impl DeviceProvider for Swappable {
+    fn devices(&self) -> Pin<Box<dyn Future<Output = Vec<Device>> + Send + '_>> {
+        Box::pin(async { self.0.0.lock().unwrap().clone() })
+    }
+
+    fn changed(&self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(self.0.1.notified())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn provider_changes_are_announced() {
+    let network = MockNetwork::new();
+    let provider = Swappable::default();
+    provider.set(vec![printer(1)]);
+    serve(&network, Server::new([]).device_provider(provider.clone())).await;
+
+    // The printer moves, then leaves
+    let moved = "http://192.0.2.10/moved.xml";
+    let uuid = printer(1).uuid().to_owned();
+    let device = Device::new(uuid, PRINTER, moved).with_service(PRINT_SERVICE);
+    provider.set(vec![device]);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    provider.set(vec![]);
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let group: SocketAddr = GROUP.parse().unwrap();
+    let notifies: Vec<(u64, String, String)> = network
+        .packets()
+        .into_iter()
+        .filter(|p| p.to == group)
+        .map(|p| {
+            let text = p.text();
+            let nts = header(&text, "NTS").unwrap();
+            (
+                p.at.as_secs(),
+                nts,
+                header(&text, "LOCATION").unwrap_or_default(),
+            )
+        })
+        .collect();
+    let count = |at: u64, nts: &str, location: Option<&str>| {
+        notifies
+            .iter()
+            .filter(|n| n.0 == at && n.1 == nts && location.is_none_or(|l| n.2 == l))
+            .count()
+    };
+    // Every advertisement is announced again at the new location
+    assert_eq!(count(0, "ssdp:alive", Some(moved)), 4);
+    assert_eq!(count(1, "ssdp:byebye", None), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn postponed_announcements_outlast_the_delay() {
+    let network = MockNetwork::new();
+    let mut server = Server::new([printer(1)]).announce_interval(Duration::from_secs(60));
+    let handle = server.handle();
+    let start = Instant::now();
+    serve(&network, server).await;
+
+    // The interval runs from the end of the paced first round
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let next = handle.next_announcement().unwrap() - start;
+    assert!(next > Duration::from_secs(60) && next < Duration::from_secs(61));
+
+    // Ten seconds in, hold the next round until 200 s
+    tokio::time::sleep(Duration::from_secs(9)).await;
+    let at = start + Duration::from_secs(200);
+    handle.reschedule_announcement(at).unwrap();
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert_eq!(handle.next_announcement(), Some(at));
+    let addr = "192.0.2.50:5000";
+    search(&control_point(&network, addr), PRINTER, 1).await;
+    tokio::time::sleep(Duration::from_secs(255)).await;
+
+    let group: SocketAddr = GROUP.parse().unwrap();
+    let mut rounds: Vec<(u64, String)> = network
+        .packets()
+        .into_iter()
+        .filter(|p| p.to == group && p.text().starts_with("NOTIFY"))
+        .map(|p| (p.at.as_secs(), header(&p.text(), "CACHE-CONTROL").unwrap()))
+        .collect();
+    rounds.dedup();
+    let rounds: Vec<(u64, &str)> = rounds.iter().map(|(at, cc)| (*at, cc.as_str())).collect();
+    assert_eq!(
+        rounds,
+        [
+            (0, "max-age=100"),
+            (10, "max-age=289"),
+            (200, "max-age=100"),
+            (260, "max-age=100"),
+        ]
+    );
+    // Search responses meanwhile cover the delay too
+    let response = network
+        .packets()
+        .into_iter()
+        .find(|p| p.to == addr.parse().unwrap());
+    let cache_control = header(&response.unwrap().text(), "CACHE-CONTROL");
+    assert_eq!(cache_control.as_deref(), Some("max-age=289"));
+}